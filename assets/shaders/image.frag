@@ -0,0 +1,11 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 uv;
+
+uniform sampler2D texture0;
+uniform vec4 tint;
+
+void main() {
+    FragColor = texture(texture0, uv) * tint;
+}
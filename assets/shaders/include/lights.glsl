@@ -0,0 +1,25 @@
+// Binding point 1, same layout as `LightsUbo`.
+
+// Same as the renderer.
+const int MAX_LIGHTS = 8;
+
+struct Light {
+    // W is the range.
+    vec4 position;
+    // W is the cosine of the outer angle, -1 for point lights.
+    vec4 direction;
+    // W is the cosine of the inner angle.
+    vec4 color;
+};
+
+layout (std140) uniform Lights {
+    // W is 1 if there is a sun.
+    vec4 sun_direction;
+    vec4 sun_color;
+    vec4 ambient;
+    vec4 camera_position;
+    mat4 sun_space;
+    // Y is 1 if the sun casts shadows.
+    ivec4 lights_count;
+    Light lights[MAX_LIGHTS];
+};
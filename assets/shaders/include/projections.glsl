@@ -0,0 +1,7 @@
+// Binding point 0, updated once per frame (see `ShaderManager`).
+layout (std140) uniform Projections {
+    // Ortho for the gui.
+    mat4 gui;
+    mat4 perspective;
+    mat4 view;
+};
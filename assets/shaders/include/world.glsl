@@ -0,0 +1,28 @@
+// World matrix of the vertex, from its instance or the model uniform,
+// times its skin.
+layout (location = 4) in uvec4 a_joints;
+layout (location = 5) in vec4 a_weights;
+// World matrix of the instance, replaces the model one.
+layout (location = 7) in mat4 a_instance;
+
+// Same as the model importer.
+const int MAX_JOINTS = 64;
+
+uniform mat4 model;
+uniform mat4 joints[MAX_JOINTS];
+
+mat4 world_matrix() {
+    mat4 skin = mat4(1.0);
+#ifdef HAS_SKIN
+    skin = a_weights.x * joints[a_joints.x]
+        + a_weights.y * joints[a_joints.y]
+        + a_weights.z * joints[a_joints.z]
+        + a_weights.w * joints[a_joints.w];
+#endif
+
+#ifdef IS_INSTANCED
+    return a_instance * skin;
+#else
+    return model * skin;
+#endif
+}
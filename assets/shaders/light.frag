@@ -0,0 +1,14 @@
+#version 330 core
+
+out vec4 FragColor;
+
+uniform vec3 entity_color;
+uniform vec3 light_color;
+
+void main() {
+    float ambient_strength = 0.1;
+    vec3 ambient = ambient_strength * light_color;
+
+    vec3 result = ambient * entity_color;
+    FragColor = vec4(result, 1.0);
+}
@@ -0,0 +1,10 @@
+#version 330 core
+layout (location = 0) in vec3 local_pos;
+
+uniform mat4 projection;
+uniform mat4 model;
+uniform mat4 view;
+
+void main() {
+    gl_Position = projection * view * model * vec4(local_pos, 1.0);
+}
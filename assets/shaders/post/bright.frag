@@ -0,0 +1,18 @@
+#version 330 core
+// Keeps only what is brighter than the threshold, the source of the
+// bloom.
+out vec4 FragColor;
+
+in vec2 uv;
+
+uniform sampler2D scene;
+uniform float threshold;
+
+void main() {
+    vec3 color = texture(scene, uv).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+    float contribution =
+        max(brightness - threshold, 0.0) / max(brightness, 0.0001);
+
+    FragColor = vec4(color * contribution, 1.0);
+}
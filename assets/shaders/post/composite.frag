@@ -0,0 +1,54 @@
+#version 330 core
+// From the HDR scene to displayable colors, each step is enabled by
+// its define.
+out vec4 FragColor;
+
+in vec2 uv;
+
+uniform sampler2D scene;
+uniform sampler2D bloom;
+uniform sampler3D lut;
+
+uniform float exposure;
+uniform float bloom_intensity;
+uniform float vignette_strength;
+uniform float lut_size;
+
+// Filmic curve fitted by Krzysztof Narkowicz.
+vec3 aces(vec3 x) {
+    return clamp(
+        (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14),
+        0.0,
+        1.0
+    );
+}
+
+void main() {
+    vec3 color = texture(scene, uv).rgb;
+
+#ifdef HAS_BLOOM
+    color += texture(bloom, uv).rgb * bloom_intensity;
+#endif
+
+#ifdef HAS_TONE_MAPPING
+    color = aces(color * exposure);
+#endif
+    color = clamp(color, 0.0, 1.0);
+
+#ifdef HAS_COLOR_GRADING
+    // Sample the texel centers, not the edges of the volume.
+    vec3 coords = color * (lut_size - 1.0) / lut_size + 0.5 / lut_size;
+    color = texture(lut, coords).rgb;
+#endif
+
+#ifdef HAS_GAMMA
+    color = pow(color, vec3(1.0 / 2.2));
+#endif
+
+#ifdef HAS_VIGNETTE
+    float distance = length(uv - 0.5) * 1.4142;
+    color *= 1.0 - vignette_strength * smoothstep(0.4, 1.0, distance);
+#endif
+
+    FragColor = vec4(color, 1.0);
+}
@@ -0,0 +1,54 @@
+#version 330 core
+// Fast approximate anti-aliasing, blurs along the edges found from
+// the luma of the neighbors (after Timothy Lottes).
+out vec4 FragColor;
+
+in vec2 uv;
+
+uniform sampler2D image;
+uniform vec2 texel;
+
+const float REDUCE_MIN = 1.0 / 128.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float SPAN_MAX = 8.0;
+
+float luma(vec3 color) {
+    return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+float luma_at(vec2 offset) {
+    return luma(texture(image, uv + offset * texel).rgb);
+}
+
+void main() {
+    float nw = luma_at(vec2(-1.0, -1.0));
+    float ne = luma_at(vec2(1.0, -1.0));
+    float sw = luma_at(vec2(-1.0, 1.0));
+    float se = luma_at(vec2(1.0, 1.0));
+    float m = luma_at(vec2(0.0));
+
+    float luma_min = min(m, min(min(nw, ne), min(sw, se)));
+    float luma_max = max(m, max(max(nw, ne), max(sw, se)));
+
+    vec2 dir = vec2(-((nw + ne) - (sw + se)), (nw + sw) - (ne + se));
+    float reduce =
+        max((nw + ne + sw + se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float scale = 1.0 / (min(abs(dir.x), abs(dir.y)) + reduce);
+    dir = clamp(dir * scale, -SPAN_MAX, SPAN_MAX) * texel;
+
+    vec3 near = 0.5 * (
+        texture(image, uv + dir * (1.0 / 3.0 - 0.5)).rgb
+        + texture(image, uv + dir * (2.0 / 3.0 - 0.5)).rgb
+    );
+    vec3 far = near * 0.5 + 0.25 * (
+        texture(image, uv - dir * 0.5).rgb
+        + texture(image, uv + dir * 0.5).rgb
+    );
+
+    // Too far, the edge was overshot.
+    float luma_far = luma(far);
+    vec3 color =
+        luma_far < luma_min || luma_far > luma_max ? near : far;
+
+    FragColor = vec4(color, 1.0);
+}
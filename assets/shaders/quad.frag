@@ -0,0 +1,31 @@
+#version 330 core
+in vec2 local;
+out vec4 FragColor;
+
+uniform vec2 size;
+uniform vec4 bg_c;
+uniform float radius;
+uniform float border_width;
+uniform vec4 border_c;
+
+// Signed distance to a rounded rectangle, negative inside.
+float rounded_box(vec2 p, vec2 half_size, float r) {
+    vec2 q = abs(p) - half_size + r;
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - r;
+}
+
+void main() {
+    vec2 half_size = size * 0.5;
+    float r = clamp(radius, 0.0, min(half_size.x, half_size.y));
+    float dist = rounded_box(local, half_size, r);
+
+    // One pixel of antialiasing on the edges.
+    float coverage = clamp(0.5 - dist, 0.0, 1.0);
+    float border = clamp(dist + border_width + 0.5, 0.0, 1.0);
+    if (border_width <= 0.0) {
+        border = 0.0;
+    }
+
+    vec4 color = mix(bg_c, border_c, border);
+    FragColor = vec4(color.rgb, color.a * coverage);
+}
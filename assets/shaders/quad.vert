@@ -0,0 +1,15 @@
+#version 330 core
+#include "include/projections.glsl"
+
+layout (location = 0) in vec4 vertex;
+
+uniform mat4 model;
+uniform vec2 size;
+
+// From the center of the quad, in pixels.
+out vec2 local;
+
+void main() {
+    local = vertex.xy * size * 0.5;
+    gl_Position = gui * model * vec4(vertex.xy, 0.0, 1.0);
+}
@@ -0,0 +1,11 @@
+#version 330 core
+// Depth only, rendered from the sun.
+#include "include/world.glsl"
+
+layout (location = 0) in vec3 a_pos;
+
+uniform mat4 light_space;
+
+void main() {
+    gl_Position = light_space * world_matrix() * vec4(a_pos, 1.0);
+}
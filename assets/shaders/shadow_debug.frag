@@ -0,0 +1,11 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 uv;
+
+uniform sampler2D shadow_map;
+
+void main() {
+    float depth = texture(shadow_map, uv).r;
+    FragColor = vec4(vec3(depth), 1.0);
+}
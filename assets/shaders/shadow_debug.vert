@@ -0,0 +1,14 @@
+#version 330 core
+// Shadow map displayed on a screen quad, for the editor.
+#include "include/projections.glsl"
+
+layout (location = 0) in vec4 vertex;
+
+uniform mat4 model;
+
+out vec2 uv;
+
+void main() {
+    uv = vertex.xy * 0.5 + 0.5;
+    gl_Position = gui * model * vec4(vertex.xy, 0.0, 1.0);
+}
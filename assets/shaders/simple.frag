@@ -0,0 +1,209 @@
+#version 330 core
+#include "include/lights.glsl"
+
+// Material, factors are multiplied with their texture. Textures are
+// defined by the material, each one sampled from its uv set.
+uniform vec4 base_color;
+uniform vec4 tint;
+uniform vec3 emissive;
+uniform float roughness;
+uniform float metalness;
+// 0 opaque, 1 mask, 2 blend.
+uniform int alpha_mode;
+uniform float alpha_cutoff;
+
+#ifdef HAS_BASE_COLOR_TEXTURE
+uniform sampler2D base_color_texture;
+uniform int base_color_texture_uv;
+#endif
+#ifdef HAS_BASE_COLOR_ARRAY
+uniform sampler2DArray base_color_array;
+uniform int base_color_array_uv;
+#endif
+#ifdef HAS_METALLIC_ROUGHNESS_TEXTURE
+uniform sampler2D metallic_roughness_texture;
+uniform int metallic_roughness_texture_uv;
+#endif
+#ifdef HAS_OCCLUSION_TEXTURE
+uniform sampler2D occlusion_texture;
+uniform int occlusion_texture_uv;
+#endif
+#ifdef HAS_EMISSIVE_TEXTURE
+uniform sampler2D emissive_texture;
+uniform int emissive_texture_uv;
+#endif
+
+uniform sampler2D shadow_map;
+
+uniform bool is_hover;
+
+in VERTEX_OUT {
+    vec4 color;
+    vec2 uv_coords[2];
+    vec3 position;
+    vec3 normal;
+    flat uint layer;
+} vertex_in;
+
+out vec4 FragColor;
+
+struct Surface {
+    vec3 albedo;
+    float roughness;
+    float metalness;
+};
+
+vec2 uv(int set) {
+    return set == 0 ? vertex_in.uv_coords[0] : vertex_in.uv_coords[1];
+}
+
+// Part of the sun light reaching the fragment, 3x3 PCF to smooth
+// the shadow edges.
+float sun_visibility(vec3 normal) {
+    if (lights_count.y == 0) {
+        return 1.0;
+    }
+
+    vec4 light_pos = sun_space * vec4(vertex_in.position, 1.0);
+    vec3 coords = light_pos.xyz / light_pos.w * 0.5 + 0.5;
+    // Beyond the far plane of the sun.
+    if (coords.z > 1.0) {
+        return 1.0;
+    }
+
+    // Slope scaled bias against shadow acne.
+    float slope = 1.0 - max(dot(normal, -sun_direction.xyz), 0.0);
+    float bias = max(0.005 * slope, 0.001);
+
+    vec2 texel = 1.0 / vec2(textureSize(shadow_map, 0));
+    float lit = 0.0;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            float depth =
+                texture(shadow_map, coords.xy + vec2(x, y) * texel).r;
+            lit += coords.z - bias > depth ? 0.0 : 1.0;
+        }
+    }
+
+    return lit / 9.0;
+}
+
+// Blinn-Phong, the light direction goes from the fragment
+// to the light. Rough surfaces get a wide and dim highlight,
+// metals a colored one and no diffuse.
+vec3 shade(
+    vec3 light_dir,
+    vec3 light_color,
+    vec3 normal,
+    vec3 view_dir,
+    Surface surface
+) {
+    float diffuse = max(dot(normal, light_dir), 0.0);
+
+    float shininess = exp2(10.0 * (1.0 - surface.roughness) + 1.0);
+    vec3 halfway = normalize(light_dir + view_dir);
+    float specular = pow(max(dot(normal, halfway), 0.0), shininess)
+        * (shininess + 8.0) / 25.13;
+    // No specular on the back faces.
+    specular *= step(0.0, dot(normal, light_dir));
+
+    vec3 specular_color =
+        mix(vec3(0.04), surface.albedo, surface.metalness);
+    vec3 diffuse_color = surface.albedo * (1.0 - surface.metalness);
+
+    return light_color
+        * (diffuse * diffuse_color + specular * specular_color);
+}
+
+vec3 lighting(Surface surface, float occlusion) {
+    vec3 normal = normalize(vertex_in.normal);
+    vec3 view_dir = normalize(camera_position.xyz - vertex_in.position);
+    vec3 result = ambient.rgb * surface.albedo * occlusion;
+
+    if (sun_direction.w > 0.0) {
+        result += sun_visibility(normal) * shade(
+            -sun_direction.xyz,
+            sun_color.rgb,
+            normal,
+            view_dir,
+            surface
+        );
+    }
+
+    for (int i = 0; i < lights_count.x; i++) {
+        Light light = lights[i];
+        vec3 to_light = light.position.xyz - vertex_in.position;
+        float distance = length(to_light);
+        vec3 light_dir = to_light / max(distance, 0.0001);
+
+        // Smooth falloff reaching 0 at the range.
+        float falloff = clamp(1.0 - distance / light.position.w, 0.0, 1.0);
+        falloff *= falloff;
+
+        // Spot cone.
+        if (light.direction.w > -1.0) {
+            float theta = dot(-light_dir, light.direction.xyz);
+            falloff *= smoothstep(light.direction.w, light.color.w, theta);
+        }
+
+        result += falloff
+            * shade(light_dir, light.color.rgb, normal, view_dir, surface);
+    }
+
+    return result;
+}
+
+void main() {
+    vec4 color = base_color * tint;
+
+#ifdef HAS_VERT_COLORS
+    color *= vertex_in.color;
+#endif
+
+#ifdef HAS_BASE_COLOR_TEXTURE
+    color *= texture(base_color_texture, uv(base_color_texture_uv));
+#endif
+#ifdef HAS_BASE_COLOR_ARRAY
+    color *= texture(
+        base_color_array,
+        vec3(uv(base_color_array_uv), float(vertex_in.layer))
+    );
+#endif
+
+    if (alpha_mode == 0) {
+        color.a = 1.0;
+    } else if (alpha_mode == 1 && color.a < alpha_cutoff) {
+        discard;
+    }
+
+    Surface surface = Surface(color.rgb, roughness, metalness);
+#ifdef HAS_METALLIC_ROUGHNESS_TEXTURE
+    vec4 texel = texture(
+        metallic_roughness_texture,
+        uv(metallic_roughness_texture_uv)
+    );
+    surface.roughness *= texel.g;
+    surface.metalness *= texel.b;
+#endif
+
+    float occlusion = 1.0;
+#ifdef HAS_OCCLUSION_TEXTURE
+    occlusion = texture(occlusion_texture, uv(occlusion_texture_uv)).r;
+#endif
+
+#ifdef HAS_NORMALS
+    color.rgb = lighting(surface, occlusion);
+#endif
+
+    vec3 glow = emissive;
+#ifdef HAS_EMISSIVE_TEXTURE
+    glow *= texture(emissive_texture, uv(emissive_texture_uv)).rgb;
+#endif
+    color.rgb += glow;
+
+    if (is_hover) {
+        color = vec4(color.xyz, 0.7);
+    }
+
+    FragColor = color;
+}
@@ -0,0 +1,30 @@
+#version 330 core
+#include "include/projections.glsl"
+#include "include/world.glsl"
+
+layout (location = 0) in vec3 a_pos;
+layout (location = 1) in vec4 a_color;
+layout (location = 2) in vec2 a_uv_coords[2];
+layout (location = 6) in vec3 a_normal;
+// Layer of the texture arrays, 0 when not instanced.
+layout (location = 11) in uint a_layer;
+
+out VERTEX_OUT {
+    vec4 color;
+    vec2 uv_coords[2];
+    vec3 position;
+    vec3 normal;
+    flat uint layer;
+} vs_out;
+
+void main() {
+    mat4 world = world_matrix();
+    vec4 position = world * vec4(a_pos, 1.0);
+
+    gl_Position = perspective * view * position;
+    vs_out.color = a_color;
+    vs_out.uv_coords = a_uv_coords;
+    vs_out.position = position.xyz;
+    vs_out.normal = transpose(inverse(mat3(world))) * a_normal;
+    vs_out.layer = a_layer;
+}
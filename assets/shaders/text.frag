@@ -0,0 +1,29 @@
+#version 330 core
+in vec2 TexCoords;
+in vec3 TextColor;
+in vec2 Style;
+
+out vec4 FragColor;
+
+uniform sampler2D texture0;
+uniform sampler2D icons;
+
+float width = 0.51;
+float edge = 0.045;
+
+void main() {
+    // The atlases are sampled in texels, glyphs keep theirs when it
+    // grows.
+    if (Style.y > 0.5) {
+        FragColor = texture(icons, TexCoords / vec2(textureSize(icons, 0)));
+        return;
+    }
+
+    vec2 uv = TexCoords / vec2(textureSize(texture0, 0));
+    float distance = texture(texture0, uv).r;
+    // Bolder glyphs have their edge further out.
+    float threshold = width - Style.x;
+    float alpha = smoothstep(threshold - edge, threshold + edge, distance);
+
+    FragColor = vec4(TextColor, alpha);
+}
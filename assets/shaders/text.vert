@@ -0,0 +1,20 @@
+#version 330 core
+#include "include/projections.glsl"
+
+// Position in pixels and atlas texels.
+layout (location = 0) in vec4 vertex;
+layout (location = 1) in vec3 color;
+// Extra weight of the glyph, and 1 for the icons.
+layout (location = 2) in vec2 style;
+
+out vec2 TexCoords;
+out vec3 TextColor;
+out vec2 Style;
+
+void main() {
+    gl_Position = gui * vec4(vertex.xy, 0.0, 1.0);
+
+    TexCoords = vertex.zw;
+    TextColor = color;
+    Style = style;
+}
@@ -0,0 +1,550 @@
+/// Keyframe animations.
+///
+/// A clip is a set of channels, each one animating the translation,
+/// rotation or scale of a target: a part of a model or a joint of its
+/// skeleton. Sampling a clip gives a pose (a transform per target),
+/// poses can be blended together and turned into skinning matrices.
+use crate::entities::{Arena, Handle};
+use crate::renderer::{slerp, Mesh, Model, Transform, Vector};
+use nalgebra_glm as glm;
+use std::collections::HashMap;
+use std::mem::take;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Interpolation {
+    Linear,
+    Step,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Target {
+    // Index of the part in the model.
+    Part(usize),
+    // Index of the joint in the skeleton.
+    Joint(usize),
+}
+
+#[derive(Debug, Clone)]
+pub enum Keyframes {
+    Translation(Vec<glm::Vec3>),
+    Rotation(Vec<glm::Quat>),
+    Scale(Vec<glm::Vec3>),
+}
+
+/// Transform of each animated target.
+pub type Pose = HashMap<Target, Transform>;
+
+#[derive(Debug, Clone)]
+pub struct Channel {
+    pub target: Target,
+    pub interpolation: Interpolation,
+    // In seconds, sorted.
+    pub times: Vec<f32>,
+    pub keyframes: Keyframes,
+}
+
+impl Channel {
+    /// Overwrite the animated property of the transform with its
+    /// value at the given time.
+    pub fn apply(&self, time: f32, transform: &mut Transform) {
+        if self.times.is_empty() {
+            return;
+        }
+
+        let (from, to, t) = self.locate(time);
+
+        match &self.keyframes {
+            Keyframes::Translation(values) => {
+                let value = glm::lerp(&values[from], &values[to], t);
+                transform.position = Vector::from_glm(value);
+            }
+            Keyframes::Rotation(values) => {
+                transform.rotation = slerp(&values[from], &values[to], t);
+            }
+            Keyframes::Scale(values) => {
+                let value = glm::lerp(&values[from], &values[to], t);
+                transform.scale = Vector::from_glm(value);
+            }
+        }
+    }
+
+    // Keyframes around the time, and the factor between them.
+    fn locate(&self, time: f32) -> (usize, usize, f32) {
+        let last = self.times.len() - 1;
+
+        if time <= self.times[0] {
+            return (0, 0, 0.);
+        }
+        if time >= self.times[last] {
+            return (last, last, 0.);
+        }
+
+        // First keyframe after the time.
+        let to = self.times.iter().position(|t| *t > time).unwrap_or(last);
+        let from = to - 1;
+
+        match self.interpolation {
+            Interpolation::Step => (from, from, 0.),
+            Interpolation::Linear => {
+                let span = self.times[to] - self.times[from];
+                let t = if span > 0. {
+                    (time - self.times[from]) / span
+                } else {
+                    0.
+                };
+                (from, to, t)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Clip {
+    pub name: String,
+    // In seconds, time of the last keyframe.
+    pub duration: f32,
+    pub channels: Vec<Channel>,
+}
+
+impl Clip {
+    pub fn new(name: &str, channels: Vec<Channel>) -> Self {
+        let duration = channels
+            .iter()
+            .filter_map(|channel| channel.times.last())
+            .fold(0., |max: f32, t| max.max(*t));
+
+        Self {
+            name: name.to_owned(),
+            duration,
+            channels,
+        }
+    }
+
+    /// Pose at the given time (looping), targets not animated by the
+    /// clip keep their rest transform.
+    pub fn sample(&self, time: f32, rest: &Pose) -> Pose {
+        let time = if self.duration > 0. {
+            time.rem_euclid(self.duration)
+        } else {
+            0.
+        };
+
+        let mut pose = rest.clone();
+        for channel in self.channels.iter() {
+            let transform = pose.entry(channel.target).or_default();
+            channel.apply(time, transform);
+        }
+
+        pose
+    }
+}
+
+/// Interpolate two poses, `weight` 0 gives `a` and 1 gives `b`.
+pub fn blend(a: &Pose, b: &Pose, weight: f32) -> Pose {
+    let mut pose = a.clone();
+
+    for (target, to) in b.iter() {
+        let transform = match a.get(target) {
+            Some(from) => from.interpolate(to, weight),
+            None => *to,
+        };
+        pose.insert(*target, transform);
+    }
+
+    pose
+}
+
+#[derive(Debug, Clone)]
+pub struct Joint {
+    pub parent: Option<usize>,
+    pub rest: Transform,
+    // From the mesh space to the joint space, at bind time.
+    pub inverse_bind: glm::Mat4,
+}
+
+/// Joints of a skin.
+#[derive(Debug, Clone, Default)]
+pub struct Skeleton {
+    pub joints: Vec<Joint>,
+}
+
+impl Skeleton {
+    pub fn rest_pose(&self) -> Pose {
+        self.joints
+            .iter()
+            .enumerate()
+            .map(|(i, joint)| (Target::Joint(i), joint.rest))
+            .collect()
+    }
+
+    /// Matrices moving the vertices from their bind position to the
+    /// posed one, sent to the shader as is.
+    pub fn joint_matrices(&self, pose: &Pose) -> Vec<glm::Mat4> {
+        let mut globals: Vec<Option<glm::Mat4>> = vec![None; self.joints.len()];
+
+        (0..self.joints.len())
+            .map(|i| {
+                self.global_matrix(i, pose, &mut globals)
+                    * self.joints[i].inverse_bind
+            })
+            .collect()
+    }
+
+    // Joints aren't sorted by depth in glTF, parents are computed
+    // on demand and cached.
+    fn global_matrix(
+        &self,
+        index: usize,
+        pose: &Pose,
+        globals: &mut Vec<Option<glm::Mat4>>,
+    ) -> glm::Mat4 {
+        if let Some(global) = globals[index] {
+            return global;
+        }
+
+        let joint = &self.joints[index];
+        let local = pose
+            .get(&Target::Joint(index))
+            .unwrap_or(&joint.rest)
+            .to_model();
+
+        let global = match joint.parent {
+            Some(parent) => self.global_matrix(parent, pose, globals) * local,
+            None => local,
+        };
+
+        globals[index] = Some(global);
+        global
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Playing {
+    clip: usize,
+    time: f32,
+}
+
+/// Play clips, blending from the previous one when switching.
+#[derive(Debug, Default)]
+pub struct Animator {
+    clips: Vec<Clip>,
+    current: Option<Playing>,
+    previous: Option<Playing>,
+    blend_time: f32,
+    blend_duration: f32,
+}
+
+impl Animator {
+    pub fn new(clips: Vec<Clip>) -> Self {
+        Self {
+            clips,
+            ..Default::default()
+        }
+    }
+
+    /// Switch to the clip in `blend_duration` seconds.
+    /// Nothing happens if it's already playing or unknown.
+    pub fn play(&mut self, name: &str, blend_duration: f32) {
+        let clip = match self.clips.iter().position(|c| c.name == name) {
+            Some(clip) => clip,
+            None => return,
+        };
+
+        if self.current.map(|p| p.clip) == Some(clip) {
+            return;
+        }
+
+        // Without blending, the previous clip is dropped right away.
+        self.previous = if blend_duration > 0. {
+            self.current
+        } else {
+            None
+        };
+        self.current = Some(Playing { clip, time: 0. });
+        self.blend_time = 0.;
+        self.blend_duration = blend_duration;
+    }
+
+    #[allow(unused)]
+    pub fn current(&self) -> Option<&str> {
+        self.current.map(|p| self.clips[p.clip].name.as_str())
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if let Some(current) = &mut self.current {
+            current.time += dt;
+        }
+        if let Some(previous) = &mut self.previous {
+            previous.time += dt;
+        }
+
+        self.blend_time += dt;
+        if self.blend_time >= self.blend_duration {
+            self.previous = None;
+        }
+    }
+
+    pub fn pose(&self, rest: &Pose) -> Pose {
+        let sample = |p: &Playing| self.clips[p.clip].sample(p.time, rest);
+
+        match (&self.previous, &self.current) {
+            (Some(previous), Some(current)) => blend(
+                &sample(previous),
+                &sample(current),
+                (self.blend_time / self.blend_duration).min(1.),
+            ),
+            (None, Some(current)) => sample(current),
+            _ => rest.clone(),
+        }
+    }
+}
+
+/// Clips of a model played on its meshes, once inserted (see
+/// `Entities::insert_model`). The first clip plays by default.
+#[derive(Debug)]
+pub struct ModelAnimation {
+    pub animator: Animator,
+    skeleton: Option<Skeleton>,
+    // Only the animated parts and the joints.
+    rest: Pose,
+    // Placement of the root parts, like `Model::into_meshes`.
+    placements: Vec<Option<glm::Mat4>>,
+    skinned: Vec<usize>,
+    // Same order than the model parts.
+    meshes: Vec<Handle<Mesh>>,
+    // Sampled by the last update.
+    transforms: Vec<(usize, Transform)>,
+    joint_matrices: Vec<glm::Mat4>,
+}
+
+impl ModelAnimation {
+    /// Take the clips of the model, `None` if it has none.
+    pub fn from_model(
+        model: &mut Model,
+        transform: &Transform,
+    ) -> Option<Self> {
+        let clips = take(&mut model.clips);
+        let first = clips.first()?.name.clone();
+
+        let mut rest = model
+            .skeleton
+            .as_ref()
+            .map_or_else(Pose::new, Skeleton::rest_pose);
+        for clip in clips.iter() {
+            for channel in clip.channels.iter() {
+                if let Target::Part(i) = channel.target {
+                    rest.insert(channel.target, model.parts[i].transform);
+                }
+            }
+        }
+
+        let placement = transform.to_model();
+        let mut animator = Animator::new(clips);
+        animator.play(&first, 0.);
+
+        Some(Self {
+            animator,
+            skeleton: model.skeleton.clone(),
+            rest,
+            placements: model
+                .parts
+                .iter()
+                .map(|part| part.parent.map_or(Some(placement), |_| None))
+                .collect(),
+            skinned: (0..model.parts.len())
+                .filter(|i| model.parts[*i].is_skinned)
+                .collect(),
+            meshes: vec![],
+            transforms: vec![],
+            joint_matrices: vec![],
+        })
+    }
+
+    /// Meshes of the model parts, in the same order.
+    pub fn with_meshes(mut self, meshes: Vec<Handle<Mesh>>) -> Self {
+        self.meshes = meshes;
+        self
+    }
+
+    /// Sample the pose of the playing clips.
+    pub fn update(&mut self, dt: f32) {
+        self.animator.update(dt);
+        let pose = self.animator.pose(&self.rest);
+
+        self.transforms = pose
+            .iter()
+            .filter_map(|(target, transform)| match target {
+                Target::Part(i) => {
+                    let transform = match self.placements[*i] {
+                        Some(placement) => Transform::from_model(
+                            &(placement * transform.to_model()),
+                        ),
+                        None => *transform,
+                    };
+                    Some((*i, transform))
+                }
+                Target::Joint(_) => None,
+            })
+            .collect();
+
+        if let Some(skeleton) = &self.skeleton {
+            self.joint_matrices = skeleton.joint_matrices(&pose);
+        }
+    }
+
+    /// Move the meshes to the sampled pose, the joint matrices are
+    /// sent to the shader when drawn.
+    pub fn apply(&self, meshes: &mut Arena<Mesh>) {
+        for (i, transform) in self.transforms.iter() {
+            meshes.get_mut(&self.meshes[*i]).transform = *transform;
+        }
+        for i in self.skinned.iter() {
+            let mesh = meshes.get_mut(&self.meshes[*i]);
+            mesh.joint_matrices.clone_from(&self.joint_matrices);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounce() -> Channel {
+        Channel {
+            target: Target::Part(0),
+            interpolation: Interpolation::Linear,
+            times: vec![0., 1., 2.],
+            keyframes: Keyframes::Translation(vec![
+                glm::vec3(0., 0., 0.),
+                glm::vec3(0., 2., 0.),
+                glm::vec3(0., 0., 0.),
+            ]),
+        }
+    }
+
+    fn height(pose: &Pose) -> f32 {
+        pose[&Target::Part(0)].position.1
+    }
+
+    #[test]
+    fn sample_linear_and_step() {
+        let mut channel = bounce();
+        let clip = Clip::new("bounce", vec![channel.clone()]);
+        assert_eq!(clip.duration, 2.);
+
+        let rest = Pose::new();
+        assert!((height(&clip.sample(0.5, &rest)) - 1.).abs() < 1e-5);
+        assert!((height(&clip.sample(1.5, &rest)) - 1.).abs() < 1e-5);
+        // Looping.
+        assert!((height(&clip.sample(2.5, &rest)) - 1.).abs() < 1e-5);
+
+        channel.interpolation = Interpolation::Step;
+        let clip = Clip::new("bounce", vec![channel]);
+        assert_eq!(height(&clip.sample(0.9, &rest)), 0.);
+        assert_eq!(height(&clip.sample(1.1, &rest)), 2.);
+    }
+
+    #[test]
+    fn blend_clips() {
+        let idle = Clip::new("idle", vec![]);
+        let bounce = Clip::new("bounce", vec![bounce()]);
+        let mut rest = Pose::new();
+        rest.insert(Target::Part(0), Transform::default());
+
+        let mut animator = Animator::new(vec![idle, bounce]);
+        animator.play("idle", 0.);
+        animator.update(0.5);
+        assert_eq!(height(&animator.pose(&rest)), 0.);
+
+        // Half way through the blend, bounce is at 1 (time 0.5).
+        animator.play("bounce", 1.);
+        animator.update(0.5);
+        assert_eq!(animator.current(), Some("bounce"));
+        assert!((height(&animator.pose(&rest)) - 0.5).abs() < 1e-5);
+
+        // Blend done.
+        animator.update(0.5);
+        assert!((height(&animator.pose(&rest)) - 2.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn skin_joint_matrices() {
+        let skeleton = Skeleton {
+            joints: vec![
+                Joint {
+                    parent: None,
+                    rest: Transform::default(),
+                    inverse_bind: glm::identity(),
+                },
+                Joint {
+                    parent: Some(0),
+                    rest: Transform::from_pos(Vector(0., 1., 0.)),
+                    inverse_bind: glm::translate(
+                        &glm::identity(),
+                        &glm::vec3(0., -1., 0.),
+                    ),
+                },
+            ],
+        };
+
+        // At rest, vertices don't move.
+        let rest = skeleton.rest_pose();
+        for matrix in skeleton.joint_matrices(&rest) {
+            assert!((matrix - glm::Mat4::identity()).abs().max() < 1e-5);
+        }
+
+        // Rotating the root moves the child with it.
+        let mut pose = rest.clone();
+        pose.insert(
+            Target::Joint(0),
+            Transform::default().euler(Vector(0., 0., 90f32.to_radians())),
+        );
+        let matrices = skeleton.joint_matrices(&pose);
+        let moved = matrices[1] * glm::vec4(0., 2., 0., 1.);
+        assert!(glm::distance(&moved.xyz(), &glm::vec3(-2., 0., 0.)) < 1e-5);
+    }
+
+    #[test]
+    fn play_model_clips() {
+        let mut model =
+            Model::from_gltf("assets/models/skin/SimpleSkin.gltf").unwrap();
+        assert!(ModelAnimation::from_model(
+            &mut Model::default(),
+            &Transform::default()
+        )
+        .is_none());
+
+        let mut animation =
+            ModelAnimation::from_model(&mut model, &Transform::default())
+                .unwrap();
+        assert!(model.clips.is_empty());
+        assert_eq!(animation.animator.current(), Some("wave"));
+        assert_eq!(animation.skinned, vec![0]);
+
+        animation.update(0.5);
+        assert_eq!(animation.joint_matrices.len(), 2);
+        // Only joints are animated.
+        assert!(animation.transforms.is_empty());
+        let root = animation.joint_matrices[0];
+        assert!((root - glm::Mat4::identity()).abs().max() < 1e-5);
+        let upper = animation.joint_matrices[1];
+        assert!((upper - glm::Mat4::identity()).abs().max() > 0.1);
+    }
+
+    #[test]
+    fn place_animated_roots() {
+        let mut model =
+            Model::from_gltf("assets/models/skin/SimpleSkin.gltf").unwrap();
+        model.clips = vec![Clip::new("bounce", vec![bounce()])];
+
+        let placement = Transform::from_pos(Vector(5., 0., 0.));
+        let mut animation =
+            ModelAnimation::from_model(&mut model, &placement).unwrap();
+        animation.update(1.);
+
+        let (part, transform) = animation.transforms[0];
+        assert_eq!(part, 0);
+        assert!((transform.position.0 - 5.).abs() < 1e-5);
+        assert!((transform.position.1 - 2.).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,547 @@
+/// Bounding volume hierarchies used for picking.
+///
+/// `Bvh` is built once per mesh from its triangles (in local space) and
+/// gives exact ray/triangle intersections. `Broadphase` is a scene-level
+/// tree over world space boxes, so we only test meshes the ray can reach.
+use crate::renderer::Vertex;
+use nalgebra_glm as glm;
+
+pub type Ray = (glm::Vec3, glm::Vec3);
+pub type Triangle = [glm::Vec3; 3];
+
+// Max number of triangles stored in a leaf.
+const LEAF_SIZE: usize = 4;
+
+/// Axis-aligned bounding box.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb {
+    pub min: glm::Vec3,
+    pub max: glm::Vec3,
+}
+
+impl Aabb {
+    /// Inverted box, any point added will become the box.
+    pub fn empty() -> Self {
+        Self {
+            min: glm::vec3(f32::MAX, f32::MAX, f32::MAX),
+            max: glm::vec3(f32::MIN, f32::MIN, f32::MIN),
+        }
+    }
+
+    pub fn new(min: glm::Vec3, max: glm::Vec3) -> Self {
+        Self { min, max }
+    }
+
+    pub fn grow(self, point: &glm::Vec3) -> Self {
+        Self {
+            min: glm::min2(&self.min, point),
+            max: glm::max2(&self.max, point),
+        }
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            min: glm::min2(&self.min, &other.min),
+            max: glm::max2(&self.max, &other.max),
+        }
+    }
+
+    pub fn center(&self) -> glm::Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Box enclosing this box once transformed by the given matrix.
+    pub fn transform(&self, model: &glm::Mat4) -> Self {
+        let (min, max) = (self.min, self.max);
+        let corners = [
+            glm::vec3(min.x, min.y, min.z),
+            glm::vec3(max.x, min.y, min.z),
+            glm::vec3(min.x, max.y, min.z),
+            glm::vec3(max.x, max.y, min.z),
+            glm::vec3(min.x, min.y, max.z),
+            glm::vec3(max.x, min.y, max.z),
+            glm::vec3(min.x, max.y, max.z),
+            glm::vec3(max.x, max.y, max.z),
+        ];
+
+        corners.iter().fold(Self::empty(), |aabb, c| {
+            aabb.grow(&(model * glm::vec4(c.x, c.y, c.z, 1.)).xyz())
+        })
+    }
+
+    /// Slab test, return the distance where the ray enters the box.
+    /// The direction doesn't have to be normalized.
+    pub fn ray_hit(&self, ray: &Ray) -> Option<f32> {
+        let (origin, direction) = ray;
+        let mut t_min = 0f32;
+        let mut t_max = f32::MAX;
+
+        for i in 0..3 {
+            let inv = 1. / direction[i];
+            let mut t1 = (self.min[i] - origin[i]) * inv;
+            let mut t2 = (self.max[i] - origin[i]) * inv;
+
+            if t1 > t2 {
+                std::mem::swap(&mut t1, &mut t2);
+            }
+
+            // NaN (ray parallel and on a slab) won't shrink the range.
+            t_min = t_min.max(t1);
+            t_max = t_max.min(t2);
+
+            if t_min > t_max {
+                return None;
+            }
+        }
+
+        Some(t_min)
+    }
+}
+
+/// Möller–Trumbore ray/triangle intersection.
+/// Return the distance along the ray if hit, both faces are tested.
+pub fn ray_triangle(ray: &Ray, triangle: &Triangle) -> Option<f32> {
+    let (origin, direction) = ray;
+    let [v0, v1, v2] = triangle;
+
+    let edge_1 = v1 - v0;
+    let edge_2 = v2 - v0;
+    let p = direction.cross(&edge_2);
+    let det = edge_1.dot(&p);
+
+    // Parallel to the triangle.
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let inv_det = 1. / det;
+    let s = origin - v0;
+    let u = s.dot(&p) * inv_det;
+
+    if !(0. ..=1.).contains(&u) {
+        return None;
+    }
+
+    let q = s.cross(&edge_1);
+    let v = direction.dot(&q) * inv_det;
+
+    if v < 0. || u + v > 1. {
+        return None;
+    }
+
+    let t = edge_2.dot(&q) * inv_det;
+
+    if t > f32::EPSILON {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BvhNode {
+    aabb: Aabb,
+    // Leaf: first triangle index. Inner: index of the left child,
+    // the right child is always stored just after the left subtree.
+    first: usize,
+    count: usize,
+    right: usize,
+}
+
+impl BvhNode {
+    fn is_leaf(&self) -> bool {
+        self.count > 0
+    }
+}
+
+/// Triangle hierarchy of one mesh, in local space.
+#[derive(Debug, Clone)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    triangles: Vec<Triangle>,
+}
+
+impl Bvh {
+    /// Build the hierarchy from the vertex primitives and indices.
+    /// Return `None` if the vertex doesn't hold any triangle.
+    pub fn from_vertex(vertex: &Vertex) -> Option<Self> {
+        let points: Vec<glm::Vec3> =
+            vertex.primitives.iter().map(|v| v.to_glm()).collect();
+
+        let triangles: Vec<Triangle> = if vertex.indices.is_empty() {
+            points.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect()
+        } else {
+            vertex
+                .indices
+                .chunks_exact(3)
+                .filter(|t| t.iter().all(|i| (*i as usize) < points.len()))
+                .map(|t| {
+                    [
+                        points[t[0] as usize],
+                        points[t[1] as usize],
+                        points[t[2] as usize],
+                    ]
+                })
+                .collect()
+        };
+
+        Self::new(triangles)
+    }
+
+    pub fn new(mut triangles: Vec<Triangle>) -> Option<Self> {
+        if triangles.is_empty() {
+            return None;
+        }
+
+        let mut nodes = Vec::with_capacity(triangles.len() * 2);
+        let len = triangles.len();
+        Self::build(&mut nodes, &mut triangles, 0, len);
+
+        Some(Self { nodes, triangles })
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        self.nodes[0].aabb
+    }
+
+    #[cfg(test)]
+    pub fn triangles(&self) -> &[Triangle] {
+        &self.triangles
+    }
+
+    // Recursive median split over the longest axis of the centroids.
+    fn build(
+        nodes: &mut Vec<BvhNode>,
+        triangles: &mut [Triangle],
+        first: usize,
+        count: usize,
+    ) -> usize {
+        let slice = &mut triangles[first..first + count];
+        let aabb = slice.iter().fold(Aabb::empty(), |aabb, t| {
+            aabb.grow(&t[0]).grow(&t[1]).grow(&t[2])
+        });
+
+        let index = nodes.len();
+        nodes.push(BvhNode {
+            aabb,
+            first,
+            count,
+            right: 0,
+        });
+
+        if count <= LEAF_SIZE {
+            return index;
+        }
+
+        let centroids = slice
+            .iter()
+            .fold(Aabb::empty(), |aabb, t| aabb.grow(&centroid(t)));
+        let extent = centroids.max - centroids.min;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+
+        slice.sort_by(|a, b| {
+            centroid(a)[axis]
+                .partial_cmp(&centroid(b)[axis])
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let half = count / 2;
+        nodes[index].count = 0;
+        Self::build(nodes, triangles, first, half);
+        let right = Self::build(nodes, triangles, first + half, count - half);
+        nodes[index].right = right;
+
+        index
+    }
+
+    /// Nearest hit distance of the ray (in the same space as the mesh).
+    pub fn ray_intersect(&self, ray: &Ray) -> Option<f32> {
+        let mut nearest = f32::MAX;
+        let mut stack = vec![0];
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+
+            match node.aabb.ray_hit(ray) {
+                Some(t) if t <= nearest => (),
+                _ => continue,
+            }
+
+            if node.is_leaf() {
+                for triangle in
+                    &self.triangles[node.first..node.first + node.count]
+                {
+                    if let Some(t) = ray_triangle(ray, triangle) {
+                        nearest = nearest.min(t);
+                    }
+                }
+            } else {
+                stack.push(node.right);
+                stack.push(index + 1);
+            }
+        }
+
+        if nearest < f32::MAX {
+            Some(nearest)
+        } else {
+            None
+        }
+    }
+}
+
+fn centroid(triangle: &Triangle) -> glm::Vec3 {
+    (triangle[0] + triangle[1] + triangle[2]) / 3.
+}
+
+#[derive(Debug, Clone)]
+struct BroadNode<T> {
+    aabb: Aabb,
+    parent: Option<usize>,
+    // Children for inner nodes, `None` for leaves.
+    children: Option<(usize, usize)>,
+    item: Option<T>,
+}
+
+/// Scene-level hierarchy of world space boxes.
+///
+/// Built once, then kept up to date with `update` when an item moves,
+/// which refits its ancestors instead of rebuilding the whole tree.
+#[derive(Debug, Clone)]
+pub struct Broadphase<T: Copy + PartialEq> {
+    nodes: Vec<BroadNode<T>>,
+    // Leaf node index of each item.
+    items: Vec<(T, usize)>,
+}
+
+impl<T: Copy + PartialEq> Default for Broadphase<T> {
+    fn default() -> Self {
+        Self {
+            nodes: vec![],
+            items: vec![],
+        }
+    }
+}
+
+impl<T: Copy + PartialEq> Broadphase<T> {
+    pub fn build(mut items: Vec<(T, Aabb)>) -> Self {
+        let mut broadphase = Self::default();
+
+        if !items.is_empty() {
+            broadphase.build_node(&mut items, None);
+        }
+
+        broadphase
+    }
+
+    fn build_node(
+        &mut self,
+        items: &mut [(T, Aabb)],
+        parent: Option<usize>,
+    ) -> usize {
+        let aabb = items
+            .iter()
+            .fold(Aabb::empty(), |acc, (_, aabb)| acc.union(aabb));
+
+        let index = self.nodes.len();
+        self.nodes.push(BroadNode {
+            aabb,
+            parent,
+            children: None,
+            item: None,
+        });
+
+        if items.len() == 1 {
+            self.nodes[index].item = Some(items[0].0);
+            self.items.push((items[0].0, index));
+            return index;
+        }
+
+        let extent = aabb.max - aabb.min;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+
+        items.sort_by(|(_, a), (_, b)| {
+            a.center()[axis]
+                .partial_cmp(&b.center()[axis])
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let half = items.len() / 2;
+        let (left, right) = items.split_at_mut(half);
+        let left = self.build_node(left, Some(index));
+        let right = self.build_node(right, Some(index));
+        self.nodes[index].children = Some((left, right));
+
+        index
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Replace the box of an item and refit its ancestors.
+    pub fn update(&mut self, item: &T, aabb: Aabb) {
+        let leaf = match self.items.iter().find(|(i, _)| i == item) {
+            Some((_, leaf)) => *leaf,
+            None => return,
+        };
+
+        self.nodes[leaf].aabb = aabb;
+
+        let mut parent = self.nodes[leaf].parent;
+        while let Some(index) = parent {
+            if let Some((left, right)) = self.nodes[index].children {
+                self.nodes[index].aabb =
+                    self.nodes[left].aabb.union(&self.nodes[right].aabb);
+            }
+            parent = self.nodes[index].parent;
+        }
+    }
+
+    /// All items whose box is crossed by the ray, with the
+    /// entering distance. Not sorted.
+    pub fn query_ray(&self, ray: &Ray) -> Vec<(T, f32)> {
+        let mut hits = vec![];
+
+        if self.nodes.is_empty() {
+            return hits;
+        }
+
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+
+            let t = match node.aabb.ray_hit(ray) {
+                Some(t) => t,
+                None => continue,
+            };
+
+            if let Some((left, right)) = node.children {
+                stack.push(left);
+                stack.push(right);
+            } else if let Some(item) = node.item {
+                hits.push((item, t));
+            }
+        }
+
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::Vector;
+
+    fn unit_quad(z: f32) -> Vec<Triangle> {
+        vec![
+            [
+                glm::vec3(0., 0., z),
+                glm::vec3(1., 0., z),
+                glm::vec3(1., 1., z),
+            ],
+            [
+                glm::vec3(0., 0., z),
+                glm::vec3(1., 1., z),
+                glm::vec3(0., 1., z),
+            ],
+        ]
+    }
+
+    #[test]
+    fn triangle_hit_and_miss() {
+        let triangle = unit_quad(2.)[0];
+        let ray = (glm::vec3(0.8, 0.2, 0.), glm::vec3(0., 0., 1.));
+        assert_eq!(ray_triangle(&ray, &triangle), Some(2.));
+
+        let ray = (glm::vec3(0.2, 0.8, 0.), glm::vec3(0., 0., 1.));
+        assert_eq!(ray_triangle(&ray, &triangle), None);
+
+        // Behind the origin.
+        let ray = (glm::vec3(0.8, 0.2, 0.), glm::vec3(0., 0., -1.));
+        assert_eq!(ray_triangle(&ray, &triangle), None);
+    }
+
+    #[test]
+    fn aabb_slab() {
+        let aabb = Aabb::new(glm::vec3(-1., -1., -1.), glm::vec3(1., 1., 1.));
+        let ray = (glm::vec3(0., 0., -5.), glm::vec3(0., 0., 1.));
+        assert_eq!(aabb.ray_hit(&ray), Some(4.));
+
+        let ray = (glm::vec3(3., 0., -5.), glm::vec3(0., 0., 1.));
+        assert_eq!(aabb.ray_hit(&ray), None);
+    }
+
+    #[test]
+    fn bvh_nearest_triangle() {
+        // Ten stacked quads, the nearest one is at z = 1.
+        let triangles: Vec<Triangle> =
+            (1..=10).rev().flat_map(|z| unit_quad(z as f32)).collect();
+        let bvh = Bvh::new(triangles).unwrap();
+
+        let ray = (glm::vec3(0.5, 0.5, -1.), glm::vec3(0., 0., 1.));
+        assert_eq!(bvh.ray_intersect(&ray), Some(2.));
+
+        let ray = (glm::vec3(2., 2., -1.), glm::vec3(0., 0., 1.));
+        assert_eq!(bvh.ray_intersect(&ray), None);
+    }
+
+    #[test]
+    fn bvh_from_vertex() {
+        let vertex = Vertex {
+            primitives: vec![
+                Vector(0., 0., 0.),
+                Vector(1., 0., 0.),
+                Vector(1., 1., 0.),
+                Vector(0., 1., 0.),
+            ],
+            indices: vec![0, 1, 2, 0, 2, 3],
+            ..Default::default()
+        };
+
+        let bvh = Bvh::from_vertex(&vertex).unwrap();
+        assert_eq!(bvh.triangles().len(), 2);
+        assert_eq!(bvh.aabb().max, glm::vec3(1., 1., 0.));
+
+        assert!(Bvh::from_vertex(&Vertex::default()).is_none());
+    }
+
+    #[test]
+    fn broadphase_query_and_update() {
+        let items: Vec<(usize, Aabb)> = (0..8)
+            .map(|i| {
+                let x = i as f32 * 2.;
+                let aabb =
+                    Aabb::new(glm::vec3(x, 0., 0.), glm::vec3(x + 1., 1., 1.));
+                (i, aabb)
+            })
+            .collect();
+
+        let mut broadphase = Broadphase::build(items);
+        assert!(!broadphase.is_empty());
+
+        let ray = (glm::vec3(4.5, 0.5, -5.), glm::vec3(0., 0., 1.));
+        let hits = broadphase.query_ray(&ray);
+        assert_eq!(hits, vec![(2, 5.)]);
+
+        // Move the item 7 under the ray.
+        broadphase.update(
+            &7,
+            Aabb::new(glm::vec3(4., 0., 2.), glm::vec3(5., 1., 3.)),
+        );
+        let mut hits = broadphase.query_ray(&ray);
+        hits.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        assert_eq!(hits, vec![(2, 5.), (7, 7.)]);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::bvh::Ray;
 use crate::renderer::{Mesh, Vector, Vertex};
 use nalgebra_glm as glm;
 
@@ -57,26 +58,56 @@ impl BoundingBox {
     }
 }
 
-pub fn ray_intersect_aabb(ray: (glm::Vec3, glm::Vec3), entity: &Mesh) {
-    let (origin, distance) = ray;
-    let bv_model = entity.transform.to_model();
+/// Exact hit against the mesh triangles if its bvh was built,
+/// otherwise fallback on the mesh collider.
+/// The model is the world matrix of the mesh.
+pub fn mesh_hit(ray: &Ray, entity: &Mesh, model: &glm::Mat4) -> Option<f32> {
+    let (origin, direction) = *ray;
+
+    if let Some(bvh) = &entity.bvh {
+        // Bring the ray into the mesh local space. The direction isn't
+        // normalized again, so the distance stays the world one.
+        let inverse = glm::inverse(model);
+        let local_ray = (
+            (inverse * glm::vec4(origin.x, origin.y, origin.z, 1.)).xyz(),
+            (inverse * glm::vec4(direction.x, direction.y, direction.z, 0.))
+                .xyz(),
+        );
 
-    let mut t_min = 0.;
-    // Set the max distance ray can travel.
-    let mut t_max = 100000.;
+        return bvh.ray_intersect(&local_ray);
+    }
 
-    // Iterate over a range of 3, for 3 axis (x, y, z).
-    for i in 0..3 {}
+    let (is_hit, t) =
+        entity
+            .collider
+            .map_or((false, 0.), |collider| match &collider {
+                Collider::Plane => {
+                    plane_hit((origin, direction), entity, model)
+                }
+                Collider::Sphere => {
+                    sphere_hit((origin, direction), entity, model)
+                }
+                Collider::Cube => {
+                    intersect_ray_box((origin, direction), entity, model)
+                }
+            });
+
+    if is_hit {
+        Some(t)
+    } else {
+        None
+    }
 }
 
 pub fn intersect_ray_box(
     ray: (glm::Vec3, glm::Vec3),
     entity: &Mesh,
+    model: &glm::Mat4,
 ) -> (bool, f32) {
     let mut t_min = 0.;
     let mut t_max = 100000.;
 
-    let entity_model = entity.transform.to_model();
+    let entity_model = model;
 
     let entity_pos = glm::vec3(
         entity_model.column(3)[0],
@@ -87,7 +118,7 @@ pub fn intersect_ray_box(
     // The vector between the origin and the object position.
     // Used to compute the intersection with the plane.
     let delta = entity_pos - ray.0;
-    let scale = entity.transform.scale.to_glm();
+    let scale = world_scale(model);
 
     let (aabb_max, aabb_min) = {
         let (max, min) = (
@@ -204,12 +235,16 @@ pub fn intersect_ray_box(
     return (true, t_min);
 }
 
-pub fn sphere_hit(ray: (glm::Vec3, glm::Vec3), entity: &Mesh) -> (bool, f32) {
+pub fn sphere_hit(
+    ray: (glm::Vec3, glm::Vec3),
+    entity: &Mesh,
+    model: &glm::Mat4,
+) -> (bool, f32) {
     let bb = entity.bounding_box;
     let r = bb.max.0 - bb.center.0;
 
     let (origin, direction) = ray;
-    let mesh_pos = entity.transform.position.to_glm();
+    let mesh_pos = world_position(model);
 
     let center = mesh_pos;
 
@@ -240,14 +275,18 @@ pub fn sphere_hit(ray: (glm::Vec3, glm::Vec3), entity: &Mesh) -> (bool, f32) {
 // check if it is on the delimited bounds. So we're going to do it with
 // a circle around the center of the plane (not very precise with the edges
 // but it will be enough.
-pub fn plane_hit(ray: (glm::Vec3, glm::Vec3), entity: &Mesh) -> (bool, f32) {
+pub fn plane_hit(
+    ray: (glm::Vec3, glm::Vec3),
+    entity: &Mesh,
+    model: &glm::Mat4,
+) -> (bool, f32) {
     let mut is_hit = false;
     let mut t = 0f32;
     let (origin, direction) = ray;
 
     // This position is the center of the plane.
     let bb = entity.bounding_box;
-    let plane_pos = entity.transform.position.to_glm();
+    let plane_pos = world_position(model);
 
     // Already normalized.
     let normal = glm::vec3(0., 1., 0.);
@@ -283,3 +322,16 @@ pub fn plane_hit(ray: (glm::Vec3, glm::Vec3), entity: &Mesh) -> (bool, f32) {
 
     (is_hit, t)
 }
+
+fn world_position(model: &glm::Mat4) -> glm::Vec3 {
+    glm::vec3(model[(0, 3)], model[(1, 3)], model[(2, 3)])
+}
+
+// Length of each basis vector of the world matrix.
+fn world_scale(model: &glm::Mat4) -> glm::Vec3 {
+    glm::vec3(
+        glm::length(&model.column(0).xyz()),
+        glm::length(&model.column(1).xyz()),
+        glm::length(&model.column(2).xyz()),
+    )
+}
@@ -1,14 +1,21 @@
 mod debug_camera;
 
+use crate::bvh::Broadphase;
 use crate::colliders::*;
 use crate::entities::{Entities, Entity, Handle};
 use crate::global::*;
 use crate::input::{Input, Key, MouseButton};
 use crate::platform::WinitPlatform;
-use crate::renderer::{Mesh, Renderer, Text, Vector};
+use crate::player::Player;
+use crate::renderer::{DebugInfo, Mesh, Renderer, Text, Vector};
+use crate::tilemap::World;
 use crate::time::{Time, Timer};
 use debug_camera::Camera;
 use nalgebra_glm as glm;
+use std::fs;
+
+// Size in pixels of the tilemap thumbnails.
+const THUMBNAIL_SIZE: u32 = 256;
 
 #[derive(PartialEq, Debug)]
 enum ObjectTransformMode {
@@ -22,6 +29,13 @@ pub struct Editor {
     timer: Timer,
     object_mode: ObjectTransformMode,
     selected_handle: Option<Handle<Mesh>>,
+    hovered_handles: Vec<Handle<Mesh>>,
+    // Scene boxes used to only test meshes crossed by the mouse ray.
+    broadphase: Broadphase<Handle<Mesh>>,
+    // All the meshes at the last rebuild.
+    broadphase_meshes: Vec<Handle<Mesh>>,
+    // World matrices of the meshes in the broadphase at the last sync.
+    synced: Vec<(Handle<Mesh>, glm::Mat4)>,
 }
 
 impl Editor {
@@ -31,9 +45,54 @@ impl Editor {
             camera: Camera::default(),
             object_mode: ObjectTransformMode::Position,
             selected_handle: None,
+            hovered_handles: vec![],
+            broadphase: Broadphase::default(),
+            broadphase_meshes: vec![],
+            synced: vec![],
         }
     }
 
+    /// Mesh picked with the mouse, if any.
+    pub fn selected(&self) -> Option<Handle<Mesh>> {
+        self.selected_handle
+    }
+
+    /// Rebuild the broadphase when meshes were added or removed, else
+    /// refit the meshes moved since the last sync, by the editor or by
+    /// the game.
+    fn sync_broadphase(&mut self, entities: &Entities) {
+        // Handles of reused slots differ by their version.
+        let meshes: Vec<Handle<Mesh>> =
+            entities.meshes.iter().map(|(_, handle)| *handle).collect();
+        if meshes == self.broadphase_meshes && !self.broadphase.is_empty() {
+            for (handle, synced) in self.synced.iter_mut() {
+                let model = entities.world_matrix(handle);
+                if model != *synced {
+                    let aabb = entities.get(handle).world_aabb(&model);
+                    self.broadphase.update(handle, aabb);
+                    *synced = model;
+                }
+            }
+            return;
+        }
+
+        self.synced = entities
+            .meshes
+            .iter()
+            .filter(|(mesh, _)| mesh.collider.is_some() || mesh.bvh.is_some())
+            .map(|(_, handle)| (*handle, entities.world_matrix(handle)))
+            .collect();
+        self.broadphase = Broadphase::build(
+            self.synced
+                .iter()
+                .map(|(handle, model)| {
+                    (*handle, entities.get(handle).world_aabb(model))
+                })
+                .collect(),
+        );
+        self.broadphase_meshes = meshes;
+    }
+
     pub fn run(
         &mut self,
         entities: &mut Entities,
@@ -46,6 +105,28 @@ impl Editor {
             renderer.toggle_wireframe();
         };
 
+        if input.modifiers.shift && input.is_pressed_once(Key::M) {
+            renderer.toggle_shadow_map();
+        };
+
+        // Shift + 1..6 toggles the post passes.
+        let pass_keys = [
+            Key::Key1,
+            Key::Key2,
+            Key::Key3,
+            Key::Key4,
+            Key::Key5,
+            Key::Key6,
+        ];
+        for (key, (_, is_enabled)) in pass_keys
+            .iter()
+            .zip(renderer.post_settings_mut().passes_mut().iter_mut())
+        {
+            if input.modifiers.shift && input.is_pressed_once(*key) {
+                **is_enabled = !**is_enabled;
+            }
+        }
+
         if input.is_pressed_once(Key::J) {
             dbg!(self.camera.front);
             dbg!(self.camera.position);
@@ -73,26 +154,60 @@ impl Editor {
             let content = format!("Frame: {} ms", (time.dt * 1000.).round());
             entities.insert(Text {
                 position: Vector(x, y * 0.9, 0.),
-                font_size: 31.,
+                font_size: 23.,
                 content,
+                is_dynamic: true,
                 ..Text::default()
             });
 
             let content =
-                format!("Meshes rendered: {}", renderer.debug_info.draw_call);
+                format!("Draw calls: {}", renderer.debug_info.draw_call);
             entities.insert(Text {
                 position: Vector(x, y * 0.86, 0.),
-                font_size: 31.,
+                font_size: 23.,
                 content,
+                is_dynamic: true,
                 ..Text::default()
             });
 
             entities.insert(Text {
                 position: Vector(x, y * 0.82, 0.),
-                font_size: 31.,
+                font_size: 23.,
                 content: format!("Mode: {:?}", self.object_mode),
                 ..Text::default()
             });
+
+            let DebugInfo {
+                tilemaps, meshes, ..
+            } = renderer.debug_info;
+            let content = format!(
+                "Culled: {}/{} tilemaps, {}/{} meshes",
+                tilemaps.culled,
+                tilemaps.visible + tilemaps.culled,
+                meshes.culled,
+                meshes.visible + meshes.culled,
+            );
+            entities.insert(Text {
+                position: Vector(x, y * 0.78, 0.),
+                font_size: 23.,
+                content,
+                is_dynamic: true,
+                ..Text::default()
+            });
+
+            let passes: Vec<&str> = renderer
+                .post_settings_mut()
+                .passes_mut()
+                .iter()
+                .filter(|(_, is_enabled)| **is_enabled)
+                .map(|(name, _)| *name)
+                .collect();
+            entities.insert(Text {
+                position: Vector(x, y * 0.74, 0.),
+                font_size: 23.,
+                content: format!("Post: {}", passes.join(", ")),
+                ..Text::default()
+            });
         }
 
         if input.is_clicked(MouseButton::Right) {
@@ -129,61 +244,70 @@ impl Editor {
             let origin = cam_pos;
             let direction = glm::normalize(&ray_world);
 
-            // All meshes
-            let entity_handles: Vec<Handle<Mesh>> =
-                entities.meshes.iter().map(|(_, h)| *h).collect();
-
-            let mut hit_array: Vec<(Handle<Mesh>, f32)> = vec![];
+            let ray = (origin, direction);
+            entities.update_scene();
+            self.sync_broadphase(entities);
 
             let mouse_is_hold = input.is_clicked(MouseButton::Left);
             let mouse_is_down = input.is_clicked_once(MouseButton::Left);
 
-            for handle in entity_handles {
-                let entity = entities.get_mut(&handle);
-                let is_current =
-                    self.selected_handle.map_or(false, |sh| sh == handle);
-
-                ray_intersect_aabb((origin, direction), entity);
-                let (is_hit, t) =
-                    entity.collider.map_or((false, 0.), |collider| {
-                        match &collider {
-                            Collider::Plane => {
-                                plane_hit((origin, direction), entity)
-                            }
-                            Collider::Sphere => {
-                                sphere_hit((origin, direction), entity)
-                            }
-                            Collider::Cube => {
-                                intersect_ray_box((origin, direction), entity)
-                            }
-                        }
-                    });
+            // Exact test only on meshes crossed by the ray.
+            let mut hit_array: Vec<(Handle<Mesh>, f32)> = self
+                .broadphase
+                .query_ray(&ray)
+                .into_iter()
+                .filter_map(|(handle, _)| {
+                    let model = entities.world_matrix(&handle);
+                    mesh_hit(&ray, entities.get(&handle), &model)
+                        .map(|t| (handle, t))
+                })
+                .collect();
 
-                // Hover objects.
-                entity.is_hover = !is_current && is_hit;
+            // Sort by nearest.
+            hit_array.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap());
 
-                if !is_hit && is_current && mouse_is_down {
-                    self.selected_handle = None;
-                }
+            // Hover objects.
+            for handle in self.hovered_handles.drain(..) {
+                entities.get_mut(&handle).is_hover = false;
+            }
 
-                if is_hit && mouse_is_hold {
-                    hit_array.push((handle, t));
-                } else {
-                    entity.is_selected = false;
+            for (handle, _) in hit_array.iter() {
+                if self.selected_handle != Some(*handle) {
+                    entities.get_mut(handle).is_hover = true;
+                    self.hovered_handles.push(*handle);
                 }
             }
 
-            // Sort by nearest.
-            hit_array.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap());
+            let is_current_hit = hit_array
+                .iter()
+                .any(|(h, _)| self.selected_handle == Some(*h));
+
+            if !is_current_hit && mouse_is_down {
+                if let Some(handle) = self.selected_handle.take() {
+                    entities.get_mut(&handle).is_selected = false;
+                }
+            }
 
             // If no current selected, we take the nearest.
-            if self.selected_handle.is_none() && !hit_array.is_empty() {
+            if self.selected_handle.is_none()
+                && mouse_is_hold
+                && !hit_array.is_empty()
+            {
                 self.selected_handle = Some(hit_array[0].0);
             }
 
             if let Some(handle) = self.selected_handle {
                 self.selected_handle = Some(handle);
 
+                // Shift + K attaches the selection to the nearest hovered
+                // mesh, or detaches it when nothing is hovered.
+                if input.modifiers.shift && input.is_pressed_once(Key::K) {
+                    let parent = self.hovered_handles.first().copied();
+                    if let Err(e) = entities.set_parent(&handle, parent) {
+                        dbg!(e);
+                    }
+                }
+
                 let entity = entities.get_mut(&handle);
                 entity.is_selected = true;
 
@@ -204,19 +328,18 @@ impl Editor {
 
                         if input.modifiers.shift {
                             pos_ptr.1 -= delta_y;
-                            return;
+                        } else {
+                            pos_ptr.0 += delta_x;
+                            pos_ptr.2 += delta_y;
                         }
-
-                        pos_ptr.0 += delta_x;
-                        pos_ptr.2 += delta_y;
                     }
                     ObjectTransformMode::Rotation => {
-                        let rotation_ptr = &mut entity.transform.rotation;
+                        let transform = &mut entity.transform;
                         if input.modifiers.shift {
-                            rotation_ptr.0 += delta_y;
-                            return;
+                            transform.rotate(Vector(1., 0., 0.), delta_y);
+                        } else {
+                            transform.rotate(Vector(0., 0., 1.), delta_x);
                         }
-                        rotation_ptr.2 += delta_x;
                     }
                     ObjectTransformMode::Scale => {
                         let scale_ptr = &mut entity.transform.scale;
@@ -229,3 +352,39 @@ impl Editor {
         }
     }
 }
+
+/// Save a top down image of each tilemap of the world grid, named
+/// after its position in the grid.
+pub fn save_thumbnails(
+    renderer: &mut Renderer,
+    entities: &Entities,
+    world: &World,
+    player: &Player,
+) {
+    let dir = format!("{}thumbnails/", TILEMAPS_DIR_PATH);
+    if let Err(e) = fs::create_dir_all(&dir) {
+        dbg!(e);
+        return;
+    }
+
+    for (y, row) in world.grid.iter().enumerate() {
+        for (x, handle) in row.iter().enumerate() {
+            if let Some(handle) = handle {
+                let pos = glm::vec2(x as i32, y as i32);
+                let thumbnail = renderer.tilemap_thumbnail(
+                    entities,
+                    world,
+                    player,
+                    (*handle, pos),
+                    THUMBNAIL_SIZE,
+                );
+
+                if let Err(e) =
+                    thumbnail.save(format!("{}{}_{}.png", dir, x, y))
+                {
+                    dbg!(e);
+                }
+            }
+        }
+    }
+}
@@ -1,7 +1,11 @@
-use crate::renderer::{LightProbes, Mesh, Text};
+use crate::animation::ModelAnimation;
+use crate::renderer::{LightProbes, Material, Mesh, Model, Text, Transform};
+use crate::scene_graph::{SceneGraph, SceneGraphError};
 use crate::tilemap::Tilemap;
 use crate::wall::Wall;
+use nalgebra_glm as glm;
 use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
 use std::iter::Iterator;
 use std::marker::PhantomData;
 use std::mem::*;
@@ -24,8 +28,10 @@ pub struct Markers {
 pub struct Entities {
     pub light_probes: Arena<LightProbes>,
     pub meshes: Arena<Mesh>,
+    pub materials: Arena<Material>,
     pub walls: Arena<Wall>,
     pub tilemaps: Arena<Tilemap>,
+    pub animations: Arena<ModelAnimation>,
 
     // Widgets.
     pub text_widgets: Arena<Text>,
@@ -33,6 +39,92 @@ pub struct Entities {
     // Some useful handle to remember.
     // This should become "asset" with enum/hashmap instead.
     pub markers: Option<Markers>,
+
+    // World transforms of meshes, composed from their parents.
+    pub scene: SceneGraph<Mesh>,
+}
+
+impl Entities {
+    /// Recompute world matrices of meshes whose transform
+    /// or parent changed since the last update.
+    pub fn update_scene(&mut self) {
+        let meshes = &self.meshes;
+        self.scene.update(
+            meshes
+                .iter()
+                .map(|(mesh, handle)| (*handle, mesh.transform, mesh.parent)),
+        );
+    }
+
+    /// World matrix of the mesh, from the scene graph.
+    pub fn world_matrix(&self, handle: &Handle<Mesh>) -> glm::Mat4 {
+        self.scene
+            .world_matrix(handle)
+            .unwrap_or_else(|| self.get(handle).transform.to_model())
+    }
+
+    pub fn mesh_material(&self, mesh: &Mesh) -> Option<&Material> {
+        mesh.material.as_ref().map(|handle| self.get(handle))
+    }
+
+    /// Play the model animations, before drawing.
+    pub fn animate(&mut self, dt: f32) {
+        let handles: Vec<Handle<ModelAnimation>> =
+            self.animations.iter().map(|(_, handle)| *handle).collect();
+
+        for handle in handles.iter() {
+            let animation = self.animations.get_mut(handle);
+            animation.update(dt);
+            animation.apply(&mut self.meshes);
+        }
+    }
+
+    /// Insert all meshes of a model, linked to their parents, and
+    /// the materials they share. Its clips are played with the
+    /// other animations (see `Entities::animate`).
+    /// Roots are placed with the given transform.
+    pub fn insert_model(
+        &mut self,
+        mut model: Model,
+        transform: Transform,
+    ) -> Vec<Handle<Mesh>> {
+        let animation = ModelAnimation::from_model(&mut model, &transform);
+        let materials: Vec<Handle<Material>> = take(&mut model.materials)
+            .into_iter()
+            .map(|material| self.materials.insert(material.load_gl()))
+            .collect();
+        let mut handles: Vec<Handle<Mesh>> = vec![];
+
+        for (mut mesh, parent) in model.into_meshes(transform, &materials) {
+            mesh.parent = parent.map(|index| handles[index]);
+            handles.push(self.meshes.insert(mesh));
+        }
+
+        if let Some(animation) = animation {
+            self.animations
+                .insert(animation.with_meshes(handles.clone()));
+        }
+
+        handles
+    }
+
+    /// Attach (or detach with `None`) a mesh to a new parent,
+    /// keeping its current world position.
+    pub fn set_parent(
+        &mut self,
+        child: &Handle<Mesh>,
+        parent: Option<Handle<Mesh>>,
+    ) -> Result<(), SceneGraphError> {
+        self.update_scene();
+        let transform = self.scene.reparent(child, parent)?;
+
+        let mesh = self.meshes.get_mut(child);
+        mesh.parent = parent;
+        mesh.transform = transform;
+
+        self.update_scene();
+        Ok(())
+    }
 }
 
 impl Entity<Mesh> for Entities {
@@ -53,6 +145,24 @@ impl Entity<Mesh> for Entities {
     }
 }
 
+impl Entity<Material> for Entities {
+    fn get(&self, handle: &Handle<Material>) -> &Material {
+        self.materials.get(handle)
+    }
+
+    fn get_mut(&mut self, handle: &Handle<Material>) -> &mut Material {
+        self.materials.get_mut(handle)
+    }
+
+    fn insert(&mut self, value: Material) -> Handle<Material> {
+        self.materials.insert(value)
+    }
+
+    fn remove(&mut self, handle: Handle<Material>) {
+        self.materials.remove(handle);
+    }
+}
+
 impl Entity<Tilemap> for Entities {
     fn get(&self, handle: &Handle<Tilemap>) -> &Tilemap {
         self.tilemaps.get(handle)
@@ -294,7 +404,7 @@ impl<T: Debug> Default for Arena<T> {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct Handle<T: ?Sized> {
     value: usize,
     version: usize,
@@ -321,12 +431,28 @@ impl<T> Handle<T> {
     }
 }
 
+// Not derived, it would require the type itself to be default.
+impl<T> Default for Handle<T> {
+    fn default() -> Self {
+        Self::new(0, 0)
+    }
+}
+
 impl<T> PartialEq for Handle<T> {
     fn eq(&self, other: &Self) -> bool {
         self.value == other.value && self.version == other.version
     }
 }
 
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.hash(state);
+        self.version.hash(state);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
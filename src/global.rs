@@ -22,13 +22,17 @@ lazy_static! {
 // Path stuff.
 pub static WORLD_FILE_PATH: &str = "assets/maps/world.json";
 pub static TILEMAPS_DIR_PATH: &str = "assets/maps/";
+pub static SCREENSHOTS_DIR_PATH: &str = "screenshots/";
+pub static SHADERS_DIR_PATH: &str = "assets/shaders/";
 
 // Window stuff.
 pub static mut SCREEN_WIDTH: f32 = 1200.;
 pub static mut SCREEN_HEIGHT: f32 = 800.;
 pub static mut SCREEN_DPI: u32 = 2;
 pub static WITH_VSYNC: bool = false;
-pub static MULTISAMPLING: u16 = 8;
+// Of the window and of the scene (see `PostSettings::msaa`).
+pub static MULTISAMPLING: u16 = 4;
+pub static SCREENSHOT_SIZE: (u32, u32) = (2400, 1600);
 pub const GAME_NAME: &str = "Boreal Kiss";
 
 // Map stuff
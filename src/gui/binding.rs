@@ -0,0 +1,110 @@
+use super::{Status, Widget};
+
+/// Shown or edited by a widget, like the state of a checkbox or the
+/// content of a text input.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Number(f32),
+    Index(usize),
+    Text(String),
+}
+
+/// Sent to the game loop by the widgets having an id.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Clicked(String),
+    // With the new value of the widget.
+    Changed(String, Value),
+}
+
+impl Message {
+    pub(super) fn new(status: Status, widget: &dyn Widget) -> Option<Self> {
+        let id = widget.state().id.clone()?;
+        match status {
+            Status::Clicked => Some(Message::Clicked(id)),
+            Status::Changed => {
+                widget.value().map(|value| Message::Changed(id, value))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Game values bound to the widgets, converted from the values of
+/// the other types when it makes sense.
+pub trait Bindable: Sized {
+    fn to_value(&self) -> Value;
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+impl Bindable for f32 {
+    fn to_value(&self) -> Value {
+        Value::Number(*self)
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Number(n) => Some(*n),
+            Value::Index(i) => Some(*i as f32),
+            Value::Text(text) => text.trim().parse().ok(),
+            Value::Bool(_) => None,
+        }
+    }
+}
+
+impl Bindable for bool {
+    fn to_value(&self) -> Value {
+        Value::Bool(*self)
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+impl Bindable for usize {
+    fn to_value(&self) -> Value {
+        Value::Index(*self)
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Index(i) => Some(*i),
+            _ => None,
+        }
+    }
+}
+
+impl Bindable for String {
+    fn to_value(&self) -> Value {
+        Value::Text(self.clone())
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Text(text) => Some(text.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            Value::Index(i) => Some(i.to_string()),
+            Value::Bool(b) => Some(b.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_values() {
+        assert_eq!(f32::from_value(&Value::Text(" 1.5".into())), Some(1.5));
+        assert_eq!(f32::from_value(&Value::Text("1.5a".into())), None);
+        assert_eq!(f32::from_value(&Value::Bool(true)), None);
+        assert_eq!(String::from_value(&Value::Number(2.)), Some("2".into()));
+        assert_eq!(usize::from_value(&3.to_value()), Some(3));
+        assert_eq!(bool::from_value(&Value::Index(1)), None);
+    }
+}
@@ -0,0 +1,371 @@
+use super::{Element, Message, Widget};
+use crate::input::{Key, MouseButton};
+
+/// Indices of the widgets from the root of the gui to the widget.
+pub type Path = Vec<usize>;
+
+/// Events routed to the widgets by the gui, positions are from the
+/// bottom left corner of the screen.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    MouseDown(MouseButton, (f32, f32)),
+    // Sent to the pressed widget, even when released outside.
+    MouseUp(MouseButton, (f32, f32)),
+    MouseMove((f32, f32)),
+    // In wheel lines, up is positive.
+    Scroll(f32),
+    // Sent to the focused widget, like the texts.
+    KeyDown(Key),
+    Text(String),
+    Focus,
+    Blur,
+}
+
+/// Consumed events don't bubble to the parents, and they aren't seen
+/// by the game.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Status {
+    Ignored,
+    Consumed,
+    // Consumed, and sent to the game as a message if the widget has
+    // an id.
+    Clicked,
+    Changed,
+}
+
+impl Status {
+    pub fn is_consumed(self) -> bool {
+        self != Status::Ignored
+    }
+}
+
+/// Set by the gui, id, class and disabled excepted, the widgets draw
+/// from it.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct State {
+    // Used by the game to find the widget and in its messages.
+    pub id: Option<String>,
+    // Of the theme, see `Styled::class`.
+    pub class: Option<String>,
+    pub is_hover: bool,
+    pub is_pressed: bool,
+    pub is_focused: bool,
+    pub is_disabled: bool,
+}
+
+pub(super) fn widget<'a>(
+    elements: &'a [Element],
+    path: &[usize],
+) -> Option<&'a dyn Widget> {
+    let (first, rest) = path.split_first()?;
+    let widget = elements.get(*first)?.widget.as_ref();
+    if rest.is_empty() {
+        Some(widget)
+    } else {
+        self::widget(widget.children(), rest)
+    }
+}
+
+pub(super) fn widget_mut<'a>(
+    elements: &'a mut [Element],
+    path: &[usize],
+) -> Option<&'a mut dyn Widget> {
+    let (first, rest) = path.split_first()?;
+    let widget = elements.get_mut(*first)?.widget.as_mut();
+    if rest.is_empty() {
+        Some(widget)
+    } else {
+        widget_mut(widget.children_mut(), rest)
+    }
+}
+
+/// Deepest widget under the point, the last drawn first. Children of
+/// the clipping widgets are only hit inside their parent.
+pub(super) fn hit(elements: &[Element], point: (f32, f32)) -> Option<Path> {
+    for (i, elem) in elements.iter().enumerate().rev() {
+        let widget = elem.widget.as_ref();
+        let is_inside = widget.contains(point);
+
+        if is_inside || !widget.clips_children() {
+            if let Some(mut path) = hit(widget.children(), point) {
+                path.insert(0, i);
+                return Some(path);
+            }
+        }
+        if is_inside {
+            return Some(vec![i]);
+        }
+    }
+
+    None
+}
+
+/// Give the event to the widget then to its parents, until one of
+/// them consumes it. Disabled widgets consume it without reacting.
+pub(super) fn dispatch(
+    elements: &mut [Element],
+    path: &[usize],
+    event: &Event,
+    messages: &mut Vec<Message>,
+) -> Status {
+    for depth in (1..=path.len()).rev() {
+        let widget = match widget_mut(elements, &path[..depth]) {
+            Some(widget) => widget,
+            None => continue,
+        };
+        if widget.state().is_disabled {
+            return Status::Consumed;
+        }
+
+        let status = widget.on_event(event);
+        messages.extend(Message::new(status, widget));
+        if status.is_consumed() {
+            return status;
+        }
+    }
+
+    Status::Ignored
+}
+
+/// First widget with the id, parents before their children.
+pub(super) fn find(elements: &[Element], id: &str) -> Option<Path> {
+    for (i, elem) in elements.iter().enumerate() {
+        let widget = elem.widget.as_ref();
+        if widget.state().id.as_deref() == Some(id) {
+            return Some(vec![i]);
+        }
+        if let Some(mut path) = find(widget.children(), id) {
+            path.insert(0, i);
+            return Some(path);
+        }
+    }
+
+    None
+}
+
+/// Set a flag of the state on the widget and its parents.
+pub(super) fn set_state(
+    elements: &mut [Element],
+    path: &[usize],
+    set: impl Fn(&mut State),
+) {
+    for depth in 1..=path.len() {
+        if let Some(widget) = widget_mut(elements, &path[..depth]) {
+            set(widget.state_mut());
+        }
+    }
+}
+
+/// Focusable widgets in the order of the tab navigation, the disabled
+/// ones and their children are skipped.
+pub(super) fn focus_order(elements: &[Element], parent: &[usize]) -> Vec<Path> {
+    let mut order = vec![];
+    for (i, elem) in elements.iter().enumerate() {
+        let widget = elem.widget.as_ref();
+        if widget.state().is_disabled {
+            continue;
+        }
+
+        let mut path = parent.to_vec();
+        path.push(i);
+        if widget.is_focusable() {
+            order.push(path.clone());
+        }
+        order.extend(focus_order(widget.children(), &path));
+    }
+
+    order
+}
+
+/// Next focusable widget after the focused one, or before it. It
+/// wraps around.
+pub(super) fn next_focus(
+    order: &[Path],
+    focused: Option<&Path>,
+    is_backward: bool,
+) -> Option<Path> {
+    if order.is_empty() {
+        return None;
+    }
+
+    let len = order.len();
+    let current = focused.and_then(|f| order.iter().position(|p| p == f));
+    let next = match (current, is_backward) {
+        (None, false) => 0,
+        (None, true) => len - 1,
+        (Some(i), false) => (i + 1) % len,
+        (Some(i), true) => (i + len - 1) % len,
+    };
+
+    Some(order[next].clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::global::SCREEN_HEIGHT;
+    use crate::gui::{
+        Button, Checkbox, Container, Slider, Styled, TextInput, Value, GUI,
+    };
+    use crate::input::{Cursor, Input};
+    use crate::renderer::{Font, RenderQueue, Text};
+
+    // A text input, a button, a disabled checkbox and a slider in a row
+    // at the top of the screen.
+    fn form() -> GUI {
+        let row = Container::row()
+            .push(TextInput::new().id("name"))
+            .push(Button::new(Text::new("ok")).id("ok"))
+            .push(Checkbox::new(Text::new("off")).id("off").disabled(true))
+            .push(Slider::new(0., 1.));
+        let mut gui = GUI::new().add_elem(row);
+
+        let font = Font::new(
+            "assets/fonts/Helvetica/helvetica.json",
+            "assets/fonts/Helvetica/helvetica.png",
+        );
+        gui.draw(&mut RenderQueue::default(), &font);
+        gui
+    }
+
+    // From the bottom left of the screen, like the widgets.
+    fn move_cursor(input: &mut Input, x: f32, y: f32) {
+        let y = unsafe { SCREEN_HEIGHT } - y;
+        input.update_cursor_position(Cursor {
+            position: (f64::from(x), f64::from(y)),
+            has_moved: true,
+            ..Cursor::default()
+        });
+    }
+
+    fn tap(gui: &mut GUI, input: &mut Input, key: Key) {
+        input.update_key(key, true);
+        gui.on_event(input);
+        input.update_key(key, false);
+        gui.on_event(input);
+    }
+
+    fn center(gui: &GUI, path: &[usize]) -> (f32, f32) {
+        let rect = widget(&gui.elements, path).unwrap().get_rect();
+        (rect.x + rect.width * 0.5, rect.y + rect.height * 0.5)
+    }
+
+    #[test]
+    fn consume_clicks_on_widgets() {
+        let mut gui = form();
+        let mut input = Input::new();
+
+        let (x, y) = center(&gui, &[0, 1]);
+        move_cursor(&mut input, x, y);
+        input.update_mouse(MouseButton::Left, true);
+        gui.on_event(&mut input);
+        let button = widget(&gui.elements, &[0, 1]).unwrap();
+        assert!(button.state().is_hover && button.state().is_pressed);
+        // The game doesn't see it, the button is clicked on release.
+        assert!(!input.is_clicked_once(MouseButton::Left));
+        assert!(gui.messages().is_empty());
+        input.update_mouse(MouseButton::Left, false);
+        gui.on_event(&mut input);
+        assert_eq!(gui.messages(), [Message::Clicked("ok".into())]);
+        assert_eq!(gui.focused, Some(vec![0, 1]));
+
+        // Disabled widgets swallow the clicks.
+        let (x, y) = center(&gui, &[0, 2]);
+        move_cursor(&mut input, x, y);
+        input.update_mouse(MouseButton::Left, true);
+        gui.on_event(&mut input);
+        assert!(!input.is_clicked(MouseButton::Left));
+        assert_eq!(gui.focused, None);
+        input.update_mouse(MouseButton::Left, false);
+        gui.on_event(&mut input);
+
+        // Nothing under the cursor, the game gets it.
+        move_cursor(&mut input, 600., 300.);
+        input.update_mouse(MouseButton::Left, true);
+        gui.on_event(&mut input);
+        assert_eq!(gui.hovered, None);
+        assert!(input.is_clicked_once(MouseButton::Left));
+        let button = widget(&gui.elements, &[0, 1]).unwrap();
+        assert!(!button.state().is_hover);
+    }
+
+    #[test]
+    fn navigate_with_tab() {
+        let mut gui = form();
+        let mut input = Input::new();
+
+        tap(&mut gui, &mut input, Key::Tab);
+        assert_eq!(gui.focused, Some(vec![0, 0]));
+        tap(&mut gui, &mut input, Key::Tab);
+        assert_eq!(gui.focused, Some(vec![0, 1]));
+        // The disabled checkbox is skipped, then it wraps around.
+        tap(&mut gui, &mut input, Key::Tab);
+        assert_eq!(gui.focused, Some(vec![0, 3]));
+        tap(&mut gui, &mut input, Key::Tab);
+        assert_eq!(gui.focused, Some(vec![0, 0]));
+        input.modifiers.shift = true;
+        tap(&mut gui, &mut input, Key::Tab);
+        assert_eq!(gui.focused, Some(vec![0, 3]));
+        tap(&mut gui, &mut input, Key::Tab);
+        tap(&mut gui, &mut input, Key::Tab);
+        input.modifiers.shift = false;
+        assert_eq!(gui.focused, Some(vec![0, 0]));
+        let text_input = widget(&gui.elements, &[0, 0]).unwrap();
+        assert!(text_input.state().is_focused);
+
+        // Typed in the focused input, the player doesn't move.
+        input.update_key(Key::W, true);
+        gui.on_event(&mut input);
+        assert!(!input.is_pressed(Key::W));
+        input.update_key(Key::W, false);
+        gui.on_event(&mut input);
+        let typed = Message::Changed("name".into(), Value::Text("w".into()));
+        assert_eq!(gui.messages(), [typed]);
+
+        // Released for the game once pressed again without focus.
+        gui.set_focus(None);
+        input.update_key(Key::W, true);
+        gui.on_event(&mut input);
+        assert!(input.is_pressed(Key::W));
+    }
+
+    #[test]
+    fn bind_game_values() {
+        let mut gui = form();
+        let mut input = Input::new();
+        let mut name = String::from("kiss");
+        let mut is_off = false;
+
+        // Shown by the widgets, until edited.
+        gui.bind("name", &mut name);
+        gui.bind("off", &mut is_off);
+        assert_eq!(gui.value("name"), Some(Value::Text("kiss".into())));
+        assert_eq!(gui.value("off"), Some(Value::Bool(false)));
+        assert_eq!(gui.value("unknown"), None);
+
+        tap(&mut gui, &mut input, Key::Tab);
+        input.update_key(Key::Bspc, true);
+        gui.on_event(&mut input);
+        gui.bind("name", &mut name);
+        assert_eq!(name, "kis");
+
+        // Changed elsewhere, not shown while it's focused.
+        name = String::from("boreal");
+        gui.on_event(&mut input);
+        gui.bind("name", &mut name);
+        assert_eq!(gui.value("name"), Some(Value::Text("kis".into())));
+        gui.set_focus(None);
+        gui.bind("name", &mut name);
+        assert_eq!(gui.value("name"), Some(Value::Text("boreal".into())));
+    }
+
+    #[test]
+    fn next_focus_wraps() {
+        let order = vec![vec![0], vec![1, 0], vec![2]];
+        assert_eq!(next_focus(&order, None, false), Some(vec![0]));
+        assert_eq!(next_focus(&order, None, true), Some(vec![2]));
+        assert_eq!(next_focus(&order, Some(&vec![2]), false), Some(vec![0]));
+        assert_eq!(next_focus(&order, Some(&vec![0]), true), Some(vec![2]));
+        assert_eq!(next_focus(&[], None, false), None);
+    }
+}
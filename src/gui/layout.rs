@@ -0,0 +1,437 @@
+use super::Styles;
+use serde::Deserialize;
+
+/// Size of a widget along an axis.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Size {
+    // Fits the content.
+    Auto,
+    Px(f32),
+    // Of the content box of the parent.
+    Percent(f32),
+}
+
+/// Main axis of a container, children are stacked along it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Direction {
+    // From left to right.
+    Row,
+    // From top to bottom.
+    Col,
+}
+
+/// Where children are put along the main axis, when they don't fill
+/// it.
+#[allow(unused)]
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Justify {
+    Start,
+    Center,
+    End,
+    SpaceBetween,
+    SpaceAround,
+}
+
+/// Where children are put along the cross axis.
+#[allow(unused)]
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Align {
+    Start,
+    Center,
+    End,
+    // As large as the container, for the `Auto` sizes.
+    Stretch,
+}
+
+/// Screen rectangle in pixels, from its bottom left corner.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn top(&self) -> f32 {
+        self.y + self.height
+    }
+
+    pub fn contains(&self, point: (f32, f32)) -> bool {
+        point.0 >= self.x
+            && point.0 <= self.x + self.width
+            && point.1 >= self.y
+            && point.1 <= self.top()
+    }
+
+    /// Smaller by the amount on each side.
+    pub fn shrink(&self, amount: f32) -> Self {
+        Self {
+            x: self.x + amount,
+            y: self.y + amount,
+            width: (self.width - 2. * amount).max(0.),
+            height: (self.height - 2. * amount).max(0.),
+        }
+    }
+}
+
+/// Child of a container, with the size of its content.
+pub struct FlexItem<'a> {
+    pub styles: &'a Styles,
+    pub content: (f32, f32),
+}
+
+// Size of a box along an axis.
+#[derive(Debug, Copy, Clone)]
+struct Extent {
+    size: Size,
+    min: f32,
+    max: f32,
+    content: f32,
+}
+
+impl Extent {
+    fn resolve(&self, available: f32) -> f32 {
+        let size = match self.size {
+            Size::Auto => self.content,
+            Size::Px(size) => size,
+            Size::Percent(percent) => available * percent * 0.01,
+        };
+        self.clamp(size)
+    }
+
+    // Percents of an unknown size fit the content.
+    fn hypothetical(&self) -> f32 {
+        match self.size {
+            Size::Px(size) => self.clamp(size),
+            _ => self.clamp(self.content),
+        }
+    }
+
+    // The min wins over the max.
+    fn clamp(&self, size: f32) -> f32 {
+        size.min(self.max).max(self.min)
+    }
+}
+
+// Pair along the main then the cross axis, or back.
+fn swap<T>(direction: Direction, (a, b): (T, T)) -> (T, T) {
+    match direction {
+        Direction::Row => (a, b),
+        Direction::Col => (b, a),
+    }
+}
+
+// Main and cross extents of an item.
+fn extents(item: &FlexItem, direction: Direction) -> (Extent, Extent) {
+    let styles = item.styles;
+    let (width, height) = (
+        Extent {
+            size: styles.width,
+            min: styles.min_width,
+            max: styles.max_width,
+            content: item.content.0,
+        },
+        Extent {
+            size: styles.height,
+            min: styles.min_height,
+            max: styles.max_height,
+            content: item.content.1,
+        },
+    );
+
+    swap(direction, (width, height))
+}
+
+/// Size of a container fitting its children, padding included.
+pub fn measure(styles: &Styles, items: &[FlexItem]) -> (f32, f32) {
+    let (main, cross) =
+        items
+            .iter()
+            .fold((0., 0.), |(main, cross): (f32, f32), item| {
+                let (item_main, item_cross) = extents(item, styles.direction);
+                let margin = 2. * item.styles.margin;
+                (
+                    main + item_main.hypothetical() + margin,
+                    cross.max(item_cross.hypothetical() + margin),
+                )
+            });
+    let (width, height) = swap(styles.direction, (main, cross));
+
+    (width + 2. * styles.padding, height + 2. * styles.padding)
+}
+
+/// Boxes of the children in the box of their container, margins
+/// excluded. Children grow to fill the free space of the main axis or
+/// shrink to fit in it, by their factors and within their min and
+/// max sizes.
+pub fn flex(styles: &Styles, rect: Rect, items: &[FlexItem]) -> Vec<Rect> {
+    let direction = styles.direction;
+    let inner = rect.shrink(styles.padding);
+    let (available_main, available_cross) =
+        swap(direction, (inner.width, inner.height));
+    let extents: Vec<(Extent, Extent)> =
+        items.iter().map(|item| extents(item, direction)).collect();
+    let margins: f32 = items.iter().map(|item| 2. * item.styles.margin).sum();
+
+    let bases: Vec<f32> = extents
+        .iter()
+        .map(|(main, _)| main.resolve(available_main))
+        .collect();
+    let is_growing = available_main - margins - bases.iter().sum::<f32>() > 0.;
+    let factors: Vec<f32> = items
+        .iter()
+        .zip(&bases)
+        .map(|(item, base)| {
+            if is_growing {
+                item.styles.grow
+            } else {
+                // Larger items shrink more.
+                item.styles.shrink * base
+            }
+        })
+        .collect();
+
+    // Items are frozen at their size once it's within their min and
+    // max, the others share what's left.
+    let mut sizes = bases.clone();
+    let mut frozen: Vec<bool> = factors.iter().map(|f| *f <= 0.).collect();
+    while frozen.contains(&false) {
+        let used: f32 = (0..items.len())
+            .map(|i| if frozen[i] { sizes[i] } else { bases[i] })
+            .sum();
+        let free = available_main - margins - used;
+        let total: f32 = (0..items.len())
+            .filter(|i| !frozen[*i])
+            .map(|i| factors[i])
+            .sum();
+
+        let mut violations = vec![];
+        for i in (0..items.len()).filter(|i| !frozen[*i]) {
+            let target = bases[i] + free * factors[i] / total;
+            sizes[i] = extents[i].0.clamp(target);
+            violations.push((i, sizes[i] - target));
+        }
+
+        let violation: f32 = violations.iter().map(|(_, v)| v).sum();
+        for (i, v) in violations {
+            frozen[i] = violation == 0. || v * violation > 0.;
+        }
+    }
+
+    let count = items.len() as f32;
+    let leftover =
+        (available_main - margins - sizes.iter().sum::<f32>()).max(0.);
+    let (mut main, gap) = match styles.justify {
+        Justify::Start => (0., 0.),
+        Justify::Center => (leftover * 0.5, 0.),
+        Justify::End => (leftover, 0.),
+        Justify::SpaceBetween if count > 1. => (0., leftover / (count - 1.)),
+        Justify::SpaceBetween => (0., 0.),
+        Justify::SpaceAround => {
+            let gap = leftover / count.max(1.);
+            (gap * 0.5, gap)
+        }
+    };
+
+    let mut rects = vec![];
+    for (i, item) in items.iter().enumerate() {
+        let margin = item.styles.margin;
+        let align = item.styles.align_self.unwrap_or(styles.align);
+        let cross_extent = extents[i].1;
+
+        let cross = match (align, cross_extent.size) {
+            (Align::Stretch, Size::Auto) => {
+                cross_extent.clamp(available_cross - 2. * margin)
+            }
+            _ => cross_extent.resolve(available_cross),
+        };
+        let cross_offset = match align {
+            Align::Start | Align::Stretch => margin,
+            Align::Center => (available_cross - cross) * 0.5,
+            Align::End => available_cross - cross - margin,
+        };
+
+        main += margin;
+        // Offsets are from the top left corner of the content box.
+        let (x, y) = swap(direction, (main, cross_offset));
+        let (width, height) = swap(direction, (sizes[i], cross));
+        rects.push(Rect::new(
+            inner.x + x,
+            inner.top() - y - height,
+            width,
+            height,
+        ));
+        main += sizes[i] + margin + gap;
+    }
+
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn styles(width: Size, height: Size) -> Styles {
+        Styles {
+            width,
+            height,
+            ..Styles::default()
+        }
+    }
+
+    fn items(styles: &[Styles]) -> Vec<FlexItem<'_>> {
+        styles
+            .iter()
+            .map(|styles| FlexItem {
+                styles,
+                content: (10., 10.),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn grow_and_shrink() {
+        let row = Styles::default();
+        let rect = Rect::new(0., 0., 100., 50.);
+        let children = [
+            Styles {
+                grow: 1.,
+                ..styles(Size::Px(10.), Size::Auto)
+            },
+            Styles {
+                grow: 3.,
+                ..styles(Size::Px(10.), Size::Auto)
+            },
+            styles(Size::Px(20.), Size::Auto),
+        ];
+
+        // The 60 pixels left are shared by the growing ones.
+        let rects = flex(&row, rect, &items(&children));
+        let widths: Vec<f32> = rects.iter().map(|r| r.width).collect();
+        assert_eq!(widths, vec![25., 55., 20.]);
+        assert_eq!(rects[1].x, 25.);
+        assert_eq!(rects[2].x, 80.);
+
+        // By their shrink factor and size, within their min.
+        let children = [
+            styles(Size::Px(100.), Size::Auto),
+            Styles {
+                min_width: 40.,
+                ..styles(Size::Px(50.), Size::Auto)
+            },
+            Styles {
+                shrink: 0.,
+                ..styles(Size::Px(20.), Size::Auto)
+            },
+        ];
+        let rects = flex(&row, rect, &items(&children));
+        let widths: Vec<f32> = rects.iter().map(|r| r.width).collect();
+        assert_eq!(widths, vec![40., 40., 20.]);
+    }
+
+    #[test]
+    fn clamp_to_min_and_max() {
+        let row = Styles::default();
+        let rect = Rect::new(0., 0., 100., 50.);
+        let children = [
+            Styles {
+                grow: 1.,
+                max_width: 20.,
+                ..Styles::default()
+            },
+            Styles {
+                grow: 1.,
+                ..Styles::default()
+            },
+        ];
+
+        // The first is frozen at its max, the second takes the rest.
+        let rects = flex(&row, rect, &items(&children));
+        assert_eq!((rects[0].width, rects[1].width), (20., 80.));
+
+        let child = Styles {
+            min_height: 60.,
+            ..styles(Size::Percent(50.), Size::Px(10.))
+        };
+        let rects = flex(&row, rect, &items(&[child]));
+        assert_eq!((rects[0].width, rects[0].height), (50., 60.));
+    }
+
+    #[test]
+    fn justify_and_align() {
+        let rect = Rect::new(0., 0., 100., 50.);
+        let children = [
+            styles(Size::Px(20.), Size::Px(10.)),
+            styles(Size::Px(20.), Size::Auto),
+        ];
+        let justified = |justify| {
+            let row = Styles {
+                justify,
+                ..Styles::default()
+            };
+            flex(&row, rect, &items(&children))
+                .iter()
+                .map(|r| r.x)
+                .collect::<Vec<f32>>()
+        };
+
+        assert_eq!(justified(Justify::Start), vec![0., 20.]);
+        assert_eq!(justified(Justify::Center), vec![30., 50.]);
+        assert_eq!(justified(Justify::End), vec![60., 80.]);
+        assert_eq!(justified(Justify::SpaceBetween), vec![0., 80.]);
+        assert_eq!(justified(Justify::SpaceAround), vec![15., 65.]);
+
+        // Only the auto sizes are stretched, from the top.
+        let row = Styles::default();
+        let rects = flex(&row, rect, &items(&children));
+        assert_eq!((rects[0].y, rects[0].height), (40., 10.));
+        assert_eq!((rects[1].y, rects[1].height), (0., 50.));
+
+        let row = Styles {
+            align: Align::Center,
+            ..Styles::default()
+        };
+        let mut end = children[1];
+        end.align_self = Some(Align::End);
+        let rects = flex(&row, rect, &items(&[children[0], end]));
+        assert_eq!(rects[0].y, 20.);
+        assert_eq!((rects[1].y, rects[1].height), (0., 10.));
+    }
+
+    #[test]
+    fn columns_with_padding_and_margin() {
+        let col = Styles {
+            direction: Direction::Col,
+            padding: 5.,
+            ..Styles::default()
+        };
+        let children = [
+            Styles {
+                margin: 2.,
+                ..styles(Size::Auto, Size::Px(20.))
+            },
+            styles(Size::Percent(50.), Size::Percent(50.)),
+        ];
+
+        // Stacked from the top of the content box.
+        let rect = Rect::new(10., 10., 100., 110.);
+        let rects = flex(&col, rect, &items(&children));
+        assert_eq!(rects[0], Rect::new(17., 93., 86., 20.));
+        assert_eq!(rects[1], Rect::new(15., 41., 45., 50.));
+
+        // Percents fit the content when measuring.
+        assert_eq!(measure(&col, &items(&children)), (24., 44.));
+    }
+}
@@ -0,0 +1,1240 @@
+mod binding;
+mod event;
+mod layout;
+mod screen;
+mod theme;
+mod widgets;
+
+use crate::global::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::input::{Input, Key, MouseButton};
+use crate::renderer::{
+    DrawItem, Font, RenderQueue, Rgb, Rgba, Shape, Text, Vector,
+};
+use event::Path;
+use layout::FlexItem;
+use std::fmt::{self, Debug};
+use std::path::Path as FilePath;
+use std::time::{Duration, Instant, SystemTime};
+use std::{error, io};
+use theme::Variants;
+
+pub use binding::{Bindable, Message, Value};
+pub use event::{Event, State, Status};
+pub use layout::{Align, Direction, Justify, Rect, Size};
+pub use screen::Screen;
+pub use theme::Theme;
+pub use widgets::{
+    Checkbox, Dropdown, Image, Label, ProgressBar, ScrollPanel, Slider,
+};
+
+// Of the text inputs, their label is put above.
+const FIELD_HEIGHT: f32 = 40.;
+const LABEL_GAP: f32 = 5.;
+// Of the outline around the focused widget.
+const FOCUS_WIDTH: f32 = 2.;
+// Between two checks of the file of the gui.
+const RELOAD_DELAY: Duration = Duration::from_millis(500);
+
+/// Of the data files of the gui, like the screens and the themes.
+#[derive(Debug)]
+pub enum GuiError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    // An image of the screen couldn't be decoded.
+    Image(image::ImageError),
+}
+
+impl fmt::Display for GuiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GuiError::Io(e) => write!(f, "Couldn't read the gui file: {}", e),
+            GuiError::Json(e) => write!(f, "Invalid gui file: {}", e),
+            GuiError::Image(e) => write!(f, "Invalid gui image: {}", e),
+        }
+    }
+}
+
+impl error::Error for GuiError {}
+
+impl From<io::Error> for GuiError {
+    fn from(e: io::Error) -> Self {
+        GuiError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for GuiError {
+    fn from(e: serde_json::Error) -> Self {
+        GuiError::Json(e)
+    }
+}
+
+impl From<image::ImageError> for GuiError {
+    fn from(e: image::ImageError) -> Self {
+        GuiError::Image(e)
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Styles {
+    pub width: Size,
+    pub height: Size,
+    pub min_width: f32,
+    pub min_height: f32,
+    pub max_width: f32,
+    pub max_height: f32,
+    // Share of the free space taken by the widget, or given when
+    // there isn't enough.
+    pub grow: f32,
+    pub shrink: f32,
+    // Inside the background, around the children.
+    pub padding: f32,
+    // Outside, around the widget.
+    pub margin: f32,
+    pub text_color: Rgb,
+    pub bg_color: Rgba,
+    pub font_size: f32,
+    pub opacity: f32,
+    // Of the corners of the background.
+    pub radius: f32,
+    // Drawn inside the background.
+    pub border_width: f32,
+    pub border_color: Rgba,
+    // Looks of the hovered, pressed, focused and disabled widget.
+    pub variants: Variants,
+
+    // Used for children if any.
+    pub direction: Direction,
+    pub justify: Justify,
+    pub align: Align,
+    // Instead of the align of the parent.
+    pub align_self: Option<Align>,
+}
+
+impl Default for Styles {
+    fn default() -> Self {
+        Self {
+            width: Size::Auto,
+            height: Size::Auto,
+            min_width: 0.,
+            min_height: 0.,
+            max_width: f32::INFINITY,
+            max_height: f32::INFINITY,
+            grow: 0.,
+            shrink: 1.,
+            padding: 0.,
+            margin: 0.,
+            font_size: 16.,
+            text_color: Rgb::new(1., 1., 1.),
+            bg_color: Rgba::new(0., 0., 1., 1.),
+            opacity: 1.,
+            radius: 0.,
+            border_width: 0.,
+            border_color: Rgba::new(0., 0., 0., 1.),
+            variants: Variants::default(),
+            direction: Direction::Row,
+            justify: Justify::Start,
+            align: Align::Stretch,
+            align_self: None,
+        }
+    }
+}
+
+impl Styles {
+    /// Looks of the widget in its state. States without a variant
+    /// shade the background instead.
+    pub fn with_state(&self, state: &State) -> Styles {
+        let mut styles = *self;
+        let mut shaded = State::default();
+        let variants = [
+            (state.is_hover, self.variants.hover, &mut shaded.is_hover),
+            (
+                state.is_focused,
+                self.variants.focused,
+                &mut shaded.is_focused,
+            ),
+            (
+                state.is_pressed,
+                self.variants.pressed,
+                &mut shaded.is_pressed,
+            ),
+            (
+                state.is_disabled,
+                self.variants.disabled,
+                &mut shaded.is_disabled,
+            ),
+        ];
+        for (is_on, variant, is_shaded) in variants {
+            match variant.filter(|_| is_on) {
+                Some(rule) => rule.apply(&mut styles),
+                None => *is_shaded = is_on,
+            }
+        }
+
+        styles.bg_color = state_color(styles.bg_color, &shaded);
+        styles
+    }
+
+    pub fn shape(&self) -> Shape {
+        Shape {
+            radius: self.radius,
+            border_width: self.border_width,
+            border_color: self.border_color,
+        }
+    }
+}
+
+impl From<f32> for Size {
+    fn from(px: f32) -> Self {
+        Size::Px(px)
+    }
+}
+
+/// Builder methods shared by the widgets, for their box.
+pub trait Styled: Sized {
+    fn styles_mut(&mut self) -> &mut Styles;
+
+    /// In pixels, or like `Size::Percent(50.)`.
+    #[allow(unused)]
+    fn width(mut self, width: impl Into<Size>) -> Self {
+        self.styles_mut().width = width.into();
+        self
+    }
+
+    #[allow(unused)]
+    fn height(mut self, height: impl Into<Size>) -> Self {
+        self.styles_mut().height = height.into();
+        self
+    }
+
+    #[allow(unused)]
+    fn min_size(mut self, width: f32, height: f32) -> Self {
+        self.styles_mut().min_width = width;
+        self.styles_mut().min_height = height;
+        self
+    }
+
+    #[allow(unused)]
+    fn max_size(mut self, width: f32, height: f32) -> Self {
+        self.styles_mut().max_width = width;
+        self.styles_mut().max_height = height;
+        self
+    }
+
+    #[allow(unused)]
+    fn grow(mut self, grow: f32) -> Self {
+        self.styles_mut().grow = grow;
+        self
+    }
+
+    #[allow(unused)]
+    fn shrink(mut self, shrink: f32) -> Self {
+        self.styles_mut().shrink = shrink;
+        self
+    }
+
+    #[allow(unused)]
+    fn margin(mut self, margin: f32) -> Self {
+        self.styles_mut().margin = margin;
+        self
+    }
+
+    #[allow(unused)]
+    fn padding(mut self, padding: f32) -> Self {
+        self.styles_mut().padding = padding;
+        self
+    }
+
+    #[allow(unused)]
+    fn align_self(mut self, align: Align) -> Self {
+        self.styles_mut().align_self = Some(align);
+        self
+    }
+
+    #[allow(unused)]
+    fn bg_color(mut self, color: Rgba) -> Self {
+        self.styles_mut().bg_color = color;
+        self
+    }
+
+    #[allow(unused)]
+    fn radius(mut self, radius: f32) -> Self {
+        self.styles_mut().radius = radius;
+        self
+    }
+
+    #[allow(unused)]
+    fn border(mut self, width: f32, color: Rgba) -> Self {
+        self.styles_mut().border_width = width;
+        self.styles_mut().border_color = color;
+        self
+    }
+
+    /// Styled by the theme with this class, after the class of its
+    /// kind.
+    #[allow(unused)]
+    fn class(mut self, class: &str) -> Self
+    where
+        Self: Widget,
+    {
+        self.state_mut().class = Some(class.to_owned());
+        self
+    }
+
+    /// Found by the game with it, and sent in the messages.
+    #[allow(unused)]
+    fn id(mut self, id: &str) -> Self
+    where
+        Self: Widget,
+    {
+        self.state_mut().id = Some(id.to_owned());
+        self
+    }
+
+    /// Faded, it consumes the events without reacting.
+    #[allow(unused)]
+    fn disabled(mut self, is_disabled: bool) -> Self
+    where
+        Self: Widget,
+    {
+        self.state_mut().is_disabled = is_disabled;
+        self
+    }
+}
+
+/// Generic wrapper for widget trait.
+/// Store the given widget on the heap.
+pub struct Element {
+    widget: Box<dyn Widget>,
+}
+
+impl Element {
+    fn new(widget: impl Widget + 'static) -> Self {
+        Self {
+            widget: Box::new(widget),
+        }
+    }
+}
+
+/// TODO: Remove the required font.
+pub trait Widget {
+    /// Used to react from the events routed by the gui, the ignored
+    /// ones are given to the parent.
+    fn on_event(&mut self, _event: &Event) -> Status {
+        Status::Ignored
+    }
+
+    /// Size of the content, padding included, used by the `Auto`
+    /// sizes.
+    fn measure(&self, font: &Font) -> (f32, f32);
+    /// Place the widget in its box, margin excluded.
+    /// Parents place their children in theirs.
+    fn compute_layout(&mut self, rect: Rect, font: &Font);
+    /// Submit the widget to the render queue, the font is only used
+    /// to measure texts.
+    fn draw(&mut self, queue: &mut RenderQueue, font: &Font);
+
+    /// Get the style struct of the widget.
+    fn get_styles(&self) -> &Styles;
+    fn get_styles_mut(&mut self) -> &mut Styles;
+    /// Class of the theme styling all the widgets of this kind.
+    fn kind(&self) -> &'static str;
+    /// Box given by the last layout.
+    fn get_rect(&self) -> Rect;
+
+    /// Hover, press and focus of the widget, set by the gui.
+    fn state(&self) -> &State;
+    fn state_mut(&mut self) -> &mut State;
+
+    /// Shown or edited by the widget, if any.
+    fn value(&self) -> Option<Value> {
+        None
+    }
+    /// Return true if it changed, the widgets ignore the values they
+    /// can't show.
+    fn set_value(&mut self, _value: &Value) -> bool {
+        false
+    }
+
+    /// Reached with the tab key, and given the keyboard events.
+    fn is_focusable(&self) -> bool {
+        false
+    }
+
+    /// Used by the hit-testing and the focus navigation.
+    fn children(&self) -> &[Element] {
+        &[]
+    }
+    fn children_mut(&mut self) -> &mut [Element] {
+        &mut []
+    }
+
+    /// Under the point, for the hit-testing.
+    fn contains(&self, point: (f32, f32)) -> bool {
+        self.get_rect().contains(point)
+    }
+
+    /// Children are only hit inside of the widget.
+    fn clips_children(&self) -> bool {
+        false
+    }
+}
+
+// Background of a widget, lighter when hovered, darker when pressed
+// and faded when disabled.
+fn state_color(color: Rgba, state: &State) -> Rgba {
+    let shade = if state.is_disabled {
+        1.
+    } else if state.is_pressed {
+        0.8
+    } else if state.is_hover {
+        1.15
+    } else {
+        1.
+    };
+    let alpha = if state.is_disabled { 0.5 } else { 1. };
+
+    Rgba::new(
+        (color.r * shade).min(1.),
+        (color.g * shade).min(1.),
+        (color.b * shade).min(1.),
+        color.a * alpha,
+    )
+}
+
+fn flex_items<'a>(elements: &'a [Element], font: &Font) -> Vec<FlexItem<'a>> {
+    elements
+        .iter()
+        .map(|elem| FlexItem {
+            styles: elem.widget.get_styles(),
+            content: elem.widget.measure(font),
+        })
+        .collect()
+}
+
+// Children boxes of the widgets, laid out with the styles.
+fn layout_children(
+    styles: &Styles,
+    rect: Rect,
+    elements: &mut [Element],
+    font: &Font,
+) {
+    let rects = layout::flex(styles, rect, &flex_items(elements, font));
+    for (elem, rect) in elements.iter_mut().zip(rects) {
+        elem.widget.compute_layout(rect, font);
+    }
+}
+
+// From the bottom left corner of the screen, like the widgets.
+fn cursor_position(input: &Input) -> (f32, f32) {
+    unsafe {
+        (
+            input.cursor.position.0 as f32,
+            SCREEN_HEIGHT - input.cursor.position.1 as f32,
+        )
+    }
+}
+
+// File the gui is built from, checked for changes.
+struct Source {
+    path: String,
+    modified: Option<SystemTime>,
+    checked_at: Instant,
+}
+
+fn file_modified(path: &str) -> Option<SystemTime> {
+    FilePath::new(path)
+        .metadata()
+        .and_then(|m| m.modified())
+        .ok()
+}
+
+pub struct GUI {
+    pub elements: Vec<Element>,
+    // Stacked from the top of the screen.
+    styles: Styles,
+    // Screen size of the last layout, laid out again when it changes.
+    layout_size: Option<(f32, f32)>,
+
+    hovered: Option<Path>,
+    // Given the mouse events until released.
+    pressed: Option<Path>,
+    focused: Option<Path>,
+    // Of the last frame, to send the presses once.
+    held_keys: Vec<Key>,
+    held_buttons: Vec<MouseButton>,
+    // Of the last events.
+    messages: Vec<Message>,
+    // Given to the added widgets.
+    theme: Option<Theme>,
+    source: Option<Source>,
+}
+
+impl GUI {
+    pub fn new() -> Self {
+        Self {
+            elements: vec![],
+            styles: Styles {
+                direction: Direction::Col,
+                ..Styles::default()
+            },
+            layout_size: None,
+            hovered: None,
+            pressed: None,
+            focused: None,
+            held_keys: vec![],
+            held_buttons: vec![],
+            messages: vec![],
+            theme: None,
+            source: None,
+        }
+    }
+
+    /// Built from a screen file, see `Screen`.
+    pub fn from_file(path: &str) -> Result<Self, GuiError> {
+        let modified = file_modified(path);
+        let mut gui = Self::new();
+        gui.elements = Screen::from_file(path)?.build()?;
+        gui.source = Some(Source {
+            path: path.to_owned(),
+            modified,
+            checked_at: Instant::now(),
+        });
+        Ok(gui)
+    }
+
+    /// Built again from its file once modified, checked at most every
+    /// `RELOAD_DELAY`. The widgets are kept while the file is invalid.
+    /// Return true if it was reloaded.
+    pub fn hot_reload(&mut self) -> Result<bool, GuiError> {
+        let source = match &mut self.source {
+            Some(source) if source.checked_at.elapsed() >= RELOAD_DELAY => {
+                source
+            }
+            _ => return Ok(false),
+        };
+        source.checked_at = Instant::now();
+        let modified = file_modified(&source.path);
+        if modified == source.modified {
+            return Ok(false);
+        }
+        // Reported once by change.
+        source.modified = modified;
+
+        let mut elements = Screen::from_file(&source.path)?.build()?;
+        if let Some(theme) = &self.theme {
+            for elem in elements.iter_mut() {
+                theme.apply(elem.widget.as_mut());
+            }
+        }
+        self.elements = elements;
+        // Their paths were in the old widgets, the bound values are
+        // shown again by the next binds.
+        self.hovered = None;
+        self.pressed = None;
+        self.focused = None;
+        self.relayout();
+        Ok(true)
+    }
+
+    #[allow(unused)]
+    pub fn add_elem(mut self, widget: impl Widget + 'static) -> Self {
+        let mut elem = Element::new(widget);
+        if let Some(theme) = &self.theme {
+            theme.apply(elem.widget.as_mut());
+        }
+        self.elements.push(elem);
+        self.layout_size = None;
+        self
+    }
+
+    /// Restyle all the widgets, the classes only change their looks so
+    /// the layout is kept.
+    pub fn set_theme(&mut self, theme: Theme) {
+        for elem in self.elements.iter_mut() {
+            theme.apply(elem.widget.as_mut());
+        }
+        self.theme = Some(theme);
+    }
+
+    /// Lay out again on the next draw, when the content of the
+    /// widgets changes their size.
+    pub fn relayout(&mut self) {
+        self.layout_size = None;
+    }
+
+    /// Route the input to the widgets, what they consume isn't seen
+    /// by the game anymore.
+    pub fn on_event(&mut self, input: &mut Input) {
+        self.messages.clear();
+        let point = cursor_position(input);
+        self.hover(point);
+
+        if input.cursor.has_moved {
+            if let Some(path) =
+                self.pressed.clone().or_else(|| self.hovered.clone())
+            {
+                self.dispatch(&path, &Event::MouseMove(point));
+            }
+        }
+
+        let buttons = input.pressed_buttons();
+        let held = std::mem::replace(&mut self.held_buttons, buttons.clone());
+        for button in held.iter().filter(|b| !buttons.contains(b)) {
+            self.mouse_up(*button, point);
+        }
+        for button in buttons.into_iter().filter(|b| !held.contains(b)) {
+            if self.mouse_down(button, point).is_consumed() {
+                input.consume_click(button);
+            }
+        }
+
+        if input.cursor.scroll != 0. {
+            if let Some(path) = self.hovered.clone() {
+                let scroll = Event::Scroll(input.cursor.scroll as f32);
+                if self.dispatch(&path, &scroll).is_consumed() {
+                    input.cursor.scroll = 0.;
+                }
+            }
+        }
+
+        let keys = input.pressed_keys();
+        let held = std::mem::replace(&mut self.held_keys, keys.clone());
+        for key in keys.into_iter().filter(|k| !held.contains(k)) {
+            if self.key_down(key, input.modifiers.shift).is_consumed() {
+                input.consume_key(key);
+            }
+        }
+
+        // Typed on release.
+        if let Some(path) = self.focused.clone() {
+            if let Some(text) = input.pressed_str() {
+                self.dispatch(&path, &Event::Text(text));
+            }
+        }
+    }
+
+    /// Sent by the widgets with an id during the last events.
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    #[allow(unused)]
+    pub fn value(&self, id: &str) -> Option<Value> {
+        let path = event::find(&self.elements, id)?;
+        event::widget(&self.elements, &path)?.value()
+    }
+
+    /// Laid out again if it changed the widget.
+    pub fn set_value(&mut self, id: &str, value: Value) {
+        let widget = event::find(&self.elements, id)
+            .and_then(|path| event::widget_mut(&mut self.elements, &path));
+        if let Some(widget) = widget {
+            if widget.set_value(&value) {
+                self.relayout();
+            }
+        }
+    }
+
+    /// Two-way binding of a widget to a game value, called every
+    /// frame after the events. Edits of the widget are written to the
+    /// value, else the widget shows the value, but while it's focused.
+    pub fn bind<T: Bindable>(&mut self, id: &str, value: &mut T) {
+        let edit =
+            self.messages
+                .iter()
+                .rev()
+                .find_map(|message| match message {
+                    Message::Changed(changed, edit) if changed == id => {
+                        Some(edit)
+                    }
+                    _ => None,
+                });
+        if let Some(edit) = edit {
+            if let Some(edit) = T::from_value(edit) {
+                *value = edit;
+            }
+            return;
+        }
+
+        let path = match event::find(&self.elements, id) {
+            Some(path) => path,
+            None => return,
+        };
+        if self.focused.as_ref() != Some(&path) {
+            self.set_value(id, value.to_value());
+        }
+    }
+
+    fn dispatch(&mut self, path: &[usize], event: &Event) -> Status {
+        event::dispatch(&mut self.elements, path, event, &mut self.messages)
+    }
+
+    fn hover(&mut self, point: (f32, f32)) {
+        // Parts of the focused widget out of its box, like the options
+        // of a dropdown, are over the others.
+        let overlay = self.focused.clone().filter(|path| {
+            event::widget(&self.elements, path).is_some_and(|widget| {
+                !widget.get_rect().contains(point) && widget.contains(point)
+            })
+        });
+        let hovered = overlay.or_else(|| event::hit(&self.elements, point));
+        if hovered == self.hovered {
+            return;
+        }
+
+        if let Some(path) = &self.hovered {
+            event::set_state(&mut self.elements, path, |s| s.is_hover = false);
+        }
+        if let Some(path) = &hovered {
+            event::set_state(&mut self.elements, path, |s| s.is_hover = true);
+        }
+        self.hovered = hovered;
+    }
+
+    fn mouse_down(&mut self, button: MouseButton, point: (f32, f32)) -> Status {
+        let path = self.hovered.clone();
+        if button == MouseButton::Left {
+            // Nothing focusable under the cursor clears the focus.
+            let focus = path.as_ref().and_then(|path| {
+                (1..=path.len())
+                    .rev()
+                    .map(|depth| path[..depth].to_vec())
+                    .find(|path| {
+                        event::widget(&self.elements, path).is_some_and(|w| {
+                            w.is_focusable() && !w.state().is_disabled
+                        })
+                    })
+            });
+            self.set_focus(focus);
+
+            if let Some(path) = &path {
+                event::set_state(&mut self.elements, path, |s| {
+                    s.is_pressed = true
+                });
+            }
+            self.pressed = path.clone();
+        }
+
+        match path {
+            Some(path) => {
+                self.dispatch(&path, &Event::MouseDown(button, point))
+            }
+            None => Status::Ignored,
+        }
+    }
+
+    fn mouse_up(&mut self, button: MouseButton, point: (f32, f32)) {
+        let path = if button == MouseButton::Left {
+            let pressed = self.pressed.take();
+            if let Some(path) = &pressed {
+                event::set_state(&mut self.elements, path, |s| {
+                    s.is_pressed = false
+                });
+            }
+            pressed
+        } else {
+            self.hovered.clone()
+        };
+
+        if let Some(path) = path {
+            let up = Event::MouseUp(button, point);
+            self.dispatch(&path, &up);
+        }
+    }
+
+    fn key_down(&mut self, key: Key, is_shift: bool) -> Status {
+        let status = match self.focused.clone() {
+            Some(path) => self.dispatch(&path, &Event::KeyDown(key)),
+            None => Status::Ignored,
+        };
+        if status.is_consumed() || key != Key::Tab {
+            return status;
+        }
+
+        let order = event::focus_order(&self.elements, &[]);
+        match event::next_focus(&order, self.focused.as_ref(), is_shift) {
+            Some(next) => {
+                self.set_focus(Some(next));
+                Status::Consumed
+            }
+            None => Status::Ignored,
+        }
+    }
+
+    fn set_focus(&mut self, focus: Option<Path>) {
+        if focus == self.focused {
+            return;
+        }
+
+        if let Some(path) = self.focused.take() {
+            if let Some(widget) = event::widget_mut(&mut self.elements, &path) {
+                widget.state_mut().is_focused = false;
+                widget.on_event(&Event::Blur);
+            }
+        }
+        if let Some(path) = &focus {
+            if let Some(widget) = event::widget_mut(&mut self.elements, path) {
+                widget.state_mut().is_focused = true;
+                widget.on_event(&Event::Focus);
+            }
+        }
+        self.focused = focus;
+    }
+
+    pub fn draw(&mut self, queue: &mut RenderQueue, font: &Font) {
+        let screen = unsafe { (SCREEN_WIDTH, SCREEN_HEIGHT) };
+        if self.layout_size != Some(screen) {
+            let rect = Rect::new(0., 0., screen.0, screen.1);
+            layout_children(&self.styles, rect, &mut self.elements, font);
+            self.layout_size = Some(screen);
+        }
+
+        for elem in self.elements.iter_mut() {
+            elem.widget.draw(queue, font);
+        }
+
+        let focused = self
+            .focused
+            .as_ref()
+            .and_then(|path| event::widget(&self.elements, path));
+        if let Some(widget) = focused {
+            let color = widgets::opaque(widget.get_styles().text_color);
+            for item in widgets::outline(widget.get_rect(), FOCUS_WIDTH, color)
+            {
+                queue.push(item);
+            }
+        }
+    }
+}
+
+/// Like a "div" in html/css.
+/// Used build pattern.
+pub struct Container {
+    pub styles: Styles,
+    pub rect: Rect,
+    pub state: State,
+    pub content: Vec<Element>,
+}
+
+impl Container {
+    /// Transparent, the background is drawn once given a color or a
+    /// border.
+    pub fn row() -> Self {
+        Self {
+            styles: Styles {
+                bg_color: Rgba::new(0., 0., 0., 0.),
+                ..Styles::default()
+            },
+            rect: Rect::default(),
+            state: State::default(),
+            content: vec![],
+        }
+    }
+
+    pub fn col() -> Self {
+        let mut container = Self::row();
+        container.styles.direction = Direction::Col;
+        container
+    }
+
+    pub fn justify(mut self, justify: Justify) -> Self {
+        self.styles.justify = justify;
+        self
+    }
+
+    pub fn align(mut self, align: Align) -> Self {
+        self.styles.align = align;
+        self
+    }
+
+    #[allow(unused)]
+    pub fn push(mut self, widget: impl Widget + 'static) -> Self {
+        self.content.push(Element::new(widget));
+        self
+    }
+}
+
+impl Styled for Container {
+    fn styles_mut(&mut self) -> &mut Styles {
+        &mut self.styles
+    }
+}
+
+impl Widget for Container {
+    fn measure(&self, font: &Font) -> (f32, f32) {
+        layout::measure(&self.styles, &flex_items(&self.content, font))
+    }
+
+    fn compute_layout(&mut self, rect: Rect, font: &Font) {
+        self.rect = rect;
+        layout_children(&self.styles, rect, &mut self.content, font);
+    }
+
+    fn draw(&mut self, queue: &mut RenderQueue, font: &Font) {
+        let styles = self.styles.with_state(&self.state);
+        if styles.bg_color.a > 0. || styles.border_width > 0. {
+            queue.push(widgets::background(self.rect, &styles));
+        }
+        self.content.iter_mut().for_each(|elem| {
+            elem.widget.draw(queue, font);
+        })
+    }
+
+    fn get_styles(&self) -> &Styles {
+        &self.styles
+    }
+
+    fn get_styles_mut(&mut self) -> &mut Styles {
+        &mut self.styles
+    }
+
+    fn kind(&self) -> &'static str {
+        "container"
+    }
+
+    fn get_rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn state(&self) -> &State {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+
+    fn children(&self) -> &[Element] {
+        &self.content
+    }
+
+    fn children_mut(&mut self) -> &mut [Element] {
+        &mut self.content
+    }
+}
+
+pub struct TextInput {
+    pub styles: Styles,
+    pub rect: Rect,
+    pub value: Text,
+    pub label: Option<Text>,
+    // pub label: String,
+    pub state: State,
+    pub only_numbers: bool,
+    // Above the field, from the last layout.
+    label_height: f32,
+}
+
+impl TextInput {
+    pub fn new() -> Self {
+        let styles = Styles {
+            width: Size::Px(200.),
+            ..Styles::default()
+        };
+
+        let value = Text::new("").font_size(16.).color(styles.text_color);
+
+        Self {
+            styles,
+            rect: Rect::default(),
+            value,
+            state: State::default(),
+            label: None,
+            only_numbers: false,
+            label_height: 0.,
+        }
+    }
+
+    pub fn only_numbers(mut self, only_n: bool) -> Self {
+        self.only_numbers = only_n;
+        self
+    }
+
+    #[allow(unused)]
+    pub fn value(mut self, value: Text) -> Self {
+        self.value = value;
+        self
+    }
+
+    pub fn label(mut self, label: Text) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    fn label_height(&self, font: &Font) -> f32 {
+        self.label
+            .as_ref()
+            .map_or(0., |label| font.layout(label).height + LABEL_GAP)
+    }
+
+    // The box without the label.
+    fn field(&self) -> Rect {
+        Rect {
+            height: (self.rect.height - self.label_height).max(0.),
+            ..self.rect
+        }
+    }
+}
+
+impl Styled for TextInput {
+    fn styles_mut(&mut self) -> &mut Styles {
+        &mut self.styles
+    }
+}
+
+impl Widget for TextInput {
+    fn on_event(&mut self, event: &Event) -> Status {
+        match event {
+            Event::MouseDown(MouseButton::Left, _) => Status::Consumed,
+            Event::Text(value) => {
+                if self.only_numbers && value.parse::<f32>().is_err() {
+                    return Status::Consumed;
+                }
+                self.value.content.push_str(value.as_str());
+                Status::Changed
+            }
+            Event::KeyDown(Key::Bspc) => match self.value.content.pop() {
+                Some(_) => Status::Changed,
+                None => Status::Consumed,
+            },
+            // Typed on release, the game doesn't see them.
+            Event::KeyDown(key) if key.to_str().is_some() => Status::Consumed,
+            _ => Status::Ignored,
+        }
+    }
+
+    fn value(&self) -> Option<Value> {
+        Some(Value::Text(self.value.content.clone()))
+    }
+
+    fn set_value(&mut self, value: &Value) -> bool {
+        let content = match value {
+            Value::Text(text) => text.clone(),
+            Value::Number(n) => n.to_string(),
+            _ => return false,
+        };
+        if content == self.value.content {
+            return false;
+        }
+        self.value.set_content(&content);
+        true
+    }
+
+    /// Only the field is clicked, not the label.
+    fn contains(&self, point: (f32, f32)) -> bool {
+        self.field().contains(point)
+    }
+
+    fn measure(&self, font: &Font) -> (f32, f32) {
+        let layout = font.layout(&self.value);
+        (
+            layout.width + 2. * self.styles.padding,
+            FIELD_HEIGHT + self.label_height(font),
+        )
+    }
+
+    fn compute_layout(&mut self, rect: Rect, font: &Font) {
+        self.rect = rect;
+        self.label_height = self.label_height(font);
+    }
+
+    fn draw(&mut self, queue: &mut RenderQueue, font: &Font) {
+        let field = self.field();
+        let Rect { x, y, height, .. } = field;
+        let styles = self.styles.with_state(&self.state);
+        let padding = styles.padding;
+
+        // 1. Draw the input box.
+        //
+        queue.push(widgets::background(field, &styles));
+
+        // 2. Draw the value of the input.
+        //
+        // Centered on the line box, it doesn't move with the content.
+        let layout = font.layout(&self.value);
+        self.value.position = Vector(
+            x + padding,
+            (y + height * 0.5) + (0.5 * layout.height - layout.top),
+            0.,
+        );
+        self.value.color = styles.text_color;
+        queue.push(DrawItem::text(&self.value));
+
+        // 3. If label, draw the label.
+        //
+        if let Some(label) = &mut self.label {
+            let layout = font.layout(label);
+            let baseline = y + height + LABEL_GAP + layout.height - layout.top;
+            label.position = Vector(x, baseline, 0.);
+            label.color = styles.text_color;
+            queue.push(DrawItem::text(label));
+        }
+    }
+
+    fn get_styles(&self) -> &Styles {
+        &self.styles
+    }
+
+    fn get_styles_mut(&mut self) -> &mut Styles {
+        &mut self.styles
+    }
+
+    fn kind(&self) -> &'static str {
+        "text_input"
+    }
+
+    fn get_rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn state(&self) -> &State {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+}
+
+pub struct Button {
+    // Graphic button stuff.
+    pub styles: Styles,
+    pub rect: Rect,
+    pub text: Text,
+
+    // Button state.
+    pub is_displayed: bool,
+    pub state: State,
+}
+
+impl Button {
+    pub fn new(text: Text) -> Self {
+        let styles = Styles {
+            width: Size::Px(80.),
+            height: Size::Px(30.),
+            ..Styles::default()
+        };
+
+        Self {
+            text,
+            styles,
+            rect: Rect::default(),
+            is_displayed: true,
+            state: State::default(),
+        }
+    }
+
+    #[allow(unused)]
+    pub fn text_color(mut self, color: Rgb) -> Self {
+        self.styles.text_color = color;
+        self
+    }
+
+    #[allow(unused)]
+    pub fn font_size(mut self, size: f32) -> Self {
+        self.styles.font_size = size;
+        self
+    }
+}
+
+impl Styled for Button {
+    fn styles_mut(&mut self) -> &mut Styles {
+        &mut self.styles
+    }
+}
+
+impl Widget for Button {
+    fn on_event(&mut self, event: &Event) -> Status {
+        match event {
+            Event::MouseDown(MouseButton::Left, _) => Status::Consumed,
+            // Released on it, the press can be canceled by moving out.
+            Event::MouseUp(MouseButton::Left, point)
+                if self.rect.contains(*point) =>
+            {
+                Status::Clicked
+            }
+            Event::KeyDown(Key::Enter) | Event::KeyDown(Key::Space) => {
+                Status::Clicked
+            }
+            _ => Status::Ignored,
+        }
+    }
+
+    /// Its text, the clicks are sent as such.
+    fn value(&self) -> Option<Value> {
+        Some(Value::Text(self.text.content.clone()))
+    }
+
+    fn set_value(&mut self, value: &Value) -> bool {
+        match value {
+            Value::Text(text) if *text != self.text.content => {
+                self.text.set_content(text);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn measure(&self, font: &Font) -> (f32, f32) {
+        let layout = font.layout(&self.text);
+        let padding = 2. * self.styles.padding;
+        (layout.width + padding, layout.height + padding)
+    }
+
+    fn compute_layout(&mut self, rect: Rect, _font: &Font) {
+        self.rect = rect;
+    }
+
+    fn draw(&mut self, queue: &mut RenderQueue, font: &Font) {
+        let Rect {
+            x,
+            y,
+            width,
+            height,
+        } = self.rect;
+        let styles = self.styles.with_state(&self.state);
+        queue.push(widgets::background(self.rect, &styles));
+
+        let layout = font.layout(&self.text);
+        self.text.position = Vector(
+            (x + width * 0.5) + (-0.5 * layout.width),
+            (y + height * 0.5) + (0.5 * layout.height - layout.top),
+            0.,
+        );
+        self.text.color = styles.text_color;
+
+        queue.push(DrawItem::text(&self.text));
+    }
+
+    fn get_styles(&self) -> &Styles {
+        &self.styles
+    }
+
+    fn get_styles_mut(&mut self) -> &mut Styles {
+        &mut self.styles
+    }
+
+    fn kind(&self) -> &'static str {
+        "button"
+    }
+
+    fn get_rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn state(&self) -> &State {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+}
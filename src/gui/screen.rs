@@ -0,0 +1,408 @@
+use super::{
+    Align, Button, Checkbox, Container, Dropdown, Element, GuiError, Image,
+    Justify, Label, ProgressBar, ScrollPanel, Size, Slider, Styled, TextInput,
+    Widget,
+};
+use crate::renderer::Text;
+use serde::{de, Deserialize, Deserializer};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+/// Widgets of a gui described in a json file, like:
+///
+/// ```json
+/// {
+///     "elements": [
+///         {
+///             "type": "row",
+///             "width": "100%",
+///             "padding": 5,
+///             "children": [
+///                 { "type": "button", "text": "save", "id": "save" }
+///             ]
+///         }
+///     ]
+/// }
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct Screen {
+    pub elements: Vec<Node>,
+}
+
+impl Screen {
+    pub fn from_file(path: &str) -> Result<Self, GuiError> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    #[allow(unused)]
+    pub fn from_json(json: &str) -> Result<Self, GuiError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Missing or invalid images are returned as errors, the screen
+    /// can be fixed while the game runs.
+    pub fn build(&self) -> Result<Vec<Element>, GuiError> {
+        self.elements.iter().map(Node::build).collect()
+    }
+}
+
+/// A widget with its props, the "type" gives its kind.
+#[derive(Debug, Deserialize)]
+pub struct Node {
+    #[serde(flatten)]
+    pub kind: NodeKind,
+    #[serde(flatten)]
+    pub props: Props,
+}
+
+/// Given to all the widgets, like the `Styled` builders.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Props {
+    pub id: Option<String>,
+    pub class: Option<String>,
+    pub disabled: bool,
+    #[serde(deserialize_with = "size")]
+    pub width: Option<Size>,
+    #[serde(deserialize_with = "size")]
+    pub height: Option<Size>,
+    pub grow: Option<f32>,
+    pub shrink: Option<f32>,
+    pub margin: Option<f32>,
+    pub padding: Option<f32>,
+    pub align_self: Option<Align>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NodeKind {
+    Row(ContainerNode),
+    Col(ContainerNode),
+    Label {
+        text: String,
+    },
+    Button {
+        text: String,
+    },
+    TextInput {
+        label: Option<String>,
+        #[serde(default)]
+        value: String,
+        #[serde(default)]
+        only_numbers: bool,
+    },
+    Checkbox {
+        label: String,
+        #[serde(default)]
+        checked: bool,
+    },
+    Slider {
+        min: f32,
+        max: f32,
+        value: Option<f32>,
+        step: Option<f32>,
+    },
+    Dropdown {
+        options: Vec<String>,
+        #[serde(default)]
+        selected: usize,
+    },
+    ProgressBar {
+        #[serde(default)]
+        progress: f32,
+    },
+    // From the working directory, like the other assets.
+    Image {
+        path: String,
+    },
+    ScrollPanel {
+        content: Box<Node>,
+    },
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ContainerNode {
+    pub justify: Option<Justify>,
+    pub align: Option<Align>,
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    fn build(&self) -> Result<Element, GuiError> {
+        let props = &self.props;
+        let elem = match &self.kind {
+            NodeKind::Row(node) => props.element(node.build(Container::row())?),
+            NodeKind::Col(node) => props.element(node.build(Container::col())?),
+            NodeKind::Label { text } => {
+                props.element(Label::new(Text::new(text)))
+            }
+            NodeKind::Button { text } => {
+                props.element(Button::new(Text::new(text)))
+            }
+            NodeKind::TextInput {
+                label,
+                value,
+                only_numbers,
+            } => {
+                let mut input = TextInput::new().only_numbers(*only_numbers);
+                input.value.set_content(value);
+                if let Some(label) = label {
+                    input = input.label(Text::new(label));
+                }
+                props.element(input)
+            }
+            NodeKind::Checkbox { label, checked } => {
+                props.element(Checkbox::new(Text::new(label)).checked(*checked))
+            }
+            NodeKind::Slider {
+                min,
+                max,
+                value,
+                step,
+            } => {
+                let mut slider = Slider::new(*min, *max);
+                if let Some(value) = value {
+                    slider = slider.value(*value);
+                }
+                if let Some(step) = step {
+                    slider = slider.step(*step);
+                }
+                props.element(slider)
+            }
+            NodeKind::Dropdown { options, selected } => {
+                let options: Vec<&str> =
+                    options.iter().map(String::as_str).collect();
+                props.element(Dropdown::new(&options).selected(*selected))
+            }
+            NodeKind::ProgressBar { progress } => {
+                props.element(ProgressBar::new().progress(*progress))
+            }
+            NodeKind::Image { path } => {
+                // Reported with its path.
+                if !Path::new(path).is_file() {
+                    let message = format!("No image at {}", path);
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        message,
+                    )
+                    .into());
+                }
+                props.element(Image::from_file(path)?)
+            }
+            NodeKind::ScrollPanel { content } => {
+                props.element(ScrollPanel::from_element(content.build()?))
+            }
+        };
+
+        Ok(elem)
+    }
+}
+
+impl ContainerNode {
+    fn build(&self, mut container: Container) -> Result<Container, GuiError> {
+        if let Some(justify) = self.justify {
+            container = container.justify(justify);
+        }
+        if let Some(align) = self.align {
+            container = container.align(align);
+        }
+        for child in &self.children {
+            container.content.push(child.build()?);
+        }
+
+        Ok(container)
+    }
+}
+
+impl Props {
+    // What isn't given is left to the widget.
+    fn element<W: Styled + Widget + 'static>(&self, mut widget: W) -> Element {
+        let styles = Styled::styles_mut(&mut widget);
+        if let Some(width) = self.width {
+            styles.width = width;
+        }
+        if let Some(height) = self.height {
+            styles.height = height;
+        }
+        if let Some(grow) = self.grow {
+            styles.grow = grow;
+        }
+        if let Some(shrink) = self.shrink {
+            styles.shrink = shrink;
+        }
+        if let Some(margin) = self.margin {
+            styles.margin = margin;
+        }
+        if let Some(padding) = self.padding {
+            styles.padding = padding;
+        }
+        if self.align_self.is_some() {
+            styles.align_self = self.align_self;
+        }
+
+        let state = widget.state_mut();
+        state.id = self.id.clone();
+        state.class = self.class.clone();
+        state.is_disabled = self.disabled;
+        Element::new(widget)
+    }
+}
+
+// In pixels, "auto" or a percentage like "50%".
+fn size<'de, D>(deserializer: D) -> Result<Option<Size>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Px(f32),
+        Text(String),
+    }
+
+    let size = match Raw::deserialize(deserializer)? {
+        Raw::Px(px) => Size::Px(px),
+        Raw::Text(text) if text == "auto" => Size::Auto,
+        Raw::Text(text) => text
+            .strip_suffix('%')
+            .and_then(|percent| percent.trim().parse().ok())
+            .map(Size::Percent)
+            .ok_or_else(|| {
+                de::Error::custom(format!("invalid size \"{}\"", text))
+            })?,
+    };
+
+    Ok(Some(size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gui::{Value, GUI, RELOAD_DELAY};
+    use std::env;
+    use std::fs;
+
+    const SCREEN: &str = r#"{
+        "elements": [
+            {
+                "type": "row",
+                "width": "100%",
+                "height": 40,
+                "padding": 5,
+                "justify": "space_between",
+                "children": [
+                    { "type": "button", "text": "save", "id": "save" },
+                    {
+                        "type": "scroll_panel",
+                        "height": "auto",
+                        "content": {
+                            "type": "col",
+                            "children": [
+                                {
+                                    "type": "slider",
+                                    "id": "volume",
+                                    "min": 0,
+                                    "max": 10,
+                                    "value": 4
+                                },
+                                {
+                                    "type": "checkbox",
+                                    "label": "mute",
+                                    "disabled": true
+                                }
+                            ]
+                        }
+                    }
+                ]
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn build_screen() {
+        let elements = Screen::from_json(SCREEN).unwrap().build().unwrap();
+        assert_eq!(elements.len(), 1);
+
+        let row = elements[0].widget.as_ref();
+        let styles = row.get_styles();
+        assert_eq!(styles.width, Size::Percent(100.));
+        assert_eq!(styles.height, Size::Px(40.));
+        assert_eq!(styles.padding, 5.);
+        assert_eq!(styles.justify, Justify::SpaceBetween);
+        assert_eq!(row.children().len(), 2);
+
+        let save = row.children()[0].widget.as_ref();
+        assert_eq!(save.state().id.as_deref(), Some("save"));
+        assert_eq!(save.kind(), "button");
+        let panel = row.children()[1].widget.as_ref();
+        assert_eq!(panel.get_styles().height, Size::Auto);
+
+        let col = panel.children()[0].widget.as_ref();
+        let slider = col.children()[0].widget.as_ref();
+        assert_eq!(slider.value(), Some(Value::Number(4.)));
+        assert!(col.children()[1].widget.state().is_disabled);
+    }
+
+    #[test]
+    fn report_invalid_screens() {
+        let invalid = |json: &str| Screen::from_json(json).err();
+        let size = r#"{ "elements": [{ "type": "row", "width": "wide" }] }"#;
+        assert!(matches!(invalid(size), Some(GuiError::Json(_))));
+        let kind = r#"{ "elements": [{ "type": "table" }] }"#;
+        assert!(matches!(invalid(kind), Some(GuiError::Json(_))));
+
+        let image =
+            r#"{ "elements": [{ "type": "image", "path": "no.png" }] }"#;
+        let screen = Screen::from_json(image).unwrap();
+        assert!(matches!(screen.build(), Err(GuiError::Io(_))));
+        // Not an image.
+        let image =
+            r#"{ "elements": [{ "type": "image", "path": "Cargo.toml" }] }"#;
+        let screen = Screen::from_json(image).unwrap();
+        assert!(matches!(screen.build(), Err(GuiError::Image(_))));
+    }
+
+    #[test]
+    fn hot_reload_screen() {
+        let path = env::temp_dir().join("boreal_kiss_screen.json");
+        let path = path.to_str().unwrap();
+        let button = |id: &str| {
+            format!(
+                r#"{{ "elements": [{{ "type": "button", "text": "", "id": "{}" }}] }}"#,
+                id
+            )
+        };
+        fs::write(path, button("play")).unwrap();
+
+        let mut gui = GUI::from_file(path).unwrap();
+        assert!(gui.value("play").is_some());
+        // Not checked again before the delay.
+        fs::write(path, button("quit")).unwrap();
+        assert!(!gui.hot_reload().unwrap());
+
+        let force_check = |gui: &mut GUI| {
+            let source = gui.source.as_mut().unwrap();
+            source.modified = None;
+            source.checked_at -= RELOAD_DELAY;
+        };
+        force_check(&mut gui);
+        assert!(gui.hot_reload().unwrap());
+        assert!(gui.value("play").is_none() && gui.value("quit").is_some());
+
+        // Kept while the file is invalid.
+        fs::write(path, "{").unwrap();
+        force_check(&mut gui);
+        assert!(gui.hot_reload().is_err());
+        assert!(gui.value("quit").is_some());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn load_hud() {
+        let screen = Screen::from_file("assets/gui/hud.json").unwrap();
+        assert!(!screen.build().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,245 @@
+use super::{GuiError, Styles, Widget};
+use crate::renderer::{Rgb, Rgba};
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+
+/// Looks given by a class or one of its variants, what isn't given
+/// is left as is. Sizes and spacing belong to the screen, not here.
+#[derive(Debug, Default, Copy, Clone, Deserialize)]
+#[serde(default)]
+pub struct StyleRule {
+    #[serde(deserialize_with = "hex_color")]
+    pub bg_color: Option<Rgba>,
+    #[serde(deserialize_with = "hex_color")]
+    pub text_color: Option<Rgba>,
+    #[serde(deserialize_with = "hex_color")]
+    pub border_color: Option<Rgba>,
+    pub border_width: Option<f32>,
+    pub radius: Option<f32>,
+}
+
+impl StyleRule {
+    pub fn apply(&self, styles: &mut Styles) {
+        if let Some(color) = self.bg_color {
+            styles.bg_color = color;
+        }
+        if let Some(color) = self.text_color {
+            styles.text_color = Rgb::new(color.r, color.g, color.b);
+        }
+        if let Some(color) = self.border_color {
+            styles.border_color = color;
+        }
+        if let Some(width) = self.border_width {
+            styles.border_width = width;
+        }
+        if let Some(radius) = self.radius {
+            styles.radius = radius;
+        }
+    }
+}
+
+/// Applied over the looks of the widget in these states, instead of
+/// the default shading.
+#[derive(Debug, Default, Copy, Clone, Deserialize)]
+#[serde(default)]
+pub struct Variants {
+    pub hover: Option<StyleRule>,
+    pub pressed: Option<StyleRule>,
+    pub focused: Option<StyleRule>,
+    pub disabled: Option<StyleRule>,
+}
+
+impl Variants {
+    // The ones given by the other replace these.
+    fn merge(&mut self, other: &Variants) {
+        self.hover = other.hover.or(self.hover);
+        self.pressed = other.pressed.or(self.pressed);
+        self.focused = other.focused.or(self.focused);
+        self.disabled = other.disabled.or(self.disabled);
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, Deserialize)]
+pub struct StyleClass {
+    #[serde(flatten)]
+    pub rule: StyleRule,
+    #[serde(flatten)]
+    pub variants: Variants,
+}
+
+/// Named style classes, loaded from a json file like:
+///
+/// ```json
+/// {
+///     "name": "dark",
+///     "classes": {
+///         "button": {
+///             "bg_color": "#3a3f4b",
+///             "radius": 6,
+///             "hover": { "bg_color": "#4b5263" }
+///         }
+///     }
+/// }
+/// ```
+///
+/// Widgets take the class of their kind, like "button", then the one
+/// given with `Styled::class`.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct Theme {
+    #[allow(unused)]
+    pub name: String,
+    #[serde(default)]
+    pub classes: HashMap<String, StyleClass>,
+}
+
+impl Theme {
+    pub fn from_file(path: &str) -> Result<Self, GuiError> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    #[allow(unused)]
+    pub fn from_json(json: &str) -> Result<Self, GuiError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Restyle the widget and its children. Only what the classes give
+    /// is changed, so themes switched at runtime should give the same
+    /// properties.
+    pub fn apply(&self, widget: &mut dyn Widget) {
+        let classes = [Some(widget.kind()), widget.state().class.as_deref()]
+            .iter()
+            .flatten()
+            .filter_map(|name| self.classes.get(*name))
+            .copied()
+            .collect::<Vec<_>>();
+
+        let styles = widget.get_styles_mut();
+        if !classes.is_empty() {
+            styles.variants = Variants::default();
+        }
+        for class in classes {
+            class.rule.apply(styles);
+            styles.variants.merge(&class.variants);
+        }
+
+        for elem in widget.children_mut() {
+            self.apply(elem.widget.as_mut());
+        }
+    }
+}
+
+/// Parse "#rrggbb" or "#rrggbbaa".
+pub fn parse_hex(hex: &str) -> Option<Rgba> {
+    let hex = hex.strip_prefix('#')?;
+    if !hex.is_ascii() || (hex.len() != 6 && hex.len() != 8) {
+        return None;
+    }
+
+    let channel = |i: usize| -> Option<f32> {
+        let value = u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()?;
+        Some(f32::from(value) / 255.)
+    };
+    let alpha = if hex.len() == 8 { channel(6)? } else { 1. };
+
+    Some(Rgba::new(channel(0)?, channel(2)?, channel(4)?, alpha))
+}
+
+fn hex_color<'de, D>(deserializer: D) -> Result<Option<Rgba>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let hex = String::deserialize(deserializer)?;
+    parse_hex(&hex).map(Some).ok_or_else(|| {
+        serde::de::Error::custom(format!("invalid color \"{}\"", hex))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gui::{Button, Container, State, Styled};
+    use crate::renderer::Text;
+
+    const THEME: &str = r##"{
+        "name": "test",
+        "classes": {
+            "button": {
+                "bg_color": "#ff0000",
+                "radius": 4,
+                "hover": { "bg_color": "#00ff00" },
+                "disabled": { "bg_color": "#0000ff80" }
+            },
+            "accent": { "text_color": "#000000", "border_width": 2 },
+            "container": { "bg_color": "#101010" }
+        }
+    }"##;
+
+    #[test]
+    fn parse_hex_colors() {
+        let color = parse_hex("#ff800080").unwrap();
+        assert_eq!((color.r, color.g, color.a), (1., 128. / 255., 128. / 255.));
+        assert_eq!(parse_hex("#000000").unwrap().a, 1.);
+        assert!(parse_hex("ff0000").is_none());
+        assert!(parse_hex("#ff00").is_none());
+        assert!(parse_hex("#gg0000").is_none());
+
+        let invalid =
+            r#"{ "name": "x", "classes": { "a": { "bg_color": "red" } } }"#;
+        assert!(matches!(Theme::from_json(invalid), Err(GuiError::Json(_))));
+        assert!(matches!(
+            Theme::from_file("assets/themes/unknown.json"),
+            Err(GuiError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn apply_classes_and_variants() {
+        let theme = Theme::from_json(THEME).unwrap();
+        let mut row = Container::row()
+            .padding(10.)
+            .push(Button::new(Text::new("a")))
+            .push(Button::new(Text::new("b")).class("accent"));
+        theme.apply(&mut row);
+        // The spacing given by the screen is kept.
+        assert_eq!(row.styles.padding, 10.);
+        assert_eq!(row.styles.bg_color.r, 16. / 255.);
+
+        let accent = row.content[1].widget.get_styles();
+        assert_eq!(accent.bg_color.r, 1.);
+        assert_eq!(accent.radius, 4.);
+        assert_eq!(accent.border_width, 2.);
+        assert_eq!(accent.text_color.r, 0.);
+        let plain = row.content[0].widget.get_styles();
+        assert_eq!(plain.border_width, 0.);
+
+        // Variants replace the shading of their states.
+        let hover = State {
+            is_hover: true,
+            ..State::default()
+        };
+        assert_eq!(plain.with_state(&hover).bg_color.g, 1.);
+        let disabled = State {
+            is_disabled: true,
+            ..hover
+        };
+        let styles = plain.with_state(&disabled);
+        assert_eq!((styles.bg_color.b, styles.bg_color.a), (1., 128. / 255.));
+        // Without one, the background is shaded.
+        let pressed = State {
+            is_pressed: true,
+            ..State::default()
+        };
+        assert_eq!(plain.with_state(&pressed).bg_color.r, 0.8);
+    }
+
+    #[test]
+    fn load_themes() {
+        for path in &["assets/themes/dark.json", "assets/themes/light.json"] {
+            let theme = Theme::from_file(path).unwrap();
+            assert!(theme.classes.contains_key("button"));
+        }
+    }
+}
@@ -0,0 +1,1159 @@
+use super::{
+    Element, Event, GuiError, Rect, Size, State, Status, Styled, Styles, Value,
+    Widget,
+};
+use crate::input::{Key, MouseButton};
+use crate::renderer::{
+    Clip, DrawItem, Font, GpuTexture, RenderQueue, Rgb, Rgba, Shape, Text,
+    Texture, Transform, Vector,
+};
+
+// Between a box and its label.
+const GAP: f32 = 6.;
+// Pixels scrolled by wheel line.
+const SCROLL_SPEED: f32 = 30.;
+const SCROLLBAR_WIDTH: f32 = 4.;
+// Drawn over the other widgets, like the open dropdowns.
+const OVERLAY: u32 = 1;
+
+fn transform(rect: Rect) -> Transform {
+    Transform {
+        position: Vector(rect.x, rect.y, 0.),
+        scale: Vector(rect.width, rect.height, 1.),
+        ..Transform::default()
+    }
+}
+
+fn quad(rect: Rect, color: Rgba) -> DrawItem {
+    DrawItem::quad(&transform(rect), color)
+}
+
+// Rounded and bordered by the styles.
+pub(super) fn background(rect: Rect, styles: &Styles) -> DrawItem {
+    quad(rect, styles.bg_color).shape(styles.shape())
+}
+
+// Frame drawn around the rect, like the focused widgets.
+pub(super) fn outline(rect: Rect, width: f32, color: Rgba) -> Vec<DrawItem> {
+    let Rect { x, y, .. } = rect;
+    let outer_width = rect.width + 2. * width;
+
+    vec![
+        Rect::new(x - width, y - width, outer_width, width),
+        Rect::new(x - width, rect.top(), outer_width, width),
+        Rect::new(x - width, y, width, rect.height),
+        Rect::new(x + rect.width, y, width, rect.height),
+    ]
+    .into_iter()
+    .map(|side| quad(side, color))
+    .collect()
+}
+
+// Baseline of a line of text centered in the rect.
+fn centered_baseline(font: &Font, text: &Text, rect: Rect) -> f32 {
+    let layout = font.layout(text);
+    (rect.y + rect.height * 0.5) + (0.5 * layout.height - layout.top)
+}
+
+pub(super) fn opaque(color: Rgb) -> Rgba {
+    Rgba::new(color.r, color.g, color.b, 1.)
+}
+
+/// Text laid out with the others, it doesn't react to events.
+pub struct Label {
+    pub styles: Styles,
+    pub rect: Rect,
+    pub state: State,
+    pub text: Text,
+}
+
+impl Label {
+    pub fn new(text: Text) -> Self {
+        Self {
+            styles: Styles::default(),
+            rect: Rect::default(),
+            state: State::default(),
+            text,
+        }
+    }
+}
+
+impl Styled for Label {
+    fn styles_mut(&mut self) -> &mut Styles {
+        &mut self.styles
+    }
+}
+
+impl Widget for Label {
+    fn measure(&self, font: &Font) -> (f32, f32) {
+        let layout = font.layout(&self.text);
+        let padding = 2. * self.styles.padding;
+        (layout.width + padding, layout.height + padding)
+    }
+
+    fn compute_layout(&mut self, rect: Rect, _font: &Font) {
+        self.rect = rect;
+    }
+
+    fn draw(&mut self, queue: &mut RenderQueue, font: &Font) {
+        let inner = self.rect.shrink(self.styles.padding);
+        let layout = font.layout(&self.text);
+        self.text.position = Vector(inner.x, inner.top() - layout.top, 0.);
+        self.text.color = self.styles.with_state(&self.state).text_color;
+        queue.push(DrawItem::text(&self.text));
+    }
+
+    fn get_styles(&self) -> &Styles {
+        &self.styles
+    }
+
+    fn get_styles_mut(&mut self) -> &mut Styles {
+        &mut self.styles
+    }
+
+    fn kind(&self) -> &'static str {
+        "label"
+    }
+
+    fn get_rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn state(&self) -> &State {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+
+    fn value(&self) -> Option<Value> {
+        Some(Value::Text(self.text.content.clone()))
+    }
+
+    fn set_value(&mut self, value: &Value) -> bool {
+        let content = match value {
+            Value::Text(text) => text.clone(),
+            Value::Number(n) => n.to_string(),
+            _ => return false,
+        };
+        if content == self.text.content {
+            return false;
+        }
+        self.text.set_content(&content);
+        true
+    }
+}
+
+/// Box toggled by a click, with its label on the right.
+pub struct Checkbox {
+    pub styles: Styles,
+    pub rect: Rect,
+    pub state: State,
+    pub label: Text,
+    pub is_checked: bool,
+}
+
+impl Checkbox {
+    pub fn new(label: Text) -> Self {
+        Self {
+            styles: Styles::default(),
+            rect: Rect::default(),
+            state: State::default(),
+            label,
+            is_checked: false,
+        }
+    }
+
+    pub fn checked(mut self, is_checked: bool) -> Self {
+        self.is_checked = is_checked;
+        self
+    }
+
+    // As tall as the label font.
+    fn check_box(&self) -> Rect {
+        let size = self.label.font_size.min(self.rect.height);
+        Rect::new(
+            self.rect.x,
+            self.rect.y + (self.rect.height - size) * 0.5,
+            size,
+            size,
+        )
+    }
+}
+
+impl Styled for Checkbox {
+    fn styles_mut(&mut self) -> &mut Styles {
+        &mut self.styles
+    }
+}
+
+impl Widget for Checkbox {
+    fn on_event(&mut self, event: &Event) -> Status {
+        match event {
+            Event::MouseDown(MouseButton::Left, _) => Status::Consumed,
+            Event::MouseUp(MouseButton::Left, point)
+                if self.rect.contains(*point) =>
+            {
+                self.is_checked = !self.is_checked;
+                Status::Changed
+            }
+            Event::KeyDown(Key::Enter) | Event::KeyDown(Key::Space) => {
+                self.is_checked = !self.is_checked;
+                Status::Changed
+            }
+            _ => Status::Ignored,
+        }
+    }
+
+    fn measure(&self, font: &Font) -> (f32, f32) {
+        let layout = font.layout(&self.label);
+        let size = self.label.font_size;
+        (size + GAP + layout.width, size.max(layout.height))
+    }
+
+    fn compute_layout(&mut self, rect: Rect, _font: &Font) {
+        self.rect = rect;
+    }
+
+    fn draw(&mut self, queue: &mut RenderQueue, font: &Font) {
+        let styles = self.styles.with_state(&self.state);
+        let check_box = self.check_box();
+        queue.push(background(check_box, &styles));
+        if self.is_checked {
+            let mark = check_box.shrink(check_box.width * 0.25);
+            queue.push(quad(mark, opaque(styles.text_color)));
+        }
+
+        let baseline = centered_baseline(font, &self.label, self.rect);
+        self.label.position =
+            Vector(check_box.x + check_box.width + GAP, baseline, 0.);
+        self.label.color = styles.text_color;
+        queue.push(DrawItem::text(&self.label));
+    }
+
+    fn get_styles(&self) -> &Styles {
+        &self.styles
+    }
+
+    fn get_styles_mut(&mut self) -> &mut Styles {
+        &mut self.styles
+    }
+
+    fn kind(&self) -> &'static str {
+        "checkbox"
+    }
+
+    fn get_rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn state(&self) -> &State {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+
+    fn value(&self) -> Option<Value> {
+        Some(Value::Bool(self.is_checked))
+    }
+
+    fn set_value(&mut self, value: &Value) -> bool {
+        match value {
+            Value::Bool(is_checked) if *is_checked != self.is_checked => {
+                self.is_checked = *is_checked;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+}
+
+/// Number picked by dragging a handle along a track.
+pub struct Slider {
+    pub styles: Styles,
+    pub rect: Rect,
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+    // Values are rounded to it, if any.
+    pub step: Option<f32>,
+    pub state: State,
+}
+
+impl Slider {
+    pub fn new(min: f32, max: f32) -> Self {
+        let styles = Styles {
+            width: Size::Px(150.),
+            height: Size::Px(20.),
+            ..Styles::default()
+        };
+
+        Self {
+            styles,
+            rect: Rect::default(),
+            value: min,
+            min,
+            max,
+            step: None,
+            state: State::default(),
+        }
+    }
+
+    pub fn value(mut self, value: f32) -> Self {
+        self.value = value.max(self.min).min(self.max);
+        self
+    }
+
+    pub fn step(mut self, step: f32) -> Self {
+        self.step = Some(step);
+        self
+    }
+
+    /// Value under a screen position on the track.
+    pub fn value_at(&self, x: f32) -> f32 {
+        let ratio = if self.rect.width > 0. {
+            ((x - self.rect.x) / self.rect.width).clamp(0., 1.)
+        } else {
+            0.
+        };
+        let value = self.min + ratio * (self.max - self.min);
+
+        match self.step {
+            Some(step) if step > 0. => {
+                let value =
+                    self.min + ((value - self.min) / step).round() * step;
+                value.min(self.max)
+            }
+            _ => value,
+        }
+    }
+
+    // Changed if it's another value.
+    fn slide_to(&mut self, value: f32) -> Status {
+        let value = value.clamp(self.min, self.max);
+        if value == self.value {
+            return Status::Consumed;
+        }
+        self.value = value;
+        Status::Changed
+    }
+
+    // By the step with the keyboard, or a hundredth of the range.
+    fn nudge(&mut self, steps: f32) -> Status {
+        let step = self.step.unwrap_or((self.max - self.min) / 100.);
+        self.slide_to(self.value + steps * step)
+    }
+
+    fn ratio(&self) -> f32 {
+        if self.max > self.min {
+            (self.value - self.min) / (self.max - self.min)
+        } else {
+            0.
+        }
+    }
+}
+
+impl Styled for Slider {
+    fn styles_mut(&mut self) -> &mut Styles {
+        &mut self.styles
+    }
+}
+
+impl Widget for Slider {
+    fn on_event(&mut self, event: &Event) -> Status {
+        match event {
+            Event::MouseDown(MouseButton::Left, (x, _)) => {
+                self.slide_to(self.value_at(*x))
+            }
+            // Dragged until released, even outside of the track.
+            Event::MouseMove((x, _)) if self.state.is_pressed => {
+                self.slide_to(self.value_at(*x))
+            }
+            Event::MouseUp(MouseButton::Left, _) => Status::Consumed,
+            Event::KeyDown(Key::Left) | Event::KeyDown(Key::Down) => {
+                self.nudge(-1.)
+            }
+            Event::KeyDown(Key::Right) | Event::KeyDown(Key::Up) => {
+                self.nudge(1.)
+            }
+            _ => Status::Ignored,
+        }
+    }
+
+    fn measure(&self, _font: &Font) -> (f32, f32) {
+        (0., 0.)
+    }
+
+    fn compute_layout(&mut self, rect: Rect, _font: &Font) {
+        self.rect = rect;
+    }
+
+    fn draw(&mut self, queue: &mut RenderQueue, _font: &Font) {
+        let Rect {
+            x,
+            y,
+            width,
+            height,
+        } = self.rect;
+        let track_height = height * 0.3;
+        let track = Rect::new(
+            x,
+            y + (height - track_height) * 0.5,
+            width,
+            track_height,
+        );
+        let styles = self.styles.with_state(&self.state);
+        queue.push(background(track, &styles));
+
+        let handle_width = height * 0.5;
+        let handle_x = x + self.ratio() * (width - handle_width);
+        let handle = Rect::new(handle_x, y, handle_width, height);
+        let shape = Shape {
+            radius: styles.radius,
+            ..Shape::default()
+        };
+        queue.push(quad(handle, opaque(styles.text_color)).shape(shape));
+    }
+
+    fn get_styles(&self) -> &Styles {
+        &self.styles
+    }
+
+    fn get_styles_mut(&mut self) -> &mut Styles {
+        &mut self.styles
+    }
+
+    fn kind(&self) -> &'static str {
+        "slider"
+    }
+
+    fn get_rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn state(&self) -> &State {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+
+    fn value(&self) -> Option<Value> {
+        Some(Value::Number(self.value))
+    }
+
+    fn set_value(&mut self, value: &Value) -> bool {
+        match value {
+            Value::Number(n) => self.slide_to(*n) == Status::Changed,
+            _ => false,
+        }
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+}
+
+/// One option among a list, shown under the box once clicked.
+pub struct Dropdown {
+    pub styles: Styles,
+    pub rect: Rect,
+    pub options: Vec<Text>,
+    pub selected: usize,
+    pub state: State,
+    pub is_open: bool,
+    pub hovered: Option<usize>,
+}
+
+impl Dropdown {
+    pub fn new(options: &[&str]) -> Self {
+        let styles = Styles {
+            width: Size::Px(150.),
+            height: Size::Px(30.),
+            padding: 8.,
+            ..Styles::default()
+        };
+        let options = options
+            .iter()
+            .map(|option| {
+                Text::new(option)
+                    .font_size(styles.font_size)
+                    .color(styles.text_color)
+            })
+            .collect();
+
+        Self {
+            styles,
+            rect: Rect::default(),
+            options,
+            selected: 0,
+            state: State::default(),
+            is_open: false,
+            hovered: None,
+        }
+    }
+
+    pub fn selected(mut self, selected: usize) -> Self {
+        self.selected = selected.min(self.options.len().saturating_sub(1));
+        self
+    }
+
+    /// Content of the selected option.
+    #[allow(unused)]
+    pub fn selected_option(&self) -> Option<&str> {
+        self.options.get(self.selected).map(|text| text.content())
+    }
+
+    fn select(&mut self, i: usize) -> Status {
+        if i >= self.options.len() || i == self.selected {
+            return Status::Consumed;
+        }
+        self.selected = i;
+        Status::Changed
+    }
+
+    fn option_at(&self, point: (f32, f32)) -> Option<usize> {
+        if !self.is_open {
+            return None;
+        }
+        (0..self.options.len()).find(|i| self.option_rect(*i).contains(point))
+    }
+
+    // Stacked under the box, as tall as it.
+    fn option_rect(&self, i: usize) -> Rect {
+        let height = self.rect.height;
+        Rect {
+            y: self.rect.y - (i + 1) as f32 * height,
+            ..self.rect
+        }
+    }
+}
+
+impl Styled for Dropdown {
+    fn styles_mut(&mut self) -> &mut Styles {
+        &mut self.styles
+    }
+}
+
+impl Widget for Dropdown {
+    fn on_event(&mut self, event: &Event) -> Status {
+        match event {
+            Event::MouseMove(point) => {
+                self.hovered = self.option_at(*point);
+                Status::Ignored
+            }
+            Event::MouseDown(MouseButton::Left, point) => {
+                match self.option_at(*point) {
+                    Some(i) => {
+                        self.is_open = false;
+                        self.select(i)
+                    }
+                    None => {
+                        self.is_open = !self.is_open;
+                        Status::Consumed
+                    }
+                }
+            }
+            Event::KeyDown(Key::Enter) | Event::KeyDown(Key::Space) => {
+                self.is_open = !self.is_open;
+                Status::Consumed
+            }
+            Event::KeyDown(Key::Up) if self.selected > 0 => {
+                self.select(self.selected - 1)
+            }
+            Event::KeyDown(Key::Down) => self.select(self.selected + 1),
+            // Closed by a click anywhere else.
+            Event::Blur => {
+                self.is_open = false;
+                self.hovered = None;
+                Status::Ignored
+            }
+            _ => Status::Ignored,
+        }
+    }
+
+    /// The options are part of it once open.
+    fn contains(&self, point: (f32, f32)) -> bool {
+        self.rect.contains(point) || self.option_at(point).is_some()
+    }
+
+    fn measure(&self, font: &Font) -> (f32, f32) {
+        let (width, height) =
+            self.options
+                .iter()
+                .fold((0., 0.), |(w, h): (f32, f32), text| {
+                    let layout = font.layout(text);
+                    (w.max(layout.width), h.max(layout.height))
+                });
+        let padding = 2. * self.styles.padding;
+        (width + padding, height + padding)
+    }
+
+    fn compute_layout(&mut self, rect: Rect, _font: &Font) {
+        self.rect = rect;
+    }
+
+    fn draw(&mut self, queue: &mut RenderQueue, font: &Font) {
+        let styles = self.styles.with_state(&self.state);
+        let padding = styles.padding;
+        let bg_color = self.styles.bg_color;
+        queue.push(background(self.rect, &styles));
+
+        if let Some(text) = self.options.get(self.selected) {
+            let mut text = text.clone();
+            text.color = styles.text_color;
+            let baseline = centered_baseline(font, &text, self.rect);
+            text.position = Vector(self.rect.x + padding, baseline, 0.);
+            queue.push(DrawItem::text(&text));
+        }
+
+        if !self.is_open {
+            return;
+        }
+        for i in 0..self.options.len() {
+            let rect = self.option_rect(i);
+            let color = if self.hovered == Some(i) {
+                opaque(styles.text_color)
+            } else {
+                bg_color
+            };
+            queue.push(quad(rect, color).sort_key(OVERLAY));
+
+            let baseline = centered_baseline(font, &self.options[i], rect);
+            let text = &mut self.options[i];
+            text.color = if self.hovered == Some(i) {
+                Rgb::new(bg_color.r, bg_color.g, bg_color.b)
+            } else {
+                styles.text_color
+            };
+            text.position = Vector(rect.x + padding, baseline, 0.);
+            queue.push(DrawItem::text(text).sort_key(OVERLAY));
+        }
+    }
+
+    fn get_styles(&self) -> &Styles {
+        &self.styles
+    }
+
+    fn get_styles_mut(&mut self) -> &mut Styles {
+        &mut self.styles
+    }
+
+    fn kind(&self) -> &'static str {
+        "dropdown"
+    }
+
+    fn get_rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn state(&self) -> &State {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+
+    fn value(&self) -> Option<Value> {
+        Some(Value::Index(self.selected))
+    }
+
+    fn set_value(&mut self, value: &Value) -> bool {
+        match value {
+            Value::Index(i) => self.select(*i) == Status::Changed,
+            _ => false,
+        }
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+}
+
+/// Shows a part of a taller widget, scrolled with the wheel. What's
+/// outside is clipped.
+pub struct ScrollPanel {
+    pub styles: Styles,
+    pub rect: Rect,
+    pub state: State,
+    pub content: Element,
+    // Pixels scrolled from the top.
+    pub offset: f32,
+    content_height: f32,
+    needs_layout: bool,
+}
+
+impl ScrollPanel {
+    #[allow(unused)]
+    pub fn new(content: impl Widget + 'static) -> Self {
+        Self::from_element(Element::new(content))
+    }
+
+    pub(super) fn from_element(content: Element) -> Self {
+        Self {
+            styles: Styles::default(),
+            rect: Rect::default(),
+            state: State::default(),
+            content,
+            offset: 0.,
+            content_height: 0.,
+            needs_layout: false,
+        }
+    }
+
+    pub fn max_offset(&self) -> f32 {
+        (self.content_height - self.rect.height).max(0.)
+    }
+
+    pub fn scroll(&mut self, pixels: f32) {
+        let offset = (self.offset + pixels).clamp(0., self.max_offset());
+        if offset != self.offset {
+            self.offset = offset;
+            self.needs_layout = true;
+        }
+    }
+
+    fn layout_content(&mut self, font: &Font) {
+        let rect = self.rect;
+        self.content_height =
+            self.content.widget.measure(font).1.max(rect.height);
+        self.offset = self.offset.min(self.max_offset());
+
+        // From the top, moved up by the offset.
+        let content = Rect::new(
+            rect.x,
+            rect.top() + self.offset - self.content_height,
+            rect.width,
+            self.content_height,
+        );
+        self.content.widget.compute_layout(content, font);
+        self.needs_layout = false;
+    }
+}
+
+impl Styled for ScrollPanel {
+    fn styles_mut(&mut self) -> &mut Styles {
+        &mut self.styles
+    }
+}
+
+impl Widget for ScrollPanel {
+    fn on_event(&mut self, event: &Event) -> Status {
+        match event {
+            // Wheel up shows the top.
+            Event::Scroll(lines) => {
+                self.scroll(-lines * SCROLL_SPEED);
+                Status::Consumed
+            }
+            _ => Status::Ignored,
+        }
+    }
+
+    fn measure(&self, font: &Font) -> (f32, f32) {
+        self.content.widget.measure(font)
+    }
+
+    fn compute_layout(&mut self, rect: Rect, font: &Font) {
+        self.rect = rect;
+        self.layout_content(font);
+    }
+
+    fn draw(&mut self, queue: &mut RenderQueue, font: &Font) {
+        if self.needs_layout {
+            self.layout_content(font);
+        }
+        let styles = self.styles.with_state(&self.state);
+        queue.push(background(self.rect, &styles));
+
+        let start = queue.len();
+        self.content.widget.draw(queue, font);
+        let Rect {
+            x,
+            y,
+            width,
+            height,
+        } = self.rect;
+        queue.clip_since(
+            start,
+            Clip {
+                x,
+                y,
+                width,
+                height,
+            },
+        );
+
+        // Its height is the visible part of the content.
+        if self.max_offset() > 0. {
+            let thumb_height = height * height / self.content_height;
+            let ratio = self.offset / self.max_offset();
+            let thumb = Rect::new(
+                x + width - SCROLLBAR_WIDTH,
+                y + (height - thumb_height) * (1. - ratio),
+                SCROLLBAR_WIDTH,
+                thumb_height,
+            );
+            queue.push(quad(thumb, opaque(styles.text_color)));
+        }
+    }
+
+    fn get_styles(&self) -> &Styles {
+        &self.styles
+    }
+
+    fn get_styles_mut(&mut self) -> &mut Styles {
+        &mut self.styles
+    }
+
+    fn kind(&self) -> &'static str {
+        "scroll_panel"
+    }
+
+    fn get_rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn state(&self) -> &State {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+
+    fn children(&self) -> &[Element] {
+        std::slice::from_ref(&self.content)
+    }
+
+    fn children_mut(&mut self) -> &mut [Element] {
+        std::slice::from_mut(&mut self.content)
+    }
+
+    // Clipped children can't be clicked.
+    fn clips_children(&self) -> bool {
+        true
+    }
+}
+
+/// Filled from the left by the progress, between 0 and 1.
+pub struct ProgressBar {
+    pub styles: Styles,
+    pub rect: Rect,
+    pub state: State,
+    pub progress: f32,
+    pub fill_color: Rgba,
+}
+
+impl ProgressBar {
+    pub fn new() -> Self {
+        let styles = Styles {
+            width: Size::Px(150.),
+            height: Size::Px(12.),
+            // Around the fill.
+            padding: 2.,
+            ..Styles::default()
+        };
+
+        Self {
+            styles,
+            rect: Rect::default(),
+            state: State::default(),
+            progress: 0.,
+            fill_color: Rgba::new(0.3, 0.8, 0.3, 1.),
+        }
+    }
+
+    pub fn progress(mut self, progress: f32) -> Self {
+        self.set_progress(progress);
+        self
+    }
+
+    #[allow(unused)]
+    pub fn fill_color(mut self, color: Rgba) -> Self {
+        self.fill_color = color;
+        self
+    }
+
+    pub fn set_progress(&mut self, progress: f32) {
+        self.progress = progress.clamp(0., 1.);
+    }
+}
+
+impl Styled for ProgressBar {
+    fn styles_mut(&mut self) -> &mut Styles {
+        &mut self.styles
+    }
+}
+
+impl Widget for ProgressBar {
+    fn measure(&self, _font: &Font) -> (f32, f32) {
+        (0., 0.)
+    }
+
+    fn compute_layout(&mut self, rect: Rect, _font: &Font) {
+        self.rect = rect;
+    }
+
+    fn draw(&mut self, queue: &mut RenderQueue, _font: &Font) {
+        let styles = self.styles.with_state(&self.state);
+        queue.push(background(self.rect, &styles));
+
+        let inner = self.rect.shrink(styles.padding);
+        let fill = Rect {
+            width: inner.width * self.progress,
+            ..inner
+        };
+        queue.push(quad(fill, self.fill_color));
+    }
+
+    fn get_styles(&self) -> &Styles {
+        &self.styles
+    }
+
+    fn get_styles_mut(&mut self) -> &mut Styles {
+        &mut self.styles
+    }
+
+    fn kind(&self) -> &'static str {
+        "progress_bar"
+    }
+
+    fn get_rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn state(&self) -> &State {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+
+    fn value(&self) -> Option<Value> {
+        Some(Value::Number(self.progress))
+    }
+
+    fn set_value(&mut self, value: &Value) -> bool {
+        match value {
+            Value::Number(n) if n.clamp(0., 1.) != self.progress => {
+                self.set_progress(*n);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Texture stretched on the box, as large as the texture by default.
+pub struct Image {
+    pub styles: Styles,
+    pub rect: Rect,
+    pub state: State,
+    pub texture: GpuTexture,
+    pub tint: Rgba,
+}
+
+impl Image {
+    pub fn new(texture: Texture) -> Self {
+        Self {
+            styles: Styles::default(),
+            rect: Rect::default(),
+            state: State::default(),
+            texture: GpuTexture::new(texture),
+            tint: Rgba::new(1., 1., 1., 1.),
+        }
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, GuiError> {
+        Ok(Self::new(Texture::try_from_file(path)?))
+    }
+
+    #[allow(unused)]
+    pub fn tint(mut self, tint: Rgba) -> Self {
+        self.tint = tint;
+        self
+    }
+}
+
+impl Styled for Image {
+    fn styles_mut(&mut self) -> &mut Styles {
+        &mut self.styles
+    }
+}
+
+impl Widget for Image {
+    fn measure(&self, _font: &Font) -> (f32, f32) {
+        let (width, height) = self.texture.texture.dim;
+        (width as f32, height as f32)
+    }
+
+    fn compute_layout(&mut self, rect: Rect, _font: &Font) {
+        self.rect = rect;
+    }
+
+    fn draw(&mut self, queue: &mut RenderQueue, _font: &Font) {
+        let t = transform(self.rect);
+        queue.push(DrawItem::image(self.texture.tex_id(), &t, self.tint));
+    }
+
+    fn get_styles(&self) -> &Styles {
+        &self.styles
+    }
+
+    fn get_styles_mut(&mut self) -> &mut Styles {
+        &mut self.styles
+    }
+
+    fn kind(&self) -> &'static str {
+        "image"
+    }
+
+    fn get_rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn state(&self) -> &State {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gui::{event, Button, Container, Message};
+
+    fn helvetica() -> Font {
+        Font::new(
+            "assets/fonts/Helvetica/helvetica.json",
+            "assets/fonts/Helvetica/helvetica.png",
+        )
+    }
+
+    #[test]
+    fn drag_slider() {
+        let mut slider = Slider::new(0., 10.);
+        slider.rect = Rect::new(10., 0., 100., 20.);
+        assert_eq!(slider.value_at(0.), 0.);
+        assert_eq!(slider.value_at(35.), 2.5);
+        assert_eq!(slider.value_at(500.), 10.);
+
+        let down = Event::MouseDown(MouseButton::Left, (35., 10.));
+        assert_eq!(slider.on_event(&down), Status::Changed);
+        assert_eq!(slider.value, 2.5);
+        assert_eq!(slider.on_event(&down), Status::Consumed);
+
+        // Dragged outside of the track while pressed.
+        let moved = Event::MouseMove((500., 300.));
+        slider.state.is_pressed = true;
+        slider.on_event(&moved);
+        assert_eq!(slider.value, 10.);
+        slider.state.is_pressed = false;
+        let moved = Event::MouseMove((10., 10.));
+        assert_eq!(slider.on_event(&moved), Status::Ignored);
+        assert_eq!(slider.value, 10.);
+
+        let mut slider = slider.step(3.);
+        assert_eq!(slider.value_at(65.), 6.);
+        assert_eq!(slider.value_at(110.), 9.);
+        slider.on_event(&Event::KeyDown(Key::Left));
+        assert_eq!(slider.value, 7.);
+        assert!(slider.set_value(&Value::Number(-5.)));
+        assert_eq!(Widget::value(&slider), Some(Value::Number(0.)));
+        assert!(!slider.set_value(&Value::Bool(true)));
+        slider.max = 0.;
+        assert_eq!(slider.ratio(), 0.);
+    }
+
+    #[test]
+    fn select_in_dropdown() {
+        let mut dropdown = Dropdown::new(&["low", "medium", "high"]);
+        dropdown.rect = Rect::new(0., 100., 150., 30.);
+        let click = |x, y| Event::MouseDown(MouseButton::Left, (x, y));
+
+        assert!(!dropdown.contains((10., 50.)));
+        dropdown.on_event(&click(10., 110.));
+        assert!(dropdown.is_open);
+
+        // Options are stacked under the box.
+        assert!(dropdown.contains((10., 50.)));
+        assert_eq!(dropdown.on_event(&click(10., 50.)), Status::Changed);
+        assert_eq!(dropdown.selected, 1);
+        assert!(!dropdown.is_open);
+
+        let down = Event::KeyDown(Key::Down);
+        assert_eq!(dropdown.on_event(&down), Status::Changed);
+        assert_eq!(dropdown.selected_option(), Some("high"));
+        assert_eq!(dropdown.on_event(&down), Status::Consumed);
+        assert_eq!(dropdown.value(), Some(Value::Index(2)));
+
+        dropdown.on_event(&click(10., 110.));
+        dropdown.on_event(&Event::Blur);
+        assert!(!dropdown.is_open);
+    }
+
+    #[test]
+    fn scroll_and_clip_panel() {
+        let font = helvetica();
+        let mut content = Container::col();
+        for i in 0..5 {
+            let id = format!("button_{}", i);
+            content = content.push(Button::new(Text::new("")).id(&id));
+        }
+        let mut panel = ScrollPanel::new(content);
+        panel.compute_layout(Rect::new(0., 0., 100., 60.), &font);
+        assert_eq!(panel.max_offset(), 150. - 60.);
+
+        panel.on_event(&Event::Scroll(-10.));
+        assert_eq!(panel.offset, panel.max_offset());
+
+        // Background, a quad and a text by button, then the scrollbar.
+        let mut queue = RenderQueue::default();
+        panel.draw(&mut queue, &font);
+        let items = queue.items();
+        assert_eq!(items.len(), 1 + 5 * 2 + 1);
+        let clip = Clip {
+            x: 0.,
+            y: 0.,
+            width: 100.,
+            height: 60.,
+        };
+        assert!(items[1..11].iter().all(|item| item.clip == Some(clip)));
+        assert!(items[0].clip.is_none() && items[11].clip.is_none());
+
+        // Quads are centered, the last button is at the bottom and the
+        // first one out of the panel.
+        assert_eq!(items[9].model[(1, 3)], 15.);
+        assert_eq!(items[1].model[(1, 3)], 135.);
+
+        // Clipped buttons can't be hit.
+        let mut elements = vec![Element::new(panel)];
+        assert_eq!(event::hit(&elements, (50., 130.)), None);
+        let path = event::hit(&elements, (50., 10.)).unwrap();
+        assert_eq!(path, vec![0, 0, 4]);
+
+        let mut messages = vec![];
+        let up = Event::MouseUp(MouseButton::Left, (50., 10.));
+        event::dispatch(&mut elements, &path, &up, &mut messages);
+        assert_eq!(messages, vec![Message::Clicked("button_4".into())]);
+        // Scrolling a button bubbles to the panel.
+        let scroll = Event::Scroll(10.);
+        let status =
+            event::dispatch(&mut elements, &path, &scroll, &mut messages);
+        assert_eq!(status, Status::Consumed);
+        assert_eq!(messages.len(), 1);
+    }
+}
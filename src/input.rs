@@ -46,10 +46,7 @@ impl Input {
 
     /// Add keycode if not already there.
     pub fn register_key(&mut self, keycode: Key) {
-        self.keyboard.entry(keycode).or_insert_with(|| KeyState {
-            once: false,
-            delay: Instant::now(),
-        });
+        self.keyboard.entry(keycode).or_insert_with(KeyState::new);
     }
 
     pub fn update_cursor_position(&mut self, position: Cursor) {
@@ -57,10 +54,7 @@ impl Input {
     }
 
     pub fn register_click(&mut self, button: MouseButton) {
-        self.mouse.entry(button).or_insert_with(|| KeyState {
-            once: false,
-            delay: Instant::now(),
-        });
+        self.mouse.entry(button).or_insert_with(KeyState::new);
     }
 
     pub fn remove_click(&mut self, button: MouseButton) {
@@ -71,12 +65,14 @@ impl Input {
 
     /// Return true is specified key is pressed.
     pub fn is_pressed(&self, keycode: Key) -> bool {
-        self.keyboard.contains_key(&keycode)
+        self.keyboard
+            .get(&keycode)
+            .is_some_and(|key| !key.is_consumed)
     }
 
     pub fn is_pressed_delay(&mut self, delay: Duration, keycode: &Key) -> bool {
         if let Some(key) = self.keyboard.get_mut(&keycode) {
-            if key.delay.elapsed() >= delay {
+            if !key.is_consumed && key.delay.elapsed() >= delay {
                 key.delay = Instant::now();
                 return true;
             }
@@ -98,7 +94,8 @@ impl Input {
 
     /// Return true is specified key is clicked.
     pub fn is_clicked(&mut self, button: MouseButton) -> bool {
-        let is_clicked = self.mouse.contains_key(&button);
+        let is_clicked =
+            self.mouse.get(&button).is_some_and(|b| !b.is_consumed);
 
         if is_clicked {
             self.cursor.is_dragged = true;
@@ -123,7 +120,7 @@ impl Input {
 
     pub fn is_pressed_once(&mut self, keycode: Key) -> bool {
         if let Some(pressed) = self.keyboard.get_mut(&keycode) {
-            if !pressed.once {
+            if !pressed.once && !pressed.is_consumed {
                 pressed.once = true;
                 return true;
             }
@@ -147,7 +144,7 @@ impl Input {
     /// Will call only once the closure when the given closure is pressed.
     pub fn is_clicked_once(&mut self, button: MouseButton) -> bool {
         if let Some(clicked) = self.mouse.get_mut(&button) {
-            if !clicked.once {
+            if !clicked.once && !clicked.is_consumed {
                 clicked.once = true;
                 return true;
             }
@@ -156,6 +153,31 @@ impl Input {
         false
     }
 
+    /// Held keys, consumed or not.
+    pub fn pressed_keys(&self) -> Vec<Key> {
+        self.keyboard.keys().cloned().collect()
+    }
+
+    /// Held buttons, consumed or not.
+    pub fn pressed_buttons(&self) -> Vec<MouseButton> {
+        self.mouse.keys().cloned().collect()
+    }
+
+    /// The key is released for the others until it's pressed again,
+    /// like when the gui used it.
+    pub fn consume_key(&mut self, keycode: Key) {
+        if let Some(key) = self.keyboard.get_mut(&keycode) {
+            key.is_consumed = true;
+        }
+    }
+
+    /// Same as the keys, until the button is pressed again.
+    pub fn consume_click(&mut self, button: MouseButton) {
+        if let Some(clicked) = self.mouse.get_mut(&button) {
+            clicked.is_consumed = true;
+        }
+    }
+
     #[allow(unused)]
     pub fn clear(&mut self) {
         self.keyboard.clear();
@@ -167,6 +189,17 @@ impl Input {
 pub struct KeyState {
     once: bool,
     delay: Instant,
+    is_consumed: bool,
+}
+
+impl KeyState {
+    fn new() -> Self {
+        Self {
+            once: false,
+            delay: Instant::now(),
+            is_consumed: false,
+        }
+    }
 }
 
 /// Mouse state.
@@ -177,10 +210,12 @@ pub struct Cursor {
     pub delta: (f64, f64),
     pub has_moved: bool,
     pub is_dragged: bool,
+    // Wheel lines of the frame.
+    pub scroll: f64,
 }
 
 /// List of all keys available.
-#[derive(Hash, Eq, PartialEq, Copy, Clone)]
+#[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
 pub enum Key {
     A,
     B,
@@ -230,7 +265,7 @@ pub enum Key {
 }
 
 impl Key {
-    fn to_str(&self) -> Option<String> {
+    pub fn to_str(self) -> Option<String> {
         let value = match self {
             Key::A => Some("a"),
             Key::B => Some("b"),
@@ -284,7 +319,7 @@ pub struct Modifier {
     pub os: bool,
 }
 
-#[derive(Hash, Eq, PartialEq)]
+#[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
 pub enum MouseButton {
     Right,
     Left,
@@ -1,3 +1,5 @@
+mod animation;
+mod bvh;
 mod camera;
 mod colliders;
 mod debug_scenes;
@@ -11,6 +13,7 @@ mod math;
 mod platform;
 mod player;
 mod renderer;
+mod scene_graph;
 mod tilemap;
 mod time;
 mod wall;
@@ -21,9 +24,14 @@ use editor::Editor;
 use entities::{Entities, Entity};
 use game_loop::GameLoop;
 use global::*;
-use gui::{Button, Container, TextInput, GUI};
+use gui::{Message, Theme, Value, GUI};
 use input::{Input, Key};
-use renderer::{Colors, Font, Renderer, Rgb, Rgba, Text};
+use renderer::{
+    DrawItem, Face, Font, IconAtlas, Model, Quality, Renderer, Rgba, Text,
+    Transform, Vector,
+};
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tilemap::init_world_and_player;
 
 fn main() {
@@ -48,66 +56,60 @@ fn main() {
         "assets/fonts/Helvetica/helvetica.json",
         "assets/fonts/Helvetica/helvetica.png",
     );
+    if let Err(e) = font.add_fallback("assets/fonts/DejaVu/DejaVuSans.ttf") {
+        dbg!(e);
+    }
+    let faces = [
+        (Face::Bold, "assets/fonts/DejaVu/DejaVuSans-Bold.ttf"),
+        (Face::Italic, "assets/fonts/DejaVu/DejaVuSans-Oblique.ttf"),
+        (
+            Face::BoldItalic,
+            "assets/fonts/DejaVu/DejaVuSans-BoldOblique.ttf",
+        ),
+    ];
+    for (face, path) in faces.iter() {
+        if let Err(e) = font.add_face(*face, path) {
+            dbg!(e);
+        }
+    }
+    font.set_icons(IconAtlas::new(
+        "assets/icons/keys.json",
+        "assets/icons/keys.png",
+    ));
+
+    let prompt = Text::markup(
+        "Press <icon=key_w><icon=key_a><icon=key_s><icon=key_d> to <b>move</b>",
+    )
+    .expect("Invalid markup of the prompt.")
+    .position(Vector(20., 20., 0.));
 
     let mut counter = 0;
 
-    let submit = Button::new(Text::new("counter: 0").color(Rgb::black()))
-        .width(120.)
-        .height(40.)
-        .margin(2.5)
-        .bg_color(Rgba::new(1., 0.5, 0.5, 1.))
-        .callback(move |text| {
-            counter += 1;
-            text.set_content(&format!("counter: {}", counter));
-        });
-
-    let save = Button::new(Text::new("save"))
-        .width(120.)
-        .height(40.)
-        .margin(2.5)
-        .bg_color(Rgba::new(1., 1., 0.5, 1.))
-        .text_color(Rgb::new(0., 0., 0.))
-        .callback(move |_| {
-            dbg!("clicked!");
-        });
-
-    let test = Button::new(Text::new("test"))
-        .width(120.)
-        .height(40.)
-        .margin(2.5)
-        .bg_color(Rgba::new(0.5, 1., 0.5, 1.))
-        .text_color(Rgb::new(0., 0., 1.))
-        .callback(move |_| {
-            dbg!("clicked!");
-        });
-
-    let text_input = TextInput::new()
-        .label(Text::new("Text label :").color(Rgb::white()))
-        .value(Text::new("2").color(Rgb::white()))
-        .padding(8.)
-        .only_numbers(true)
-        .on_update(|t| {
-            dbg!(t.content());
-        });
-
-    let col_left = Container::row().margin(5.).push(test);
-    let row_right = Container::col()
-        .margin(5.)
-        .push(text_input)
-        .push(save)
-        .push(submit);
-
-    let container = Container::row()
-        .width(1200.)
-        .height(800.)
-        .margin(5.)
-        .push(col_left)
-        .push(row_right);
-
-    let mut gui = GUI::new().add_elem(container);
-    gui.draw(&mut font);
+    // Looks of the widgets, in the order of the theme dropdown.
+    let themes: Vec<Theme> = ["dark", "light"]
+        .iter()
+        .map(|name| {
+            Theme::from_file(&format!("assets/themes/{}.json", name))
+                .expect("Couldn't load the theme.")
+        })
+        .collect();
+
+    // Edited while the game runs, see `GUI::hot_reload`.
+    let mut gui =
+        GUI::from_file("assets/gui/hud.json").expect("Couldn't load the gui.");
+    gui.set_theme(themes[0].clone());
 
     let (mut world, mut player) = init_world_and_player(&mut entities);
+    // Waving next to the player, skinned and animated.
+    match Model::from_gltf("assets/models/skin/SimpleSkin.gltf") {
+        Ok(model) => {
+            let position =
+                Vector::from_glm(player.world_pos) + Vector(2., 0., 0.);
+            let transform = Transform::from_pos(position);
+            entities.insert_model(model, transform);
+        }
+        Err(e) => eprintln!("{}", e),
+    }
     let mut camera = Camera::new(&player);
 
     let mut renderer =
@@ -117,6 +119,7 @@ fn main() {
     *VIEW_MATRIX.lock().unwrap() = editor.camera.get_look_at();
 
     let mut is_debug_mode = false;
+    let mut progress = 30.;
 
     game_loop.start(|time| {
         platform.map_winit_inputs(&mut input);
@@ -125,7 +128,50 @@ fn main() {
             is_debug_mode = !is_debug_mode;
         }
 
+        if let Err(e) = gui.hot_reload() {
+            eprintln!("{}", e);
+        }
         gui.on_event(&mut input);
+        for message in gui.messages().to_vec() {
+            match message {
+                Message::Clicked(id) if id == "counter" => {
+                    counter += 1;
+                    let label = format!("counter: {}", counter);
+                    gui.set_value("counter", Value::Text(label));
+                }
+                Message::Clicked(id) if id == "save" => {
+                    editor::save_thumbnails(
+                        &mut renderer,
+                        &entities,
+                        &world,
+                        &player,
+                    );
+                }
+                Message::Clicked(id) if id == "editor" => {
+                    is_debug_mode = !is_debug_mode;
+                }
+                Message::Changed(id, Value::Index(i)) if id == "quality" => {
+                    if let Some(quality) = Quality::ALL.get(i) {
+                        renderer.post_settings_mut().set_quality(*quality);
+                    }
+                }
+                Message::Changed(id, Value::Index(i)) if id == "theme" => {
+                    if let Some(theme) = themes.get(i) {
+                        gui.set_theme(theme.clone());
+                    }
+                }
+                _ => {}
+            }
+        }
+        gui.bind("shadow_map", &mut renderer.debug_info.show_shadow_map);
+        let post_settings = renderer.post_settings_mut();
+        gui.bind("msaa", &mut post_settings.msaa);
+        for (name, is_enabled) in post_settings.passes_mut().iter_mut() {
+            let id = format!("post_{}", name.replace(' ', "_"));
+            gui.bind(&id, &mut **is_enabled);
+        }
+        gui.bind("progress", &mut progress);
+        gui.set_value("progress_bar", Value::Number(progress / 100.));
 
         // Editor stuff here. With menu etc...
         player.update_player(
@@ -148,11 +194,51 @@ fn main() {
                 &mut renderer,
                 time,
             );
+
+            // After the editor, the fields follow the dragged mesh.
+            if let Some(handle) = editor.selected() {
+                let position =
+                    &mut entities.get_mut(&handle).transform.position;
+                gui.bind("position_x", &mut position.0);
+                gui.bind("position_y", &mut position.1);
+                gui.bind("position_z", &mut position.2);
+            }
+
+            if input.modifiers.shift && input.is_pressed_once(Key::T) {
+                editor::save_thumbnails(
+                    &mut renderer,
+                    &entities,
+                    &world,
+                    &player,
+                );
+            }
         }
 
+        entities.animate(time.dt as f32);
         renderer.clear_screen();
         renderer.draw(&mut entities, &world, &player);
-        gui.draw(&mut font);
+        gui.draw(renderer.queue_mut(), &font);
+        if !is_debug_mode {
+            renderer.queue_mut().push(DrawItem::text(&prompt));
+        }
+
+        // Same frame as the screen, at a higher resolution.
+        if input.modifiers.ctrl && input.is_pressed_once(Key::P) {
+            let screenshot =
+                renderer.screenshot(&entities, &mut font, SCREENSHOT_SIZE);
+            let seconds = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs());
+            let path = format!("{}{}.png", SCREENSHOTS_DIR_PATH, seconds);
+
+            if let Err(e) = fs::create_dir_all(SCREENSHOTS_DIR_PATH)
+                .and_then(|_| screenshot.save(&path))
+            {
+                dbg!(e);
+            }
+        }
+
+        renderer.flush(&entities, &mut font);
 
         // Actually "draw": swap the back buffer into the front buffer.
         platform.swap_buffers();
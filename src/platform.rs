@@ -7,9 +7,12 @@ use super::input::{Cursor, Input, Key, Modifier, MouseButton};
 // use super::platform::{Platform, PlatformWrapper};
 use crate::global::*;
 use gl;
+#[cfg(target_os = "linux")]
+use glutin::os::unix::HeadlessContextExt;
 use glutin::{
-    dpi, Api, ContextBuilder, ContextWrapper, DeviceEvent, ElementState, Event,
-    EventsLoop, GlRequest, MouseButton as GlMouseButton, PossiblyCurrent,
+    dpi, Api, Context, ContextBuilder, ContextWrapper, CreationError,
+    DeviceEvent, ElementState, Event, EventsLoop, GlProfile, GlRequest,
+    MouseButton as GlMouseButton, MouseScrollDelta, PossiblyCurrent,
     VirtualKeyCode, Window as GlutinWindow, WindowBuilder, WindowEvent,
 };
 use nalgebra_glm as glm;
@@ -90,10 +93,14 @@ impl WinitPlatform {
             *ORTHO_MATRIX.lock().unwrap() =
                 glm::ortho(0., SCREEN_WIDTH, 0., SCREEN_HEIGHT, -1., 1.);
 
-            // opengl::set_viewport(
-            //     (width * dpi) as i32,
-            //     (height * dpi) as i32,
-            // );
+            // In pixels, the renderer resizes its targets to match.
+            let physical_size = inner_size.to_physical(dpi);
+            gl::Viewport(
+                0,
+                0,
+                physical_size.width as i32,
+                physical_size.height as i32,
+            );
         }
     }
 
@@ -116,6 +123,7 @@ impl WinitPlatform {
         let mut window_size_changed = false;
         let mut should_close = false;
         game_input.cursor.has_moved = false;
+        game_input.cursor.scroll = 0.;
 
         self.event_loop
             .poll_events(|glutin_event| match &glutin_event {
@@ -148,6 +156,17 @@ impl WinitPlatform {
 
                             game_input.update_cursor_position(moved);
                         }
+                        // In lines, positive upward.
+                        WindowEvent::MouseWheel { delta, .. } => {
+                            game_input.cursor.scroll += match delta {
+                                MouseScrollDelta::LineDelta(_, y) => {
+                                    f64::from(*y)
+                                }
+                                MouseScrollDelta::PixelDelta(position) => {
+                                    position.y / 20.
+                                }
+                            };
+                        }
                         WindowEvent::MouseInput { state, button, .. } => {
                             let is_clicked = *state == ElementState::Pressed;
 
@@ -308,11 +327,54 @@ impl WinitPlatform {
     }
 }
 
+/// OpenGL context without any window, to only render offscreen.
+/// On Linux, it's an OSMesa context so it runs without a gpu nor a
+/// display server with a software OpenGL like Mesa llvmpipe.
+#[allow(unused)]
+pub struct HeadlessPlatform {
+    _context: Context<PossiblyCurrent>,
+    // Only needed by the context on macOS and Windows.
+    _event_loop: Option<EventsLoop>,
+}
+
+impl HeadlessPlatform {
+    #[allow(unused)]
+    pub fn new((width, height): (u32, u32)) -> Result<Self, CreationError> {
+        let size = dpi::PhysicalSize::new(f64::from(width), f64::from(height));
+        let builder = ContextBuilder::new()
+            .with_gl(GlRequest::Specific(Api::OpenGl, (4, 1)))
+            .with_gl_profile(GlProfile::Core);
+
+        #[cfg(target_os = "linux")]
+        let (context, event_loop) = (builder.build_osmesa(size)?, None);
+        #[cfg(not(target_os = "linux"))]
+        let (context, event_loop) = {
+            let event_loop = EventsLoop::new();
+            let context = builder.build_headless(&event_loop, size)?;
+            (context, Some(event_loop))
+        };
+
+        let context = unsafe {
+            context
+                .make_current()
+                .map_err(|(_, e)| CreationError::OsError(e.to_string()))?
+        };
+        gl::load_with(|symbol| context.get_proc_address(symbol) as *const _);
+
+        Ok(Self {
+            _context: context,
+            _event_loop: event_loop,
+        })
+    }
+}
+
 pub fn check_platform_supported() {
     let _target_os: &str = if cfg!(target_os = "macos") {
         "macOS"
     } else if cfg!(target_os = "windows") {
         "Windows"
+    } else if cfg!(target_os = "linux") {
+        "Linux"
     } else {
         panic!("Target system not currently supported");
     };
@@ -327,5 +389,7 @@ pub fn check_platform_supported() {
 }
 
 pub fn is_desktop() -> bool {
-    cfg!(target_os = "macos") || cfg!(target_os = "windows")
+    cfg!(target_os = "macos")
+        || cfg!(target_os = "windows")
+        || cfg!(target_os = "linux")
 }
@@ -1,17 +1,49 @@
+use crate::animation::{
+    Animator, Channel, Clip, Interpolation, Keyframes, Pose, Target,
+};
 use crate::camera::{CamRotation, Camera};
 use crate::entities::{Entities, Entity};
 use crate::global::{TILEMAP_HEIGHT, TILEMAP_WIDTH, TILE_SIZE};
 use crate::input::{Input, Key};
+use crate::renderer::Transform;
 use crate::tilemap::{AbsolutePosition, Tile, World};
 use crate::time::Time;
 use nalgebra_glm as glm;
 use std::time::Duration;
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PlayerState {
+    Idle,
+    Walk,
+    // Walking against a wall.
+    Push,
+}
+
+impl PlayerState {
+    fn clip_name(&self) -> &'static str {
+        match self {
+            Self::Idle => "idle",
+            Self::Walk => "walk",
+            Self::Push => "push",
+        }
+    }
+}
+
+// How long the player keeps pushing after bumping into a wall.
+const PUSH_DURATION: f32 = 0.3;
+// Time to blend from a clip to another.
+const BLEND_DURATION: f32 = 0.15;
+
 #[derive(Debug)]
 pub struct Player {
     pub tilemap_pos: AbsolutePosition,
     pub world_pos: glm::TVec3<f32>,
     pub end_pos: glm::TVec3<f32>,
+    pub state: PlayerState,
+    push_time: f32,
+    animator: Animator,
+    // Animated transform of the player mesh, relative to its position.
+    pub pose: Transform,
 }
 
 impl Player {
@@ -24,13 +56,39 @@ impl Player {
                 + tilemap_pos.tilemap.y as f32 * TILE_SIZE,
         );
 
+        let mut animator = Animator::new(player_clips());
+        animator.play(PlayerState::Idle.clip_name(), 0.);
+
         Self {
             tilemap_pos,
             world_pos,
             end_pos: world_pos,
+            state: PlayerState::Idle,
+            push_time: 0.,
+            animator,
+            pose: Transform::default(),
         }
     }
 
+    fn update_animation(&mut self, dt: f32) {
+        self.push_time = (self.push_time - dt).max(0.);
+
+        self.state = if self.push_time > 0. {
+            PlayerState::Push
+        } else if glm::distance(&self.world_pos, &self.end_pos) > 0.01 {
+            PlayerState::Walk
+        } else {
+            PlayerState::Idle
+        };
+
+        self.animator.play(self.state.clip_name(), BLEND_DURATION);
+        self.animator.update(dt);
+
+        let mut rest = Pose::new();
+        rest.insert(Target::Part(0), Transform::default());
+        self.pose = self.animator.pose(&rest)[&Target::Part(0)];
+    }
+
     pub fn update_player(
         &mut self,
         time: &Time,
@@ -117,6 +175,7 @@ impl Player {
         let speed = 7. * time.dt as f32;
         let a = glm::vec3(speed, 0., speed);
         self.world_pos = glm::lerp_vec(&self.world_pos, &self.end_pos, &a);
+        self.update_animation(time.dt as f32);
 
         // Only if input is pressed.
         if let Some(dir) = direction {
@@ -144,6 +203,10 @@ impl Player {
                 return;
             }
 
+            if self.is_facing_wall(world, delta, entities) {
+                self.push_time = PUSH_DURATION;
+            }
+
             // let mut pushable_walls: Vec<glm::TVec2<i32>> = vec![];
             // let is_pushable = self.is_wall_pushable(
             //     &mut map,
@@ -218,6 +281,25 @@ impl Player {
     //     }
     // }
 
+    fn is_facing_wall(
+        &self,
+        world: &World,
+        delta: glm::TVec2<i32>,
+        entities: &Entities,
+    ) -> bool {
+        match world.get_next_position(&self.tilemap_pos, &delta) {
+            Some(AbsolutePosition {
+                handle: Some(handle),
+                tilemap: pos,
+                ..
+            }) => {
+                let tile = entities.get(&handle).get_tile(pos.x, pos.y);
+                matches!(tile, Tile::Wall(_))
+            }
+            _ => false,
+        }
+    }
+
     pub fn can_move(
         &mut self,
         world: &mut World,
@@ -298,3 +380,56 @@ fn convert_dir_from_cam(
         },
     }
 }
+
+/// Procedural clips of the player cube, it has no skeleton.
+fn player_clips() -> Vec<Clip> {
+    let channel = |times: Vec<f32>, keyframes| Channel {
+        target: Target::Part(0),
+        interpolation: Interpolation::Linear,
+        times,
+        keyframes,
+    };
+    let translation = |heights: &[f32]| {
+        Keyframes::Translation(
+            heights.iter().map(|y| glm::vec3(0., *y, 0.)).collect(),
+        )
+    };
+    let scale = |values: &[(f32, f32)]| {
+        Keyframes::Scale(
+            values
+                .iter()
+                .map(|(xz, y)| glm::vec3(*xz, *y, *xz))
+                .collect(),
+        )
+    };
+
+    vec![
+        // Slow breathing.
+        Clip::new(
+            "idle",
+            vec![channel(
+                vec![0., 0.8, 1.6],
+                scale(&[(1., 1.), (1.02, 0.96), (1., 1.)]),
+            )],
+        ),
+        // Small hops, squashed on landing.
+        Clip::new(
+            "walk",
+            vec![
+                channel(vec![0., 0.15, 0.3], translation(&[0., 0.15, 0.])),
+                channel(
+                    vec![0., 0.15, 0.3],
+                    scale(&[(1.08, 0.9), (0.95, 1.08), (1.08, 0.9)]),
+                ),
+            ],
+        ),
+        // Flattened against the wall.
+        Clip::new(
+            "push",
+            vec![channel(
+                vec![0., 0.1, 0.2],
+                scale(&[(1.1, 0.85), (1.05, 0.9), (1.1, 0.85)]),
+            )],
+        ),
+    ]
+}
@@ -0,0 +1,175 @@
+use image::{imageops, GrayImage};
+
+// Between the packed images, so filtering doesn't bleed.
+const GAP: u32 = 1;
+
+/// Packs areas in rows as tall as their tallest area.
+#[derive(Debug)]
+pub struct ShelfPacker {
+    width: u32,
+    height: u32,
+    shelves: Vec<Shelf>,
+}
+
+#[derive(Debug)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    // Start of the free space.
+    x: u32,
+}
+
+impl ShelfPacker {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            shelves: vec![],
+        }
+    }
+
+    /// Top left corner of the area, None when full.
+    pub fn pack(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let atlas_width = self.width;
+
+        // The shelf wasting the least height.
+        let shelf = self
+            .shelves
+            .iter_mut()
+            .filter(|shelf| {
+                shelf.height >= height && atlas_width - shelf.x >= width
+            })
+            .min_by_key(|shelf| shelf.height - height);
+        if let Some(shelf) = shelf {
+            let position = (shelf.x, shelf.y);
+            shelf.x += width;
+            return Some(position);
+        }
+
+        let y = self
+            .shelves
+            .last()
+            .map_or(0, |shelf| shelf.y + shelf.height);
+        if width > self.width || y + height > self.height {
+            return None;
+        }
+        self.shelves.push(Shelf {
+            y,
+            height,
+            x: width,
+        });
+
+        Some((0, y))
+    }
+
+    /// Twice as tall, packed areas keep their place.
+    pub fn grow(&mut self) {
+        self.height *= 2;
+    }
+}
+
+/// Single channel atlas growing when full.
+#[derive(Debug)]
+pub struct GlyphAtlas {
+    pub image: GrayImage,
+    packer: ShelfPacker,
+    // Changes with the image, to upload it again.
+    pub revision: u32,
+}
+
+impl GlyphAtlas {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            image: GrayImage::new(width, height),
+            packer: ShelfPacker::new(width, height),
+            revision: 0,
+        }
+    }
+
+    /// Start from the pixels of a packed atlas, new images are put
+    /// under them.
+    pub fn from_image(image: GrayImage) -> Self {
+        let (width, height) = image.dimensions();
+        let mut packer = ShelfPacker::new(width, height);
+        packer.pack(width, height);
+
+        Self {
+            image,
+            packer,
+            revision: 0,
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.image.dimensions()
+    }
+
+    /// Copy the pixels in a free area and return its top left corner.
+    /// None if the image is wider than the atlas.
+    pub fn insert(&mut self, pixels: &GrayImage) -> Option<(u32, u32)> {
+        let (width, height) = pixels.dimensions();
+        if width + GAP > self.image.width() {
+            return None;
+        }
+
+        let (x, y) = loop {
+            match self.packer.pack(width + GAP, height + GAP) {
+                Some(position) => break position,
+                None => self.grow(),
+            }
+        };
+        imageops::replace(&mut self.image, pixels, x, y);
+        self.revision += 1;
+
+        Some((x, y))
+    }
+
+    fn grow(&mut self) {
+        let (width, height) = self.size();
+        let mut image = GrayImage::new(width, height * 2);
+        imageops::replace(&mut image, &self.image, 0, 0);
+
+        self.image = image;
+        self.packer.grow();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    #[test]
+    fn pack_on_shelves() {
+        let mut packer = ShelfPacker::new(10, 10);
+        assert_eq!(packer.pack(6, 4), Some((0, 0)));
+        assert_eq!(packer.pack(4, 3), Some((6, 0)));
+        // Too tall for the first shelf.
+        assert_eq!(packer.pack(3, 5), Some((0, 4)));
+        assert_eq!(packer.pack(11, 1), None);
+        assert_eq!(packer.pack(5, 2), Some((3, 4)));
+        assert_eq!(packer.pack(10, 2), None);
+
+        packer.grow();
+        assert_eq!(packer.pack(10, 2), Some((0, 9)));
+    }
+
+    #[test]
+    fn grow_atlas() {
+        let mut atlas = GlyphAtlas::new(8, 4);
+        let glyph = GrayImage::from_pixel(3, 3, Luma([255]));
+
+        assert_eq!(atlas.insert(&glyph), Some((0, 0)));
+        assert_eq!(atlas.insert(&glyph), Some((4, 0)));
+        assert_eq!(atlas.size(), (8, 4));
+
+        // Full, twice as tall and the first glyphs are kept.
+        assert_eq!(atlas.insert(&glyph), Some((0, 4)));
+        assert_eq!(atlas.size(), (8, 8));
+        assert_eq!(atlas.image.get_pixel(4, 2)[0], 255);
+        assert_eq!(atlas.image.get_pixel(3, 0)[0], 0);
+        assert_eq!(atlas.revision, 3);
+
+        assert!(atlas.insert(&GrayImage::new(8, 1)).is_none());
+    }
+}
@@ -1,13 +1,21 @@
 use super::{
-    opengl,
-    shaders::{self, ShaderType},
-    Font, Mesh, Rgba, SunLight, Text, Transform, Vector,
+    frustum::{CullCount, Frustum},
+    material::Material,
+    opengl::{self, TexId, VBO},
+    queue::{Backend, Clip, DrawItem, DrawSource, Layer, RenderQueue, Shape},
+    shaders::{self, ShaderProgramId, ShaderType},
+    shadow::SHADOW_MAP_UNIT,
+    tile_batch::{TileBatch, TileBatches, TileInstance, TileKind},
+    Font, Mesh, Rgba, Transform, Vector,
 };
+use crate::entities::Handle;
 use crate::global::*;
 use crate::player::Player;
-use crate::tilemap::{Tile, Tilemap, World};
+use crate::tilemap::World;
 use crate::{Entities, Entity};
 use nalgebra_glm as glm;
+use std::mem;
+use std::ops::Range;
 use std::ptr;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -19,113 +27,290 @@ pub enum DrawMode {
     Points,
 }
 
+/// The shadow pass only writes the depth of the meshes seen from the
+/// sun, the color pass shades them.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RenderPass {
+    Shadow,
+    Color,
+}
+
 #[allow(unused)]
 /// Used for debug purpose only.
-pub fn draw_world(entities: &Entities, player: &Player, world: &World) {
+pub fn queue_world(
+    queue: &mut RenderQueue,
+    entities: &Entities,
+    player: &Player,
+    world: &World,
+    batches: &mut TileBatches,
+) {
     for i in 0..TILEMAPS_COUNT.0 {
         for j in 0..TILEMAPS_COUNT.1 {
-            let pos = glm::vec2(i as i32, j as i32);
-            let world_tilemap = world.get_tilemap(&pos);
+            let pos = glm::vec2(i, j);
 
-            if let Some(tilemap) = world_tilemap {
-                let tilemap = entities.get(&tilemap);
-                draw_tilemap(entities, player, world, tilemap, Some(&pos));
+            if let Some(handle) = world.get_tilemap(&pos) {
+                let batch = batches.get(entities, world, &handle, &pos);
+                queue_tilemap(queue, entities, player, batch);
             }
         }
     }
 }
 
-pub fn draw_tilemap(
+/// Submit the grounds and the walls of a tilemap as one instanced
+/// item each, then the player if it's on it.
+pub fn queue_tilemap(
+    queue: &mut RenderQueue,
     entities: &Entities,
     player: &Player,
-    world: &World,
-    tilemap: &Tilemap,
-    offset: Option<&glm::TVec2<i32>>,
+    batch: &TileBatch,
 ) {
-    for x in 0..TILES_COUNT.0 {
-        for y in 0..TILES_COUNT.1 {
-            let tile = tilemap.get_tile(x, y);
-
-            let mut x = world.offset.x + x as f32 * TILE_SIZE;
-            // Grid is in 2d, so "y" become "z" in 3d.
-            let mut z = world.offset.y + y as f32 * TILE_SIZE;
-
-            // Used for drawing the world map.
-            if let Some(offset) = offset {
-                x += offset.x as f32 * TILEMAP_WIDTH;
-                z += offset.y as f32 * TILEMAP_HEIGHT;
-            }
+    // Refence of all Tile meshes.
+    let markers = &entities
+        .markers
+        .as_ref()
+        .expect("WTF, marker should be loaded before");
+
+    let kinds = [
+        (TileKind::Ground, markers.ground),
+        (TileKind::Wall, markers.wall),
+    ];
+    for (kind, handle) in kinds.iter() {
+        let range = batch.range(*kind);
+        if range.is_empty() || entities.get(handle).is_hidden {
+            continue;
+        }
+
+        let material = entities.get(handle).material;
+        queue.push(DrawItem::instances(*handle, material, batch.vbo, range));
+    }
 
-            let position = Transform::from_pos(Vector(x, 0., z));
-            draw_tile(entities, player, &tile, &position);
+    if batch.has_player {
+        let position = Transform::from_pos(Vector(
+            player.world_pos.x,
+            player.world_pos.y,
+            player.world_pos.z,
+        ));
+        let model = position.to_model() * player.pose.to_model();
+
+        if let Some(item) = mesh_item(entities, &markers.player, model) {
+            queue.push(item);
         }
     }
 }
 
-pub fn draw_tile(
+/// Submit all meshes of the scene graph with their world transform,
+/// if their box is in the frustum.
+/// Markers are only templates, drawn by the tilemaps.
+pub fn queue_scene(
+    queue: &mut RenderQueue,
     entities: &Entities,
-    player: &Player,
-    tile: &Tile,
-    position: &Transform,
-) {
-    // Refence of all Tile meshes.
-    let markers = &entities
+    frustum: &Frustum,
+) -> CullCount {
+    let mut count = CullCount::default();
+    let markers = entities
         .markers
         .as_ref()
-        .expect("WTF, marker should be loaded before");
+        .map(|m| [m.ground, m.wall, m.player, m.quad]);
+    let is_marker = |handle: &Handle<Mesh>| {
+        markers.iter().any(|markers| markers.contains(handle))
+    };
 
-    // Always draw the ground (for now).
-    let ground = entities.get(&markers.ground);
-
-    // Match the tile type, and draw accordingly.
-    // After, i should call func like "draw_player" or "draw_wall".
-    match tile {
-        Tile::Wall(handle) => {
-            draw_ground(ground, position);
-            let wall = entities.get(handle);
-            let mut transform = position.clone();
-            // transform.position.1 = 1.;
-            transform.position =
-                Vector(wall.float_pos.x, wall.float_pos.y, wall.float_pos.z);
-            draw_mesh(&entities.get(&markers.wall), None, &transform);
+    for (mesh, handle) in entities.meshes.iter() {
+        if is_marker(handle) {
+            continue;
         }
-        Tile::Player => {
-            draw_ground(ground, position);
-            let mut transform = position.clone();
-            transform.position = Vector(
-                player.world_pos.x,
-                player.world_pos.y,
-                player.world_pos.z,
-            );
-
-            draw_mesh(&entities.get(&markers.player), None, &transform);
+
+        let mut model = mesh.transform.to_model();
+        // Perform parent transform to child.
+        if let Some(parent) = mesh.parent {
+            model = entities.world_matrix(&parent) * model;
         }
-        Tile::Ground => {
-            draw_ground(ground, position);
+
+        if let Some(item) = mesh_item(entities, handle, model) {
+            let is_visible = frustum.intersects(&mesh.world_aabb(&item.model));
+            count.add(is_visible);
+
+            if is_visible {
+                queue.push(item);
+            }
         }
-        Tile::Void => (),
+    }
+
+    count
+}
+
+// Hovered meshes are see-through, like transparent materials.
+fn mesh_item(
+    entities: &Entities,
+    handle: &Handle<Mesh>,
+    model: glm::Mat4,
+) -> Option<DrawItem> {
+    let mesh = entities.get(handle);
+    if mesh.is_hidden {
+        return None;
+    }
+
+    let is_transparent = mesh.is_hover
+        || entities
+            .mesh_material(mesh)
+            .is_some_and(|material| material.is_transparent());
+    let layer = if is_transparent {
+        Layer::Transparent
+    } else {
+        Layer::Opaque
     };
+
+    Some(DrawItem::mesh(*handle, mesh.material, model).layer(layer))
+}
+
+/// Draws the items of a render queue with OpenGL.
+/// Texts are skipped without a font.
+pub struct GlBackend<'a> {
+    entities: &'a Entities,
+    font: Option<&'a mut Font>,
+    // Of the items drawn last.
+    clip: Option<Clip>,
+    // Since the last end.
+    draw_call: u32,
+}
+
+impl<'a> GlBackend<'a> {
+    pub fn new(entities: &'a Entities, font: Option<&'a mut Font>) -> Self {
+        Self {
+            entities,
+            font,
+            clip: None,
+            draw_call: 0,
+        }
+    }
+
+    // Batched texts are drawn with the clip they were queued with.
+    fn set_clip(&mut self, clip: Option<Clip>) {
+        if self.clip != clip {
+            self.flush_texts();
+            opengl::set_scissor(clip);
+            self.clip = clip;
+        }
+    }
+
+    // Texts are batched until something else is drawn over them.
+    fn flush_texts(&mut self) {
+        if let Some(font) = self.font.as_mut() {
+            if font.has_queued() {
+                draw_texts(font);
+                self.draw_call += 1;
+            }
+        }
+    }
+
+    fn quad(&self) -> &'a Mesh {
+        let markers = self.entities.markers.as_ref();
+        self.entities
+            .get(&markers.expect("Markers not loaded").quad)
+    }
 }
 
-fn draw_ground(ground: &Mesh, pos: &Transform) {
-    let mut transform = pos.clone();
-    let y_scale = 0.2;
-    transform.position.1 -= y_scale;
-    transform.scale = Vector(1., y_scale, 1.);
-    draw_mesh(ground, None, &transform)
+impl<'a> Backend for GlBackend<'a> {
+    fn begin_layer(&mut self, layer: Layer, _pass: RenderPass) {
+        self.flush_texts();
+        self.set_clip(None);
+
+        unsafe {
+            match layer {
+                Layer::Opaque => {
+                    gl::Enable(gl::DEPTH_TEST);
+                    gl::DepthMask(gl::TRUE);
+                    gl::Disable(gl::BLEND);
+                }
+                // Still tested against the opaque depth, but they
+                // don't hide each other.
+                Layer::Transparent => {
+                    gl::Enable(gl::DEPTH_TEST);
+                    gl::DepthMask(gl::FALSE);
+                    gl::Enable(gl::BLEND);
+                    gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+                }
+                Layer::Ui => {
+                    gl::Disable(gl::DEPTH_TEST);
+                    gl::Enable(gl::BLEND);
+                    gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+                }
+            }
+        }
+    }
+
+    fn draw(&mut self, item: &DrawItem, pass: RenderPass) {
+        self.set_clip(item.clip);
+        if !matches!(item.source, DrawSource::Text(_)) {
+            self.flush_texts();
+            self.draw_call += 1;
+        }
+        let material = item.material.map(|handle| self.entities.get(&handle));
+
+        match &item.source {
+            DrawSource::Mesh(handle) => {
+                let mesh = self.entities.get(handle);
+                draw_mesh(mesh, material, &item.model, pass);
+            }
+            DrawSource::Instances {
+                mesh,
+                buffer,
+                range,
+            } => {
+                let mesh = self.entities.get(mesh);
+                draw_mesh_instanced(
+                    mesh,
+                    material,
+                    *buffer,
+                    range.clone(),
+                    pass,
+                );
+            }
+            DrawSource::Quad(color, shape) => {
+                draw_quad(self.quad(), &item.model, *color, shape);
+            }
+            DrawSource::Text(text) => {
+                if let Some(font) = self.font.as_mut() {
+                    font.queue(text);
+                }
+            }
+            DrawSource::Depth(texture) => {
+                draw_depth(self.quad(), *texture, &item.model);
+            }
+            DrawSource::Image { texture, tint } => {
+                draw_image(self.quad(), *texture, &item.model, *tint);
+            }
+        }
+    }
+
+    fn end(&mut self) -> u32 {
+        self.flush_texts();
+        self.set_clip(None);
+
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::BLEND);
+        }
+
+        mem::take(&mut self.draw_call)
+    }
 }
 
 // obb
-pub fn draw_bbox(entity: &Mesh, bbox: &Mesh) {
+// The model is the world matrix of the entity.
+pub fn draw_bbox(entity: &Mesh, bbox: &Mesh, model: &glm::Mat4) {
     if entity.collider.is_none() {
         return;
     }
 
-    let prog_id = SHADERS.activate(ShaderType::SimpleShader);
+    let prog_id = SHADERS.activate_permutation(
+        ShaderType::SimpleShader,
+        &bbox.flags.permutation(),
+    );
     opengl::use_vao(bbox.gpu_bound.vao);
 
     let identity = glm::identity();
-    let model = entity.transform.to_model();
 
     let size = bbox.bounding_box.max.to_glm();
     let center = bbox.bounding_box.center.to_glm();
@@ -136,8 +321,6 @@ pub fn draw_bbox(entity: &Mesh, bbox: &Mesh) {
     let model = model * bbox_model;
     shaders::set_matrix4(prog_id, "model", model.as_slice());
 
-    // Set shader flags.
-    bbox.flags.set_flags_to_shader(prog_id);
     let ebo = bbox.gpu_bound.ebo.unwrap();
 
     unsafe {
@@ -156,127 +339,172 @@ pub fn draw_bbox(entity: &Mesh, bbox: &Mesh) {
     }
 }
 
-pub fn draw_mesh(mesh: &Mesh, parent: Option<&Mesh>, position: &Transform) {
-    if mesh.is_hidden {
-        return;
-    }
+/// Meshes without material use the default one.
+pub fn draw_mesh(
+    mesh: &Mesh,
+    material: Option<&Material>,
+    model: &glm::Mat4,
+    pass: RenderPass,
+) {
+    let prog_id = bind_mesh(mesh, material, pass, false);
+    shaders::set_matrix4(prog_id, "model", model.as_slice());
 
-    let gpu_bound = &mesh.gpu_bound;
+    draw_elements(mesh, None);
+}
+
+/// Draw a range of instances of the buffer, each one with its own
+/// world matrix (see `TileInstance`).
+pub fn draw_mesh_instanced(
+    mesh: &Mesh,
+    material: Option<&Material>,
+    instances: VBO,
+    range: Range<usize>,
+    pass: RenderPass,
+) {
+    bind_mesh(mesh, material, pass, true);
+    opengl::bind_instances::<TileInstance>(instances, range.start);
 
-    let prog_id = SHADERS.activate(ShaderType::SimpleShader);
-    opengl::use_vao(gpu_bound.vao);
+    draw_elements(mesh, Some(range.len()));
 
-    shaders::set_bool(prog_id, "is_hover", mesh.is_hover);
-    if mesh.is_hover {
-        unsafe {
-            gl::Enable(gl::BLEND);
-            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+    opengl::unbind_instances();
+}
+
+// Activate the permutation of the mesh for the pass and set
+// everything but the world matrix. Return the program id.
+fn bind_mesh(
+    mesh: &Mesh,
+    material: Option<&Material>,
+    pass: RenderPass,
+    is_instanced: bool,
+) -> ShaderProgramId {
+    let default_material;
+    let material = match material {
+        Some(material) => material,
+        None => {
+            default_material = Material::default();
+            &default_material
         }
-    }
+    };
 
-    let mut entity_model = position.to_model();
-    // Perform parent transform to child.
-    if let Some(parent) = parent {
-        entity_model = parent.transform.to_model() * entity_model;
+    let permutation = mesh
+        .flags
+        .permutation()
+        .define("IS_INSTANCED", is_instanced);
+    let prog_id = match pass {
+        RenderPass::Shadow => {
+            SHADERS.activate_permutation(ShaderType::ShadowShader, &permutation)
+        }
+        RenderPass::Color => SHADERS.activate_permutation(
+            ShaderType::SimpleShader,
+            &material.permutation(permutation),
+        ),
+    };
+    opengl::use_vao(mesh.gpu_bound.vao);
+
+    if mesh.flags.has_skin {
+        shaders::set_matrix4_array(prog_id, "joints", &mesh.joint_matrices);
     }
 
-    shaders::set_matrix4(prog_id, "model", entity_model.as_slice());
+    if pass == RenderPass::Color {
+        shaders::set_bool(prog_id, "is_hover", mesh.is_hover);
+        shaders::set_i32(prog_id, "shadow_map", SHADOW_MAP_UNIT as i32);
+        material.bind(prog_id);
+    }
 
-    // Set shader flags.
-    mesh.flags.set_flags_to_shader(prog_id);
+    prog_id
+}
 
-    gpu_bound
-        .tex_ids
-        .iter()
-        .enumerate()
-        .for_each(|(index, tex_id)| {
-            shaders::set_sampler(prog_id, index);
-            opengl::bind_texture(*tex_id, index);
-        });
+// Issue the draw call of the bound mesh, instanced if there is a
+// count of instances.
+fn draw_elements(mesh: &Mesh, instances: Option<usize>) {
+    let gpu_bound = &mesh.gpu_bound;
+    let mode = match mesh.mode {
+        DrawMode::Triangles => gl::TRIANGLES,
+        DrawMode::Lines => gl::LINES,
+        _ => unimplemented!(),
+    };
+    let count = gpu_bound.primitives_len as i32;
 
     unsafe {
-        match mesh.mode {
-            DrawMode::Triangles => {
-                if let Some(ebo) = gpu_bound.ebo {
-                    gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
-                    gl::DrawElements(
-                        gl::TRIANGLES,
-                        gpu_bound.primitives_len as i32,
-                        gl::UNSIGNED_INT,
-                        ptr::null(),
-                    );
-                } else {
-                    gl::DrawArrays(
-                        gl::TRIANGLES,
-                        0,
-                        gpu_bound.primitives_len as i32,
-                    );
-                }
+        match (gpu_bound.ebo, instances) {
+            (Some(ebo), None) if mode == gl::TRIANGLES => {
+                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
+                gl::DrawElements(mode, count, gl::UNSIGNED_INT, ptr::null());
             }
-
-            DrawMode::Lines => {
-                gl::DrawArrays(gl::LINES, 0, gpu_bound.primitives_len as i32);
+            (Some(ebo), Some(instances)) if mode == gl::TRIANGLES => {
+                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
+                gl::DrawElementsInstanced(
+                    mode,
+                    count,
+                    gl::UNSIGNED_INT,
+                    ptr::null(),
+                    instances as i32,
+                );
+            }
+            (_, None) => gl::DrawArrays(mode, 0, count),
+            (_, Some(instances)) => {
+                gl::DrawArraysInstanced(mode, 0, count, instances as i32)
             }
-
-            _ => unimplemented!(),
         }
-        gl::Disable(gl::BLEND);
     }
 }
 
-// Draw directional sun light over the scene.
-pub fn draw_sun_light(_light_source: &SunLight) {
-    // draw stuff.
-}
-
-pub fn draw_quad(mesh: &Mesh, transform: &Transform, bg_color: Rgba) {
+/// The model is in screen space, see `DrawItem::quad`.
+pub fn draw_quad(
+    mesh: &Mesh,
+    model: &glm::Mat4,
+    bg_color: Rgba,
+    shape: &Shape,
+) {
     let prog_id = SHADERS.activate(ShaderType::QuadShader);
-    opengl::use_shader_program(prog_id);
 
-    shaders::set_matrix4(
-        prog_id,
-        "projection",
-        ORTHO_MATRIX.lock().unwrap().as_slice(),
-    );
+    // The quad is scaled by half its size.
+    let size = [model[(0, 0)] * 2., model[(1, 1)] * 2.];
+    shaders::set_matrix4(prog_id, "model", model.as_slice());
+    shaders::set_vec2(prog_id, "size", &size);
+    shaders::set_vec4(prog_id, "bg_c", &bg_color.into());
+    shaders::set_f32(prog_id, "radius", shape.radius);
+    shaders::set_f32(prog_id, "border_width", shape.border_width);
+    shaders::set_vec4(prog_id, "border_c", &shape.border_color.into());
+
+    opengl::use_vao(mesh.gpu_bound.vao);
+    draw_elements(mesh, None);
+}
 
-    let (position, _, scale) = transform.to_glm();
-    let mut model = glm::Mat4::identity();
-    model = glm::translate(
-        &model,
-        &glm::vec3(
-            (0.5 * scale.x) + position.x,
-            (0.5 * scale.y) + position.y,
-            0.,
-        ),
-    );
-    model = glm::scale(&model, &glm::vec3(scale.x * 0.5, scale.y * 0.5, 1.));
+/// Display a depth texture on a screen quad, for debugging.
+pub fn draw_depth(quad: &Mesh, texture: TexId, model: &glm::Mat4) {
+    let prog_id = SHADERS.activate(ShaderType::ShadowDebugShader);
 
     shaders::set_matrix4(prog_id, "model", model.as_slice());
-    shaders::set_vec3(prog_id, "bg_c", &bg_color.into());
+    shaders::set_i32(prog_id, "shadow_map", SHADOW_MAP_UNIT as i32);
+    opengl::bind_texture(texture, SHADOW_MAP_UNIT);
 
-    unsafe {
-        opengl::use_vao(mesh.gpu_bound.vao);
+    opengl::use_vao(quad.gpu_bound.vao);
+    draw_elements(quad, None);
+}
 
-        gl::Disable(gl::DEPTH_TEST);
-        gl::Disable(gl::BLEND);
+/// Display a texture on a screen quad, like the images of the ui.
+pub fn draw_image(quad: &Mesh, texture: TexId, model: &glm::Mat4, tint: Rgba) {
+    let prog_id = SHADERS.activate(ShaderType::ImageShader);
 
-        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, mesh.gpu_bound.ebo.unwrap());
-        gl::DrawElements(
-            gl::TRIANGLES,
-            mesh.gpu_bound.primitives_len as i32,
-            gl::UNSIGNED_INT,
-            ptr::null(),
-        );
-        gl::Enable(gl::DEPTH_TEST);
-    }
+    shaders::set_matrix4(prog_id, "model", model.as_slice());
+    shaders::set_vec4(prog_id, "tint", &[tint.r, tint.g, tint.b, tint.a]);
+    shaders::set_sampler(prog_id, 0);
+    opengl::bind_texture(texture, 0);
+
+    opengl::use_vao(quad.gpu_bound.vao);
+    draw_elements(quad, None);
 }
 
-// Render some text to the screen.
+// Render the texts queued in the font to the screen.
 // Used only for the editor/UI for now.
-pub fn draw_text(font: &mut Font, text: &Text) {
+pub fn draw_texts(font: &mut Font) {
+    if !font.has_queued() {
+        return;
+    }
+
     // Activate the text shader.
     let prog_id = SHADERS.activate(ShaderType::TextShader);
-    opengl::use_shader_program(prog_id);
 
     shaders::set_matrix4(
         prog_id,
@@ -284,5 +512,130 @@ pub fn draw_text(font: &mut Font, text: &Text) {
         ORTHO_MATRIX.lock().unwrap().as_slice(),
     );
 
-    font.render(text);
+    font.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::Markers;
+    use crate::renderer::material::AlphaMode;
+    use crate::renderer::mesh::Vertex;
+    use crate::renderer::queue::{Command, Layer, RecordingBackend};
+    use crate::renderer::tile_batch::TileInstance;
+    use crate::tilemap::AbsolutePosition;
+
+    fn mesh(position: Vector) -> Mesh {
+        let vertex = Vertex {
+            primitives: vec![
+                Vector(-0.5, 0., 0.),
+                Vector(0.5, 0., 0.),
+                Vector(0., 1., 0.),
+            ],
+            ..Default::default()
+        };
+        Mesh::headless(vertex, Transform::from_pos(position))
+    }
+
+    fn instance(kind: TileKind) -> TileInstance {
+        let mut model = [0.; 16];
+        model.copy_from_slice(glm::Mat4::identity().as_slice());
+        TileInstance {
+            model,
+            layer: 0,
+            kind,
+        }
+    }
+
+    #[test]
+    fn queue_tilemap_and_scene() {
+        let mut entities = Entities::default();
+        let origin = Vector(0., 0., 0.);
+        let markers = Markers {
+            ground: entities.insert(mesh(origin)),
+            wall: entities.insert(mesh(origin)),
+            player: entities.insert(mesh(origin)),
+            quad: entities.insert(mesh(origin)),
+        };
+        let (ground, wall, player_mesh) =
+            (markers.ground, markers.wall, markers.player);
+        entities.markers = Some(markers);
+
+        let glass = entities
+            .materials
+            .insert(Material::default().alpha_mode(AlphaMode::Blend));
+        let opaque = entities.insert(mesh(Vector(1., 0., 0.)));
+        let near =
+            entities.insert(mesh(Vector(0., 0., 4.)).material(Some(glass)));
+        let far =
+            entities.insert(mesh(Vector(0., 0., -4.)).material(Some(glass)));
+        // Behind the camera.
+        entities.insert(mesh(Vector(0., 0., 50.)));
+
+        let batch = TileBatch::headless(
+            vec![
+                instance(TileKind::Ground),
+                instance(TileKind::Ground),
+                instance(TileKind::Wall),
+            ],
+            true,
+        );
+        let position =
+            AbsolutePosition::new(glm::vec2(0, 0), glm::vec2(0, 0), None);
+        let player = Player::new(position);
+
+        let eye = glm::vec3(0., 2., 10.);
+        let view = glm::look_at(&eye, &glm::vec3(0., 0., 0.), &glm::Vec3::y());
+        let projection = glm::perspective(1., 45f32.to_radians(), 0.1, 100.);
+        let frustum = Frustum::from_matrix(&(projection * view));
+
+        let mut queue = RenderQueue::default();
+        queue_tilemap(&mut queue, &entities, &player, &batch);
+        let count = queue_scene(&mut queue, &entities, &frustum);
+        assert_eq!((count.visible, count.culled), (3, 1));
+        queue.sort(&eye);
+
+        let mut backend = RecordingBackend::default();
+        assert_eq!(queue.execute(&mut backend, RenderPass::Color), 6);
+
+        // Tiles and the player first, then the transparent meshes
+        // back to front.
+        let instances = |mesh, range| DrawSource::Instances {
+            mesh,
+            buffer: 0,
+            range,
+        };
+        let sources: Vec<&DrawSource> =
+            backend.draws().iter().map(|item| &item.source).collect();
+        assert_eq!(
+            sources,
+            vec![
+                &instances(ground, 0..2),
+                &instances(wall, 2..3),
+                &DrawSource::Mesh(player_mesh),
+                &DrawSource::Mesh(opaque),
+                &DrawSource::Mesh(far),
+                &DrawSource::Mesh(near),
+            ]
+        );
+        assert_eq!(
+            backend.commands[0],
+            Command::Layer(Layer::Opaque, RenderPass::Color)
+        );
+        assert_eq!(
+            backend.commands[5],
+            Command::Layer(Layer::Transparent, RenderPass::Color)
+        );
+        assert_eq!(backend.commands.last(), Some(&Command::End));
+
+        // Same items in the shadow pass.
+        let mut backend = RecordingBackend::default();
+        assert_eq!(queue.execute(&mut backend, RenderPass::Shadow), 6);
+        assert!(backend.commands.iter().all(|command| match command {
+            Command::Layer(_, pass) | Command::Draw(_, pass) => {
+                *pass == RenderPass::Shadow
+            }
+            Command::End => true,
+        }));
+    }
 }
@@ -1,45 +1,182 @@
 use super::{
-    opengl,
+    atlas::GlyphAtlas,
+    icons::{Icon, IconAtlas},
+    opengl::{self, TexId, VAO, VBO},
+    sdf,
     shaders::{self, ShaderType},
     text::Text,
-    texture::Texture,
-    GpuBound,
+    text_cache::TextCache,
+    text_layout::{self, PositionedGlyph, TextLayout},
+    texture::{ColorSpace, Sampler, Texture},
+    types::Rgb,
 };
 use crate::global::*;
-use nalgebra_glm as glm;
+use image::{DynamicImage, GrayImage, Luma};
+use rusttype::{Font as TrueTypeFont, Scale};
 use serde::Deserialize;
-use std::{collections::HashMap, fs::File, io::BufReader};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    error, fmt,
+    fs::{self, File},
+    io::{self, BufReader},
+};
 
-#[derive(Debug, Deserialize)]
+// Of the atlas of the fonts without a baked one.
+const ATLAS_SIZE: u32 = 512;
+// Texture units of the text shader.
+const GLYPHS_UNIT: usize = 0;
+const ICONS_UNIT: usize = 1;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Face {
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+}
+
+impl Face {
+    pub fn new(is_bold: bool, is_italic: bool) -> Self {
+        match (is_bold, is_italic) {
+            (false, false) => Face::Regular,
+            (true, false) => Face::Bold,
+            (false, true) => Face::Italic,
+            (true, true) => Face::BoldItalic,
+        }
+    }
+
+    pub fn is_bold(self) -> bool {
+        self == Face::Bold || self == Face::BoldItalic
+    }
+
+    pub fn is_italic(self) -> bool {
+        self == Face::Italic || self == Face::BoldItalic
+    }
+}
+
+// Where a glyph was rasterized from.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+enum Source {
+    #[default]
+    Baked,
+    Fallback(usize),
+    Face(Face),
+}
+
+#[derive(Debug)]
+pub enum FontError {
+    Io(io::Error),
+    Parse(rusttype::Error),
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FontError::Io(e) => write!(f, "Couldn't read the font: {}", e),
+            FontError::Parse(e) => write!(f, "Invalid font file: {}", e),
+        }
+    }
+}
+
+impl error::Error for FontError {}
+
+impl From<io::Error> for FontError {
+    fn from(e: io::Error) -> Self {
+        FontError::Io(e)
+    }
+}
+
+impl From<rusttype::Error> for FontError {
+    fn from(e: rusttype::Error) -> Self {
+        FontError::Parse(e)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Deserialize)]
 pub struct Character {
     #[serde(alias = "x")]
     atlas_pos_x: f32,
     #[serde(alias = "y")]
     atlas_pos_y: f32,
-    width: f32,
-    height: f32,
+    pub(super) width: f32,
+    pub(super) height: f32,
     #[serde(alias = "originY")]
-    origin_y: f32,
+    pub(super) origin_y: f32,
     #[serde(alias = "originX")]
-    origin_x: f32,
-    advance: f32,
+    pub(super) origin_x: f32,
+    pub(super) advance: f32,
+    #[serde(skip)]
+    source: Source,
+}
+
+impl Character {
+    /// Face of the font it comes from, the regular glyph is used when
+    /// a face is missing.
+    pub(super) fn face(&self) -> Face {
+        match self.source {
+            Source::Face(face) => face,
+            _ => Face::Regular,
+        }
+    }
 }
 
+// Metadata of a baked atlas.
 #[derive(Debug, Deserialize)]
-pub struct Font {
+struct Metadata {
     name: String,
+    size: f32,
     characters: HashMap<String, Character>,
-    #[serde(skip_deserializing)]
-    atlas_texture: Texture,
-    #[serde(alias = "width")]
-    atlas_width: f32,
-    #[serde(alias = "height")]
-    atlas_height: f32,
-    #[serde(alias = "bold")]
-    is_bold: bool,
+}
+
+#[derive(Debug)]
+struct Glyphs {
+    characters: HashMap<(Face, char), Character>,
+    // Not in any font of the stack, reported once.
+    missing: HashSet<char>,
+    atlas: GlyphAtlas,
+    // Center of an opaque block, for the underlines.
+    solid: Option<(f32, f32)>,
+}
+
+impl Glyphs {
+    fn new(
+        characters: HashMap<(Face, char), Character>,
+        atlas: GlyphAtlas,
+    ) -> Self {
+        Self {
+            characters,
+            missing: HashSet::new(),
+            atlas,
+            solid: None,
+        }
+    }
+}
+
+/// Glyphs of a baked atlas, completed by the ones rasterized from
+/// the fallback fonts when a text needs them.
+#[derive(Debug)]
+pub struct Font {
+    name: String,
+    // Pixel size of the glyphs in the atlas.
     pub size: f32,
-    #[serde(skip_deserializing)]
-    text_caching: HashMap<String, GpuBound>,
+    // Texts are laid out from a shared font.
+    glyphs: RefCell<Glyphs>,
+    // Tried in order for the characters the atlas doesn't have.
+    fallbacks: Vec<TrueTypeFont<'static>>,
+    // Styled faces, synthesized from the regular glyphs without them.
+    faces: HashMap<Face, TrueTypeFont<'static>>,
+    icons: Option<IconAtlas>,
+    // Highest glyph above the baseline and lowest one under it.
+    ascent: f32,
+    descent: f32,
+    atlas_tex: Option<TexId>,
+    // Revision of the atlas on the gpu.
+    uploaded_revision: u32,
+    cache: TextCache,
+    // Vertices of the texts queued since the last flush.
+    queued: Vec<f32>,
+    batch: Option<TextBatch>,
 }
 
 impl Font {
@@ -50,166 +187,538 @@ impl Font {
 
         let reader = BufReader::new(file);
 
-        let mut font: Font =
+        let metadata: Metadata =
             serde_json::from_reader(reader).expect("Error while reading JSON");
 
-        let texture = Texture::from_file(atlas);
-        font.atlas_texture = texture;
+        // Glyph positions are from the top of the atlas, distances are
+        // in the red channel.
+        let atlas = image::open(atlas)
+            .expect("Error while opening the font atlas.")
+            .to_rgba();
+        let distances =
+            GrayImage::from_fn(atlas.width(), atlas.height(), |x, y| {
+                Luma([atlas.get_pixel(x, y)[0]])
+            });
+
+        let characters: HashMap<(Face, char), Character> = metadata
+            .characters
+            .into_iter()
+            .filter_map(|(key, character)| {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Some(((Face::Regular, c), character)),
+                    _ => None,
+                }
+            })
+            .collect();
+        let (ascent, descent): (f32, f32) =
+            characters.values().fold((0., 0.), |(ascent, descent), c| {
+                (ascent.max(c.origin_y), descent.max(c.height - c.origin_y))
+            });
+
+        Self {
+            name: metadata.name,
+            size: metadata.size,
+            glyphs: RefCell::new(Glyphs::new(
+                characters,
+                GlyphAtlas::from_image(distances),
+            )),
+            fallbacks: vec![],
+            faces: HashMap::new(),
+            icons: None,
+            ascent,
+            descent,
+            atlas_tex: None,
+            uploaded_revision: 0,
+            cache: TextCache::default(),
+            queued: vec![],
+            batch: None,
+        }
+    }
 
-        font
+    /// TrueType or OpenType font rasterized at a pixel size, without
+    /// a baked atlas.
+    #[allow(unused)]
+    pub fn from_file(path: &str, size: f32) -> Result<Self, FontError> {
+        let font = TrueTypeFont::from_bytes(fs::read(path)?)?;
+        let metrics = font.v_metrics(Scale::uniform(size));
+
+        Ok(Self {
+            name: path.to_owned(),
+            size,
+            glyphs: RefCell::new(Glyphs::new(
+                HashMap::new(),
+                GlyphAtlas::new(ATLAS_SIZE, ATLAS_SIZE),
+            )),
+            fallbacks: vec![font],
+            faces: HashMap::new(),
+            icons: None,
+            ascent: metrics.ascent,
+            descent: -metrics.descent,
+            atlas_tex: None,
+            uploaded_revision: 0,
+            cache: TextCache::default(),
+            queued: vec![],
+            batch: None,
+        })
     }
 
-    /// Compute the text length in pixels.
-    pub fn text_length(&self, text: &Text) -> (f32, f32) {
-        let scale = text.font_size / self.size;
-        let mut cursor = 0.;
-        let mut height = 0.;
+    /// Font tried after the previous ones for the missing characters.
+    pub fn add_fallback(&mut self, path: &str) -> Result<(), FontError> {
+        let font = TrueTypeFont::from_bytes(fs::read(path)?)?;
+        self.fallbacks.push(font);
+        self.glyphs.borrow_mut().missing.clear();
 
-        if text.is_empty() {
-            return (0., height);
+        Ok(())
+    }
+
+    /// Styled face, used instead of synthesizing it from the regular
+    /// glyphs.
+    pub fn add_face(
+        &mut self,
+        face: Face,
+        path: &str,
+    ) -> Result<(), FontError> {
+        let font = TrueTypeFont::from_bytes(fs::read(path)?)?;
+        self.faces.insert(face, font);
+        self.glyphs
+            .borrow_mut()
+            .characters
+            .retain(|(glyph_face, _), _| *glyph_face != face);
+
+        Ok(())
+    }
+
+    /// Icons the texts can show inline, see `markup`.
+    pub fn set_icons(&mut self, icons: IconAtlas) {
+        self.icons = Some(icons);
+    }
+
+    /// Glyph runs of the text, what `queue` draws.
+    pub fn layout(&self, text: &Text) -> TextLayout {
+        text_layout::layout(
+            self,
+            &text.content,
+            &text.spans,
+            text.font_size,
+            &text.layout,
+        )
+    }
+
+    /// Rasterize the glyph from the fonts of the face the first time
+    /// it's used, None if no font of the stack has it.
+    pub(super) fn glyph(&self, c: char, face: Face) -> Option<Character> {
+        if let Some(character) = self.glyphs.borrow().characters.get(&(face, c))
+        {
+            return Some(*character);
         }
 
-        let content: Vec<(f32, f32)> = text
-            .content
-            .split("")
-            .filter_map(|c| {
-                self.characters.get(c).and_then(|l| {
-                    let x_pos = (cursor - l.origin_x) * scale;
-                    let y_pos = (0. - (l.height - l.origin_y)) * scale;
-                    let h = y_pos + (l.height * scale);
-                    let w = l.width * scale;
+        if face != Face::Regular {
+            let rasterized = self
+                .faces
+                .get(&face)
+                .and_then(|font| sdf::rasterize(font, c, self.size));
+            return match rasterized {
+                Some(glyph) => {
+                    self.insert_glyph(c, face, glyph, Source::Face(face))
+                }
+                None => {
+                    let regular = self.glyph(c, Face::Regular)?;
+                    let mut glyphs = self.glyphs.borrow_mut();
+                    glyphs.characters.insert((face, c), regular);
+                    Some(regular)
+                }
+            };
+        }
 
-                    if height <= h {
-                        height = h;
-                    }
+        if self.glyphs.borrow().missing.contains(&c) {
+            return None;
+        }
+        let rasterized =
+            self.fallbacks.iter().enumerate().find_map(|(i, font)| {
+                sdf::rasterize(font, c, self.size).map(|glyph| (i, glyph))
+            });
+        match rasterized {
+            Some((i, glyph)) => {
+                self.insert_glyph(c, face, glyph, Source::Fallback(i))
+            }
+            None => {
+                dbg!(format!(
+                    "No glyph for {:?} in the {} font stack.",
+                    c, self.name
+                ));
+                self.glyphs.borrow_mut().missing.insert(c);
+                None
+            }
+        }
+    }
 
-                    if height <= h {
-                        height = h;
-                    }
+    fn insert_glyph(
+        &self,
+        c: char,
+        face: Face,
+        glyph: sdf::SdfGlyph,
+        source: Source,
+    ) -> Option<Character> {
+        let mut glyphs = self.glyphs.borrow_mut();
+        let (width, height) = glyph.image.dimensions();
+        let position = if width == 0 || height == 0 {
+            Some((0, 0))
+        } else {
+            glyphs.atlas.insert(&glyph.image)
+        };
+        let (x, y) = match position {
+            Some(position) => position,
+            None => {
+                dbg!(format!("The glyph of {:?} is wider than the atlas.", c));
+                glyphs.missing.insert(c);
+                return None;
+            }
+        };
 
-                    cursor += l.advance;
-                    return Some((x_pos, x_pos + w));
-                })
-            })
-            .collect();
+        let character = Character {
+            atlas_pos_x: x as f32,
+            atlas_pos_y: y as f32,
+            width: width as f32,
+            height: height as f32,
+            origin_y: glyph.origin_y,
+            origin_x: glyph.origin_x,
+            advance: glyph.advance,
+            source,
+        };
+        glyphs.characters.insert((face, c), character);
 
-        let width = content[content.len() - 1].1 - content[0].0;
+        Some(character)
+    }
+
+    pub(super) fn kerning(&self, face: Face, first: char, second: char) -> f32 {
+        // Only between glyphs of the same font.
+        let source = match (self.glyph(first, face), self.glyph(second, face)) {
+            (Some(a), Some(b)) if a.source == b.source => a.source,
+            _ => return 0.,
+        };
 
-        (width, height)
+        let scale = Scale::uniform(self.size);
+        match source {
+            Source::Fallback(i) => {
+                self.fallbacks[i].pair_kerning(scale, first, second)
+            }
+            Source::Face(face) => {
+                self.faces[&face].pair_kerning(scale, first, second)
+            }
+            // The atlas is baked without kerning pairs.
+            Source::Baked => 0.,
+        }
+    }
+
+    pub(super) fn icon(&self, name: &str) -> Option<Icon> {
+        self.icons.as_ref().and_then(|icons| icons.get(name))
     }
 
-    pub fn render(&mut self, text: &Text) {
-        let scale = text.font_size / self.size;
+    /// Atlas texels of the top left and bottom right corners, they
+    /// don't move when the atlas grows.
+    pub(super) fn uv(&self, glyph: &Character) -> ((f32, f32), (f32, f32)) {
+        let top_left = (glyph.atlas_pos_x, glyph.atlas_pos_y);
+        let bottom_right =
+            (top_left.0 + glyph.width, top_left.1 + glyph.height);
 
-        // Caching system.
-        // If this text was previously rendered, we just use our existing
-        // gpu data.
-        if let Some(gpu_bound) = self.text_caching.get(&text.content) {
-            self.to_opengl(&text, &gpu_bound);
+        (top_left, bottom_right)
+    }
+
+    /// Highest glyph above the baseline and lowest one under it, at
+    /// the font size.
+    pub(super) fn vertical_metrics(&self) -> (f32, f32) {
+        (self.ascent, self.descent)
+    }
+
+    #[cfg(test)]
+    fn atlas_size(&self) -> (u32, u32) {
+        self.glyphs.borrow().atlas.size()
+    }
+
+    /// Add the text to the batch drawn by `flush`. Its quads are laid
+    /// out once and cached, unless the text is dynamic.
+    pub fn queue(&mut self, text: &Text) {
+        let dynamic;
+        let vertices = if text.is_dynamic {
+            dynamic = self.quads(text);
+            &dynamic[..]
+        } else {
+            if self.cache.get(text).is_none() {
+                let quads = self.quads(text);
+                self.cache.insert(text, quads);
+            }
+            self.cache.get(text).unwrap_or_default()
+        };
+
+        let position = text.position;
+        for vertex in vertices.chunks(opengl::TEXT_VERTEX_LEN) {
+            self.queued.push(vertex[0] + position.0);
+            self.queued.push(vertex[1] + position.1);
+            self.queued.extend_from_slice(&vertex[2..]);
+        }
+    }
+
+    pub fn has_queued(&self) -> bool {
+        !self.queued.is_empty()
+    }
+
+    /// Draw the queued texts in a single call, the text shader must be
+    /// active.
+    pub fn flush(&mut self) {
+        if self.queued.is_empty() {
             return;
         }
+        self.upload_atlas();
+
+        let batch = self.batch.get_or_insert_with(TextBatch::new);
+        batch.capacity = opengl::stream_text_vertices(
+            batch.vbo,
+            &self.queued,
+            batch.capacity,
+        );
+
+        opengl::use_vao(batch.vao);
+        let prog_id = SHADERS.get_program(ShaderType::TextShader);
+        if let Some(tex_id) = self.atlas_tex {
+            opengl::bind_texture(tex_id, GLYPHS_UNIT);
+        }
+        shaders::set_sampler(prog_id, GLYPHS_UNIT);
+        if let Some(icons) = self.icons.as_mut() {
+            icons.bind(ICONS_UNIT);
+            shaders::set_i32(prog_id, "icons", ICONS_UNIT as i32);
+        }
+
+        // Depth and blending are set by the ui layer.
+        let count = self.queued.len() / opengl::TEXT_VERTEX_LEN;
+        unsafe {
+            gl::DrawArrays(gl::TRIANGLES, 0, count as i32);
+        }
+        self.queued.clear();
+    }
+
+    /// Evict the cached texts not drawn for a while.
+    pub fn end_frame(&mut self) {
+        self.cache.end_frame();
+    }
 
-        let mut cursor = 0.;
+    // Two triangles by glyph and underline, in pixels from the text
+    // position.
+    fn quads(&self, text: &Text) -> Vec<f32> {
+        let layout = self.layout(text);
         let mut vertices: Vec<f32> = vec![];
 
-        text.content
-            .split("")
-            // If character not found in our atlas, we skip.
-            .filter(|l| self.characters.get(l.to_owned()).is_some())
-            .for_each(|letter| {
-                // We can unwrap it safely now.
-                let letter = self.characters.get(letter).unwrap();
-
-                let (top_left, top_right, bottom_left, bottom_right) = {
-                    let top_left = (
-                        letter.atlas_pos_x / self.atlas_width,
-                        letter.atlas_pos_y / self.atlas_height,
-                    );
-
-                    let top_right = (
-                        top_left.0 + (letter.width / self.atlas_width),
-                        top_left.1,
-                    );
-
-                    let bottom_left = (
-                        top_left.0,
-                        top_left.1 + (letter.height / self.atlas_height),
-                    );
-
-                    let bottom_right = (top_right.0, bottom_left.1);
-
-                    (top_left, top_right, bottom_left, bottom_right)
-                };
-
-                let x_pos = (cursor - letter.origin_x) * scale;
-                // 0 is our baseline.
-                let y_pos = (0. - (letter.height - letter.origin_y)) * scale;
-                let width = letter.width * scale;
-                let height = letter.height * scale;
-
-                // Quad data for our character.
-                #[rustfmt::skip]
-                let character_quad: [f32; 24] = [
-                    x_pos, y_pos + height,  top_left.0, top_left.1,
-                    x_pos,  y_pos,          bottom_left.0, bottom_left.1,
-                    x_pos + width, y_pos,   bottom_right.0, bottom_right.1,
-
-                    x_pos, y_pos + height,  top_left.0, top_left.1,
-                    x_pos + width, y_pos,   bottom_right.0, bottom_right.1,
-                    x_pos + width, y_pos + height, top_right.0, top_right.1,
-                ];
-
-                vertices.extend_from_slice(&character_quad);
-
-                cursor += letter.advance;
-            });
+        for glyph in &layout.glyphs {
+            let PositionedGlyph {
+                x,
+                y,
+                width,
+                height,
+                skew,
+                ..
+            } = *glyph;
+            let (top_left, bottom_right) = (glyph.uv_min, glyph.uv_max);
+            let color = glyph.color.unwrap_or(text.color);
+            let style = [glyph.weight, if glyph.is_icon { 1. } else { 0. }];
+
+            // Quad data for our character.
+            #[rustfmt::skip]
+            let corners = [
+                (x + skew, y + height,          top_left.0, top_left.1),
+                (x, y,                          top_left.0, bottom_right.1),
+                (x + width, y,                  bottom_right.0, bottom_right.1),
+
+                (x + skew, y + height,          top_left.0, top_left.1),
+                (x + width, y,                  bottom_right.0, bottom_right.1),
+                (x + width + skew, y + height,  bottom_right.0, top_left.1),
+            ];
+            push_quad(&mut vertices, &corners, color, style);
+        }
 
-        let (vao, vbo, tex_id) =
-            opengl::load_font_to_gpu(&vertices, &self.atlas_texture);
+        let uv = self.solid_texel();
+        for line in &layout.underlines {
+            let color = line.color.unwrap_or(text.color);
+            let (left, right) = (line.x, line.x + line.width);
+            let (bottom, top) = (line.y - line.thickness, line.y);
+
+            #[rustfmt::skip]
+            let corners = [
+                (left, top, uv.0, uv.1),
+                (left, bottom, uv.0, uv.1),
+                (right, bottom, uv.0, uv.1),
+
+                (left, top, uv.0, uv.1),
+                (right, bottom, uv.0, uv.1),
+                (right, top, uv.0, uv.1),
+            ];
+            push_quad(&mut vertices, &corners, color, [0., 0.]);
+        }
 
-        let gpu_bound = GpuBound {
-            vao,
-            vbo,
-            ebo: None,
-            tex_ids: vec![tex_id],
-            primitives_len: vertices.len(),
-            shader: ShaderType::TextShader,
-        };
+        vertices
+    }
+
+    // Inside an opaque block of the atlas, added when first needed.
+    fn solid_texel(&self) -> (f32, f32) {
+        let mut glyphs = self.glyphs.borrow_mut();
+        if let Some(solid) = glyphs.solid {
+            return solid;
+        }
 
-        self.to_opengl(&text, &gpu_bound);
+        let block = GrayImage::from_pixel(4, 4, Luma([255]));
+        let (x, y) = glyphs.atlas.insert(&block).unwrap_or((0, 0));
+        let solid = (x as f32 + 2., y as f32 + 2.);
+        glyphs.solid = Some(solid);
 
-        self.text_caching.insert(text.content.clone(), gpu_bound);
+        solid
     }
 
-    fn to_opengl(&self, text: &Text, gpu_bound: &GpuBound) {
-        let Text {
-            position,
-            color,
-            font_size,
-            ..
-        } = text;
+    // Replace the atlas texture when glyphs were added.
+    fn upload_atlas(&mut self) {
+        let glyphs = self.glyphs.borrow();
+        if self.atlas_tex.is_some()
+            && self.uploaded_revision == glyphs.atlas.revision
+        {
+            return;
+        }
 
-        opengl::use_vao(gpu_bound.vao);
-        opengl::bind_texture(gpu_bound.tex_ids[0], 0);
+        let texture = Texture::from_image(DynamicImage::ImageLuma8(
+            glyphs.atlas.image.clone(),
+        ))
+        .color_space(ColorSpace::Linear)
+        .sampler(Sampler::clamped());
+        if let Some(tex_id) = self.atlas_tex {
+            unsafe {
+                gl::DeleteTextures(1, &tex_id);
+            }
+        }
 
-        let mut model = glm::Mat4::identity();
-        model = glm::translate(&model, &position.to_glm());
+        self.atlas_tex = Some(unsafe { opengl::load_tex_to_gpu(&texture) });
+        self.uploaded_revision = glyphs.atlas.revision;
+    }
+}
 
-        let size_ratio = font_size / self.size;
+// Corners with their atlas texels, then the color and the style (see
+// `TEXT_VERTEX_LEN`).
+fn push_quad(
+    vertices: &mut Vec<f32>,
+    corners: &[(f32, f32, f32, f32); 6],
+    color: Rgb,
+    style: [f32; 2],
+) {
+    for (x, y, u, v) in corners.iter() {
+        vertices.extend_from_slice(&[
+            *x, *y, *u, *v, color.r, color.g, color.b, style[0], style[1],
+        ]);
+    }
+}
 
-        let prog_id = SHADERS.get_program(ShaderType::TextShader);
-        shaders::set_sampler(prog_id, 0);
-        shaders::set_matrix4(prog_id, "model", model.as_slice());
-        shaders::set_f32(prog_id, "font_size", size_ratio);
-        shaders::set_vec3(prog_id, "text_color", &color.into());
+// Vertices of the texts of a frame, streamed in a shared buffer.
+#[derive(Debug)]
+struct TextBatch {
+    vao: VAO,
+    vbo: VBO,
+    // Floats the buffer can hold.
+    capacity: usize,
+}
 
-        unsafe {
-            gl::Disable(gl::DEPTH_TEST);
-            gl::Enable(gl::BLEND);
-            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+impl TextBatch {
+    fn new() -> Self {
+        let (vao, vbo) = opengl::gen_text_buffer();
+        Self {
+            vao,
+            vbo,
+            capacity: 0,
+        }
+    }
+}
+
+impl Drop for TextBatch {
+    fn drop(&mut self) {
+        opengl::delete_vao(self.vao);
+        opengl::delete_buffer(self.vbo);
+    }
+}
 
-            gl::DrawArrays(gl::TRIANGLES, 0, gpu_bound.primitives_len as i32);
-            gl::Enable(gl::DEPTH_TEST);
+impl Drop for Font {
+    fn drop(&mut self) {
+        if let Some(tex_id) = self.atlas_tex {
+            unsafe {
+                gl::DeleteTextures(1, &tex_id);
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEJAVU: &str = "assets/fonts/DejaVu/DejaVuSans.ttf";
+    const DEJAVU_BOLD: &str = "assets/fonts/DejaVu/DejaVuSans-Bold.ttf";
+
+    #[test]
+    fn fall_back_on_truetype_fonts() {
+        let mut font = Font::new(
+            "assets/fonts/Helvetica/helvetica.json",
+            "assets/fonts/Helvetica/helvetica.png",
+        );
+        let baked_size = font.atlas_size();
+        assert!(
+            font.glyph('A', Face::Regular).unwrap().source == Source::Baked
+        );
+        assert!(font.glyph('Ж', Face::Regular).is_none());
+
+        font.add_fallback(DEJAVU).unwrap();
+        let glyph = font.glyph('Ж', Face::Regular).unwrap();
+        assert_eq!(glyph.source, Source::Fallback(0));
+        assert!(glyph.width > 0. && glyph.advance > 0.);
+
+        // Packed under the baked glyphs, twice as tall.
+        assert_eq!(font.atlas_size(), (baked_size.0, baked_size.1 * 2));
+        let (top_left, bottom_right) = font.uv(&glyph);
+        assert!(top_left.1 >= baked_size.1 as f32);
+        assert!(bottom_right.1 <= font.atlas_size().1 as f32);
+        // Rasterized once.
+        let revision = font.glyphs.borrow().atlas.revision;
+        font.glyph('Ж', Face::Regular);
+        assert_eq!(font.glyphs.borrow().atlas.revision, revision);
+
+        assert!(font.glyph('\u{e000}', Face::Regular).is_none());
+        assert!(font.add_fallback("assets/fonts/missing.ttf").is_err());
+    }
+
+    #[test]
+    fn rasterize_from_file() {
+        let font = Font::from_file(DEJAVU, 32.).unwrap();
+        let (ascent, descent) = font.vertical_metrics();
+        assert!(ascent > 0. && descent > 0.);
+
+        let a = font.glyph('A', Face::Regular).unwrap();
+        let b = font.glyph('B', Face::Regular).unwrap();
+        assert_eq!(a.source, Source::Fallback(0));
+        assert!(
+            a.atlas_pos_x != b.atlas_pos_x || a.atlas_pos_y != b.atlas_pos_y
+        );
+        assert_eq!(font.atlas_size(), (ATLAS_SIZE, ATLAS_SIZE));
+    }
+
+    #[test]
+    fn rasterize_styled_faces() {
+        let mut font = Font::from_file(DEJAVU, 32.).unwrap();
+        // Synthesized from the regular glyph.
+        let regular = font.glyph('A', Face::Regular).unwrap();
+        let synthesized = font.glyph('A', Face::Bold).unwrap();
+        assert_eq!(synthesized.atlas_pos_y, regular.atlas_pos_y);
+        assert_eq!(synthesized.atlas_pos_x, regular.atlas_pos_x);
+
+        font.add_face(Face::Bold, DEJAVU_BOLD).unwrap();
+        let bold = font.glyph('A', Face::Bold).unwrap();
+        assert_eq!(bold.source, Source::Face(Face::Bold));
+        assert!(bold.advance > regular.advance);
+        assert!(bold.face().is_bold() && !regular.face().is_bold());
+        assert_eq!(
+            font.glyph('A', Face::Italic).unwrap().face(),
+            Face::Regular
+        );
+    }
+}
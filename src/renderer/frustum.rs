@@ -0,0 +1,89 @@
+use crate::bvh::Aabb;
+use nalgebra_glm as glm;
+
+/// Volume seen by a camera, as six planes pointing inward.
+#[derive(Debug, Clone)]
+pub struct Frustum {
+    // Xyz is the normal, w the distance to the origin.
+    planes: [glm::Vec4; 6],
+}
+
+impl Frustum {
+    /// Planes extracted from the projection times the view matrix
+    /// (Gribb and Hartmann).
+    pub fn from_matrix(view_projection: &glm::Mat4) -> Self {
+        let row = |i: usize| view_projection.row(i).transpose();
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+        let mut planes = [w + x, w - x, w + y, w - y, w + z, w - z];
+        for plane in planes.iter_mut() {
+            *plane /= plane.xyz().norm();
+        }
+
+        Self { planes }
+    }
+
+    /// Conservative test, a box near a corner can be kept even if
+    /// it's outside.
+    pub fn intersects(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // Corner the farthest along the normal.
+            let corner = glm::vec3(
+                if plane.x > 0. { aabb.max.x } else { aabb.min.x },
+                if plane.y > 0. { aabb.max.y } else { aabb.min.y },
+                if plane.z > 0. { aabb.max.z } else { aabb.min.z },
+            );
+            plane.xyz().dot(&corner) + plane.w >= 0.
+        })
+    }
+}
+
+/// Objects kept and skipped by the culling of a frame.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct CullCount {
+    pub visible: u32,
+    pub culled: u32,
+}
+
+impl CullCount {
+    pub fn add(&mut self, is_visible: bool) {
+        if is_visible {
+            self.visible += 1;
+        } else {
+            self.culled += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cull_boxes_outside() {
+        let view = glm::look_at(
+            &glm::vec3(0., 0., 5.),
+            &glm::vec3(0., 0., 0.),
+            &glm::vec3(0., 1., 0.),
+        );
+        let projection = glm::perspective(1.5, 45., 0.1, 100.);
+        let frustum = Frustum::from_matrix(&(projection * view));
+        let unit = |x: f32, y: f32, z: f32| {
+            let center = glm::vec3(x, y, z);
+            Aabb::new(center.add_scalar(-0.5), center.add_scalar(0.5))
+        };
+
+        assert!(frustum.intersects(&unit(0., 0., 0.)));
+        // Behind the camera and past the far plane.
+        assert!(!frustum.intersects(&unit(0., 0., 10.)));
+        assert!(!frustum.intersects(&unit(0., 0., -200.)));
+        // Far on the sides.
+        assert!(!frustum.intersects(&unit(50., 0., 0.)));
+        assert!(!frustum.intersects(&unit(0., -50., 0.)));
+        // Crossing a plane is still visible.
+        assert!(frustum.intersects(&Aabb::new(
+            glm::vec3(-100., -1., -1.),
+            glm::vec3(-1., 1., 1.),
+        )));
+    }
+}
@@ -0,0 +1,80 @@
+use super::opengl::{self, GpuTexture};
+use super::texture::{ColorSpace, Sampler, Texture};
+use serde::Deserialize;
+use std::{collections::HashMap, fs::File, io::BufReader};
+
+/// Area of an icon in its atlas, in pixels from the top left corner.
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+pub struct Icon {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+// Like { "icons": { "key_w": { "x": 0, "y": 0, ... } } }.
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    icons: HashMap<String, Icon>,
+}
+
+/// Colored images drawn inline with the texts, by name.
+#[derive(Debug)]
+pub struct IconAtlas {
+    icons: HashMap<String, Icon>,
+    texture: GpuTexture,
+}
+
+impl IconAtlas {
+    /// Take a metadata path and the texture atlas path.
+    pub fn new(metadata: &str, atlas: &str) -> Self {
+        let file =
+            File::open(metadata).expect("Error while openning the icons.");
+        let metadata: Metadata = serde_json::from_reader(BufReader::new(file))
+            .expect("Error while reading JSON");
+
+        // Drawn over the encoded image, like the colors of the ui.
+        let atlas =
+            image::open(atlas).expect("Error while opening the icon atlas.");
+        let texture = Texture::from_image(atlas)
+            .color_space(ColorSpace::Linear)
+            .sampler(Sampler::clamped());
+
+        Self {
+            icons: metadata.icons,
+            texture: GpuTexture::new(texture),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Icon> {
+        self.icons.get(name).cloned()
+    }
+
+    /// Uploaded the first time it's bound.
+    pub fn bind(&mut self, unit: usize) {
+        opengl::bind_texture(self.texture.tex_id(), unit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_key_icons() {
+        let icons =
+            IconAtlas::new("assets/icons/keys.json", "assets/icons/keys.png");
+
+        let w = icons.get("key_w").unwrap();
+        assert_eq!((w.width, w.height), (32., 32.));
+        assert!(icons.get("key_space").unwrap().width > w.width);
+        assert!(icons.get("key_unknown").is_none());
+
+        // Inside the atlas.
+        let (width, height) = icons.texture.texture.dim;
+        for icon in icons.icons.values() {
+            assert!(icon.x + icon.width <= width as f32);
+            assert!(icon.y + icon.height <= height as f32);
+        }
+    }
+}
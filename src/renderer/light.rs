@@ -1,15 +1,26 @@
-use super::{Vector, Rgba};
+use super::{Rgba, Vector};
+use nalgebra_glm as glm;
+
+// Same as the simple shader.
+pub const MAX_LIGHTS: usize = 8;
 
 #[derive(Debug)]
 pub enum LightProbes {
     Sun(SunLight),
+    #[allow(unused)]
+    Point(PointLight),
+    #[allow(unused)]
+    Spot(SpotLight),
 }
 
+/// Directional light, lighting the whole world from far away.
 #[derive(Debug)]
 pub struct SunLight {
-    position: Vector,
-    direction: Vector,
-    ambient: Rgba,
+    pub position: Vector,
+    // Where the light goes to.
+    pub direction: Vector,
+    pub ambient: Rgba,
+    pub color: Rgba,
 }
 
 impl SunLight {
@@ -18,8 +29,209 @@ impl SunLight {
             position,
             direction,
             ambient,
+            color: Rgba::default(),
         }
     }
+
+    #[allow(unused)]
+    pub fn color(mut self, color: Rgba) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+/// Light in all directions, fading out until its range.
+#[derive(Debug)]
+pub struct PointLight {
+    pub position: Vector,
+    pub color: Rgba,
+    pub range: f32,
+}
+
+/// Cone of light, angles (in radians) are from the cone axis. The light
+/// fades out between the inner and the outer angle.
+#[derive(Debug)]
+pub struct SpotLight {
+    pub position: Vector,
+    pub direction: Vector,
+    pub color: Rgba,
+    pub range: f32,
+    pub inner_angle: f32,
+    pub outer_angle: f32,
 }
 
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct GpuLight {
+    // W is the range.
+    pub position: [f32; 4],
+    // W is the cosine of the outer angle, -1 for point lights.
+    pub direction: [f32; 4],
+    // W is the cosine of the inner angle.
+    pub color: [f32; 4],
+}
+
+/// Data of the "Lights" uniform block (binding point 1), every field
+/// is a vec4 so the layout is the same in std140.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct LightsUbo {
+    // W is 1 if there is a sun, 0 otherwise.
+    pub sun_direction: [f32; 4],
+    pub sun_color: [f32; 4],
+    pub ambient: [f32; 4],
+    pub camera_position: [f32; 4],
+    // From world space to the sun shadow map, column major.
+    pub sun_space: [f32; 16],
+    // X is the number of point and spot lights, Y is 1 if the sun
+    // casts shadows.
+    pub lights_count: [i32; 4],
+    pub lights: [GpuLight; MAX_LIGHTS],
+}
+
+impl LightsUbo {
+    /// Only the first sun is used, point and spot lights above
+    /// `MAX_LIGHTS` are ignored.
+    pub fn new<'l>(
+        lights: impl Iterator<Item = &'l LightProbes>,
+        camera_position: &glm::Vec3,
+    ) -> Self {
+        let mut ubo = Self {
+            camera_position: [
+                camera_position.x,
+                camera_position.y,
+                camera_position.z,
+                1.,
+            ],
+            ..Default::default()
+        };
+        let mut count = 0;
+
+        for light in lights {
+            let gpu_light = match light {
+                LightProbes::Sun(sun) => {
+                    if ubo.sun_direction[3] == 0. {
+                        let direction = normalize(&sun.direction);
+                        ubo.sun_direction =
+                            [direction.x, direction.y, direction.z, 1.];
+                        ubo.sun_color = sun.color.into();
+                        ubo.ambient = sun.ambient.into();
+                    }
+                    continue;
+                }
+                LightProbes::Point(point) => GpuLight {
+                    position: with_w(&point.position, point.range),
+                    direction: [0., 0., 0., -1.],
+                    color: rgb_with_w(&point.color, -1.),
+                },
+                LightProbes::Spot(spot) => {
+                    let direction = normalize(&spot.direction);
+                    GpuLight {
+                        position: with_w(&spot.position, spot.range),
+                        direction: [
+                            direction.x,
+                            direction.y,
+                            direction.z,
+                            spot.outer_angle.cos(),
+                        ],
+                        color: rgb_with_w(&spot.color, spot.inner_angle.cos()),
+                    }
+                }
+            };
+
+            if count < MAX_LIGHTS {
+                ubo.lights[count] = gpu_light;
+                count += 1;
+            }
+        }
+
+        ubo.lights_count[0] = count as i32;
+        ubo
+    }
 
+    /// Enable the sun shadows, from its shadow map matrix.
+    pub fn shadows(mut self, sun_space: &glm::Mat4) -> Self {
+        self.sun_space.copy_from_slice(sun_space.as_slice());
+        self.lights_count[1] = 1;
+        self
+    }
+}
+
+fn normalize(vector: &Vector) -> glm::Vec3 {
+    let vector = vector.to_glm();
+    if glm::length(&vector) > f32::EPSILON {
+        glm::normalize(&vector)
+    } else {
+        // Straight down.
+        glm::vec3(0., -1., 0.)
+    }
+}
+
+fn with_w(vector: &Vector, w: f32) -> [f32; 4] {
+    [vector.0, vector.1, vector.2, w]
+}
+
+fn rgb_with_w(color: &Rgba, w: f32) -> [f32; 4] {
+    [color.r, color.g, color.b, w]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem;
+
+    #[test]
+    fn std140_layout() {
+        // 5 vec4, a mat4 then the array of 3 vec4 structs.
+        assert_eq!(
+            mem::size_of::<LightsUbo>(),
+            (5 + 4 + 3 * MAX_LIGHTS) * mem::size_of::<[f32; 4]>()
+        );
+    }
+
+    #[test]
+    fn fill_lights() {
+        let lights = [
+            LightProbes::Point(PointLight {
+                position: Vector(1., 2., 3.),
+                color: Rgba::new(1., 0., 0., 1.),
+                range: 5.,
+            }),
+            LightProbes::Sun(SunLight::new(
+                Vector(0., 10., 0.),
+                Vector(0., -2., 0.),
+                Rgba::new(0.2, 0.2, 0.2, 1.),
+            )),
+            LightProbes::Spot(SpotLight {
+                position: Vector(0., 1., 0.),
+                direction: Vector(1., 0., 0.),
+                color: Rgba::default(),
+                range: 10.,
+                inner_angle: 0.,
+                outer_angle: std::f32::consts::FRAC_PI_2,
+            }),
+        ];
+
+        let ubo = LightsUbo::new(lights.iter(), &glm::vec3(0., 0., 0.));
+        assert_eq!(ubo.sun_direction, [0., -1., 0., 1.]);
+        assert_eq!(ubo.lights_count[0], 2);
+        assert_eq!(ubo.lights[0].position, [1., 2., 3., 5.]);
+        assert_eq!(ubo.lights[0].direction[3], -1.);
+        assert_eq!(ubo.lights[1].color[3], 1.);
+        assert!(ubo.lights[1].direction[3].abs() < 1e-6);
+
+        // Extra lights are dropped.
+        let points: Vec<LightProbes> = (0..MAX_LIGHTS + 2)
+            .map(|_| {
+                LightProbes::Point(PointLight {
+                    position: Vector::default(),
+                    color: Rgba::default(),
+                    range: 1.,
+                })
+            })
+            .collect();
+        let ubo = LightsUbo::new(points.iter(), &glm::vec3(0., 0., 0.));
+        assert_eq!(ubo.lights_count[0], MAX_LIGHTS as i32);
+        assert_eq!(ubo.sun_direction[3], 0.);
+    }
+}
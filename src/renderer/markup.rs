@@ -0,0 +1,253 @@
+use super::Rgb;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::{error, fmt};
+
+/// Stands for an icon in the content of a text, which keeps one
+/// character by laid out element.
+pub const ICON_CHAR: char = '\u{fffc}';
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Style {
+    Bold,
+    Italic,
+    Underline,
+    Color(Rgb),
+    // Font size in pixels.
+    Size(f32),
+    // Name in the icon atlas, on an `ICON_CHAR`.
+    Icon(String),
+}
+
+/// Style of a range of characters, inner spans come after the ones
+/// containing them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub range: Range<usize>,
+    pub style: Style,
+}
+
+// Spans are part of the key of the cached texts.
+impl Eq for Span {}
+impl Hash for Span {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.range.hash(state);
+        match &self.style {
+            Style::Bold => 0.hash(state),
+            Style::Italic => 1.hash(state),
+            Style::Underline => 2.hash(state),
+            Style::Color(color) => {
+                3.hash(state);
+                [color.r, color.g, color.b]
+                    .iter()
+                    .for_each(|c| c.to_bits().hash(state));
+            }
+            Style::Size(size) => {
+                4.hash(state);
+                size.to_bits().hash(state);
+            }
+            Style::Icon(name) => {
+                5.hash(state);
+                name.hash(state);
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MarkupError {
+    // Closed without being opened, or before the tags it contains.
+    UnexpectedClose(String),
+    Unclosed(String),
+    InvalidColor(String),
+    InvalidSize(String),
+}
+
+impl fmt::Display for MarkupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MarkupError::UnexpectedClose(tag) => {
+                write!(f, "Unexpected closing tag </{}>", tag)
+            }
+            MarkupError::Unclosed(tag) => {
+                write!(f, "Tag <{}> is not closed", tag)
+            }
+            MarkupError::InvalidColor(value) => {
+                write!(f, "Invalid color {}, expected #rrggbb", value)
+            }
+            MarkupError::InvalidSize(value) => {
+                write!(f, "Invalid size {}", value)
+            }
+        }
+    }
+}
+
+impl error::Error for MarkupError {}
+
+/// Split the markup in its content and the spans styling it, like
+/// "press <icon=key_w> to <b>move</b>". Tags are `b`, `i`, `u`,
+/// `color=#rrggbb` and `size=pixels`, closed by `</name>`, and
+/// `icon=name` without closing tag. Anything else is kept as text.
+pub fn parse(markup: &str) -> Result<(String, Vec<Span>), MarkupError> {
+    let mut content = String::new();
+    let mut len = 0;
+    let mut spans: Vec<Span> = vec![];
+    // Tag name and span of the tags not closed yet.
+    let mut open: Vec<(&str, usize)> = vec![];
+
+    let mut rest = markup;
+    while let Some(start) = rest.find('<') {
+        content.push_str(&rest[..start]);
+        len += rest[..start].chars().count();
+        rest = &rest[start..];
+
+        let tag = match rest.find('>') {
+            Some(end) => &rest[1..end],
+            None => break,
+        };
+
+        if let Some(name) = tag.strip_prefix('/') {
+            if !is_tag(name) {
+                content.push('<');
+                len += 1;
+                rest = &rest[1..];
+                continue;
+            }
+            match open.pop() {
+                Some((open_name, span)) if open_name == name => {
+                    spans[span].range.end = len;
+                }
+                _ => return Err(MarkupError::UnexpectedClose(name.to_owned())),
+            }
+        } else {
+            let (name, value) = match tag.find('=') {
+                Some(i) => (&tag[..i], Some(&tag[i + 1..])),
+                None => (tag, None),
+            };
+            let style = match (name, value) {
+                ("b", None) => Style::Bold,
+                ("i", None) => Style::Italic,
+                ("u", None) => Style::Underline,
+                ("color", Some(value)) => {
+                    Style::Color(parse_color(value).ok_or_else(|| {
+                        MarkupError::InvalidColor(value.to_owned())
+                    })?)
+                }
+                ("size", Some(value)) => Style::Size(
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|size: &f32| *size > 0.)
+                        .ok_or_else(|| {
+                            MarkupError::InvalidSize(value.to_owned())
+                        })?,
+                ),
+                ("icon", Some(value)) => Style::Icon(value.to_owned()),
+                _ => {
+                    content.push('<');
+                    len += 1;
+                    rest = &rest[1..];
+                    continue;
+                }
+            };
+
+            if let Style::Icon(_) = style {
+                spans.push(Span {
+                    range: len..len + 1,
+                    style,
+                });
+                content.push(ICON_CHAR);
+                len += 1;
+            } else {
+                open.push((name, spans.len()));
+                spans.push(Span {
+                    range: len..len,
+                    style,
+                });
+            }
+        }
+        rest = &rest[tag.len() + 2..];
+    }
+    content.push_str(rest);
+
+    match open.pop() {
+        Some((name, _)) => Err(MarkupError::Unclosed(name.to_owned())),
+        None => Ok((content, spans)),
+    }
+}
+
+fn is_tag(name: &str) -> bool {
+    ["b", "i", "u", "color", "size"].contains(&name)
+}
+
+// Like #ff8800.
+fn parse_color(value: &str) -> Option<Rgb> {
+    let hex = value.strip_prefix('#').filter(|hex| hex.len() == 6)?;
+    let channel = |i: usize| {
+        u8::from_str_radix(hex.get(i..i + 2)?, 16)
+            .ok()
+            .map(|c| f32::from(c) / 255.)
+    };
+
+    Some(Rgb::new(channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_nested_tags() {
+        let (content, spans) = parse(
+            "press <icon=key_w> to <b>move <color=#ff0000>fast</color></b>!",
+        )
+        .unwrap();
+
+        assert_eq!(content, "press \u{fffc} to move fast!");
+        assert_eq!(
+            spans,
+            vec![
+                Span {
+                    range: 6..7,
+                    style: Style::Icon("key_w".to_owned()),
+                },
+                Span {
+                    range: 11..20,
+                    style: Style::Bold,
+                },
+                Span {
+                    range: 16..20,
+                    style: Style::Color(Rgb::new(1., 0., 0.)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn keep_unknown_tags() {
+        let (content, spans) = parse("a < b, <W> or <u>x</u> </p> <i").unwrap();
+        assert_eq!(content, "a < b, <W> or x </p> <i");
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].range, 14..15);
+    }
+
+    #[test]
+    fn reject_invalid_markup() {
+        assert_eq!(
+            parse("<b><i>text</b></i>"),
+            Err(MarkupError::UnexpectedClose("b".to_owned()))
+        );
+        assert_eq!(
+            parse("<size=20>big"),
+            Err(MarkupError::Unclosed("size".to_owned()))
+        );
+        assert_eq!(
+            parse("<color=red>x</color>"),
+            Err(MarkupError::InvalidColor("red".to_owned()))
+        );
+        assert_eq!(
+            parse("<size=-2>x</size>"),
+            Err(MarkupError::InvalidSize("-2".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,357 @@
+use super::opengl::{self, TexId};
+use super::shaders::{self, Permutation, ShaderProgramId};
+use super::texture::{ColorSpace, Texture};
+use super::types::{Colors, Rgb, Rgba};
+
+// Arrays have their own sampler type in the shader, which can't share
+// a unit with the 2d samplers.
+const BASE_COLOR_ARRAY_UNIT: usize = 5;
+
+/// What a texture means for the shader. Each role has its own
+/// texture unit and sampler in the simple shader.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TextureRole {
+    BaseColor,
+    // Roughness in the green channel, metalness in the blue one.
+    MetallicRoughness,
+    // Kept from the imported models, not used by the shader yet
+    // (tangents aren't uploaded).
+    Normal,
+    // Ambient occlusion in the red channel.
+    Occlusion,
+    Emissive,
+}
+
+impl TextureRole {
+    pub const ALL: [TextureRole; 5] = [
+        TextureRole::BaseColor,
+        TextureRole::MetallicRoughness,
+        TextureRole::Normal,
+        TextureRole::Occlusion,
+        TextureRole::Emissive,
+    ];
+
+    pub fn unit(self) -> usize {
+        match self {
+            TextureRole::BaseColor => 0,
+            TextureRole::MetallicRoughness => 1,
+            TextureRole::Normal => 2,
+            TextureRole::Occlusion => 3,
+            TextureRole::Emissive => 4,
+        }
+    }
+
+    /// Colors are authored in sRGB, other textures hold data.
+    pub fn color_space(self) -> ColorSpace {
+        match self {
+            TextureRole::BaseColor | TextureRole::Emissive => ColorSpace::Srgb,
+            _ => ColorSpace::Linear,
+        }
+    }
+
+    // Name of the sampler in the shader, its uv set uniform is
+    // suffixed by "_uv".
+    fn sampler_name(self) -> &'static str {
+        match self {
+            TextureRole::BaseColor => "base_color_texture",
+            TextureRole::MetallicRoughness => "metallic_roughness_texture",
+            TextureRole::Normal => "normal_texture",
+            TextureRole::Occlusion => "occlusion_texture",
+            TextureRole::Emissive => "emissive_texture",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AlphaMode {
+    // Alpha is ignored.
+    Opaque,
+    // Fragments under the cutoff are discarded.
+    Mask(f32),
+    // Blended with what's behind.
+    Blend,
+}
+
+impl AlphaMode {
+    fn to_shader(self) -> (i32, f32) {
+        match self {
+            AlphaMode::Opaque => (0, 0.),
+            AlphaMode::Mask(cutoff) => (1, cutoff),
+            AlphaMode::Blend => (2, 0.),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TextureSlot {
+    pub role: TextureRole,
+    pub texture: Texture,
+    // Index of the vertex uv set used to sample the texture.
+    pub uv_set: u32,
+    tex_id: Option<TexId>,
+}
+
+/// How a mesh surface looks, shared between meshes by handle.
+/// Colors and factors are multiplied with their texture, if any.
+#[derive(Debug)]
+pub struct Material {
+    pub name: String,
+    pub base_color: Rgba,
+    // Applied over the base color, meant to be changed at runtime.
+    pub tint: Rgba,
+    pub emissive: Rgb,
+    pub roughness: f32,
+    pub metalness: f32,
+    pub alpha_mode: AlphaMode,
+    pub slots: Vec<TextureSlot>,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            name: "default".to_owned(),
+            base_color: Rgba::default(),
+            tint: Rgba::default(),
+            emissive: Rgb::black(),
+            roughness: 1.,
+            metalness: 0.,
+            alpha_mode: AlphaMode::Opaque,
+            slots: vec![],
+        }
+    }
+}
+
+impl Material {
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_owned();
+        self
+    }
+
+    pub fn base_color(mut self, color: Rgba) -> Self {
+        self.base_color = color;
+        self
+    }
+
+    #[allow(unused)]
+    pub fn tint(mut self, tint: Rgba) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    pub fn emissive(mut self, emissive: Rgb) -> Self {
+        self.emissive = emissive;
+        self
+    }
+
+    pub fn roughness(mut self, roughness: f32) -> Self {
+        self.roughness = roughness;
+        self
+    }
+
+    pub fn metalness(mut self, metalness: f32) -> Self {
+        self.metalness = metalness;
+        self
+    }
+
+    pub fn alpha_mode(mut self, alpha_mode: AlphaMode) -> Self {
+        self.alpha_mode = alpha_mode;
+        self
+    }
+
+    /// Set the texture of a role, replacing the previous one. Its
+    /// color space is the one of the role. Only the base color can be
+    /// a texture array, sampled with the layer of the instance.
+    pub fn texture(
+        mut self,
+        role: TextureRole,
+        texture: Texture,
+        uv_set: u32,
+    ) -> Self {
+        self.slots.retain(|slot| slot.role != role);
+        self.slots.push(TextureSlot {
+            role,
+            texture: texture.color_space(role.color_space()),
+            uv_set,
+            tex_id: None,
+        });
+        self
+    }
+
+    pub fn get_slot(&self, role: TextureRole) -> Option<&TextureSlot> {
+        self.slots.iter().find(|slot| slot.role == role)
+    }
+
+    /// Upload the textures not loaded on the gpu yet.
+    pub fn load_gl(mut self) -> Self {
+        for slot in self.slots.iter_mut() {
+            if slot.tex_id.is_none() {
+                let tex_id = unsafe { opengl::load_tex_to_gpu(&slot.texture) };
+                slot.tex_id = Some(tex_id);
+            }
+        }
+        self
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.alpha_mode == AlphaMode::Blend
+    }
+
+    /// Set the material uniforms and bind its textures, the shader
+    /// must be active.
+    pub fn bind(&self, prog_id: ShaderProgramId) {
+        shaders::set_vec4(prog_id, "base_color", &self.base_color.into());
+        shaders::set_vec4(prog_id, "tint", &self.tint.into());
+        shaders::set_vec3(prog_id, "emissive", &(&self.emissive).into());
+        shaders::set_f32(prog_id, "roughness", self.roughness);
+        shaders::set_f32(prog_id, "metalness", self.metalness);
+
+        let (alpha_mode, alpha_cutoff) = self.alpha_mode.to_shader();
+        shaders::set_i32(prog_id, "alpha_mode", alpha_mode);
+        shaders::set_f32(prog_id, "alpha_cutoff", alpha_cutoff);
+
+        // Only the loaded textures are defined in the shader, see
+        // `permutation`.
+        for role in TextureRole::ALL.iter() {
+            let (tex_id, uv_set, is_array) = match self.get_slot(*role) {
+                Some(TextureSlot {
+                    tex_id: Some(tex_id),
+                    uv_set,
+                    texture,
+                    ..
+                }) => (*tex_id, *uv_set as i32, texture.layers.is_some()),
+                _ => continue,
+            };
+
+            let name = if is_array && *role == TextureRole::BaseColor {
+                opengl::bind_texture_array(tex_id, BASE_COLOR_ARRAY_UNIT);
+                shaders::set_i32(
+                    prog_id,
+                    "base_color_array",
+                    BASE_COLOR_ARRAY_UNIT as i32,
+                );
+                "base_color_array"
+            } else if is_array {
+                dbg!("Only the base color can be a texture array.");
+                continue;
+            } else {
+                let name = role.sampler_name();
+                opengl::bind_texture(tex_id, role.unit());
+                shaders::set_i32(prog_id, name, role.unit() as i32);
+                name
+            };
+            shaders::set_i32(prog_id, &format!("{}_uv", name), uv_set);
+        }
+    }
+
+    /// Defines of the textures sampled by the shader, added to the
+    /// permutation of the mesh.
+    pub fn permutation(&self, mut permutation: Permutation) -> Permutation {
+        for slot in self.slots.iter().filter(|slot| slot.tex_id.is_some()) {
+            let define = match slot.role {
+                TextureRole::BaseColor if slot.texture.layers.is_some() => {
+                    "HAS_BASE_COLOR_ARRAY"
+                }
+                TextureRole::BaseColor => "HAS_BASE_COLOR_TEXTURE",
+                TextureRole::MetallicRoughness => {
+                    "HAS_METALLIC_ROUGHNESS_TEXTURE"
+                }
+                TextureRole::Occlusion => "HAS_OCCLUSION_TEXTURE",
+                TextureRole::Emissive => "HAS_EMISSIVE_TEXTURE",
+                TextureRole::Normal => continue,
+            };
+            permutation = permutation.define(define, true);
+        }
+
+        permutation
+    }
+}
+
+impl Drop for Material {
+    fn drop(&mut self) {
+        let tex_ids: Vec<TexId> =
+            self.slots.iter().filter_map(|slot| slot.tex_id).collect();
+
+        if !tex_ids.is_empty() {
+            unsafe {
+                gl::DeleteTextures(tex_ids.len() as i32, tex_ids.as_ptr());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::shadow::SHADOW_MAP_UNIT;
+
+    #[test]
+    fn texture_slots_by_role() {
+        let texture = Texture::new((1, 1), vec![255; 4]);
+        let material = Material::default()
+            .texture(TextureRole::BaseColor, texture.clone(), 0)
+            .texture(TextureRole::Emissive, texture.clone(), 1)
+            .texture(TextureRole::BaseColor, texture, 1);
+
+        // The base color texture was replaced.
+        assert_eq!(material.slots.len(), 2);
+        assert_eq!(
+            material.get_slot(TextureRole::BaseColor).unwrap().uv_set,
+            1
+        );
+        assert!(material.get_slot(TextureRole::Occlusion).is_none());
+
+        // Every role has its own unit, before the shadow map one.
+        let mut units: Vec<usize> =
+            TextureRole::ALL.iter().map(|role| role.unit()).collect();
+        units.sort();
+        units.dedup();
+        assert_eq!(units.len(), TextureRole::ALL.len());
+        assert!(!units.contains(&BASE_COLOR_ARRAY_UNIT));
+        units.push(BASE_COLOR_ARRAY_UNIT);
+        assert!(units.iter().all(|unit| *unit < SHADOW_MAP_UNIT));
+
+        // Data textures aren't decoded from sRGB.
+        let material = material.texture(
+            TextureRole::Occlusion,
+            Texture::new((1, 1), vec![255; 4]),
+            0,
+        );
+        let color_space =
+            |role| material.get_slot(role).unwrap().texture.color_space;
+        assert_eq!(color_space(TextureRole::BaseColor), ColorSpace::Srgb);
+        assert_eq!(color_space(TextureRole::Occlusion), ColorSpace::Linear);
+    }
+
+    #[test]
+    fn define_loaded_textures() {
+        let texture = Texture::new((1, 1), vec![255; 4]);
+        let array = Texture::array(vec![texture.clone()]).unwrap();
+        let mut material = Material::default()
+            .texture(TextureRole::BaseColor, array, 0)
+            .texture(TextureRole::Occlusion, texture.clone(), 0)
+            .texture(TextureRole::Emissive, texture, 0);
+
+        // Not on the gpu, nothing to sample.
+        assert_eq!(
+            material.permutation(Permutation::default()),
+            Permutation::default()
+        );
+
+        // Fake ids, cleared before the drop.
+        for slot in material.slots.iter_mut() {
+            slot.tex_id = Some(1);
+        }
+        material.slots[2].tex_id = None;
+        let permutation = material.permutation(Permutation::default());
+        for slot in material.slots.iter_mut() {
+            slot.tex_id = None;
+        }
+
+        assert_eq!(
+            permutation,
+            Permutation::default()
+                .define("HAS_BASE_COLOR_ARRAY", true)
+                .define("HAS_OCCLUSION_TEXTURE", true)
+        );
+    }
+}
@@ -1,38 +1,84 @@
+mod atlas;
 mod draw;
 mod font;
+mod frustum;
+mod icons;
 mod light;
+mod markup;
+mod material;
 mod mesh;
+mod model;
+mod offscreen;
 mod opengl;
+mod post;
 pub mod primitives;
+mod queue;
+mod sdf;
 mod shaders;
+mod shadow;
 mod text;
+mod text_cache;
+mod text_layout;
 mod texture;
+mod tile_batch;
+mod transform;
 mod types;
 
 // Internal...
-use crate::entities::{Entities, Entity, Markers};
+use crate::bvh::Aabb;
+use crate::entities::{Entities, Entity, Handle, Markers};
 use crate::global::*;
 use crate::player::Player;
-use crate::tilemap::World;
+use crate::tilemap::{Tilemap, World};
 pub use draw::*;
+use image::RgbaImage;
+use nalgebra_glm as glm;
+use offscreen::RenderTarget;
+use opengl::Fbo;
+use post::{PostProcess, PostTargets};
+use queue::Layer;
+use std::mem;
 // Pub
-pub use font::Font;
-pub use light::{LightProbes, SunLight};
-pub use mesh::{Mesh, Transform, Vertex};
-pub use opengl::GpuBound;
+pub use font::{Face, Font};
+pub use frustum::{CullCount, Frustum};
+pub use icons::IconAtlas;
+pub use light::{LightProbes, LightsUbo, SunLight};
+#[allow(unused)]
+pub use material::{AlphaMode, Material, TextureRole};
+pub use mesh::{Mesh, Vertex};
+pub use model::Model;
+pub use opengl::{GpuBound, GpuTexture};
+pub use post::{PostSettings, Quality};
+pub use queue::{Clip, DrawItem, RenderQueue, Shape};
 pub use shaders::ShaderManager;
+pub use shadow::ShadowMap;
 pub use text::Text;
+pub use texture::Texture;
+pub use tile_batch::TileBatches;
+pub use transform::{slerp, Transform};
 pub use types::{Colors, Dimension, Position, Rgb, Rgba, Vector};
 
 #[derive(Default)]
 pub struct DebugInfo {
     pub draw_call: u32,
+    // Culled by the camera frustum.
+    pub tilemaps: CullCount,
+    pub meshes: CullCount,
     pub gpu_loaded_size: u32,
     pub is_wireframe: bool,
+    pub show_shadow_map: bool,
 }
 
 pub struct Renderer {
     back_buffer_color: Rgba,
+    shadow_map: ShadowMap,
+    // There is a sun this frame.
+    has_shadows: bool,
+    tile_batches: TileBatches,
+    queue: RenderQueue,
+    post: PostProcess,
+    // Sized like the window, created on the first flush.
+    post_targets: Option<PostTargets>,
     pub debug_info: DebugInfo,
 }
 
@@ -51,30 +97,54 @@ impl Renderer {
         opengl::clear(&back_buffer_color);
 
         // Load mesh assets.
+        let ground_variants = Texture::array(
+            tile_batch::GROUND_VARIANTS
+                .iter()
+                .map(|path| Texture::from_file(path))
+                .collect(),
+        )
+        .expect("Grounds should have at least one variant");
+        let ground_material = entities.insert(
+            Material::default()
+                .name("ground")
+                .texture(TextureRole::BaseColor, ground_variants, 0)
+                .load_gl(),
+        );
         let ground = entities.insert(primitives::create_cube(
-            "assets/textures/ground.png",
             Transform::default().scale(Vector(1., 0.5, 1.)),
             None,
-            Rgba::new(0., 1., 1., 1.),
+            Some(ground_material),
         ));
 
+        let wall_material =
+            entities.insert(Material::default().name("wall").roughness(0.6));
         let wall = entities.insert(primitives::create_cube(
-            "",
             Transform::default(),
             None,
-            Rgba::default(),
+            Some(wall_material),
         ));
 
+        let player_material = entities.insert(
+            Material::default()
+                .name("player")
+                .texture(
+                    TextureRole::BaseColor,
+                    Texture::from_file("assets/textures/player.png"),
+                    0,
+                )
+                .load_gl(),
+        );
         let player = entities.insert(primitives::create_cube(
-            "assets/textures/player.png",
             Transform::default(),
             None,
-            Rgba::red(),
+            Some(player_material),
         ));
 
         let quad =
             entities.insert(primitives::create_quad(Transform::default()));
 
+        entities.insert(LightProbes::Sun(primitives::add_light()));
+
         entities.markers = Some(Markers {
             ground,
             wall,
@@ -84,79 +154,342 @@ impl Renderer {
 
         Self {
             back_buffer_color,
+            shadow_map: ShadowMap::new(shadow::SHADOW_MAP_SIZE),
+            has_shadows: false,
+            tile_batches: TileBatches::default(),
+            queue: RenderQueue::default(),
+            post: PostProcess::new(),
+            post_targets: None,
             debug_info: DebugInfo::default(),
         }
     }
 
+    /// Submit the world to the render queue, drawn with the gui on
+    /// `flush`.
     pub fn draw(
         &mut self,
         entities: &mut Entities,
         world: &World,
         player: &Player,
     ) {
-        // let bbox_mesh = primitives::create_cube(
-        //     Transform::default(),
-        //     None,
-        //     Rgba::default(),
-        // );
-
         // Updates UBOs...
         SHADERS.update_all_ubo();
 
+        // Compose world transforms of meshes from their parents.
+        entities.update_scene();
+
+        let view = *VIEW_MATRIX.lock().unwrap();
+        let projection = *PERSPECTIVE_MATRIX.lock().unwrap();
+        let frustum = Frustum::from_matrix(&(projection * view));
+
+        // The current tilemap and its siblings are drawn, if seen.
+        let mut tilemaps = vec![(
+            player.tilemap_pos.handle.unwrap(),
+            player.tilemap_pos.world,
+        )];
+        tilemaps.extend(world.get_sibling_tilemap(&player.tilemap_pos.world));
+
+        let tilemaps_count = &mut self.debug_info.tilemaps;
+        *tilemaps_count = CullCount::default();
+        tilemaps.retain(|(_, pos)| {
+            let (min, max) = world.get_tilemap_bounds(pos);
+            let is_visible = frustum.intersects(&Aabb::new(min, max));
+            tilemaps_count.add(is_visible);
+            is_visible
+        });
+
+        for (handle, pos) in tilemaps.iter() {
+            let batch = self.tile_batches.get(entities, world, handle, pos);
+            queue_tilemap(&mut self.queue, entities, player, batch);
+        }
+
+        // Render free meshes (i.e. not part of a tilemap).
+        self.debug_info.meshes =
+            queue_scene(&mut self.queue, entities, &frustum);
+
+        self.update_lights(entities, world, &tilemaps, &camera_position());
+
+        // Shadow map in the bottom left corner.
+        if self.debug_info.show_shadow_map {
+            let size = 300.;
+            self.queue.push(DrawItem::depth(
+                self.shadow_map.depth,
+                &Transform::from_pos(Vector(10., 10., 0.))
+                    .scale(Vector(size, size, 1.)),
+            ));
+        }
+    }
+
+    pub fn queue_mut(&mut self) -> &mut RenderQueue {
+        &mut self.queue
+    }
+
+    /// Draw and clear everything submitted since the last flush.
+    pub fn flush(&mut self, entities: &Entities, font: &mut Font) {
+        // Targets follow the viewport, set when the window is resized.
+        let viewport = opengl::get_viewport();
+        let size = (viewport[2] as u32, viewport[3] as u32);
+        let samples = self.post.settings.samples();
+        let targets = self
+            .post_targets
+            .take()
+            .filter(|targets| {
+                targets.size() == size && targets.samples() == samples
+            })
+            .unwrap_or_else(|| PostTargets::new(size, samples));
+
+        self.render(entities, Some(font), 0, &targets, &camera_position());
+        self.post_targets = Some(targets);
+        self.queue.clear();
+        font.end_frame();
+    }
+
+    pub fn post_settings_mut(&mut self) -> &mut PostSettings {
+        &mut self.post.settings
+    }
+
+    /// Draw everything submitted since the last flush into an image
+    /// of the given size. The queue is kept to be flushed on screen.
+    pub fn screenshot(
+        &mut self,
+        entities: &Entities,
+        font: &mut Font,
+        (width, height): (u32, u32),
+    ) -> RgbaImage {
+        // Same camera, only the aspect ratio changes.
+        let perspective =
+            glm::perspective(width as f32 / height as f32, 45.0, 0.1, 100.0);
+        SHADERS.update_projections(
+            *ORTHO_MATRIX.lock().unwrap(),
+            perspective,
+            *VIEW_MATRIX.lock().unwrap(),
+        );
+
+        let image = self.render_to_image(
+            entities,
+            Some(font),
+            (width, height),
+            &camera_position(),
+        );
+        SHADERS.update_all_ubo();
+
+        image
+    }
+
+    /// Top down view of a tilemap in a square image, without texts.
+    /// The frame being submitted is left untouched.
+    pub fn tilemap_thumbnail(
+        &mut self,
+        entities: &Entities,
+        world: &World,
+        player: &Player,
+        tilemap: (Handle<Tilemap>, glm::TVec2<i32>),
+        size: u32,
+    ) -> RgbaImage {
+        let frame = mem::take(&mut self.queue);
+        let (handle, pos) = tilemap;
+        let batch = self.tile_batches.get(entities, world, &handle, &pos);
+        queue_tilemap(&mut self.queue, entities, player, batch);
+
+        // Looking down with the top of the image toward -z.
+        let (min, max) = world.get_tilemap_bounds(&pos);
+        let center = (min + max) * 0.5;
+        let eye = glm::vec3(center.x, max.y + 10., center.z);
+        let view = glm::look_at(&eye, &center, &glm::vec3(0., 0., -1.));
+        let half = TILEMAP_WIDTH.max(TILEMAP_HEIGHT) * 0.5;
+        let projection = glm::ortho(-half, half, -half, half, 0.1, 100.);
+        SHADERS.update_projections(
+            *ORTHO_MATRIX.lock().unwrap(),
+            projection,
+            view,
+        );
+        self.update_lights(entities, world, &[tilemap], &eye);
+
+        let image = self.render_to_image(entities, None, (size, size), &eye);
+        SHADERS.update_all_ubo();
+        self.queue = frame;
+
+        image
+    }
+
+    // Draw the queue into the given framebuffer, after the sun depth
+    // pass. The world goes through the post passes, not the ui.
+    fn render(
+        &mut self,
+        entities: &Entities,
+        font: Option<&mut Font>,
+        target: Fbo,
+        post_targets: &PostTargets,
+        camera_position: &glm::Vec3,
+    ) {
+        self.queue.sort(camera_position);
+        let mut backend = GlBackend::new(entities, font);
+
         // Reset the debug counter.
         self.debug_info.draw_call = 0;
 
-        // Draw current tilemap.
-        let tilemap = entities.get(&player.tilemap_pos.handle.unwrap());
-        draw_tilemap(
-            entities,
-            player,
-            world,
-            tilemap,
-            Some(&player.tilemap_pos.world),
+        // Lit in linear space, encoded back by the gamma pass.
+        let scene = post_targets.scene_fbo();
+        opengl::bind_framebuffer(scene);
+        opengl::clear(&self.back_buffer_color.to_linear());
+
+        if self.has_shadows {
+            self.debug_info.draw_call +=
+                self.draw_shadow_map(&mut backend, scene);
+        }
+
+        // Sampled by every permutation of the color pass.
+        opengl::bind_texture(self.shadow_map.depth, shadow::SHADOW_MAP_UNIT);
+
+        self.debug_info.draw_call += self.queue.execute_layers(
+            &mut backend,
+            RenderPass::Color,
+            &[Layer::Opaque, Layer::Transparent],
         );
 
-        for (handle, pos) in
-            world.get_sibling_tilemap(&player.tilemap_pos.world)
-        {
-            // Render the "current" tilemap.
-            let tilemap = entities.get(&handle);
-            draw_tilemap(entities, player, world, tilemap, Some(&pos));
+        post_targets.resolve();
+        // The screen triangle is always filled.
+        let is_wireframe = self.debug_info.is_wireframe;
+        if is_wireframe {
+            set_polygon_mode(false);
         }
+        self.post.apply(post_targets, target);
+        if is_wireframe {
+            set_polygon_mode(true);
+        }
+
+        self.debug_info.draw_call += self.queue.execute_layers(
+            &mut backend,
+            RenderPass::Color,
+            &[Layer::Ui],
+        );
+    }
+
+    fn render_to_image(
+        &mut self,
+        entities: &Entities,
+        font: Option<&mut Font>,
+        (width, height): (u32, u32),
+        camera_position: &glm::Vec3,
+    ) -> RgbaImage {
+        let target = RenderTarget::new(width, height);
+        let post_targets =
+            PostTargets::new((width, height), self.post.settings.samples());
+        let viewport = opengl::get_viewport();
+
+        opengl::set_viewport(width as i32, height as i32);
+        self.render(entities, font, target.fbo, &post_targets, camera_position);
+
+        opengl::bind_framebuffer(0);
+        opengl::set_viewport(viewport[2], viewport[3]);
+
+        target.read_image()
+    }
 
-        // Render all our light probes into the scene.
-        for (light, _) in entities.light_probes.iter() {
-            self.debug_info.draw_call += 1;
-            match light {
-                LightProbes::Sun(sun) => draw_sun_light(sun),
+    // Lights of the frame, the sun shadows cover the drawn tilemaps.
+    fn update_lights(
+        &mut self,
+        entities: &Entities,
+        world: &World,
+        tilemaps: &[(Handle<Tilemap>, glm::TVec2<i32>)],
+        camera_position: &glm::Vec3,
+    ) {
+        let sun = entities.light_probes.iter().find_map(|(light, _)| {
+            if let LightProbes::Sun(sun) = light {
+                Some(sun.direction.to_glm())
+            } else {
+                None
             }
+        });
+
+        let mut lights = LightsUbo::new(
+            entities.light_probes.iter().map(|(light, _)| light),
+            camera_position,
+        );
+
+        // Nothing to shadow when all tilemaps are culled.
+        let sun = sun.filter(|_| !tilemaps.is_empty());
+        self.has_shadows = sun.is_some();
+        if let Some(direction) = sun {
+            self.fit_shadow_map(world, tilemaps, &direction);
+            lights = lights.shadows(&self.shadow_map.light_space);
+        }
+        SHADERS.update_lights(lights);
+    }
+
+    // The sun frustum is fitted to the drawn tilemaps.
+    fn fit_shadow_map(
+        &mut self,
+        world: &World,
+        tilemaps: &[(Handle<Tilemap>, glm::TVec2<i32>)],
+        direction: &glm::Vec3,
+    ) {
+        let (mut min, mut max) = world.get_tilemap_bounds(&tilemaps[0].1);
+        for (_, pos) in tilemaps.iter().skip(1) {
+            let (low, high) = world.get_tilemap_bounds(pos);
+            min = glm::min2(&min, &low);
+            max = glm::max2(&max, &high);
+        }
+        self.shadow_map.light_space =
+            shadow::fit_light_space(direction, &min, &max);
+    }
+
+    // Depth pre-pass from the sun, return the number of draws.
+    // The target framebuffer is bound back once done.
+    fn draw_shadow_map(&self, backend: &mut GlBackend, target: Fbo) -> u32 {
+        let prog_id = SHADERS.activate(shaders::ShaderType::ShadowShader);
+        shaders::set_matrix4(
+            prog_id,
+            "light_space",
+            self.shadow_map.light_space.as_slice(),
+        );
+
+        let viewport = opengl::get_viewport();
+        opengl::bind_framebuffer(self.shadow_map.fbo);
+        opengl::set_viewport(self.shadow_map.size, self.shadow_map.size);
+        opengl::clear_depth();
+
+        unsafe {
+            // Push the depth a bit further against shadow acne.
+            gl::Enable(gl::POLYGON_OFFSET_FILL);
+            gl::PolygonOffset(2., 4.);
         }
 
-        // Render all our GUI texts to the screen.
-        // for (text, _) in entities.text_widgets.iter() {
-        //     self.debug_info.draw_call += 1;
-        //     draw_text(&mut font, text);
-        // }
+        let draw_call = self.queue.execute(backend, RenderPass::Shadow);
+
+        unsafe {
+            gl::Disable(gl::POLYGON_OFFSET_FILL);
+        }
 
-        // let quad = entities.get(&entities.markers.as_ref().unwrap().quad);
-        // draw_quad(quad);
+        opengl::bind_framebuffer(target);
+        opengl::set_viewport(viewport[2], viewport[3]);
 
-        // bbox goes out of scope so drop so gl cleanup functions are called.
+        draw_call
     }
 
     pub fn clear_screen(&self) {
         opengl::clear(&self.back_buffer_color);
     }
 
+    pub fn toggle_shadow_map(&mut self) {
+        self.debug_info.show_shadow_map = !self.debug_info.show_shadow_map;
+    }
+
     pub fn toggle_wireframe(&mut self) {
-        unsafe {
-            if self.debug_info.is_wireframe {
-                gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
-                self.debug_info.is_wireframe = false;
-            } else {
-                gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
-                self.debug_info.is_wireframe = true;
-            }
-        }
+        self.debug_info.is_wireframe = !self.debug_info.is_wireframe;
+        set_polygon_mode(self.debug_info.is_wireframe);
+    }
+}
+
+fn set_polygon_mode(is_wireframe: bool) {
+    let mode = if is_wireframe { gl::LINE } else { gl::FILL };
+    unsafe {
+        gl::PolygonMode(gl::FRONT_AND_BACK, mode);
     }
 }
+
+// Position of the camera in world space, from the view matrix.
+fn camera_position() -> glm::Vec3 {
+    let view = *VIEW_MATRIX.lock().unwrap();
+    glm::inverse(&view).column(3).xyz()
+}
@@ -0,0 +1,794 @@
+use super::material::{AlphaMode, Material, TextureRole};
+use super::mesh::{UVSet, Vertex, MAX_UV_SETS, UV};
+use super::shaders::ShaderType;
+use super::texture::{Sampler, Texture};
+use super::types::{Rgb, Rgba};
+use super::{DrawMode, Mesh, Transform, Vector};
+use crate::animation::{
+    Channel, Clip, Interpolation, Joint, Keyframes, Skeleton, Target,
+};
+use crate::entities::Handle;
+use gltf::animation::util::ReadOutputs;
+use gltf::image::Format;
+use gltf::mesh::Mode;
+use nalgebra_glm as glm;
+use std::collections::HashMap;
+use std::{error, fmt};
+
+// Same as the simple shader.
+pub const MAX_JOINTS: usize = 64;
+
+#[derive(Debug)]
+pub enum ModelError {
+    Gltf(gltf::Error),
+    // The file needs an extension we don't handle.
+    UnsupportedExtension(String),
+    UnsupportedMode(Mode),
+    UnsupportedImageFormat(Format),
+    MissingPositions { mesh: usize },
+    // An index points past the positions of its primitive.
+    IndexOutOfRange { mesh: usize },
+    // Texture coordinates set used by a material but not in the mesh.
+    MissingTexCoords { mesh: usize, set: u32 },
+    // Only one skin by model is handled.
+    MultipleSkins,
+    TooManyJoints(usize),
+    UnsupportedInterpolation(gltf::animation::Interpolation),
+    UnsupportedMorphTargets,
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ModelError::Gltf(e) => write!(f, "glTF error: {}", e),
+            ModelError::UnsupportedExtension(name) => {
+                write!(f, "Unsupported required extension: {}", name)
+            }
+            ModelError::UnsupportedMode(mode) => {
+                write!(f, "Unsupported primitive mode: {:?}", mode)
+            }
+            ModelError::UnsupportedImageFormat(format) => {
+                write!(f, "Unsupported image format: {:?}", format)
+            }
+            ModelError::MissingPositions { mesh } => {
+                write!(f, "Mesh {} has a primitive without positions", mesh)
+            }
+            ModelError::IndexOutOfRange { mesh } => {
+                write!(f, "Mesh {} has an index out of its vertices", mesh)
+            }
+            ModelError::MissingTexCoords { mesh, set } => write!(
+                f,
+                "Mesh {} has no texture coordinates set {}",
+                mesh, set
+            ),
+            ModelError::MultipleSkins => {
+                write!(f, "Only one skin by model is supported")
+            }
+            ModelError::TooManyJoints(count) => write!(
+                f,
+                "Skin has {} joints, the maximum is {}",
+                count, MAX_JOINTS
+            ),
+            ModelError::UnsupportedInterpolation(interpolation) => write!(
+                f,
+                "Unsupported animation interpolation: {:?}",
+                interpolation
+            ),
+            ModelError::UnsupportedMorphTargets => {
+                write!(f, "Morph targets aren't supported")
+            }
+        }
+    }
+}
+
+impl error::Error for ModelError {}
+
+impl From<gltf::Error> for ModelError {
+    fn from(e: gltf::Error) -> Self {
+        ModelError::Gltf(e)
+    }
+}
+
+/// One drawable part of a model, not loaded on the gpu yet.
+#[derive(Debug)]
+pub struct ModelPart {
+    pub vertex: Vertex,
+    // Index of the material in the model, the default one if none.
+    pub material: Option<usize>,
+    pub mode: DrawMode,
+    pub transform: Transform,
+    // Index of the parent part in the model.
+    pub parent: Option<usize>,
+    // Deformed by the model skeleton.
+    pub is_skinned: bool,
+}
+
+/// Meshes of a glTF scene, with their node hierarchy.
+/// Parents always come before their children.
+#[derive(Debug, Default)]
+pub struct Model {
+    pub parts: Vec<ModelPart>,
+    // Same order than the glTF materials, not loaded on the gpu yet.
+    pub materials: Vec<Material>,
+    pub skeleton: Option<Skeleton>,
+    // Animation targets are indices in `parts` or in the skeleton.
+    pub clips: Vec<Clip>,
+}
+
+impl Model {
+    /// Import the default scene (or the first one) of a glTF file.
+    ///
+    /// Each primitive becomes a part. The first primitive of a node
+    /// holds the node transform, the other ones are its children.
+    /// Nodes without mesh aren't kept, their transform is applied
+    /// to their children instead.
+    ///
+    /// The first skin becomes the model skeleton, the skinned parts
+    /// ignore their node transform (like glTF wants).
+    pub fn from_gltf(path: &str) -> Result<Self, ModelError> {
+        let (document, buffers, images) = gltf::import(path)?;
+
+        if let Some(name) = document.extensions_required().next() {
+            return Err(ModelError::UnsupportedExtension(name.to_owned()));
+        }
+        if document.skins().count() > 1 {
+            return Err(ModelError::MultipleSkins);
+        }
+
+        let images = images
+            .into_iter()
+            .map(image_to_texture)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut model = Self {
+            materials: document
+                .materials()
+                .map(|material| read_material(&material, &images))
+                .collect(),
+            ..Default::default()
+        };
+
+        let mut node_parts = HashMap::new();
+        let scene = match document.default_scene() {
+            Some(scene) => Some(scene),
+            None => document.scenes().next(),
+        };
+
+        if let Some(scene) = scene {
+            for node in scene.nodes() {
+                model.read_node(
+                    &node,
+                    None,
+                    glm::identity(),
+                    &buffers,
+                    &mut node_parts,
+                )?;
+            }
+        }
+
+        let mut node_joints = HashMap::new();
+        if let Some(skin) = document.skins().next() {
+            let skeleton = read_skeleton(&document, &skin, &buffers)?;
+            model.skeleton = Some(skeleton);
+
+            for (i, joint) in skin.joints().enumerate() {
+                node_joints.insert(joint.index(), i);
+            }
+        }
+
+        for animation in document.animations() {
+            let clip = read_clip(&animation, &buffers, |node| {
+                node_joints
+                    .get(&node)
+                    .map(|i| Target::Joint(*i))
+                    .or_else(|| node_parts.get(&node).map(|i| Target::Part(*i)))
+            })?;
+            model.clips.push(clip);
+        }
+
+        Ok(model)
+    }
+
+    // `carry` is the transform of the parent nodes we didn't keep.
+    fn read_node(
+        &mut self,
+        node: &gltf::Node,
+        parent: Option<usize>,
+        carry: glm::Mat4,
+        buffers: &[gltf::buffer::Data],
+        node_parts: &mut HashMap<usize, usize>,
+    ) -> Result<(), ModelError> {
+        let local = carry
+            * Transform::from_decomposed(node.transform().decomposed())
+                .to_model();
+
+        let (parent, carry) = match node.mesh() {
+            Some(mesh) => {
+                let mut node_part = None;
+
+                for prim in mesh.primitives() {
+                    let (vertex, mode) =
+                        read_primitive(&mesh, &prim, buffers, &self.materials)?;
+
+                    let is_skinned =
+                        node.skin().is_some() && !vertex.joints.is_empty();
+
+                    let (transform, parent) = match node_part {
+                        _ if is_skinned => (Transform::default(), None),
+                        None => (Transform::from_model(&local), parent),
+                        Some(index) => (Transform::default(), Some(index)),
+                    };
+
+                    self.parts.push(ModelPart {
+                        vertex,
+                        material: prim.material().index(),
+                        mode,
+                        transform,
+                        parent,
+                        is_skinned,
+                    });
+
+                    if node_part.is_none() {
+                        node_part = Some(self.parts.len() - 1);
+                        node_parts.insert(node.index(), self.parts.len() - 1);
+                    }
+                }
+
+                match node_part {
+                    Some(index) => (Some(index), glm::identity()),
+                    None => (parent, local),
+                }
+            }
+            None => (parent, local),
+        };
+
+        for child in node.children() {
+            self.read_node(&child, parent, carry, buffers, node_parts)?;
+        }
+
+        Ok(())
+    }
+
+    /// Load the parts on the gpu, roots are placed with the given
+    /// transform. Meshes are returned in the same order than the
+    /// parts, they still need their parent handle (see
+    /// `Entities::insert_model`).
+    /// The material handles are in the same order than the model
+    /// materials.
+    pub fn into_meshes(
+        self,
+        transform: Transform,
+        materials: &[Handle<Material>],
+    ) -> Vec<(Mesh, Option<usize>)> {
+        let joint_matrices =
+            self.skeleton.as_ref().map_or(vec![], |skeleton| {
+                skeleton.joint_matrices(&skeleton.rest_pose())
+            });
+
+        self.parts
+            .into_iter()
+            .map(|part| {
+                let local = match part.parent {
+                    Some(_) => part.transform,
+                    None => Transform::from_model(
+                        &(transform.to_model() * part.transform.to_model()),
+                    ),
+                };
+
+                let mut mesh = Mesh::new(
+                    part.vertex,
+                    local,
+                    None,
+                    // collider
+                    None,
+                    part.mode,
+                    ShaderType::SimpleShader,
+                )
+                .with_bvh()
+                .material(part.material.map(|index| materials[index]));
+
+                if part.is_skinned {
+                    mesh.joint_matrices = joint_matrices.clone();
+                }
+
+                (mesh, part.parent)
+            })
+            .collect()
+    }
+}
+
+fn read_primitive(
+    mesh: &gltf::Mesh,
+    prim: &gltf::Primitive,
+    buffers: &[gltf::buffer::Data],
+    materials: &[Material],
+) -> Result<(Vertex, DrawMode), ModelError> {
+    let mode = match prim.mode() {
+        Mode::Triangles => DrawMode::Triangles,
+        Mode::Lines => DrawMode::Lines,
+        mode => return Err(ModelError::UnsupportedMode(mode)),
+    };
+
+    let mut vertex = Vertex::default();
+    let reader = prim.reader(|buffer| Some(&buffers[buffer.index()]));
+
+    vertex.primitives = reader
+        .read_positions()
+        .ok_or(ModelError::MissingPositions { mesh: mesh.index() })?
+        .map(|pos| Vector(pos[0], pos[1], pos[2]))
+        .collect();
+
+    vertex.indices = reader
+        .read_indices()
+        .map_or(vec![], |read_indices| read_indices.into_u32().collect());
+    let count = vertex.primitives.len();
+    if vertex.indices.iter().any(|i| *i as usize >= count) {
+        return Err(ModelError::IndexOutOfRange { mesh: mesh.index() });
+    }
+
+    vertex.normals = match reader.read_normals() {
+        Some(normals) => normals.map(|n| Vector(n[0], n[1], n[2])).collect(),
+        None if mode == DrawMode::Triangles => smooth_normals(&vertex),
+        None => vec![],
+    };
+
+    vertex.tangents = reader.read_tangents().map_or(vec![], |tangents| {
+        tangents
+            .map(|t| glm::vec4(t[0], t[1], t[2], t[3]))
+            .collect()
+    });
+
+    vertex.joints = reader
+        .read_joints(0)
+        .map_or(vec![], |joints| joints.into_u16().collect());
+
+    vertex.weights = reader
+        .read_weights(0)
+        .map_or(vec![], |weights| weights.into_f32().collect());
+
+    vertex.colors = reader.read_colors(0).map_or(vec![], |read_colors| {
+        read_colors
+            .into_rgba_f32()
+            .map(|color| Rgba::new(color[0], color[1], color[2], color[3]))
+            .collect()
+    });
+
+    while vertex.uv_coords.len() < MAX_UV_SETS {
+        let set = vertex.uv_coords.len() as u32;
+        let coords: Vec<UV> = match reader.read_tex_coords(set) {
+            Some(coords) => coords
+                .into_f32()
+                .map(|uv| glm::vec2(uv[0], uv[1]))
+                .collect(),
+            None => break,
+        };
+        vertex.uv_coords.push(UVSet::new(set, coords));
+    }
+
+    // Every texture of the material needs its uv set.
+    let material = prim.material().index().map(|index| &materials[index]);
+    if let Some(material) = material {
+        for slot in material.slots.iter() {
+            if slot.uv_set as usize >= vertex.uv_coords.len() {
+                return Err(ModelError::MissingTexCoords {
+                    mesh: mesh.index(),
+                    set: slot.uv_set,
+                });
+            }
+        }
+    }
+
+    Ok((vertex, mode))
+}
+
+fn read_material(material: &gltf::Material, images: &[Texture]) -> Material {
+    let pbr = material.pbr_metallic_roughness();
+    let [r, g, b, a] = pbr.base_color_factor();
+    let [er, eg, eb] = material.emissive_factor();
+
+    let alpha_mode = match material.alpha_mode() {
+        gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+        gltf::material::AlphaMode::Mask => {
+            AlphaMode::Mask(material.alpha_cutoff())
+        }
+        gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+    };
+
+    let mut result = Material::default()
+        .name(material.name().unwrap_or("gltf"))
+        .base_color(Rgba::new(r, g, b, a))
+        .emissive(Rgb::new(er, eg, eb))
+        .roughness(pbr.roughness_factor())
+        .metalness(pbr.metallic_factor())
+        .alpha_mode(alpha_mode);
+
+    let texture = |texture: gltf::Texture| {
+        images[texture.source().index()]
+            .clone()
+            .sampler(to_sampler(&texture.sampler()))
+    };
+
+    let mut slots = vec![];
+    if let Some(info) = pbr.base_color_texture() {
+        slots.push((TextureRole::BaseColor, info.texture(), info.tex_coord()));
+    }
+    if let Some(info) = pbr.metallic_roughness_texture() {
+        slots.push((
+            TextureRole::MetallicRoughness,
+            info.texture(),
+            info.tex_coord(),
+        ));
+    }
+    if let Some(info) = material.normal_texture() {
+        slots.push((TextureRole::Normal, info.texture(), info.tex_coord()));
+    }
+    if let Some(info) = material.occlusion_texture() {
+        slots.push((TextureRole::Occlusion, info.texture(), info.tex_coord()));
+    }
+    if let Some(info) = material.emissive_texture() {
+        slots.push((TextureRole::Emissive, info.texture(), info.tex_coord()));
+    }
+
+    for (role, source, uv_set) in slots {
+        result = result.texture(role, texture(source), uv_set);
+    }
+
+    result
+}
+
+fn read_skeleton(
+    document: &gltf::Document,
+    skin: &gltf::Skin,
+    buffers: &[gltf::buffer::Data],
+) -> Result<Skeleton, ModelError> {
+    let joints: Vec<gltf::Node> = skin.joints().collect();
+    if joints.len() > MAX_JOINTS {
+        return Err(ModelError::TooManyJoints(joints.len()));
+    }
+
+    // glTF nodes only know their children.
+    let mut node_parents = HashMap::new();
+    for node in document.nodes() {
+        for child in node.children() {
+            node_parents.insert(child.index(), node.index());
+        }
+    }
+
+    let nodes: Vec<gltf::Node> = document.nodes().collect();
+    let local = |index: usize| {
+        Transform::from_decomposed(nodes[index].transform().decomposed())
+            .to_model()
+    };
+
+    let reader = skin.reader(|buffer| Some(&buffers[buffer.index()]));
+    let mut inverse_binds =
+        reader
+            .read_inverse_bind_matrices()
+            .map_or(vec![], |matrices| {
+                matrices
+                    .map(|m| glm::make_mat4(&m.concat()))
+                    .collect::<Vec<glm::Mat4>>()
+            });
+    inverse_binds.resize(joints.len(), glm::identity());
+
+    let skeleton = joints
+        .iter()
+        .zip(inverse_binds)
+        .map(|(node, inverse_bind)| {
+            let parent = node_parents.get(&node.index()).and_then(|parent| {
+                joints.iter().position(|joint| joint.index() == *parent)
+            });
+
+            // Root joints carry the transform of all their ancestors.
+            let mut rest = local(node.index());
+            if parent.is_none() {
+                let mut current = node_parents.get(&node.index());
+                while let Some(ancestor) = current {
+                    rest = local(*ancestor) * rest;
+                    current = node_parents.get(ancestor);
+                }
+            }
+
+            Joint {
+                parent,
+                rest: Transform::from_model(&rest),
+                inverse_bind,
+            }
+        })
+        .collect();
+
+    Ok(Skeleton { joints: skeleton })
+}
+
+// Channels targeting a node we didn't keep are skipped.
+fn read_clip(
+    animation: &gltf::Animation,
+    buffers: &[gltf::buffer::Data],
+    to_target: impl Fn(usize) -> Option<Target>,
+) -> Result<Clip, ModelError> {
+    let mut channels = vec![];
+
+    for channel in animation.channels() {
+        let interpolation = match channel.sampler().interpolation() {
+            gltf::animation::Interpolation::Linear => Interpolation::Linear,
+            gltf::animation::Interpolation::Step => Interpolation::Step,
+            interpolation => {
+                return Err(ModelError::UnsupportedInterpolation(interpolation))
+            }
+        };
+
+        let target = match to_target(channel.target().node().index()) {
+            Some(target) => target,
+            None => continue,
+        };
+
+        let reader = channel.reader(|buffer| Some(&buffers[buffer.index()]));
+        let times: Vec<f32> = match reader.read_inputs() {
+            Some(inputs) => inputs.collect(),
+            None => continue,
+        };
+
+        let keyframes = match reader.read_outputs() {
+            Some(ReadOutputs::Translations(values)) => Keyframes::Translation(
+                values.map(|v| glm::vec3(v[0], v[1], v[2])).collect(),
+            ),
+            Some(ReadOutputs::Rotations(values)) => Keyframes::Rotation(
+                values
+                    .into_f32()
+                    .map(|q| glm::quat(q[0], q[1], q[2], q[3]))
+                    .collect(),
+            ),
+            Some(ReadOutputs::Scales(values)) => Keyframes::Scale(
+                values.map(|v| glm::vec3(v[0], v[1], v[2])).collect(),
+            ),
+            Some(ReadOutputs::MorphTargetWeights(_)) => {
+                return Err(ModelError::UnsupportedMorphTargets)
+            }
+            None => continue,
+        };
+
+        channels.push(Channel {
+            target,
+            interpolation,
+            times,
+            keyframes,
+        });
+    }
+
+    let name = animation.name().map_or_else(
+        || format!("animation_{}", animation.index()),
+        String::from,
+    );
+
+    Ok(Clip::new(&name, channels))
+}
+
+/// Normals averaged from the faces around each vertex, for
+/// primitives without them.
+fn smooth_normals(vertex: &Vertex) -> Vec<Vector> {
+    let positions = &vertex.primitives;
+    let indices: Vec<usize> = if vertex.indices.is_empty() {
+        (0..positions.len()).collect()
+    } else {
+        vertex.indices.iter().map(|i| *i as usize).collect()
+    };
+
+    let mut normals = vec![glm::vec3(0., 0., 0.); positions.len()];
+    for face in indices.chunks_exact(3) {
+        let (a, b, c) = (
+            positions[face[0]].to_glm(),
+            positions[face[1]].to_glm(),
+            positions[face[2]].to_glm(),
+        );
+        // Not normalized, bigger faces weigh more.
+        let normal = (b - a).cross(&(c - a));
+        for i in face {
+            normals[*i] += normal;
+        }
+    }
+
+    normals
+        .into_iter()
+        .map(|n| {
+            if glm::length(&n) > f32::EPSILON {
+                Vector::from_glm(glm::normalize(&n))
+            } else {
+                Vector(0., 1., 0.)
+            }
+        })
+        .collect()
+}
+
+fn to_sampler(sampler: &gltf::texture::Sampler) -> Sampler {
+    let default = Sampler::default();
+
+    Sampler {
+        wrap_s: sampler.wrap_s().as_gl_enum(),
+        wrap_t: sampler.wrap_t().as_gl_enum(),
+        min_filter: sampler
+            .min_filter()
+            .map_or(default.min_filter, |f| f.as_gl_enum()),
+        mag_filter: sampler
+            .mag_filter()
+            .map_or(default.mag_filter, |f| f.as_gl_enum()),
+        anisotropy: default.anisotropy,
+    }
+}
+
+// Our textures are always uploaded as RGBA.
+fn image_to_texture(image: gltf::image::Data) -> Result<Texture, ModelError> {
+    let pixels = match image.format {
+        Format::R8G8B8A8 => image.pixels,
+        Format::R8G8B8 => image
+            .pixels
+            .chunks_exact(3)
+            .flat_map(|rgb| vec![rgb[0], rgb[1], rgb[2], 255])
+            .collect(),
+        format => return Err(ModelError::UnsupportedImageFormat(format)),
+    };
+
+    Ok(Texture::new((image.width, image.height), pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world_position(model: &Model, index: usize) -> glm::Vec3 {
+        let mut world = glm::identity();
+        let mut current = Some(index);
+        while let Some(i) = current {
+            world = model.parts[i].transform.to_model() * world;
+            current = model.parts[i].parent;
+        }
+        glm::vec3(world[(0, 3)], world[(1, 3)], world[(2, 3)])
+    }
+
+    #[test]
+    fn import_node_transforms() {
+        let model = Model::from_gltf("assets/models/axis/axis.gltf").unwrap();
+        assert_eq!(model.parts.len(), 3);
+
+        let part = &model.parts[1];
+        assert!(part.parent.is_none());
+        assert!(!part.vertex.normals.is_empty());
+        assert!(
+            glm::distance(
+                &part.transform.position.to_glm(),
+                &glm::vec3(-1.1, 1.1, 0.)
+            ) < 1e-4
+        );
+        // The node rotation is kept as it is.
+        assert!(
+            glm::quat_dot(
+                &part.transform.rotation,
+                &glm::quat(0., 0., -0.70710677, 0.70710677)
+            ) > 0.9999
+        );
+    }
+
+    #[test]
+    fn fold_nodes_without_mesh() {
+        // The root node only holds a rotation for its child mesh.
+        let model = Model::from_gltf("assets/models/cube_tex/BoxTextured.gltf")
+            .unwrap();
+        assert_eq!(model.parts.len(), 1);
+
+        let part = &model.parts[0];
+        let up = glm::quat_rotate_vec3(
+            &part.transform.rotation,
+            &glm::vec3(0., 1., 0.),
+        );
+        assert!(glm::distance(&up, &glm::vec3(0., 0., -1.)) < 1e-4);
+        assert_eq!(world_position(&model, 0), glm::vec3(0., 0., 0.));
+    }
+
+    #[test]
+    fn map_material_textures() {
+        let model = Model::from_gltf("assets/models/cube_tex/BoxTextured.gltf")
+            .unwrap();
+        let part = &model.parts[0];
+        let material = &model.materials[part.material.unwrap()];
+
+        assert_eq!(material.slots.len(), 1);
+        assert_eq!(part.vertex.uv_coords.len(), 1);
+        let slot = material.get_slot(TextureRole::BaseColor).unwrap();
+        assert_eq!(slot.texture.sampler.min_filter, gl::NEAREST_MIPMAP_LINEAR);
+        let (width, height) = slot.texture.dim;
+        assert_eq!(slot.texture.raw.len(), (width * height * 4) as usize);
+        // The factors are applied with the texture.
+        assert_eq!(material.metalness, 0.);
+        assert_eq!(material.base_color.r, 1.);
+
+        // Emissive with its own uv set.
+        let model = Model::from_gltf("assets/models/multi_uv/MultiUVTest.gltf")
+            .unwrap();
+        let part = &model.parts[0];
+        let material = &model.materials[part.material.unwrap()];
+        assert_eq!(
+            material.get_slot(TextureRole::BaseColor).unwrap().uv_set,
+            0
+        );
+        assert_eq!(material.get_slot(TextureRole::Emissive).unwrap().uv_set, 1);
+        assert_eq!(material.emissive.r, 1.);
+        assert_eq!(part.vertex.uv_coords.len(), 2);
+        assert!(!part.vertex.tangents.is_empty());
+
+        // No texture, the factors are kept in the material.
+        let model =
+            Model::from_gltf("assets/models/cube_color/BoxVertexColors.gltf")
+                .unwrap();
+        let part = &model.parts[0];
+        let material = &model.materials[part.material.unwrap()];
+        assert!(material.slots.is_empty());
+        assert!(!part.vertex.colors.is_empty());
+        assert!(material.base_color.r <= 0.8);
+        assert_eq!(material.alpha_mode, AlphaMode::Opaque);
+    }
+
+    #[test]
+    fn compute_missing_normals() {
+        let vertex = Vertex {
+            primitives: vec![
+                Vector(0., 0., 0.),
+                Vector(1., 0., 0.),
+                Vector(0., 0., -1.),
+            ],
+            ..Default::default()
+        };
+
+        for normal in smooth_normals(&vertex) {
+            assert_eq!(normal, Vector(0., 1., 0.));
+        }
+    }
+
+    #[test]
+    fn import_skin_and_clips() {
+        let model =
+            Model::from_gltf("assets/models/skin/SimpleSkin.gltf").unwrap();
+        assert_eq!(model.parts.len(), 1);
+        assert!(model.parts[0].is_skinned);
+        assert_eq!(model.parts[0].vertex.joints.len(), 10);
+
+        let skeleton = model.skeleton.as_ref().unwrap();
+        assert_eq!(skeleton.joints.len(), 2);
+        assert_eq!(skeleton.joints[0].parent, None);
+        assert_eq!(skeleton.joints[1].parent, Some(0));
+        assert_eq!(skeleton.joints[1].rest.position, Vector(0., 1., 0.));
+        for matrix in skeleton.joint_matrices(&skeleton.rest_pose()) {
+            assert!((matrix - glm::Mat4::identity()).abs().max() < 1e-5);
+        }
+
+        assert_eq!(model.clips.len(), 1);
+        let clip = &model.clips[0];
+        assert_eq!((clip.name.as_str(), clip.duration), ("wave", 2.));
+        let channel = &clip.channels[0];
+        assert_eq!(channel.target, Target::Joint(1));
+        assert_eq!(channel.interpolation, Interpolation::Linear);
+        match &channel.keyframes {
+            Keyframes::Rotation(rotations) => assert_eq!(rotations.len(), 5),
+            keyframes => panic!("Unexpected keyframes: {:?}", keyframes),
+        }
+
+        // The upper joint bends the top of the strip by 45°.
+        let pose = clip.sample(0.5, &skeleton.rest_pose());
+        let matrices = skeleton.joint_matrices(&pose);
+        let top = matrices[1] * glm::vec4(0.5, 2., 0., 1.);
+        let (sin, cos) = 45f32.to_radians().sin_cos();
+        let expected = glm::vec3(0.5 * cos - sin, 1. + 0.5 * sin + cos, 0.);
+        assert!(glm::distance(&top.xyz(), &expected) < 1e-5);
+    }
+
+    #[test]
+    fn report_errors() {
+        match Model::from_gltf("assets/models/missing.gltf") {
+            Err(ModelError::Gltf(_)) => {}
+            result => panic!("Unexpected result: {:?}", result.map(|_| ())),
+        }
+
+        match Model::from_gltf("assets/models/bad_indices/BadIndices.gltf") {
+            Err(ModelError::IndexOutOfRange { mesh: 0 }) => {}
+            result => panic!("Unexpected result: {:?}", result.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use super::opengl::{self, Fbo, Rbo, TexId};
+use image::{imageops, RgbaImage};
+
+/// Color and depth buffers to render the scene without the window, at
+/// any resolution, then read it back as an image.
+#[derive(Debug)]
+pub struct RenderTarget {
+    pub fbo: Fbo,
+    pub color: TexId,
+    depth: Rbo,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl RenderTarget {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::with_format(width, height, false)
+    }
+
+    /// Float colors, not clamped to one until tone mapped.
+    pub fn hdr(width: u32, height: u32) -> Self {
+        Self::with_format(width, height, true)
+    }
+
+    fn with_format(width: u32, height: u32, is_hdr: bool) -> Self {
+        let (fbo, color, depth) =
+            opengl::gen_color_framebuffer(width as i32, height as i32, is_hdr);
+
+        Self {
+            fbo,
+            color,
+            depth,
+            width,
+            height,
+        }
+    }
+
+    /// Pixels of the color buffer, from the top row like any image.
+    pub fn read_image(&self) -> RgbaImage {
+        opengl::bind_framebuffer(self.fbo);
+        let pixels = opengl::read_pixels(self.width as i32, self.height as i32);
+        opengl::bind_framebuffer(0);
+
+        let image = RgbaImage::from_raw(self.width, self.height, pixels)
+            .expect("Pixels should fill the whole image");
+
+        // OpenGL reads from the bottom row.
+        imageops::flip_vertical(&image)
+    }
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        opengl::delete_framebuffer(self.fbo, self.color);
+        opengl::delete_renderbuffer(self.depth);
+    }
+}
+
+/// Multisampled HDR color and depth, resolved into a render target
+/// of the same size before its colors are sampled.
+#[derive(Debug)]
+pub struct MultisampleTarget {
+    pub fbo: Fbo,
+    color: Rbo,
+    depth: Rbo,
+    pub samples: u16,
+}
+
+impl MultisampleTarget {
+    pub fn hdr(width: u32, height: u32, samples: u16) -> Self {
+        let (fbo, color, depth) = opengl::gen_multisample_framebuffer(
+            width as i32,
+            height as i32,
+            samples,
+        );
+
+        Self {
+            fbo,
+            color,
+            depth,
+            samples,
+        }
+    }
+
+    pub fn resolve(&self, target: &RenderTarget) {
+        opengl::blit_color(
+            self.fbo,
+            target.fbo,
+            target.width as i32,
+            target.height as i32,
+        );
+    }
+}
+
+impl Drop for MultisampleTarget {
+    fn drop(&mut self) {
+        // Without texture, 0 is ignored.
+        opengl::delete_framebuffer(self.fbo, 0);
+        opengl::delete_renderbuffer(self.color);
+        opengl::delete_renderbuffer(self.depth);
+    }
+}
+
+/// Ratio of pixels with a channel differing by more than the
+/// tolerance. Images of different sizes are entirely different.
+#[allow(unused)]
+pub fn image_diff(a: &RgbaImage, b: &RgbaImage, tolerance: u8) -> f32 {
+    if a.dimensions() != b.dimensions() {
+        return 1.;
+    }
+
+    let differing = a
+        .pixels()
+        .zip(b.pixels())
+        .filter(|(a, b)| {
+            a.0.iter().zip(b.0.iter()).any(|(a, b)| {
+                (i16::from(*a) - i16::from(*b)).abs() > tolerance.into()
+            })
+        })
+        .count();
+
+    differing as f32 / (a.width() * a.height()) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba as Pixel;
+
+    #[test]
+    fn diff_images() {
+        let a = RgbaImage::from_pixel(4, 4, Pixel([10, 20, 30, 255]));
+        let mut b = a.clone();
+        b.put_pixel(0, 0, Pixel([14, 20, 30, 255]));
+        b.put_pixel(3, 3, Pixel([10, 20, 90, 255]));
+
+        assert_eq!(image_diff(&a, &a, 0), 0.);
+        assert_eq!(image_diff(&a, &b, 4), 1. / 16.);
+        assert_eq!(image_diff(&a, &b, 0), 2. / 16.);
+        assert_eq!(image_diff(&a, &RgbaImage::new(4, 2), 255), 1.);
+    }
+
+    // Needs a software gl like OSMesa without a gpu, run with
+    // `cargo test --features gl-tests`.
+    #[cfg(feature = "gl-tests")]
+    mod golden {
+        use super::super::image_diff;
+        use crate::entities::Entities;
+        use crate::global::VIEW_MATRIX;
+        use crate::gui::{Button, Rect, Styled, Widget};
+        use crate::platform::HeadlessPlatform;
+        use crate::renderer::{Font, Renderer, Rgba, Text};
+        use crate::tilemap::init_world_and_player;
+        use image::RgbaImage;
+        use nalgebra_glm as glm;
+        use std::{env, fs, path::Path};
+
+        const GOLDEN_DIR: &str = "assets/tests/golden/";
+
+        // Recorded again with `BLESS` set.
+        fn assert_golden(name: &str, image: &RgbaImage) {
+            let path = format!("{}{}.png", GOLDEN_DIR, name);
+            if env::var_os("BLESS").is_some() {
+                fs::create_dir_all(GOLDEN_DIR).unwrap();
+                image.save(&path).unwrap();
+                return;
+            }
+            assert!(
+                Path::new(&path).exists(),
+                "No golden image at {}, record it with BLESS=1",
+                path
+            );
+
+            let golden = image::open(&path).unwrap().to_rgba();
+            let diff = image_diff(&golden, image, 8);
+            assert!(
+                diff < 0.002,
+                "{} differs from its golden image on {:.2}% of the pixels",
+                name,
+                diff * 100.
+            );
+        }
+
+        #[test]
+        fn match_golden_images() {
+            let _platform = HeadlessPlatform::new((320, 240))
+                .expect("No headless gl context");
+
+            let mut entities = Entities::default();
+            let mut font = Font::new(
+                "assets/fonts/Helvetica/helvetica.json",
+                "assets/fonts/Helvetica/helvetica.png",
+            );
+            let (world, player) = init_world_and_player(&mut entities);
+            let mut renderer =
+                Renderer::new(Rgba::new(0.53, 0.81, 0.92, 1.0), &mut entities);
+
+            // Fixed camera behind the player.
+            let eye = player.world_pos + glm::vec3(0., 8., 10.);
+            *VIEW_MATRIX.lock().unwrap() =
+                glm::look_at(&eye, &player.world_pos, &glm::vec3(0., 1., 0.));
+
+            renderer.draw(&mut entities, &world, &player);
+            let mut button = Button::new(Text::new("golden"))
+                .width(120.)
+                .height(40.)
+                .bg_color(Rgba::new(1., 0.5, 0.5, 1.));
+            button.compute_layout(Rect::new(10., 10., 120., 40.), &font);
+            button.draw(renderer.queue_mut(), &font);
+
+            let screenshot =
+                renderer.screenshot(&entities, &mut font, (320, 240));
+            assert_eq!(screenshot.dimensions(), (320, 240));
+            assert_golden("world", &screenshot);
+
+            let tilemap =
+                (player.tilemap_pos.handle.unwrap(), player.tilemap_pos.world);
+            let thumbnail = renderer
+                .tilemap_thumbnail(&entities, &world, &player, tilemap, 128);
+            assert_golden("thumbnail", &thumbnail);
+        }
+    }
+}
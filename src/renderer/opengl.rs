@@ -1,8 +1,10 @@
 use super::mesh::{Vertex, UV};
+use super::queue::Clip;
 use super::shaders::{ShaderProgramId, ShaderType};
-use super::texture::Texture;
+use super::texture::{ColorSpace, PixelFormat, Texture};
 use super::types::Rgba;
 use super::Vector;
+use crate::global::SCREEN_WIDTH;
 use gl;
 use std::{ffi::c_void, mem, ptr, str};
 
@@ -10,6 +12,8 @@ pub type VAO = u32;
 pub type VBO = u32;
 pub type EBO = u32;
 pub type TexId = u32;
+pub type Fbo = u32;
+pub type Rbo = u32;
 
 /// All the data linked to our backend renderer.
 #[derive(Debug, Clone)]
@@ -24,6 +28,11 @@ pub struct GpuBound {
 
 impl Drop for GpuBound {
     fn drop(&mut self) {
+        // Never loaded (see `Mesh::headless`).
+        if self.vao == 0 {
+            return;
+        }
+
         unsafe {
             // Delete VAO.
             gl::DeleteVertexArrays(1, [self.vao].as_ptr());
@@ -65,13 +74,21 @@ pub fn get_opengl_loaded() {
     }
 }
 
-#[allow(dead_code)]
 pub fn set_viewport(width: i32, height: i32) {
     unsafe {
         gl::Viewport(0, 0, width, height);
     }
 }
 
+/// Current viewport as (x, y, width, height).
+pub fn get_viewport() -> [i32; 4] {
+    let mut viewport = [0; 4];
+    unsafe {
+        gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+    }
+    viewport
+}
+
 /// Set multisampling.
 pub fn set_multisampling(enabled: bool) {
     unsafe {
@@ -100,6 +117,310 @@ pub fn clear(color: &Rgba) {
     }
 }
 
+pub fn clear_depth() {
+    unsafe {
+        gl::Clear(gl::DEPTH_BUFFER_BIT);
+    }
+}
+
+/// Framebuffer with only a depth texture attached (no color), used to
+/// render shadow maps. Everything outside of the texture is at the
+/// far plane.
+pub fn gen_depth_framebuffer(size: i32) -> (Fbo, TexId) {
+    unsafe {
+        let mut fbo = 0;
+        gl::GenFramebuffers(1, &mut fbo);
+        let depth = generate_texture();
+
+        gl::BindTexture(gl::TEXTURE_2D, depth);
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::DEPTH_COMPONENT24 as i32,
+            size,
+            size,
+            0,
+            gl::DEPTH_COMPONENT,
+            gl::FLOAT,
+            ptr::null(),
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_MIN_FILTER,
+            gl::NEAREST as i32,
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_MAG_FILTER,
+            gl::NEAREST as i32,
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_WRAP_S,
+            gl::CLAMP_TO_BORDER as i32,
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_WRAP_T,
+            gl::CLAMP_TO_BORDER as i32,
+        );
+        let border = [1f32, 1., 1., 1.];
+        gl::TexParameterfv(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_BORDER_COLOR,
+            border.as_ptr(),
+        );
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+        gl::FramebufferTexture2D(
+            gl::FRAMEBUFFER,
+            gl::DEPTH_ATTACHMENT,
+            gl::TEXTURE_2D,
+            depth,
+            0,
+        );
+        gl::DrawBuffer(gl::NONE);
+        gl::ReadBuffer(gl::NONE);
+
+        if gl::CheckFramebufferStatus(gl::FRAMEBUFFER)
+            != gl::FRAMEBUFFER_COMPLETE
+        {
+            dbg!("Error while creating the depth framebuffer.");
+        }
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+
+        (fbo, depth)
+    }
+}
+
+/// Zero binds back the default framebuffer (the window).
+pub fn bind_framebuffer(fbo: Fbo) {
+    unsafe {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+    }
+}
+
+pub fn delete_framebuffer(fbo: Fbo, tex_id: TexId) {
+    unsafe {
+        gl::DeleteFramebuffers(1, [fbo].as_ptr());
+        gl::DeleteTextures(1, [tex_id].as_ptr());
+    }
+}
+
+/// Framebuffer with a RGBA color texture and a depth renderbuffer,
+/// used to render the scene offscreen.
+pub fn gen_color_framebuffer(
+    width: i32,
+    height: i32,
+    is_hdr: bool,
+) -> (Fbo, TexId, Rbo) {
+    // Half floats keep the colors brighter than white.
+    let (internal_format, data_type) = if is_hdr {
+        (gl::RGBA16F, gl::FLOAT)
+    } else {
+        (gl::RGBA8, gl::UNSIGNED_BYTE)
+    };
+
+    unsafe {
+        let mut fbo = 0;
+        gl::GenFramebuffers(1, &mut fbo);
+        let color = generate_texture();
+
+        gl::BindTexture(gl::TEXTURE_2D, color);
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            internal_format as i32,
+            width,
+            height,
+            0,
+            gl::RGBA,
+            data_type,
+            ptr::null(),
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_MIN_FILTER,
+            gl::LINEAR as i32,
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_MAG_FILTER,
+            gl::LINEAR as i32,
+        );
+        // Post passes sample around the edges.
+        gl::TexParameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_WRAP_S,
+            gl::CLAMP_TO_EDGE as i32,
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_WRAP_T,
+            gl::CLAMP_TO_EDGE as i32,
+        );
+
+        let mut depth = 0;
+        gl::GenRenderbuffers(1, &mut depth);
+        gl::BindRenderbuffer(gl::RENDERBUFFER, depth);
+        gl::RenderbufferStorage(
+            gl::RENDERBUFFER,
+            gl::DEPTH_COMPONENT24,
+            width,
+            height,
+        );
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+        gl::FramebufferTexture2D(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::TEXTURE_2D,
+            color,
+            0,
+        );
+        gl::FramebufferRenderbuffer(
+            gl::FRAMEBUFFER,
+            gl::DEPTH_ATTACHMENT,
+            gl::RENDERBUFFER,
+            depth,
+        );
+
+        if gl::CheckFramebufferStatus(gl::FRAMEBUFFER)
+            != gl::FRAMEBUFFER_COMPLETE
+        {
+            dbg!("Error while creating the color framebuffer.");
+        }
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+
+        (fbo, color, depth)
+    }
+}
+
+/// Framebuffer with multisampled HDR color and depth renderbuffers,
+/// resolved with `blit_color` before its colors can be sampled.
+pub fn gen_multisample_framebuffer(
+    width: i32,
+    height: i32,
+    samples: u16,
+) -> (Fbo, Rbo, Rbo) {
+    let samples = i32::from(samples);
+
+    unsafe {
+        let mut fbo = 0;
+        gl::GenFramebuffers(1, &mut fbo);
+
+        let mut buffers = [0; 2];
+        gl::GenRenderbuffers(2, buffers.as_mut_ptr());
+        let [color, depth] = buffers;
+
+        gl::BindRenderbuffer(gl::RENDERBUFFER, color);
+        gl::RenderbufferStorageMultisample(
+            gl::RENDERBUFFER,
+            samples,
+            gl::RGBA16F,
+            width,
+            height,
+        );
+        gl::BindRenderbuffer(gl::RENDERBUFFER, depth);
+        gl::RenderbufferStorageMultisample(
+            gl::RENDERBUFFER,
+            samples,
+            gl::DEPTH_COMPONENT24,
+            width,
+            height,
+        );
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+        gl::FramebufferRenderbuffer(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::RENDERBUFFER,
+            color,
+        );
+        gl::FramebufferRenderbuffer(
+            gl::FRAMEBUFFER,
+            gl::DEPTH_ATTACHMENT,
+            gl::RENDERBUFFER,
+            depth,
+        );
+
+        if gl::CheckFramebufferStatus(gl::FRAMEBUFFER)
+            != gl::FRAMEBUFFER_COMPLETE
+        {
+            dbg!("Error while creating the multisample framebuffer.");
+        }
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+
+        (fbo, color, depth)
+    }
+}
+
+/// Copy the colors of a framebuffer into another one of the same
+/// size, resolving its samples.
+pub fn blit_color(from: Fbo, to: Fbo, width: i32, height: i32) {
+    unsafe {
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, from);
+        gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, to);
+        gl::BlitFramebuffer(
+            0,
+            0,
+            width,
+            height,
+            0,
+            0,
+            width,
+            height,
+            gl::COLOR_BUFFER_BIT,
+            gl::NEAREST,
+        );
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+    }
+}
+
+pub fn delete_renderbuffer(rbo: Rbo) {
+    unsafe {
+        gl::DeleteRenderbuffers(1, [rbo].as_ptr());
+    }
+}
+
+/// RGBA pixels of the bound framebuffer, from the bottom row.
+pub fn read_pixels(width: i32, height: i32) -> Vec<u8> {
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+
+    unsafe {
+        // Rows are tightly packed.
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            0,
+            0,
+            width,
+            height,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut c_void,
+        );
+    }
+
+    pixels
+}
+
+/// Draw a triangle covering the screen, its corners are computed by
+/// the vertex shader so the vao has no buffer.
+pub fn draw_fullscreen_triangle(vao: VAO) {
+    unsafe {
+        gl::BindVertexArray(vao);
+        gl::DrawArrays(gl::TRIANGLES, 0, 3);
+        gl::BindVertexArray(0);
+    }
+}
+
 pub fn gen_vao() -> VAO {
     unsafe {
         let mut vao = 0;
@@ -158,6 +479,84 @@ pub fn use_vao(vao: VAO) {
     }
 }
 
+// First location of the per instance world matrix, one by column,
+// followed by the texture layer.
+const INSTANCE_LOCATION: u32 = 7;
+const INSTANCE_LAYER_LOCATION: u32 = INSTANCE_LOCATION + 4;
+
+/// Upload per instance data in a new buffer, the world matrix then
+/// the texture layer (u32) have to be the first fields of `T`.
+pub fn load_instances_to_gpu<T>(instances: &[T]) -> VBO {
+    let vbo = gen_buffer();
+
+    unsafe {
+        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+        gl::BufferData(
+            gl::ARRAY_BUFFER,
+            mem::size_of_val(instances) as isize,
+            instances.as_ptr() as *const _,
+            gl::STATIC_DRAW,
+        );
+        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+    }
+
+    vbo
+}
+
+/// Feed the instance matrices and layers of the bound vao from a
+/// buffer, starting at the `first` instance.
+pub fn bind_instances<T>(vbo: VBO, first: usize) {
+    let stride = mem::size_of::<T>();
+
+    unsafe {
+        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+        for column in 0..4 {
+            let location = INSTANCE_LOCATION + column;
+            let offset =
+                first * stride + column as usize * 4 * mem::size_of::<f32>();
+
+            gl::VertexAttribPointer(
+                location,
+                4,
+                gl::FLOAT,
+                gl::FALSE,
+                stride as i32,
+                offset as *const _,
+            );
+            gl::VertexAttribDivisor(location, 1);
+            gl::EnableVertexAttribArray(location);
+        }
+
+        let offset = first * stride + 16 * mem::size_of::<f32>();
+        gl::VertexAttribIPointer(
+            INSTANCE_LAYER_LOCATION,
+            1,
+            gl::UNSIGNED_INT,
+            stride as i32,
+            offset as *const _,
+        );
+        gl::VertexAttribDivisor(INSTANCE_LAYER_LOCATION, 1);
+        gl::EnableVertexAttribArray(INSTANCE_LAYER_LOCATION);
+        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+    }
+}
+
+/// The vao is shared with non instanced draws.
+pub fn unbind_instances() {
+    unsafe {
+        for column in 0..4 {
+            gl::DisableVertexAttribArray(INSTANCE_LOCATION + column);
+        }
+        gl::DisableVertexAttribArray(INSTANCE_LAYER_LOCATION);
+    }
+}
+
+pub fn delete_buffer(vbo: VBO) {
+    unsafe {
+        gl::DeleteBuffers(1, [vbo].as_ptr());
+    }
+}
+
 /// This create an vertex buffer object and load data.
 pub fn load_bytes_to_gpu(vao: VAO, vertex: &Vertex) -> (VBO, Option<EBO>) {
     let with_ebo = !vertex.indices.is_empty();
@@ -169,6 +568,10 @@ pub fn load_bytes_to_gpu(vao: VAO, vertex: &Vertex) -> (VBO, Option<EBO>) {
         total_size += set.coords.len() * mem::size_of::<UV>();
     });
 
+    total_size += vertex.joints.len() * mem::size_of::<[u16; 4]>()
+        + vertex.weights.len() * mem::size_of::<[f32; 4]>()
+        + vertex.normals.len() * mem::size_of::<Vector>();
+
     unsafe {
         use_vao(vao);
         let vbo = gen_buffer();
@@ -215,6 +618,33 @@ pub fn load_bytes_to_gpu(vao: VAO, vertex: &Vertex) -> (VBO, Option<EBO>) {
             data_cursor += (uv.coords.len() * mem::size_of::<UV>()) as isize;
         });
 
+        // Skinning data.
+        gl::BufferSubData(
+            gl::ARRAY_BUFFER,
+            data_cursor,
+            (vertex.joints.len() * mem::size_of::<[u16; 4]>()) as isize,
+            vertex.joints.as_ptr() as *const _,
+        );
+        data_cursor +=
+            (vertex.joints.len() * mem::size_of::<[u16; 4]>()) as isize;
+
+        gl::BufferSubData(
+            gl::ARRAY_BUFFER,
+            data_cursor,
+            (vertex.weights.len() * mem::size_of::<[f32; 4]>()) as isize,
+            vertex.weights.as_ptr() as *const _,
+        );
+        data_cursor +=
+            (vertex.weights.len() * mem::size_of::<[f32; 4]>()) as isize;
+
+        // Normals.
+        gl::BufferSubData(
+            gl::ARRAY_BUFFER,
+            data_cursor,
+            (vertex.normals.len() * mem::size_of::<Vector>()) as isize,
+            vertex.normals.as_ptr() as *const _,
+        );
+
         // Create EBO if indices is not empty.
         if let Some(ebo) = ebo {
             let indices = &vertex.indices;
@@ -248,105 +678,332 @@ pub fn bind_texture(tex_id: TexId, texture_number: usize) {
     }
 }
 
-pub unsafe fn load_tex_to_gpu(vao: VAO, tex: &Texture, is_font: bool) -> TexId {
-    let dim = &tex.dim;
-    let data = &tex.raw;
+/// Only draw inside the clip, given in screen pixels like the ui.
+pub fn set_scissor(clip: Option<Clip>) {
+    let clip = match clip {
+        Some(clip) => clip,
+        None => {
+            unsafe { gl::Disable(gl::SCISSOR_TEST) };
+            return;
+        }
+    };
 
-    use_vao(vao);
+    unsafe {
+        // The viewport can be larger, like on hidpi screens and in
+        // screenshots.
+        let mut viewport = [0; 4];
+        gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        let scale = viewport[2] as f32 / SCREEN_WIDTH;
+
+        gl::Enable(gl::SCISSOR_TEST);
+        gl::Scissor(
+            (clip.x * scale).floor() as i32,
+            (clip.y * scale).floor() as i32,
+            (clip.width * scale).ceil() as i32,
+            (clip.height * scale).ceil() as i32,
+        );
+    }
+}
+
+pub fn bind_texture_array(tex_id: TexId, texture_number: usize) {
+    unsafe {
+        gl::ActiveTexture(gl::TEXTURE0 + texture_number as u32);
+        gl::BindTexture(gl::TEXTURE_2D_ARRAY, tex_id);
+    }
+}
+
+pub fn bind_texture_3d(tex_id: TexId, texture_number: usize) {
+    unsafe {
+        gl::ActiveTexture(gl::TEXTURE0 + texture_number as u32);
+        gl::BindTexture(gl::TEXTURE_3D, tex_id);
+    }
+}
+
+/// Color lookup table as a cube of `size` RGB texels on each side,
+/// red varying the fastest.
+pub fn load_lut_to_gpu(volume: &[u8], size: i32) -> TexId {
     let tex_id = generate_texture();
 
-    let color_format = gl::RGBA;
+    unsafe {
+        gl::BindTexture(gl::TEXTURE_3D, tex_id);
+        for wrap in
+            &[gl::TEXTURE_WRAP_S, gl::TEXTURE_WRAP_T, gl::TEXTURE_WRAP_R]
+        {
+            gl::TexParameteri(gl::TEXTURE_3D, *wrap, gl::CLAMP_TO_EDGE as i32);
+        }
+        gl::TexParameteri(
+            gl::TEXTURE_3D,
+            gl::TEXTURE_MIN_FILTER,
+            gl::LINEAR as i32,
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_3D,
+            gl::TEXTURE_MAG_FILTER,
+            gl::LINEAR as i32,
+        );
 
-    let clamp = if is_font {
         gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
-        gl::CLAMP_TO_EDGE
-    } else {
-        gl::REPEAT
-    } as i32;
+        gl::TexImage3D(
+            gl::TEXTURE_3D,
+            0,
+            gl::RGB8 as i32,
+            size,
+            size,
+            size,
+            0,
+            gl::RGB,
+            gl::UNSIGNED_BYTE,
+            volume.as_ptr() as *const c_void,
+        );
+        gl::BindTexture(gl::TEXTURE_3D, 0);
+    }
+
+    tex_id
+}
+
+/// Texture uploaded the first time it's used, and deleted with it.
+#[derive(Debug)]
+pub struct GpuTexture {
+    pub texture: Texture,
+    tex_id: Option<TexId>,
+}
+
+impl GpuTexture {
+    pub fn new(texture: Texture) -> Self {
+        Self {
+            texture,
+            tex_id: None,
+        }
+    }
+
+    pub fn tex_id(&mut self) -> TexId {
+        let texture = &self.texture;
+        *self
+            .tex_id
+            .get_or_insert_with(|| unsafe { load_tex_to_gpu(texture) })
+    }
+}
+
+impl Drop for GpuTexture {
+    fn drop(&mut self) {
+        if let Some(tex_id) = self.tex_id {
+            unsafe {
+                gl::DeleteTextures(1, &tex_id);
+            }
+        }
+    }
+}
+
+// Anisotropic filtering, from an extension supported nearly
+// everywhere (core since 4.6).
+const TEXTURE_MAX_ANISOTROPY: u32 = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY: u32 = 0x84FF;
 
-    gl::BindTexture(gl::TEXTURE_2D, tex_id);
+/// Upload a texture with its sampler, a texture array is bound to
+/// `TEXTURE_2D_ARRAY` instead of `TEXTURE_2D`.
+pub unsafe fn load_tex_to_gpu(tex: &Texture) -> TexId {
+    let (width, height) = tex.dim;
+    let sampler = &tex.sampler;
+    let target = texture_target(tex);
 
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, clamp);
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, clamp);
+    let tex_id = generate_texture();
+    gl::BindTexture(target, tex_id);
+
+    gl::TexParameteri(target, gl::TEXTURE_WRAP_S, sampler.wrap_s as i32);
+    gl::TexParameteri(target, gl::TEXTURE_WRAP_T, sampler.wrap_t as i32);
     gl::TexParameteri(
-        gl::TEXTURE_2D,
+        target,
         gl::TEXTURE_MIN_FILTER,
-        gl::LINEAR as i32,
+        sampler.min_filter as i32,
     );
     gl::TexParameteri(
-        gl::TEXTURE_2D,
+        target,
         gl::TEXTURE_MAG_FILTER,
-        gl::LINEAR as i32,
+        sampler.mag_filter as i32,
     );
 
-    gl::TexImage2D(
-        gl::TEXTURE_2D,
-        0,
-        color_format as i32,
-        dim.0 as i32,
-        dim.1 as i32,
-        0,
-        color_format,
-        gl::UNSIGNED_BYTE,
-        data.as_ptr() as *const c_void,
-    );
+    if sampler.anisotropy > 1. {
+        let mut max_anisotropy = 0.;
+        gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut max_anisotropy);
+        // Zero when the extension is missing.
+        if max_anisotropy >= 1. {
+            gl::TexParameterf(
+                target,
+                TEXTURE_MAX_ANISOTROPY,
+                sampler.anisotropy.min(max_anisotropy),
+            );
+        }
+    }
+
+    let (internal_format, format) = texture_formats(tex);
 
-    if !is_font {
-        gl::GenerateMipmap(gl::TEXTURE_2D);
+    // Rows of rgb or grayscale pixels aren't aligned on 4 bytes.
+    gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+    match tex.layers {
+        Some(layers) => gl::TexImage3D(
+            target,
+            0,
+            internal_format as i32,
+            width as i32,
+            height as i32,
+            layers as i32,
+            0,
+            format,
+            gl::UNSIGNED_BYTE,
+            tex.raw.as_ptr() as *const c_void,
+        ),
+        None => gl::TexImage2D(
+            target,
+            0,
+            internal_format as i32,
+            width as i32,
+            height as i32,
+            0,
+            format,
+            gl::UNSIGNED_BYTE,
+            tex.raw.as_ptr() as *const c_void,
+        ),
     }
 
+    // Grayscale is sampled as a gray color, not as red.
+    if tex.format == PixelFormat::R8 {
+        let swizzle = [gl::RED, gl::RED, gl::RED, gl::ONE];
+        gl::TexParameteriv(
+            target,
+            gl::TEXTURE_SWIZZLE_RGBA,
+            swizzle.as_ptr() as *const i32,
+        );
+    }
+
+    if sampler.has_mipmaps() {
+        gl::GenerateMipmap(target);
+    }
+    gl::BindTexture(target, 0);
+
     tex_id
 }
 
-pub fn load_font_to_gpu(
-    vertices: &Vec<f32>,
-    texture_atlas: &Texture,
-) -> (VAO, VBO, TexId) {
+fn texture_target(tex: &Texture) -> u32 {
+    if tex.layers.is_some() {
+        gl::TEXTURE_2D_ARRAY
+    } else {
+        gl::TEXTURE_2D
+    }
+}
+
+// Sized internal format and format of the pixels. Srgb colors are
+// decoded to linear by the gpu when sampled.
+fn texture_formats(tex: &Texture) -> (u32, u32) {
+    let is_srgb = tex.color_space == ColorSpace::Srgb;
+
+    match tex.format {
+        PixelFormat::R8 => (gl::R8, gl::RED),
+        PixelFormat::Rgb8 if is_srgb => (gl::SRGB8, gl::RGB),
+        PixelFormat::Rgb8 => (gl::RGB8, gl::RGB),
+        PixelFormat::Rgba8 if is_srgb => (gl::SRGB8_ALPHA8, gl::RGBA),
+        PixelFormat::Rgba8 => (gl::RGBA8, gl::RGBA),
+    }
+}
+
+// Position and atlas texels, the color, then the extra weight of the
+// glyph and 1 for icons.
+pub const TEXT_VERTEX_LEN: usize = 9;
+
+/// Vao of the text batches, its buffer is filled each frame by
+/// `stream_text_vertices`.
+pub fn gen_text_buffer() -> (VAO, VBO) {
     let vao = gen_vao();
     use_vao(vao);
 
     unsafe {
-        let tex_id = load_tex_to_gpu(vao, texture_atlas, true);
         let vbo = gen_buffer();
-
         gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
 
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            (vertices.len() * mem::size_of::<f32>()) as _,
-            vertices.as_ptr() as *const _,
-            gl::DYNAMIC_DRAW,
-        );
-
+        let stride = (TEXT_VERTEX_LEN * mem::size_of::<f32>()) as i32;
         gl::VertexAttribPointer(
             0,
             4,
             gl::FLOAT,
             gl::FALSE,
-            (4 * mem::size_of::<f32>()) as _,
+            stride,
             ptr::null(),
         );
-
         gl::EnableVertexAttribArray(0);
+        gl::VertexAttribPointer(
+            1,
+            3,
+            gl::FLOAT,
+            gl::FALSE,
+            stride,
+            (4 * mem::size_of::<f32>()) as *const c_void,
+        );
+        gl::EnableVertexAttribArray(1);
+        gl::VertexAttribPointer(
+            2,
+            2,
+            gl::FLOAT,
+            gl::FALSE,
+            stride,
+            (7 * mem::size_of::<f32>()) as *const c_void,
+        );
+        gl::EnableVertexAttribArray(2);
+
+        (vao, vbo)
+    }
+}
+
+/// Replace the vertices of the buffer, which is grown when they don't
+/// fit. Return its new capacity, in floats.
+pub fn stream_text_vertices(
+    vbo: VBO,
+    vertices: &[f32],
+    capacity: usize,
+) -> usize {
+    let size = |len: usize| (len * mem::size_of::<f32>()) as isize;
+
+    unsafe {
+        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+
+        if vertices.len() > capacity {
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                size(vertices.len()),
+                vertices.as_ptr() as *const _,
+                gl::STREAM_DRAW,
+            );
+            return vertices.len();
+        }
+
+        // Orphaned, the draws of the previous frame can still read it.
+        gl::BufferData(
+            gl::ARRAY_BUFFER,
+            size(capacity),
+            ptr::null(),
+            gl::STREAM_DRAW,
+        );
+        gl::BufferSubData(
+            gl::ARRAY_BUFFER,
+            0,
+            size(vertices.len()),
+            vertices.as_ptr() as *const _,
+        );
+    }
+
+    capacity
+}
 
-        (vao, vbo, tex_id)
+pub fn delete_vao(vao: VAO) {
+    unsafe {
+        gl::DeleteVertexArrays(1, [vao].as_ptr());
     }
 }
 
 /// Use a given vao then load data to the gpu.
-pub fn load_object_to_gpu(
-    vertex: &Vertex,
-    textures: &Vec<Texture>,
-) -> (VAO, VBO, Option<EBO>, Vec<TexId>) {
+pub fn load_object_to_gpu(vertex: &Vertex) -> (VAO, VBO, Option<EBO>) {
     unsafe {
         let vao = gen_vao();
         let (vbo, ebo) = load_bytes_to_gpu(vao, vertex);
 
-        let tex_ids = textures
-            .iter()
-            .map(|tex| load_tex_to_gpu(vao, &tex, false))
-            .collect();
-
         use_vao(vao);
 
         let mut location = 0;
@@ -396,7 +1053,44 @@ pub fn load_object_to_gpu(
             data_cursor += set.coords.len() * mem::size_of::<UV>();
         }
 
-        (vao, vbo, ebo, tex_ids)
+        // Joints and weights, after the two uv sets of the shader.
+        if !vertex.joints.is_empty() {
+            gl::VertexAttribIPointer(
+                4,
+                4,
+                gl::UNSIGNED_SHORT,
+                mem::size_of::<[u16; 4]>() as i32,
+                data_cursor as *const _,
+            );
+            gl::EnableVertexAttribArray(4);
+            data_cursor += vertex.joints.len() * mem::size_of::<[u16; 4]>();
+
+            gl::VertexAttribPointer(
+                5,
+                4,
+                gl::FLOAT,
+                gl::FALSE,
+                mem::size_of::<[f32; 4]>() as i32,
+                data_cursor as *const _,
+            );
+            gl::EnableVertexAttribArray(5);
+        }
+        data_cursor += vertex.weights.len() * mem::size_of::<[f32; 4]>();
+
+        // Normals.
+        if !vertex.normals.is_empty() {
+            gl::VertexAttribPointer(
+                6,
+                3,
+                gl::FLOAT,
+                gl::FALSE,
+                mem::size_of::<Vector>() as i32,
+                data_cursor as *const _,
+            );
+            gl::EnableVertexAttribArray(6);
+        }
+
+        (vao, vbo, ebo)
     }
 }
 
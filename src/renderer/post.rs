@@ -0,0 +1,371 @@
+use super::offscreen::{MultisampleTarget, RenderTarget};
+use super::opengl::{self, Fbo, TexId, VAO};
+use super::shaders::{self, Permutation, ShaderType};
+use crate::global::*;
+use image::RgbImage;
+
+// Warm grading applied by the color grading pass, 16 squares of
+// 16x16 texels side by side (blue increasing with the square).
+const LUT_PATH: &str = "assets/textures/lut_warm.png";
+const IDENTITY_LUT_SIZE: u32 = 16;
+// Each pass blurs horizontally then vertically.
+const BLUR_PASSES: usize = 4;
+
+/// Presets of the costliest passes, in the order of the settings
+/// menu.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Quality {
+    Low,
+    Medium,
+    High,
+}
+
+impl Quality {
+    pub const ALL: [Quality; 3] =
+        [Quality::Low, Quality::Medium, Quality::High];
+}
+
+/// Post passes applied on the HDR scene, in this order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostSettings {
+    // The scene is drawn with `MULTISAMPLING` samples, then resolved.
+    pub msaa: bool,
+    pub bloom: bool,
+    // Brightness from which colors bleed.
+    pub bloom_threshold: f32,
+    pub bloom_intensity: f32,
+    pub tone_mapping: bool,
+    pub exposure: f32,
+    pub color_grading: bool,
+    pub gamma: bool,
+    pub vignette: bool,
+    pub vignette_strength: f32,
+    pub fxaa: bool,
+}
+
+impl Default for PostSettings {
+    fn default() -> Self {
+        Self {
+            msaa: true,
+            bloom: true,
+            bloom_threshold: 1.,
+            bloom_intensity: 0.3,
+            tone_mapping: true,
+            exposure: 1.,
+            color_grading: false,
+            gamma: true,
+            vignette: false,
+            vignette_strength: 0.4,
+            fxaa: true,
+        }
+    }
+}
+
+impl PostSettings {
+    /// Name and switch of each pass, toggled from the editor and the
+    /// debug gui.
+    pub fn passes_mut(&mut self) -> [(&'static str, &mut bool); 6] {
+        [
+            ("bloom", &mut self.bloom),
+            ("tone mapping", &mut self.tone_mapping),
+            ("color grading", &mut self.color_grading),
+            ("gamma", &mut self.gamma),
+            ("vignette", &mut self.vignette),
+            ("fxaa", &mut self.fxaa),
+        ]
+    }
+
+    /// Other passes are left as they are.
+    pub fn set_quality(&mut self, quality: Quality) {
+        let (msaa, bloom) = match quality {
+            Quality::Low => (false, false),
+            Quality::Medium => (false, true),
+            Quality::High => (true, true),
+        };
+        self.msaa = msaa;
+        self.bloom = bloom;
+    }
+
+    /// Samples of the scene, 0 without multisampling.
+    pub fn samples(&self) -> u16 {
+        if self.msaa {
+            MULTISAMPLING
+        } else {
+            0
+        }
+    }
+
+    /// Steps of the composite pass, compiled in its shader.
+    pub fn permutation(&self) -> Permutation {
+        Permutation::default()
+            .define("HAS_BLOOM", self.bloom)
+            .define("HAS_TONE_MAPPING", self.tone_mapping)
+            .define("HAS_COLOR_GRADING", self.color_grading)
+            .define("HAS_GAMMA", self.gamma)
+            .define("HAS_VIGNETTE", self.vignette)
+    }
+}
+
+/// Intermediate buffers of the post passes, sized like the output.
+pub struct PostTargets {
+    // The scene is drawn here instead of the output.
+    pub scene: RenderTarget,
+    // Or here first, when multisampled.
+    multisample: Option<MultisampleTarget>,
+    // Ping-pong blur at half the size.
+    bloom: [RenderTarget; 2],
+    // Composited colors, anti-aliased into the output.
+    ldr: RenderTarget,
+}
+
+impl PostTargets {
+    pub fn new((width, height): (u32, u32), samples: u16) -> Self {
+        let (half_width, half_height) =
+            ((width / 2).max(1), (height / 2).max(1));
+
+        Self {
+            scene: RenderTarget::hdr(width, height),
+            multisample: if samples > 0 {
+                Some(MultisampleTarget::hdr(width, height, samples))
+            } else {
+                None
+            },
+            bloom: [
+                RenderTarget::hdr(half_width, half_height),
+                RenderTarget::hdr(half_width, half_height),
+            ],
+            ldr: RenderTarget::new(width, height),
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.scene.width, self.scene.height)
+    }
+
+    pub fn samples(&self) -> u16 {
+        self.multisample.as_ref().map_or(0, |target| target.samples)
+    }
+
+    /// Framebuffer the scene is drawn into.
+    pub fn scene_fbo(&self) -> Fbo {
+        self.multisample
+            .as_ref()
+            .map_or(self.scene.fbo, |target| target.fbo)
+    }
+
+    /// Make the drawn scene ready to be sampled by the passes.
+    pub fn resolve(&self) {
+        if let Some(target) = &self.multisample {
+            target.resolve(&self.scene);
+        }
+    }
+}
+
+pub struct PostProcess {
+    pub settings: PostSettings,
+    // Empty, the screen triangle has no vertex buffer.
+    vao: VAO,
+    lut: TexId,
+    lut_size: u32,
+}
+
+impl PostProcess {
+    pub fn new() -> Self {
+        let (volume, lut_size) = match image::open(LUT_PATH) {
+            Ok(image) => strip_to_volume(&image.to_rgb()),
+            Err(e) => {
+                dbg!(e);
+                None
+            }
+        }
+        .unwrap_or_else(|| {
+            (identity_volume(IDENTITY_LUT_SIZE), IDENTITY_LUT_SIZE)
+        });
+
+        Self {
+            settings: PostSettings::default(),
+            vao: opengl::gen_vao(),
+            lut: opengl::load_lut_to_gpu(&volume, lut_size as i32),
+            lut_size,
+        }
+    }
+
+    /// Draw the scene of the targets into the output framebuffer,
+    /// through the enabled passes.
+    pub fn apply(&self, targets: &PostTargets, output: Fbo) {
+        let settings = &self.settings;
+        let (width, height) = targets.size();
+
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::BLEND);
+        }
+
+        if settings.bloom {
+            self.bloom(targets);
+        }
+
+        // Anti-aliased after, on displayable colors.
+        let composite_output = if settings.fxaa {
+            targets.ldr.fbo
+        } else {
+            output
+        };
+        opengl::bind_framebuffer(composite_output);
+        opengl::set_viewport(width as i32, height as i32);
+
+        let prog_id = SHADERS.activate_permutation(
+            ShaderType::PostCompositeShader,
+            &settings.permutation(),
+        );
+        shaders::set_i32(prog_id, "scene", 0);
+        shaders::set_i32(prog_id, "bloom", 1);
+        shaders::set_i32(prog_id, "lut", 2);
+        opengl::bind_texture(targets.scene.color, 0);
+        opengl::bind_texture(targets.bloom[0].color, 1);
+        opengl::bind_texture_3d(self.lut, 2);
+
+        shaders::set_f32(prog_id, "exposure", settings.exposure);
+        shaders::set_f32(prog_id, "bloom_intensity", settings.bloom_intensity);
+        shaders::set_f32(
+            prog_id,
+            "vignette_strength",
+            settings.vignette_strength,
+        );
+        shaders::set_f32(prog_id, "lut_size", self.lut_size as f32);
+        opengl::draw_fullscreen_triangle(self.vao);
+
+        if settings.fxaa {
+            opengl::bind_framebuffer(output);
+            let prog_id = SHADERS.activate(ShaderType::PostFxaaShader);
+            shaders::set_i32(prog_id, "image", 0);
+            shaders::set_vec2(
+                prog_id,
+                "texel",
+                &[1. / width as f32, 1. / height as f32],
+            );
+            opengl::bind_texture(targets.ldr.color, 0);
+            opengl::draw_fullscreen_triangle(self.vao);
+        }
+
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+        }
+    }
+
+    // Bright parts of the scene, blurred into the first bloom target.
+    fn bloom(&self, targets: &PostTargets) {
+        let [first, second] = &targets.bloom;
+        opengl::set_viewport(first.width as i32, first.height as i32);
+
+        opengl::bind_framebuffer(first.fbo);
+        let prog_id = SHADERS.activate(ShaderType::PostBrightShader);
+        shaders::set_i32(prog_id, "scene", 0);
+        shaders::set_f32(prog_id, "threshold", self.settings.bloom_threshold);
+        opengl::bind_texture(targets.scene.color, 0);
+        opengl::draw_fullscreen_triangle(self.vao);
+
+        let prog_id = SHADERS.activate(ShaderType::PostBlurShader);
+        shaders::set_i32(prog_id, "image", 0);
+        let texel = [1. / first.width as f32, 1. / first.height as f32];
+
+        for _ in 0..BLUR_PASSES {
+            for (source, destination, direction) in &[
+                (first, second, [texel[0], 0.]),
+                (second, first, [0., texel[1]]),
+            ] {
+                opengl::bind_framebuffer(destination.fbo);
+                shaders::set_vec2(prog_id, "direction", direction);
+                opengl::bind_texture(source.color, 0);
+                opengl::draw_fullscreen_triangle(self.vao);
+            }
+        }
+    }
+}
+
+impl Drop for PostProcess {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, [self.vao].as_ptr());
+            gl::DeleteTextures(1, [self.lut].as_ptr());
+        }
+    }
+}
+
+/// Lookup table stored as a strip of squares, one per blue level, into
+/// a volume for `load_lut_to_gpu`. None if the image isn't a strip.
+fn strip_to_volume(strip: &RgbImage) -> Option<(Vec<u8>, u32)> {
+    let size = strip.height();
+    if size == 0 || strip.width() != size * size {
+        dbg!("The color lookup table should be N squares of NxN.");
+        return None;
+    }
+
+    let mut volume = Vec::with_capacity((size * size * size * 3) as usize);
+    for b in 0..size {
+        for g in 0..size {
+            for r in 0..size {
+                volume.extend_from_slice(&strip.get_pixel(b * size + r, g).0);
+            }
+        }
+    }
+
+    Some((volume, size))
+}
+
+// Lookup table leaving the colors unchanged.
+fn identity_volume(size: u32) -> Vec<u8> {
+    let level = |i: u32| (i * 255 / (size - 1)) as u8;
+    let mut volume = Vec::with_capacity((size * size * size * 3) as usize);
+    for b in 0..size {
+        for g in 0..size {
+            for r in 0..size {
+                volume.extend_from_slice(&[level(r), level(g), level(b)]);
+            }
+        }
+    }
+
+    volume
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    #[test]
+    fn lut_strip_to_volume() {
+        let size = 4;
+        let level = |i: u32| (i * 255 / (size - 1)) as u8;
+        let strip = RgbImage::from_fn(size * size, size, |x, y| {
+            Rgb([level(x % size), level(y), level(x / size)])
+        });
+
+        let (volume, lut_size) = strip_to_volume(&strip).unwrap();
+        assert_eq!(lut_size, size);
+        assert_eq!(volume, identity_volume(size));
+
+        // Red first, then green, then blue.
+        let texel = |r: u32, g: u32, b: u32| {
+            let i = ((b * size * size + g * size + r) * 3) as usize;
+            &volume[i..i + 3]
+        };
+        assert_eq!(texel(3, 0, 0), &[255, 0, 0]);
+        assert_eq!(texel(0, 1, 2), &[0, 85, 170]);
+
+        assert!(strip_to_volume(&RgbImage::new(4, 4)).is_none());
+    }
+
+    #[test]
+    fn multisample_scene() {
+        let mut settings = PostSettings::default();
+        assert_eq!(settings.samples(), MULTISAMPLING);
+        settings.msaa = false;
+        assert_eq!(settings.samples(), 0);
+
+        settings.set_quality(Quality::Medium);
+        assert!(!settings.msaa && settings.bloom);
+        settings.set_quality(Quality::High);
+        assert_eq!(settings, PostSettings::default());
+    }
+}
@@ -1,6 +1,6 @@
+use super::material::Material;
 use super::mesh::{UVSet, Vertex};
 use super::shaders::ShaderType;
-use super::texture::Texture;
 use super::types::Rgba;
 use super::SunLight;
 use super::Vector;
@@ -26,7 +26,6 @@ pub fn create_quad(transform: Transform) -> Mesh {
 
     Mesh::new(
         vertex,
-        vec![],
         transform,
         None,
         Some(Collider::Cube),
@@ -35,152 +34,84 @@ pub fn create_quad(transform: Transform) -> Mesh {
     )
 }
 
-pub fn create_cube<'n, 't>(
-    texture_path: &'t str,
+pub fn create_cube(
     transform: Transform,
     parent: Option<Handle<Mesh>>,
-    color: Rgba,
+    material: Option<Handle<Material>>,
 ) -> Mesh {
-    let mut textures = vec![];
-
-    // Unit size of 1.
-    let mut vertex = Vertex {
-        primitives: vec![
-            Vector(0., 0., 0.),
-            Vector(1., 0., 0.),
-            Vector(1., 1., 0.),
-            Vector(0., 1., 0.),
-            Vector(0., 1., 1.),
-            Vector(1., 1., 1.),
-            Vector(1., 0., 1.),
-            Vector(0., 0., 1.),
-        ],
-        indices: vec![
-            0, 2, 1, 0, 3, 2, // Front.
-            2, 3, 4, 2, 4, 5, // Top.
-            1, 2, 5, 1, 5, 6, // Right.
-            0, 7, 4, 0, 4, 3, // Left.
-            5, 4, 7, 5, 7, 6, // Back.
-            0, 6, 7, 0, 1, 6, // Bottom.
-        ],
-        colors: vec![color, color, color, color, color, color, color, color],
-        ..Default::default()
-    };
-
-    if !texture_path.is_empty() {
-        vertex.colors = vec![];
-        vertex.uv_coords.push(UVSet::new(
-            0,
-            vec![
-                glm::vec2(0.0, 0.0),
-                glm::vec2(0.0, 1.0),
-                glm::vec2(1.0, 1.0),
-                glm::vec2(1.0, 0.0),
-
-                glm::vec2(0.0, 0.0),
-                glm::vec2(0.0, 1.0),
-                glm::vec2(1.0, 1.0),
-                glm::vec2(1.0, 0.0),
+    // Unit size of 1. Each face has its own four corners (counter
+    // clockwise seen from outside), so it gets its own normal and uvs.
+    #[rustfmt::skip]
+    let faces = [
+        // Front.
+        (Vector(0., 0., -1.), [(1., 0., 0.), (0., 0., 0.), (0., 1., 0.), (1., 1., 0.)]),
+        // Back.
+        (Vector(0., 0., 1.), [(0., 0., 1.), (1., 0., 1.), (1., 1., 1.), (0., 1., 1.)]),
+        // Left.
+        (Vector(-1., 0., 0.), [(0., 0., 0.), (0., 0., 1.), (0., 1., 1.), (0., 1., 0.)]),
+        // Right.
+        (Vector(1., 0., 0.), [(1., 0., 1.), (1., 0., 0.), (1., 1., 0.), (1., 1., 1.)]),
+        // Top.
+        (Vector(0., 1., 0.), [(0., 1., 1.), (1., 1., 1.), (1., 1., 0.), (0., 1., 0.)]),
+        // Bottom.
+        (Vector(0., -1., 0.), [(0., 0., 0.), (1., 0., 0.), (1., 0., 1.), (0., 0., 1.)]),
+    ];
+
+    let mut vertex = Vertex::default();
+    let mut uv_coords = vec![];
 
-                glm::vec2(1.0, 1.0),
-                glm::vec2(1.0, 0.0),
-                glm::vec2(0.0, 0.0),
-                glm::vec2(0.0, 1.0),
+    for (normal, corners) in faces.iter() {
+        let first = vertex.primitives.len() as u32;
+        vertex.indices.extend_from_slice(&[
+            first,
+            first + 1,
+            first + 2,
+            first,
+            first + 2,
+            first + 3,
+        ]);
 
-                glm::vec2(1.0, 1.0),
-                glm::vec2(1.0, 0.0),
-                glm::vec2(0.0, 0.0),
-                glm::vec2(0.0, 1.0),
+        for (x, y, z) in corners.iter() {
+            vertex.primitives.push(Vector(*x, *y, *z));
+            vertex.normals.push(*normal);
+        }
 
-                glm::vec2(0.0, 0.0),
-                glm::vec2(0.0, 1.0),
-                glm::vec2(1.0, 1.0),
-                glm::vec2(1.0, 0.0),
+        uv_coords.extend_from_slice(&[
+            glm::vec2(0.0, 0.0),
+            glm::vec2(1.0, 0.0),
+            glm::vec2(1.0, 1.0),
+            glm::vec2(0.0, 1.0),
+        ]);
+    }
 
-                glm::vec2(1.0, 1.0),
-                glm::vec2(1.0, 0.0),
-                glm::vec2(0.0, 0.0),
-                glm::vec2(0.0, 1.0),
-            ],
-        ));
-
-        textures.push(Texture::from_file(texture_path));
-    };
+    vertex.uv_coords.push(UVSet::new(0, uv_coords));
 
     Mesh::new(
         vertex,
-        textures,
         transform,
         parent,
         Some(Collider::Cube),
         DrawMode::Triangles,
         ShaderType::SimpleShader,
     )
+    .material(material)
 }
 
 /// Create a renderable triangle object, ready
 /// to be consumed by our renderer.
-pub fn create_plane<'t>(texture_path: &'t str, transform: Transform) -> Mesh {
-    let mut textures = vec![];
-    let mut uv_coords = vec![];
-
-    if !texture_path.is_empty() {
-        uv_coords.push(UVSet::new(
-            0,
-            vec![
-                glm::vec2(0.0, 0.0),
-                glm::vec2(1.0, 0.0),
-                glm::vec2(1.0, 1.0),
-                glm::vec2(0.0, 1.0),
-            ],
-        ));
-        textures.push(Texture::from_file(texture_path));
-    };
-
-    let vertex = Vertex {
-        primitives: vec![
-            Vector(-1., 0., -1.),
-            Vector(-1., 0., 1.),
-            Vector(1., 0., 1.),
-            Vector(1., 0., -1.),
-        ],
-        uv_coords,
-        indices: vec![0, 1, 2, 0, 2, 3],
-        ..Default::default()
-    };
-
-    Mesh::new(
-        vertex,
-        textures,
-        transform,
-        None,
-        Some(Collider::Cube),
-        DrawMode::Triangles,
-        ShaderType::SimpleShader,
-    )
-}
-
-pub fn create_tiles<'t>(
-    texture_path: &'t str,
+pub fn create_plane(
     transform: Transform,
-    color: Rgba,
+    material: Option<Handle<Material>>,
 ) -> Mesh {
-    let mut textures = vec![];
-
-    let mut vertex = Vertex {
+    let vertex = Vertex {
         primitives: vec![
             Vector(-1., 0., -1.),
             Vector(-1., 0., 1.),
             Vector(1., 0., 1.),
             Vector(1., 0., -1.),
         ],
-        colors: vec![color, color, color, color],
-        indices: vec![0, 1, 2, 0, 2, 3],
-        ..Default::default()
-    };
-
-    if !texture_path.is_empty() {
-        vertex.uv_coords.push(UVSet::new(
+        normals: vec![Vector(0., 1., 0.); 4],
+        uv_coords: vec![UVSet::new(
             0,
             vec![
                 glm::vec2(0.0, 0.0),
@@ -188,19 +119,20 @@ pub fn create_tiles<'t>(
                 glm::vec2(1.0, 1.0),
                 glm::vec2(0.0, 1.0),
             ],
-        ));
-        textures.push(Texture::from_file(texture_path));
+        )],
+        indices: vec![0, 1, 2, 0, 2, 3],
+        ..Default::default()
     };
 
     Mesh::new(
         vertex,
-        textures,
         transform,
         None,
         Some(Collider::Cube),
         DrawMode::Triangles,
         ShaderType::SimpleShader,
     )
+    .material(material)
 }
 
 // pub fn create_line<'n>(
@@ -231,8 +163,8 @@ pub fn create_tiles<'t>(
 pub fn add_light() -> SunLight {
     SunLight::new(
         Vector(2., 2., 2.),
-        Vector(0., 0., 0.),
-        Rgba::new(1., 1., 1., 1.),
+        Vector(-1., -2., -1.5),
+        Rgba::new(0.35, 0.35, 0.4, 1.),
     )
 }
 
@@ -272,7 +204,6 @@ pub fn create_grid(transform: Transform, dim: i32) -> Mesh {
 
     Mesh::new(
         vertex,
-        vec![],
         transform,
         None,
         None,
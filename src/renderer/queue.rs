@@ -0,0 +1,472 @@
+use super::material::Material;
+use super::opengl::{TexId, VBO};
+use super::{Mesh, RenderPass, Rgba, Text, Transform};
+use crate::entities::Handle;
+use nalgebra_glm as glm;
+use std::cmp::Ordering;
+use std::mem;
+use std::ops::Range;
+
+/// Layers are drawn in this order, each one with its own depth and
+/// blend state.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layer {
+    Opaque,
+    // Sorted back to front.
+    Transparent,
+    // Screen space, over everything else.
+    Ui,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 3] = [Layer::Opaque, Layer::Transparent, Layer::Ui];
+
+    // Only the world casts shadows.
+    fn is_drawn(self, pass: RenderPass) -> bool {
+        pass == RenderPass::Color || self != Layer::Ui
+    }
+}
+
+/// Corners and border of a quad, in pixels. The border is drawn
+/// inside the rectangle.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Shape {
+    pub radius: f32,
+    pub border_width: f32,
+    pub border_color: Rgba,
+}
+
+/// What a draw item draws, resolved by the backend.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrawSource {
+    Mesh(Handle<Mesh>),
+    // Range of the instances of a buffer (see `TileInstance`).
+    Instances {
+        mesh: Handle<Mesh>,
+        buffer: VBO,
+        range: Range<usize>,
+    },
+    // Screen rectangle filled with a color, rounded and bordered.
+    Quad(Rgba, Shape),
+    // Positioned by the text itself, boxed with its spans.
+    Text(Box<Text>),
+    // Depth texture on a screen rectangle, for debugging.
+    Depth(TexId),
+    // Texture on a screen rectangle, multiplied by the tint.
+    Image {
+        texture: TexId,
+        tint: Rgba,
+    },
+}
+
+/// Screen rectangle the ui items are clipped to, in pixels from the
+/// bottom left corner.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Clip {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Clip {
+    /// Empty when they don't overlap.
+    pub fn intersect(&self, other: &Clip) -> Clip {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let top = (self.y + self.height).min(other.y + other.height);
+
+        Clip {
+            x,
+            y,
+            width: (right - x).max(0.),
+            height: (top - y).max(0.),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrawItem {
+    pub source: DrawSource,
+    pub material: Option<Handle<Material>>,
+    // World matrix, or screen one for the ui.
+    pub model: glm::Mat4,
+    pub layer: Layer,
+    // Order inside the layer, lower first. Transparent items are
+    // first sorted by distance.
+    pub sort_key: u32,
+    // Only drawn inside, for the ui.
+    pub clip: Option<Clip>,
+}
+
+impl DrawItem {
+    pub fn mesh(
+        mesh: Handle<Mesh>,
+        material: Option<Handle<Material>>,
+        model: glm::Mat4,
+    ) -> Self {
+        Self {
+            source: DrawSource::Mesh(mesh),
+            material,
+            model,
+            layer: Layer::Opaque,
+            sort_key: 0,
+            clip: None,
+        }
+    }
+
+    pub fn instances(
+        mesh: Handle<Mesh>,
+        material: Option<Handle<Material>>,
+        buffer: VBO,
+        range: Range<usize>,
+    ) -> Self {
+        Self {
+            source: DrawSource::Instances {
+                mesh,
+                buffer,
+                range,
+            },
+            material,
+            model: glm::identity(),
+            layer: Layer::Opaque,
+            sort_key: 0,
+            clip: None,
+        }
+    }
+
+    /// The transform gives the bottom left corner and the size in
+    /// pixels.
+    pub fn quad(transform: &Transform, color: Rgba) -> Self {
+        let source = DrawSource::Quad(color, Shape::default());
+        Self::ui(source, screen_rect(transform))
+    }
+
+    /// Only changes the quads.
+    pub fn shape(mut self, shape: Shape) -> Self {
+        if let DrawSource::Quad(_, s) = &mut self.source {
+            *s = shape;
+        }
+        self
+    }
+
+    pub fn text(text: &Text) -> Self {
+        let model = glm::translate(&glm::identity(), &text.position.to_glm());
+        Self::ui(DrawSource::Text(Box::new(text.clone())), model)
+    }
+
+    pub fn depth(texture: TexId, transform: &Transform) -> Self {
+        Self::ui(DrawSource::Depth(texture), screen_rect(transform))
+    }
+
+    pub fn image(texture: TexId, transform: &Transform, tint: Rgba) -> Self {
+        Self::ui(DrawSource::Image { texture, tint }, screen_rect(transform))
+    }
+
+    fn ui(source: DrawSource, model: glm::Mat4) -> Self {
+        Self {
+            source,
+            material: None,
+            model,
+            layer: Layer::Ui,
+            sort_key: 0,
+            clip: None,
+        }
+    }
+
+    pub fn layer(mut self, layer: Layer) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    pub fn sort_key(mut self, sort_key: u32) -> Self {
+        self.sort_key = sort_key;
+        self
+    }
+
+    fn position(&self) -> glm::Vec3 {
+        self.model.column(3).xyz()
+    }
+}
+
+// Our quad goes from -1 to 1, scaled to the rectangle.
+fn screen_rect(transform: &Transform) -> glm::Mat4 {
+    let (position, _, scale) = transform.to_glm();
+    let center = glm::vec3(
+        (0.5 * scale.x) + position.x,
+        (0.5 * scale.y) + position.y,
+        0.,
+    );
+
+    glm::translate(&glm::identity(), &center)
+        * glm::scale(
+            &glm::identity(),
+            &glm::vec3(scale.x * 0.5, scale.y * 0.5, 1.),
+        )
+}
+
+/// Executes the draw items, the game only talks to it through the
+/// render queue.
+pub trait Backend {
+    /// Set the state of the layer before its items are drawn.
+    fn begin_layer(&mut self, layer: Layer, pass: RenderPass);
+    /// Draw the item, or batch it with the next ones.
+    fn draw(&mut self, item: &DrawItem, pass: RenderPass);
+    /// Restore the default state once all layers are drawn, return the
+    /// number of draws issued since the last end.
+    fn end(&mut self) -> u32;
+}
+
+/// Draw items of a frame, submitted by the game in any order.
+#[derive(Debug, Default)]
+pub struct RenderQueue {
+    items: Vec<DrawItem>,
+    is_sorted: bool,
+}
+
+impl RenderQueue {
+    pub fn push(&mut self, item: DrawItem) {
+        self.items.push(item);
+        self.is_sorted = false;
+    }
+
+    #[allow(unused)]
+    pub fn items(&self) -> &[DrawItem] {
+        &self.items
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    #[allow(unused)]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Clip the items pushed since `start`, inside their own clip if
+    /// they have one already.
+    pub fn clip_since(&mut self, start: usize, clip: Clip) {
+        for item in &mut self.items[start..] {
+            item.clip = Some(match item.clip {
+                Some(inner) => inner.intersect(&clip),
+                None => clip,
+            });
+        }
+    }
+
+    /// Sort by layer then by key, transparent items from the farthest
+    /// to the camera. Submission order is kept for equal keys.
+    pub fn sort(&mut self, camera_position: &glm::Vec3) {
+        let distance =
+            |item: &DrawItem| glm::distance2(&item.position(), camera_position);
+
+        self.items.sort_by(|a, b| {
+            a.layer.cmp(&b.layer).then_with(|| {
+                let by_distance = if a.layer == Layer::Transparent {
+                    distance(b)
+                        .partial_cmp(&distance(a))
+                        .unwrap_or(Ordering::Equal)
+                } else {
+                    Ordering::Equal
+                };
+                by_distance.then(a.sort_key.cmp(&b.sort_key))
+            })
+        });
+        self.is_sorted = true;
+    }
+
+    /// Draw the sorted items of the pass, return the number of draws.
+    pub fn execute(&self, backend: &mut impl Backend, pass: RenderPass) -> u32 {
+        self.execute_layers(backend, pass, &Layer::ALL)
+    }
+
+    /// Same as `execute` for some layers only, e.g. to post-process
+    /// the world before drawing the ui.
+    pub fn execute_layers(
+        &self,
+        backend: &mut impl Backend,
+        pass: RenderPass,
+        layers: &[Layer],
+    ) -> u32 {
+        debug_assert!(self.is_sorted, "The queue should be sorted first");
+
+        for layer in layers.iter().filter(|l| l.is_drawn(pass)) {
+            let mut items = self
+                .items
+                .iter()
+                .filter(|item| item.layer == *layer)
+                .peekable();
+            if items.peek().is_none() {
+                continue;
+            }
+
+            backend.begin_layer(*layer, pass);
+            for item in items {
+                backend.draw(item, pass);
+            }
+        }
+
+        backend.end()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Layer(Layer, RenderPass),
+    Draw(DrawItem, RenderPass),
+    End,
+}
+
+/// Keeps the commands instead of drawing them, no gpu needed.
+#[derive(Debug, Default)]
+pub struct RecordingBackend {
+    pub commands: Vec<Command>,
+    draw_call: u32,
+}
+
+impl RecordingBackend {
+    #[allow(unused)]
+    pub fn draws(&self) -> Vec<&DrawItem> {
+        self.commands
+            .iter()
+            .filter_map(|command| match command {
+                Command::Draw(item, _) => Some(item),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Backend for RecordingBackend {
+    fn begin_layer(&mut self, layer: Layer, pass: RenderPass) {
+        self.commands.push(Command::Layer(layer, pass));
+    }
+
+    // Texts are batched like with OpenGL, until another item or clip.
+    fn draw(&mut self, item: &DrawItem, pass: RenderPass) {
+        let is_batched = match (&item.source, self.commands.last()) {
+            (DrawSource::Text(_), Some(Command::Draw(last, _))) => {
+                matches!(last.source, DrawSource::Text(_))
+                    && last.clip == item.clip
+            }
+            _ => false,
+        };
+        if !is_batched {
+            self.draw_call += 1;
+        }
+        self.commands.push(Command::Draw(item.clone(), pass));
+    }
+
+    fn end(&mut self) -> u32 {
+        self.commands.push(Command::End);
+        mem::take(&mut self.draw_call)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::Vector;
+
+    fn at(z: f32) -> glm::Mat4 {
+        glm::translate(&glm::identity(), &glm::vec3(0., 0., z))
+    }
+
+    #[test]
+    fn sort_by_pass() {
+        let mesh = Handle::default();
+        let mut queue = RenderQueue::default();
+        queue.push(DrawItem::quad(&Transform::default(), Rgba::default()));
+        queue.push(
+            DrawItem::mesh(mesh, None, at(-2.)).layer(Layer::Transparent),
+        );
+        queue.push(DrawItem::mesh(mesh, None, at(1.)).sort_key(1));
+        queue.push(
+            DrawItem::mesh(mesh, None, at(-8.)).layer(Layer::Transparent),
+        );
+        queue.push(DrawItem::mesh(mesh, None, at(2.)));
+        queue.push(DrawItem::text(&Text::new("ui")));
+        queue.sort(&glm::vec3(0., 0., 0.));
+
+        let mut backend = RecordingBackend::default();
+        assert_eq!(queue.execute(&mut backend, RenderPass::Color), 6);
+
+        let layers: Vec<Layer> = backend
+            .commands
+            .iter()
+            .filter_map(|command| match command {
+                Command::Layer(layer, _) => Some(*layer),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(layers, Layer::ALL.to_vec());
+
+        // Opaque by key, transparent back to front, ui by submission.
+        let z: Vec<f32> =
+            backend.draws().iter().map(|i| i.position().z).collect();
+        assert_eq!(&z[..4], &[2., 1., -8., -2.]);
+        assert_eq!(
+            backend.draws()[4].source,
+            DrawSource::Quad(Rgba::default(), Shape::default())
+        );
+        assert_eq!(backend.commands.last(), Some(&Command::End));
+
+        // No ui in the shadow map.
+        let mut backend = RecordingBackend::default();
+        assert_eq!(queue.execute(&mut backend, RenderPass::Shadow), 4);
+        assert!(!backend
+            .commands
+            .contains(&Command::Layer(Layer::Ui, RenderPass::Shadow)));
+
+        // Only the ui, drawn after the post passes.
+        let mut backend = RecordingBackend::default();
+        let draw_call =
+            queue.execute_layers(&mut backend, RenderPass::Color, &[Layer::Ui]);
+        assert_eq!(draw_call, 2);
+        assert_eq!(
+            backend.commands[0],
+            Command::Layer(Layer::Ui, RenderPass::Color)
+        );
+    }
+
+    #[test]
+    fn count_batched_texts() {
+        let clip = Clip {
+            x: 0.,
+            y: 0.,
+            width: 10.,
+            height: 10.,
+        };
+        let mut queue = RenderQueue::default();
+        queue.push(DrawItem::text(&Text::new("a")));
+        queue.push(DrawItem::text(&Text::new("b")));
+        queue.push(DrawItem::text(&Text::new("c")));
+        queue.clip_since(2, clip);
+        queue.push(DrawItem::quad(&Transform::default(), Rgba::default()));
+        queue.push(DrawItem::text(&Text::new("d")));
+        queue.sort(&glm::vec3(0., 0., 0.));
+
+        // One batch by clip, broken by the quad.
+        let mut backend = RecordingBackend::default();
+        assert_eq!(queue.execute(&mut backend, RenderPass::Color), 4);
+        assert_eq!(backend.draws().len(), 5);
+    }
+
+    #[test]
+    fn quad_fills_its_rect() {
+        let transform = Transform::from_pos(Vector(10., 20., 0.))
+            .scale(Vector(100., 50., 1.));
+        let item = DrawItem::quad(&transform, Rgba::default());
+
+        let low = item.model * glm::vec4(-1., -1., 0., 1.);
+        let high = item.model * glm::vec4(1., 1., 0., 1.);
+        assert_eq!(low.xy(), glm::vec2(10., 20.));
+        assert_eq!(high.xy(), glm::vec2(110., 70.));
+    }
+}
@@ -0,0 +1,239 @@
+use image::{GrayImage, Luma};
+use rusttype::{point, Font as TrueTypeFont, GlyphId, Scale};
+
+/// Distance in pixels covered by the field on each side of the
+/// edges, also the padding around the glyphs.
+pub const SDF_SPREAD: f32 = 4.;
+// Glyphs are rasterized bigger to measure the distances on sharper
+// edges.
+const UPSCALE: u32 = 4;
+const INFINITY: f64 = 1e20;
+
+/// Distances of a glyph from 0 (outside) to 255 (inside), the edge at
+/// the middle. Metrics are in pixels like the baked atlases.
+#[derive(Debug)]
+pub struct SdfGlyph {
+    // Empty for blank characters like spaces.
+    pub image: GrayImage,
+    // From the left of the image to the pen.
+    pub origin_x: f32,
+    // From the top of the image to the baseline.
+    pub origin_y: f32,
+    pub advance: f32,
+}
+
+/// None if the font has no glyph for the character.
+pub fn rasterize(
+    font: &TrueTypeFont,
+    character: char,
+    size: f32,
+) -> Option<SdfGlyph> {
+    let glyph = font.glyph(character);
+    if glyph.id() == GlyphId(0) {
+        return None;
+    }
+
+    let scaled = glyph.clone().scaled(Scale::uniform(size));
+    let advance = scaled.h_metrics().advance_width;
+    let bounds = match scaled.positioned(point(0., 0.)).pixel_bounding_box() {
+        Some(bounds) => bounds,
+        None => {
+            return Some(SdfGlyph {
+                image: GrayImage::new(0, 0),
+                origin_x: 0.,
+                origin_y: 0.,
+                advance,
+            })
+        }
+    };
+
+    let padding = SDF_SPREAD.ceil() as i32;
+    let (left, top) = (bounds.min.x - padding, bounds.min.y - padding);
+    let width = (bounds.width() + 2 * padding) as u32;
+    let height = (bounds.height() + 2 * padding) as u32;
+
+    // Coverage of the bigger glyph over the same area.
+    let (mask_width, mask_height) = (width * UPSCALE, height * UPSCALE);
+    let mut is_inside = vec![false; (mask_width * mask_height) as usize];
+    let big = glyph
+        .scaled(Scale::uniform(size * UPSCALE as f32))
+        .positioned(point(0., 0.));
+    if let Some(big_bounds) = big.pixel_bounding_box() {
+        let offset_x = big_bounds.min.x - left * UPSCALE as i32;
+        let offset_y = big_bounds.min.y - top * UPSCALE as i32;
+
+        big.draw(|x, y, coverage| {
+            let (x, y) = (x as i32 + offset_x, y as i32 + offset_y);
+            let is_in_mask = x >= 0
+                && y >= 0
+                && (x as u32) < mask_width
+                && (y as u32) < mask_height;
+
+            if is_in_mask && coverage > 0.5 {
+                is_inside[(y as u32 * mask_width + x as u32) as usize] = true;
+            }
+        });
+    }
+
+    let (mask_width, mask_height) = (mask_width as usize, mask_height as usize);
+    let to_inside = distance_transform(&is_inside, mask_width, mask_height);
+    let is_outside: Vec<bool> = is_inside.iter().map(|is| !is).collect();
+    let to_outside = distance_transform(&is_outside, mask_width, mask_height);
+
+    let image = GrayImage::from_fn(width, height, |x, y| {
+        // Center of the texel in the mask.
+        let (x, y) = (x * UPSCALE + UPSCALE / 2, y * UPSCALE + UPSCALE / 2);
+        let i = y as usize * mask_width + x as usize;
+
+        // The edge is between the pixels, half a pixel from both.
+        let distance = if is_inside[i] {
+            to_outside[i].sqrt() - 0.5
+        } else {
+            0.5 - to_inside[i].sqrt()
+        } / f64::from(UPSCALE);
+
+        let value = 0.5 + distance / (2. * f64::from(SDF_SPREAD));
+        Luma([(value.clamp(0., 1.) * 255.).round() as u8])
+    });
+
+    Some(SdfGlyph {
+        image,
+        origin_x: -left as f32,
+        origin_y: -top as f32,
+        advance,
+    })
+}
+
+// Squared distance of each pixel to the nearest one set, in linear
+// time (Felzenszwalb and Huttenlocher).
+fn distance_transform(
+    is_set: &[bool],
+    width: usize,
+    height: usize,
+) -> Vec<f64> {
+    let mut grid: Vec<f64> = is_set
+        .iter()
+        .map(|is_set| if *is_set { 0. } else { INFINITY })
+        .collect();
+
+    let len = width.max(height);
+    let mut line = vec![0.; len];
+    let mut distances = vec![0.; len];
+    let mut parabolas = vec![0; len];
+    let mut bounds = vec![0.; len + 1];
+
+    for x in 0..width {
+        for y in 0..height {
+            line[y] = grid[y * width + x];
+        }
+        transform_line(
+            &line[..height],
+            &mut distances,
+            &mut parabolas,
+            &mut bounds,
+        );
+        for y in 0..height {
+            grid[y * width + x] = distances[y];
+        }
+    }
+
+    for y in 0..height {
+        let row = &mut grid[y * width..(y + 1) * width];
+        line[..width].copy_from_slice(row);
+        transform_line(
+            &line[..width],
+            &mut distances,
+            &mut parabolas,
+            &mut bounds,
+        );
+        row.copy_from_slice(&distances[..width]);
+    }
+
+    grid
+}
+
+// Lower envelope of the parabolas rooted at each sample.
+fn transform_line(
+    samples: &[f64],
+    distances: &mut [f64],
+    parabolas: &mut [usize],
+    bounds: &mut [f64],
+) {
+    let intersection = |q: usize, p: usize| {
+        let (qf, pf) = (q as f64, p as f64);
+        ((samples[q] + qf * qf) - (samples[p] + pf * pf)) / (2. * qf - 2. * pf)
+    };
+
+    let mut k = 0;
+    parabolas[0] = 0;
+    bounds[0] = -INFINITY;
+    bounds[1] = INFINITY;
+
+    for q in 1..samples.len() {
+        let mut s = intersection(q, parabolas[k]);
+        while s <= bounds[k] {
+            k -= 1;
+            s = intersection(q, parabolas[k]);
+        }
+        k += 1;
+        parabolas[k] = q;
+        bounds[k] = s;
+        bounds[k + 1] = INFINITY;
+    }
+
+    k = 0;
+    for (q, distance) in distances.iter_mut().enumerate().take(samples.len()) {
+        while bounds[k + 1] < q as f64 {
+            k += 1;
+        }
+        let offset = q as f64 - parabolas[k] as f64;
+        *distance = offset * offset + samples[parabolas[k]];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn exact_distances() {
+        // One pixel set at (1, 2).
+        let (width, height) = (5, 4);
+        let mut is_set = vec![false; width * height];
+        is_set[2 * width + 1] = true;
+
+        let distances = distance_transform(&is_set, width, height);
+        assert_eq!(distances[2 * width + 1], 0.);
+        assert_eq!(distances[2 * width + 4], 9.);
+        assert_eq!(distances[4], 3. * 3. + 2. * 2.);
+    }
+
+    #[test]
+    fn rasterize_glyphs() {
+        let data = fs::read("assets/fonts/DejaVu/DejaVuSans.ttf").unwrap();
+        let font = TrueTypeFont::from_bytes(data).unwrap();
+
+        let glyph = rasterize(&font, 'I', 42.).unwrap();
+        let (width, height) = glyph.image.dimensions();
+        assert!(glyph.advance > 0.);
+        // Padded, the bar starts after the pen and sits on the baseline.
+        assert!(width as f32 > 2. * SDF_SPREAD);
+        assert!(glyph.origin_x > 0. && glyph.origin_x < SDF_SPREAD);
+        assert!(glyph.origin_y > SDF_SPREAD && glyph.origin_y < height as f32);
+
+        // Inside in the middle of the bar, outside in the corners.
+        let center = glyph.image.get_pixel(width / 2, height / 2)[0];
+        assert!(center > 160);
+        assert_eq!(glyph.image.get_pixel(0, 0)[0], 0);
+        assert_eq!(glyph.image.get_pixel(width - 1, height - 1)[0], 0);
+
+        // Blank, only an advance.
+        let space = rasterize(&font, ' ', 42.).unwrap();
+        assert_eq!(space.image.dimensions(), (0, 0));
+        assert!(space.advance > 0.);
+
+        // Not in the font.
+        assert!(rasterize(&font, '\u{e000}', 42.).is_none());
+    }
+}
@@ -1,13 +1,28 @@
-pub mod light;
-pub mod simple;
-pub mod text;
-pub mod quad;
+mod preprocess;
 
-use crate::global::*;
+use super::light::LightsUbo;
 use super::opengl;
-use gl::{self, types::GLchar};
-use std::{collections::HashMap, ffi::CString, mem, ptr, str};
+use crate::global::*;
+use gl::{
+    self,
+    types::{GLchar, GLenum, GLint, GLsizei},
+};
+use lazy_static::lazy_static;
 use nalgebra_glm as glm;
+use preprocess::Source;
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    error,
+    ffi::CString,
+    fmt, fs, io, mem, ptr, str,
+    sync::Mutex,
+};
+
+lazy_static! {
+    // Uniform locations by program, asked to the driver once.
+    static ref LOCATIONS: Mutex<HashMap<ShaderProgramId, HashMap<String, i32>>> =
+        Mutex::new(HashMap::new());
+}
 
 pub type ShaderProgramId = u32;
 
@@ -18,11 +33,63 @@ pub enum ShaderType {
     TextShader,
     LightShader,
     QuadShader,
+    ShadowShader,
+    ShadowDebugShader,
+    PostBrightShader,
+    PostBlurShader,
+    PostCompositeShader,
+    PostFxaaShader,
+    ImageShader,
+}
+
+impl ShaderType {
+    pub const ALL: [ShaderType; 11] = [
+        ShaderType::SimpleShader,
+        ShaderType::TextShader,
+        ShaderType::LightShader,
+        ShaderType::QuadShader,
+        ShaderType::ShadowShader,
+        ShaderType::ShadowDebugShader,
+        ShaderType::PostBrightShader,
+        ShaderType::PostBlurShader,
+        ShaderType::PostCompositeShader,
+        ShaderType::PostFxaaShader,
+        ShaderType::ImageShader,
+    ];
+
+    /// Vertex and fragment files, in the shaders directory.
+    pub fn sources(self) -> (&'static str, &'static str) {
+        match self {
+            ShaderType::SimpleShader => ("simple.vert", "simple.frag"),
+            ShaderType::TextShader => ("text.vert", "text.frag"),
+            ShaderType::LightShader => ("light.vert", "light.frag"),
+            ShaderType::QuadShader => ("quad.vert", "quad.frag"),
+            ShaderType::ShadowShader => ("shadow.vert", "shadow.frag"),
+            ShaderType::ShadowDebugShader => {
+                ("shadow_debug.vert", "shadow_debug.frag")
+            }
+            // Post passes share the screen triangle.
+            ShaderType::PostBrightShader => {
+                ("post/screen.vert", "post/bright.frag")
+            }
+            ShaderType::PostBlurShader => {
+                ("post/screen.vert", "post/blur.frag")
+            }
+            ShaderType::PostCompositeShader => {
+                ("post/screen.vert", "post/composite.frag")
+            }
+            ShaderType::PostFxaaShader => {
+                ("post/screen.vert", "post/fxaa.frag")
+            }
+            ShaderType::ImageShader => ("image.vert", "image.frag"),
+        }
+    }
 }
 
 #[derive(PartialEq, Eq, Hash, Debug)]
 pub enum UboType {
     WorldTransformUbo,
+    LightsUbo,
 }
 
 struct WorldTransformUbo {
@@ -32,92 +99,133 @@ struct WorldTransformUbo {
     view: glm::Mat4,
 }
 
+/// Vertex attributes available to the shader, the surface itself is
+/// described by the mesh material.
 #[derive(Debug)]
 pub struct ShaderFlags {
-    pub has_uv: bool,
-    pub has_multi_uv: bool,
     pub has_vert_colors: bool,
+    pub has_skin: bool,
+    pub has_normals: bool,
 }
 
 impl ShaderFlags {
-    pub fn set_flags_to_shader(&self, program_id: ShaderProgramId) {
-        set_bool(program_id, "HAS_UV", self.has_uv);
-        set_bool(program_id, "HAS_MULTI_UV", self.has_multi_uv);
-        set_bool(program_id, "HAS_VERT_COLORS", self.has_vert_colors);
+    pub fn permutation(&self) -> Permutation {
+        Permutation::default()
+            .define("HAS_VERT_COLORS", self.has_vert_colors)
+            .define("HAS_SKIN", self.has_skin)
+            .define("HAS_NORMALS", self.has_normals)
     }
 }
 
-/// We use one vao per shader; I guess that it's the
-/// good approach for now.
-pub struct ShaderManager {
-    pub list: HashMap<ShaderType, ShaderProgramId>,
-    pub ubo: HashMap<UboType, u32>,
-}
-
-impl ShaderManager {
-    pub fn build() -> Self {
-        let mut list = HashMap::new();
-        let mut ubo = HashMap::new();
+/// Features compiled in a program, as `#define`s added to its sources.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Default)]
+pub struct Permutation(BTreeSet<&'static str>);
 
-        // Compile and link shaders.
-        {
-            let program_id = create_shader_program(
-                simple::VERTEX_SOURCE,
-                simple::FRAGMENT_SOURCE,
-                "",
-            );
-            list.insert(ShaderType::SimpleShader, program_id);
+impl Permutation {
+    pub fn define(mut self, name: &'static str, is_defined: bool) -> Self {
+        if is_defined {
+            self.0.insert(name);
         }
+        self
+    }
+}
 
-        {
-            let program_id = create_shader_program(
-                text::VERTEX_SOURCE,
-                text::FRAGMENT_SOURCE,
-                "",
-            );
-            list.insert(ShaderType::TextShader, program_id);
-        }
+#[derive(Debug)]
+pub enum ShaderError {
+    Io {
+        file: String,
+        error: io::Error,
+    },
+    // Malformed preprocessor directive.
+    Syntax {
+        file: String,
+        line: usize,
+        message: String,
+    },
+    // Lines of the log point to the files the code comes from.
+    Compile {
+        file: String,
+        line: usize,
+        log: String,
+    },
+    Link {
+        vertex: String,
+        fragment: String,
+        log: String,
+    },
+}
 
-        {
-            let program_id = create_shader_program(
-                light::VERTEX_SOURCE,
-                light::FRAGMENT_SOURCE,
-                "",
-            );
-            list.insert(ShaderType::LightShader, program_id);
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShaderError::Io { file, error } => {
+                write!(f, "Failed to read shader {}: {}", file, error)
+            }
+            ShaderError::Syntax {
+                file,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", file, line, message),
+            ShaderError::Compile { file, line, log } => {
+                write!(f, "Failed to compile {}:{}\n{}", file, line, log)
+            }
+            ShaderError::Link {
+                vertex,
+                fragment,
+                log,
+            } => write!(
+                f,
+                "Failed to link {} with {}\n{}",
+                vertex, fragment, log
+            ),
         }
+    }
+}
 
-        {
-            let program_id = create_shader_program(
-                quad::VERTEX_SOURCE,
-                quad::FRAGMENT_SOURCE,
-                "",
-            );
-            list.insert(ShaderType::QuadShader, program_id);
-        }
+impl error::Error for ShaderError {}
 
+/// Programs are compiled once by permutation, the first time they
+/// are asked.
+pub struct ShaderManager {
+    programs: Mutex<HashMap<(ShaderType, Permutation), ShaderProgramId>>,
+    pub ubo: HashMap<UboType, u32>,
+}
+
+impl ShaderManager {
+    pub fn build() -> Self {
+        let mut ubo = HashMap::new();
 
         // Right now we have 2 UBOs.
         // SpaceTransform and Lights ones.
         //
         // Binding point 0 for the projection.
         // Binding point 1 for the light.
-
-        // We bind those blocks to all shaders for now.
-        for program in list.values() {
-            bind_ubo(*program, "Projections", 0);
-            bind_ubo(*program, "Lights", 1);
-        }
-
-        // Generate all ubo...
         let space_ubo =
             opengl::generate_ubo(mem::size_of::<WorldTransformUbo>(), 0);
         ubo.insert(UboType::WorldTransformUbo, space_ubo);
 
-        // let light_ubo = opengl::generate_ubo(mem::size_of::<f32>(), 1);
-        // ubo.insert(UboType::Lights, light_ubo);
+        let light_ubo = opengl::generate_ubo(mem::size_of::<LightsUbo>(), 1);
+        ubo.insert(UboType::LightsUbo, light_ubo);
+
+        let manager = Self {
+            programs: Mutex::new(HashMap::new()),
+            ubo,
+        };
 
-        Self { list, ubo }
+        // Without features, a broken shader stops the game right away.
+        let mut programs = HashMap::new();
+        for shader_type in ShaderType::ALL.iter() {
+            let permutation = Permutation::default();
+            match compile(*shader_type, &permutation) {
+                Ok(program_id) => {
+                    programs.insert((*shader_type, permutation), program_id);
+                }
+                Err(e) => panic!("{}", e),
+            }
+        }
+        *manager.programs.lock().unwrap() = programs;
+
+        manager
     }
 
     pub fn update_all_ubo(&self) {
@@ -125,6 +233,17 @@ impl ShaderManager {
         let perspective = *PERSPECTIVE_MATRIX.lock().unwrap();
         let gui = *ORTHO_MATRIX.lock().unwrap();
 
+        self.update_projections(gui, perspective, view);
+    }
+
+    /// Set the matrices of the frame without touching the globals, to
+    /// render from another camera.
+    pub fn update_projections(
+        &self,
+        gui: glm::Mat4,
+        perspective: glm::Mat4,
+        view: glm::Mat4,
+    ) {
         let ubo = self.get_ubo(UboType::WorldTransformUbo);
         let updated_data = WorldTransformUbo {
             gui,
@@ -142,13 +261,43 @@ impl ShaderManager {
         opengl::set_ubo(ubo, offset, updated_data.view);
     }
 
+    pub fn update_lights(&self, lights: LightsUbo) {
+        let ubo = self.get_ubo(UboType::LightsUbo);
+        opengl::set_ubo(ubo, 0, lights);
+    }
+
+    /// Program without any feature defined.
     pub fn get_program(&self, shader_type: ShaderType) -> ShaderProgramId {
-        let program = self
-            .list
-            .get(&shader_type)
+        let program = *self
+            .programs
+            .lock()
+            .unwrap()
+            .get(&(shader_type, Permutation::default()))
             .expect("Error while retrieving shader.");
 
-        *program
+        program
+    }
+
+    /// Compiled the first time, a permutation failing to compile falls
+    /// back to the program without features.
+    pub fn get_permutation(
+        &self,
+        shader_type: ShaderType,
+        permutation: &Permutation,
+    ) -> ShaderProgramId {
+        let key = (shader_type, permutation.clone());
+        if let Some(program_id) = self.programs.lock().unwrap().get(&key) {
+            return *program_id;
+        }
+
+        let program_id =
+            compile(shader_type, permutation).unwrap_or_else(|e| {
+                dbg!(e.to_string());
+                self.get_program(shader_type)
+            });
+        self.programs.lock().unwrap().insert(key, program_id);
+
+        program_id
     }
 
     pub fn get_ubo(&self, ubo_type: UboType) -> u32 {
@@ -163,17 +312,56 @@ impl ShaderManager {
         opengl::use_shader_program(program_id);
         program_id
     }
+
+    pub fn activate_permutation(
+        &self,
+        shader_type: ShaderType,
+        permutation: &Permutation,
+    ) -> ShaderProgramId {
+        let program_id = self.get_permutation(shader_type, permutation);
+        opengl::use_shader_program(program_id);
+        program_id
+    }
 }
 
 /// Delete all shader programs when shader manager is drop.
 impl Drop for ShaderManager {
     fn drop(&mut self) {
-        for program in self.list.values() {
+        // Failed permutations share the program without features.
+        let programs = self.programs.lock().unwrap();
+        let programs: HashSet<_> = programs.values().collect();
+        let mut locations = LOCATIONS.lock().unwrap();
+
+        for program in programs {
+            locations.remove(program);
             unsafe { gl::DeleteProgram(*program) }
         }
     }
 }
 
+// Read and compile the sources with the defines of the permutation,
+// then bind the uniform blocks shared by all shaders.
+fn compile(
+    shader_type: ShaderType,
+    permutation: &Permutation,
+) -> Result<ShaderProgramId, ShaderError> {
+    let defines: Vec<&str> = permutation.0.iter().cloned().collect();
+    let (vertex, fragment) = shader_type.sources();
+    let vertex = load_source(vertex, &defines)?;
+    let fragment = load_source(fragment, &defines)?;
+
+    let program_id = create_shader_program(&vertex, &fragment)?;
+    bind_ubo(program_id, "Projections", 0);
+    bind_ubo(program_id, "Lights", 1);
+
+    Ok(program_id)
+}
+
+// File of the shaders directory, includes are relative to the file.
+fn load_source(file: &str, defines: &[&str]) -> Result<Source, ShaderError> {
+    let path = format!("{}{}", SHADERS_DIR_PATH, file);
+    preprocess::preprocess(&path, defines, &mut |path| fs::read_to_string(path))
+}
 
 pub fn set_matrix4(
     shader_id: ShaderProgramId,
@@ -186,6 +374,33 @@ pub fn set_matrix4(
     }
 }
 
+pub fn set_matrix4_array(
+    shader_id: ShaderProgramId,
+    var_name: &str,
+    matrices: &[glm::Mat4],
+) {
+    let shader_variable = get_location(shader_id, var_name);
+    let values: Vec<f32> = matrices
+        .iter()
+        .flat_map(|m| m.as_slice().to_vec())
+        .collect();
+    unsafe {
+        gl::UniformMatrix4fv(
+            shader_variable,
+            matrices.len() as i32,
+            gl::FALSE,
+            values.as_ptr(),
+        );
+    }
+}
+
+pub fn set_vec2(shader_id: ShaderProgramId, var_name: &str, value: &[f32; 2]) {
+    let shader_variable = get_location(shader_id, var_name);
+    unsafe {
+        gl::Uniform2f(shader_variable, value[0], value[1]);
+    }
+}
+
 pub fn set_vec3(shader_id: ShaderProgramId, var_name: &str, value: &[f32; 3]) {
     let shader_variable = get_location(shader_id, var_name);
     unsafe {
@@ -193,6 +408,13 @@ pub fn set_vec3(shader_id: ShaderProgramId, var_name: &str, value: &[f32; 3]) {
     }
 }
 
+pub fn set_vec4(shader_id: ShaderProgramId, var_name: &str, value: &[f32; 4]) {
+    let shader_variable = get_location(shader_id, var_name);
+    unsafe {
+        gl::Uniform4f(shader_variable, value[0], value[1], value[2], value[3]);
+    }
+}
+
 pub fn set_sampler(shader_id: ShaderProgramId, value: usize) {
     let name = format!("texture{}", value);
     set_i32(shader_id, &name, value as i32);
@@ -234,72 +456,134 @@ pub fn bind_ubo(
     }
 }
 
+// Cached, asking the driver stalls the pipeline.
 fn get_location(shader_id: ShaderProgramId, var_name: &str) -> i32 {
-    let var_name = CString::new(var_name)
+    let mut locations = LOCATIONS.lock().unwrap();
+    let program = locations.entry(shader_id).or_default();
+    if let Some(location) = program.get(var_name) {
+        return *location;
+    }
+
+    let c_name = CString::new(var_name)
         .expect("Crash while converting Rust str to C string");
-    unsafe { gl::GetUniformLocation(shader_id, var_name.as_ptr()) }
+    let location =
+        unsafe { gl::GetUniformLocation(shader_id, c_name.as_ptr()) };
+    program.insert(var_name.to_owned(), location);
+
+    location
 }
 
+/// Compile and link a program, errors point to the files the code
+/// comes from.
 pub fn create_shader_program(
-    vertex_source: &str,
-    fragment_source: &str,
-    geometry_source: &str,
-) -> ShaderProgramId {
+    vertex: &Source,
+    fragment: &Source,
+) -> Result<ShaderProgramId, ShaderError> {
+    let vertex_shader = compile_shader(gl::VERTEX_SHADER, vertex)?;
+    let fragment_shader = match compile_shader(gl::FRAGMENT_SHADER, fragment) {
+        Ok(fragment_shader) => fragment_shader,
+        Err(e) => {
+            unsafe { gl::DeleteShader(vertex_shader) };
+            return Err(e);
+        }
+    };
+
     unsafe {
         let shader_program = gl::CreateProgram();
-
-        // For vertex shader.
-        let vertex_shader = gl::CreateShader(gl::VERTEX_SHADER);
-        let c_str_vert = CString::new(vertex_source.as_bytes()).unwrap();
-        gl::ShaderSource(vertex_shader, 1, &c_str_vert.as_ptr(), ptr::null());
-        gl::CompileShader(vertex_shader);
         gl::AttachShader(shader_program, vertex_shader);
-
-        // For fragment shader.
-        let fragment_shader = gl::CreateShader(gl::FRAGMENT_SHADER);
-        let c_str_vert = CString::new(fragment_source.as_bytes()).unwrap();
-        gl::ShaderSource(fragment_shader, 1, &c_str_vert.as_ptr(), ptr::null());
-        gl::CompileShader(fragment_shader);
         gl::AttachShader(shader_program, fragment_shader);
-
-        if !geometry_source.is_empty() {
-            // For geometry shader.
-            let geometry_shader = gl::CreateShader(gl::GEOMETRY_SHADER);
-            let c_str_vert = CString::new(geometry_source.as_bytes()).unwrap();
-            gl::ShaderSource(
-                geometry_shader,
-                1,
-                &c_str_vert.as_ptr(),
-                ptr::null(),
-            );
-            gl::CompileShader(fragment_shader);
-            gl::AttachShader(shader_program, geometry_shader);
-        }
-
-        // Link all shaders
         gl::LinkProgram(shader_program);
 
-        // Check for linking errors
-        let mut is_success = 0;
-        let mut info_log = Vec::with_capacity(512);
-        info_log.set_len(512);
+        // Kept by the program until it's deleted.
+        gl::DeleteShader(vertex_shader);
+        gl::DeleteShader(fragment_shader);
 
+        let mut is_success = 0;
         gl::GetProgramiv(shader_program, gl::LINK_STATUS, &mut is_success);
-        if is_success != 1 {
-            gl::GetProgramInfoLog(
+        if is_success != i32::from(gl::TRUE) {
+            let log = info_log(
                 shader_program,
-                512,
-                ptr::null_mut(),
-                info_log.as_mut_ptr() as *mut GLchar,
+                gl::GetProgramiv,
+                gl::GetProgramInfoLog,
             );
-            dbg!("Error while compiling shader program:");
-            dbg!(str::from_utf8(&info_log).unwrap());
+            gl::DeleteProgram(shader_program);
+
+            return Err(ShaderError::Link {
+                vertex: vertex.file.clone(),
+                fragment: fragment.file.clone(),
+                log,
+            });
         }
 
-        // Clear our shader.
-        gl::DeleteShader(vertex_shader);
-        gl::DeleteShader(fragment_shader);
+        Ok(shader_program)
+    }
+}
 
-        shader_program
+fn compile_shader(kind: GLenum, source: &Source) -> Result<u32, ShaderError> {
+    let code = CString::new(source.code.as_bytes())
+        .expect("Crash while converting Rust str to C string");
+
+    unsafe {
+        let shader = gl::CreateShader(kind);
+        gl::ShaderSource(shader, 1, &code.as_ptr(), ptr::null());
+        gl::CompileShader(shader);
+
+        let mut is_success = 0;
+        gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut is_success);
+        if is_success != i32::from(gl::TRUE) {
+            let log = info_log(shader, gl::GetShaderiv, gl::GetShaderInfoLog);
+            gl::DeleteShader(shader);
+
+            let (origin, log) = source.locate_log(&log);
+            return Err(ShaderError::Compile {
+                file: origin.file,
+                line: origin.line,
+                log,
+            });
+        }
+
+        Ok(shader)
+    }
+}
+
+// Whole log of a shader or a program.
+unsafe fn info_log(
+    id: u32,
+    get_iv: unsafe fn(u32, GLenum, *mut GLint),
+    get_log: unsafe fn(u32, GLsizei, *mut GLsizei, *mut GLchar),
+) -> String {
+    let mut len = 0;
+    get_iv(id, gl::INFO_LOG_LENGTH, &mut len);
+
+    let mut log = vec![0u8; len.max(1) as usize];
+    let mut written = 0;
+    get_log(id, len, &mut written, log.as_mut_ptr() as *mut GLchar);
+    log.truncate(written as usize);
+
+    String::from_utf8_lossy(&log).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_all_shader_sources() {
+        let permutation = Permutation::default()
+            .define("HAS_SKIN", true)
+            .define("IS_INSTANCED", false);
+        let defines: Vec<&str> = permutation.0.iter().cloned().collect();
+        assert_eq!(defines, vec!["HAS_SKIN"]);
+
+        for shader_type in ShaderType::ALL.iter() {
+            let (vertex, fragment) = shader_type.sources();
+            for file in &[vertex, fragment] {
+                let source = load_source(file, &defines).unwrap();
+                assert!(source
+                    .code
+                    .starts_with("#version 330 core\n#define HAS_SKIN\n"));
+                assert!(!source.code.contains("#include"));
+            }
+        }
     }
 }
@@ -0,0 +1,258 @@
+use super::ShaderError;
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+
+/// Where a line of a preprocessed source comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Origin {
+    pub file: String,
+    // From 1, like compilers count.
+    pub line: usize,
+}
+
+/// Source ready to be compiled, with the origin of each line.
+#[derive(Debug)]
+pub struct Source {
+    pub file: String,
+    pub code: String,
+    origins: Vec<Origin>,
+}
+
+impl Source {
+    /// Origin of a line of the code, from 1.
+    pub fn origin(&self, line: usize) -> Option<&Origin> {
+        line.checked_sub(1).and_then(|i| self.origins.get(i))
+    }
+
+    /// Compiler log with its line numbers replaced by the file and
+    /// line they come from, and the origin of the first error.
+    pub fn locate_log(&self, log: &str) -> (Origin, String) {
+        let mut first = None;
+        let lines: Vec<String> = log
+            .lines()
+            .map(|message| {
+                match log_line(message).and_then(|line| self.origin(line)) {
+                    Some(origin) => {
+                        first = first.take().or_else(|| Some(origin.clone()));
+                        format!("{}:{}: {}", origin.file, origin.line, message)
+                    }
+                    None => message.to_owned(),
+                }
+            })
+            .collect();
+
+        let first = first.unwrap_or(Origin {
+            file: self.file.clone(),
+            line: 0,
+        });
+        (first, lines.join("\n"))
+    }
+}
+
+/// Resolve the `#include "path"` of a file, relative to the including
+/// file and each file once, then add the defines after `#version`.
+pub fn preprocess(
+    file: &str,
+    defines: &[&str],
+    read: &mut impl FnMut(&str) -> io::Result<String>,
+) -> Result<Source, ShaderError> {
+    let mut lines = vec![];
+    let mut included = HashSet::new();
+    include(file, read, &mut included, &mut lines)?;
+
+    // The version has to stay the first line.
+    let at = match lines.first() {
+        Some((line, _)) if line.trim_start().starts_with("#version") => 1,
+        _ => 0,
+    };
+    let define_origin = Origin {
+        file: file.to_owned(),
+        line: at.max(1),
+    };
+    let define_lines = defines
+        .iter()
+        .map(|define| (format!("#define {}", define), define_origin.clone()));
+    lines.splice(at..at, define_lines);
+
+    let (code, origins): (Vec<String>, Vec<Origin>) = lines.into_iter().unzip();
+    Ok(Source {
+        file: file.to_owned(),
+        code: code.join("\n") + "\n",
+        origins,
+    })
+}
+
+fn include(
+    file: &str,
+    read: &mut impl FnMut(&str) -> io::Result<String>,
+    included: &mut HashSet<String>,
+    lines: &mut Vec<(String, Origin)>,
+) -> Result<(), ShaderError> {
+    if !included.insert(file.to_owned()) {
+        return Ok(());
+    }
+
+    let code = read(file).map_err(|error| ShaderError::Io {
+        file: file.to_owned(),
+        error,
+    })?;
+
+    for (i, line) in code.lines().enumerate() {
+        let origin = Origin {
+            file: file.to_owned(),
+            line: i + 1,
+        };
+
+        let directive = line.trim();
+        if !directive.starts_with("#include") {
+            lines.push((line.to_owned(), origin));
+            continue;
+        }
+
+        let path = directive["#include".len()..].trim();
+        if path.len() < 2 || !path.starts_with('"') || !path.ends_with('"') {
+            return Err(ShaderError::Syntax {
+                file: origin.file,
+                line: origin.line,
+                message: "Expected #include \"path\"".to_owned(),
+            });
+        }
+
+        let dir = Path::new(file).parent().unwrap_or_else(|| Path::new(""));
+        let path = dir.join(&path[1..path.len() - 1]);
+        include(&path.to_string_lossy(), read, included, lines)?;
+    }
+
+    Ok(())
+}
+
+// Line of a compiler message, drivers write it after the source
+// number: "0:12(5): error" (Mesa), "0(12) : error" (Nvidia) or
+// "ERROR: 0:12:" (AMD, Apple).
+fn log_line(message: &str) -> Option<usize> {
+    let bytes = message.as_bytes();
+
+    (1..bytes.len()).find_map(|i| {
+        let is_separator = bytes[i] == b':' || bytes[i] == b'(';
+        if !is_separator || !bytes[i - 1].is_ascii_digit() {
+            return None;
+        }
+
+        let digits: String = message[i + 1..]
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        digits.parse().ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn files() -> HashMap<&'static str, &'static str> {
+        let mut files = HashMap::new();
+        files.insert(
+            "shaders/main.frag",
+            "#version 330 core\n#include \"include/a.glsl\"\n\
+             #include \"include/b.glsl\"\nvoid main() {}",
+        );
+        // Both include c, added once.
+        files.insert(
+            "shaders/include/a.glsl",
+            "  #include \"c.glsl\"\nfloat a;",
+        );
+        files.insert("shaders/include/b.glsl", "#include \"c.glsl\"\nfloat b;");
+        files.insert("shaders/include/c.glsl", "float c;");
+        files.insert("shaders/bad.frag", "#version 330 core\n#include c.glsl");
+        files
+    }
+
+    fn read_from<'a>(
+        files: &'a HashMap<&'static str, &'static str>,
+    ) -> impl FnMut(&str) -> io::Result<String> + 'a {
+        move |file| {
+            files
+                .get(file)
+                .map(|code| code.to_string())
+                .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        }
+    }
+
+    #[test]
+    fn resolve_includes_and_defines() {
+        let files = files();
+        let source = preprocess(
+            "shaders/main.frag",
+            &["HAS_SKIN", "IS_INSTANCED"],
+            &mut read_from(&files),
+        )
+        .unwrap();
+
+        assert_eq!(
+            source.code,
+            "#version 330 core\n#define HAS_SKIN\n#define IS_INSTANCED\n\
+             float c;\nfloat a;\nfloat b;\nvoid main() {}\n"
+        );
+
+        let origin = |line| {
+            let origin = source.origin(line).unwrap();
+            (origin.file.as_str(), origin.line)
+        };
+        assert_eq!(origin(2), ("shaders/main.frag", 1));
+        assert_eq!(origin(4), ("shaders/include/c.glsl", 1));
+        assert_eq!(origin(6), ("shaders/include/b.glsl", 2));
+        assert_eq!(origin(7), ("shaders/main.frag", 4));
+        assert!(source.origin(0).is_none());
+        assert!(source.origin(8).is_none());
+    }
+
+    #[test]
+    fn preprocess_errors() {
+        let files = files();
+        let mut read = read_from(&files);
+
+        match preprocess("shaders/bad.frag", &[], &mut read) {
+            Err(ShaderError::Syntax { file, line, .. }) => {
+                assert_eq!((file.as_str(), line), ("shaders/bad.frag", 2));
+            }
+            result => panic!("Expected a syntax error, got {:?}", result),
+        }
+
+        match preprocess("shaders/none.frag", &[], &mut read) {
+            Err(ShaderError::Io { file, .. }) => {
+                assert_eq!(file, "shaders/none.frag")
+            }
+            result => panic!("Expected an io error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn locate_compiler_logs() {
+        let files = files();
+        let source =
+            preprocess("shaders/main.frag", &["A"], &mut read_from(&files))
+                .unwrap();
+
+        for log in &[
+            "0:4(7): error: `x' undeclared",
+            "0(4) : error C1008: undefined variable \"x\"",
+            "ERROR: 0:4: 'x' : undeclared identifier",
+        ] {
+            let (origin, located) = source.locate_log(log);
+            assert_eq!(origin.file, "shaders/include/a.glsl");
+            assert_eq!(origin.line, 2);
+            assert!(located.starts_with("shaders/include/a.glsl:2: "));
+        }
+
+        // Nothing to locate, the error is on the file itself.
+        let (origin, located) = source.locate_log("link failed");
+        assert_eq!(
+            (origin.file.as_str(), origin.line),
+            ("shaders/main.frag", 0)
+        );
+        assert_eq!(located, "link failed");
+    }
+}
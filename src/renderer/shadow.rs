@@ -0,0 +1,113 @@
+use super::opengl::{self, Fbo, TexId};
+use nalgebra_glm as glm;
+
+pub const SHADOW_MAP_SIZE: i32 = 2048;
+// Texture unit of the shadow map, far from the mesh textures.
+pub const SHADOW_MAP_UNIT: usize = 7;
+
+/// Depth of the scene seen from the sun, rendered before the scene
+/// itself to know which fragments are in the shadow.
+#[derive(Debug)]
+pub struct ShadowMap {
+    pub fbo: Fbo,
+    pub depth: TexId,
+    pub size: i32,
+    // From world space to the sun clip space.
+    pub light_space: glm::Mat4,
+}
+
+impl ShadowMap {
+    pub fn new(size: i32) -> Self {
+        let (fbo, depth) = opengl::gen_depth_framebuffer(size);
+
+        Self {
+            fbo,
+            depth,
+            size,
+            light_space: glm::identity(),
+        }
+    }
+}
+
+impl Drop for ShadowMap {
+    fn drop(&mut self) {
+        opengl::delete_framebuffer(self.fbo, self.depth);
+    }
+}
+
+/// Orthographic view projection of a directional light, as tight as
+/// possible around the box so the shadow map resolution isn't wasted.
+pub fn fit_light_space(
+    direction: &glm::Vec3,
+    min: &glm::Vec3,
+    max: &glm::Vec3,
+) -> glm::Mat4 {
+    let direction = glm::normalize(direction);
+    let center = (min + max) * 0.5;
+
+    // Any up vector not colinear with the light will do.
+    let up = if direction.y.abs() > 0.99 {
+        glm::vec3(0., 0., 1.)
+    } else {
+        glm::vec3(0., 1., 0.)
+    };
+    let view = glm::look_at(&(center - direction), &center, &up);
+
+    // Bounds of the box corners in the light view space.
+    let mut low = glm::vec3(f32::MAX, f32::MAX, f32::MAX);
+    let mut high = -low;
+    for i in 0..8 {
+        let corner = glm::vec3(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        );
+        let corner = (view * glm::vec4(corner.x, corner.y, corner.z, 1.)).xyz();
+        low = glm::min2(&low, &corner);
+        high = glm::max2(&high, &corner);
+    }
+
+    // The light looks down -Z, a bit of margin on depth avoids
+    // clipping the casters on the edges.
+    let margin = 0.5;
+    let projection = glm::ortho(
+        low.x,
+        high.x,
+        low.y,
+        high.y,
+        -high.z - margin,
+        -low.z + margin,
+    );
+
+    projection * view
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_box_in_clip_space() {
+        let (min, max) = (glm::vec3(-2., -1., 0.), glm::vec3(18., 2., 26.));
+        let light_space =
+            fit_light_space(&glm::vec3(-1., -2., -1.5), &min, &max);
+
+        for i in 0..8 {
+            let corner = glm::vec4(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+                1.,
+            );
+            let clip = light_space * corner;
+            assert!(clip.xyz().abs().max() <= 1. + 1e-4);
+        }
+
+        // Straight down light, the box fills the whole map.
+        let light_space = fit_light_space(&glm::vec3(0., -1., 0.), &min, &max);
+        let low = light_space * glm::vec4(min.x, 0., min.z, 1.);
+        let high = light_space * glm::vec4(max.x, 0., max.z, 1.);
+        assert!((low.x.abs() - 1.).abs() < 1e-4);
+        assert!((high.y.abs() - 1.).abs() < 1e-4);
+    }
+}
@@ -1,3 +1,5 @@
+use super::markup::{self, MarkupError, Span};
+use super::text_layout::{LayoutOptions, Overflow, TextAlign};
 use super::{Colors, Rgb, Vector};
 use std::hash::{Hash, Hasher};
 
@@ -9,6 +11,11 @@ pub struct Text {
     pub font_size: f32,
     pub position: Vector,
     pub color: Rgb,
+    pub layout: LayoutOptions,
+    // Changes often, laid out each frame instead of being cached.
+    pub is_dynamic: bool,
+    // Styles of parts of the content, see `markup`.
+    pub spans: Vec<Span>,
 }
 
 impl Text {
@@ -19,6 +26,16 @@ impl Text {
         }
     }
 
+    /// Content and styles from a markup, see `markup::parse`.
+    pub fn markup(markup: &str) -> Result<Self, MarkupError> {
+        let (content, spans) = markup::parse(markup)?;
+        Ok(Self {
+            content,
+            spans,
+            ..Default::default()
+        })
+    }
+
     #[allow(unused)]
     pub fn font_size(mut self, size: f32) -> Self {
         self.font_size = size;
@@ -36,19 +53,68 @@ impl Text {
         self
     }
 
+    /// Lines wrap at this width, see `overflow`.
+    #[allow(unused)]
+    pub fn max_width(mut self, width: f32) -> Self {
+        self.layout.max_width = Some(width);
+        self
+    }
+
+    #[allow(unused)]
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.layout.overflow = overflow;
+        self
+    }
+
+    #[allow(unused)]
+    pub fn align(mut self, align: TextAlign) -> Self {
+        self.layout.align = align;
+        self
+    }
+
+    #[allow(unused)]
+    pub fn line_height(mut self, line_height: f32) -> Self {
+        self.layout.line_height = line_height;
+        self
+    }
+
+    #[allow(unused)]
+    pub fn max_lines(mut self, max_lines: usize) -> Self {
+        self.layout.max_lines = Some(max_lines);
+        self
+    }
+
+    /// Not cached, for texts like counters.
+    #[allow(unused)]
+    pub fn dynamic(mut self) -> Self {
+        self.is_dynamic = true;
+        self
+    }
+
     #[allow(unused)]
     pub fn set_pos(&mut self, pos: Vector) {
         self.position = pos;
     }
 
+    /// Unstyled, the spans are removed.
     pub fn set_content(&mut self, content: &str) {
         self.content = content.to_owned();
+        self.spans.clear();
+    }
+
+    #[allow(unused)]
+    pub fn set_markup(&mut self, markup: &str) -> Result<(), MarkupError> {
+        let (content, spans) = markup::parse(markup)?;
+        self.content = content;
+        self.spans = spans;
+        Ok(())
     }
 
     pub fn content(&self) -> &str {
         &self.content
     }
 
+    #[allow(unused)]
     pub fn is_empty(&self) -> bool {
         self.content.is_empty()
     }
@@ -57,7 +123,11 @@ impl Text {
 impl Eq for Text {}
 impl PartialEq for Text {
     fn eq(&self, other: &Self) -> bool {
-        self.font_size == other.font_size && self.content == other.content
+        self.font_size == other.font_size
+            && self.content == other.content
+            && self.color == other.color
+            && self.layout == other.layout
+            && self.spans == other.spans
     }
 }
 
@@ -65,6 +135,11 @@ impl Hash for Text {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.content.hash(state);
         (self.font_size as i32).hash(state);
+        [self.color.r, self.color.g, self.color.b]
+            .iter()
+            .for_each(|c| c.to_bits().hash(state));
+        self.layout.hash(state);
+        self.spans.hash(state);
     }
 }
 
@@ -72,9 +147,12 @@ impl Default for Text {
     fn default() -> Self {
         Self {
             content: "".to_owned(),
-            font_size: 26.,
+            font_size: 16.,
             position: Vector(0., 0., 0.),
             color: Rgb::black(),
+            layout: LayoutOptions::default(),
+            is_dynamic: false,
+            spans: vec![],
         }
     }
 }
@@ -0,0 +1,125 @@
+use super::text::Text;
+use std::collections::HashMap;
+
+// Enough for the editor and the gui, the oldest texts are evicted
+// first past this.
+const MAX_ENTRIES: usize = 256;
+// Frames a text can stay unused before being evicted.
+const MAX_AGE: u64 = 120;
+
+#[derive(Debug)]
+struct CachedText {
+    vertices: Vec<f32>,
+    last_used: u64,
+}
+
+/// Vertices of the texts laid out in the previous frames, by content,
+/// size and layout. Texts unused for a while are evicted, so changing
+/// strings don't grow it forever.
+#[derive(Debug)]
+pub struct TextCache {
+    entries: HashMap<Text, CachedText>,
+    frame: u64,
+    max_entries: usize,
+    max_age: u64,
+}
+
+impl Default for TextCache {
+    fn default() -> Self {
+        Self::new(MAX_ENTRIES, MAX_AGE)
+    }
+}
+
+impl TextCache {
+    pub fn new(max_entries: usize, max_age: u64) -> Self {
+        Self {
+            entries: HashMap::new(),
+            frame: 0,
+            max_entries,
+            max_age,
+        }
+    }
+
+    /// Vertices of the text, marked as used this frame.
+    pub fn get(&mut self, text: &Text) -> Option<&[f32]> {
+        let frame = self.frame;
+        self.entries.get_mut(text).map(|entry| {
+            entry.last_used = frame;
+            entry.vertices.as_slice()
+        })
+    }
+
+    /// The least recently used text is evicted when full.
+    pub fn insert(&mut self, text: &Text, vertices: Vec<f32>) -> &[f32] {
+        if self.entries.len() >= self.max_entries
+            && !self.entries.contains_key(text)
+        {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(text, _)| text.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+
+        let entry = CachedText {
+            vertices,
+            last_used: self.frame,
+        };
+        let entry = self.entries.entry(text.clone()).or_insert(entry);
+        entry.vertices.as_slice()
+    }
+
+    /// Evict the texts too old and start a new frame.
+    pub fn end_frame(&mut self) {
+        let (frame, max_age) = (self.frame, self.max_age);
+        self.entries
+            .retain(|_, entry| frame - entry.last_used < max_age);
+        self.frame += 1;
+    }
+
+    #[allow(unused)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::Vector;
+
+    #[test]
+    fn evict_old_texts() {
+        let mut cache = TextCache::new(8, 1);
+        cache.insert(&Text::new("kept"), vec![1.]);
+        cache.insert(&Text::new("old"), vec![2.]);
+        cache.end_frame();
+
+        // Same content and style, drawn somewhere else.
+        let moved = Text::new("kept").position(Vector(10., 20., 0.));
+        assert_eq!(cache.get(&moved), Some(&[1.][..]));
+        cache.end_frame();
+
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get(&Text::new("old")).is_none());
+        assert!(cache.get(&Text::new("kept").font_size(30.)).is_none());
+    }
+
+    #[test]
+    fn evict_least_recently_used() {
+        let mut cache = TextCache::new(2, 100);
+        cache.insert(&Text::new("a"), vec![]);
+        cache.end_frame();
+        cache.insert(&Text::new("b"), vec![]);
+        cache.end_frame();
+        cache.get(&Text::new("a"));
+
+        cache.insert(&Text::new("c"), vec![]);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&Text::new("b")).is_none());
+        assert!(cache.get(&Text::new("a")).is_some());
+    }
+}
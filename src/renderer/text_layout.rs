@@ -0,0 +1,807 @@
+use super::font::{Character, Face, Font};
+use super::icons::Icon;
+use super::markup::{Span, Style, ICON_CHAR};
+use super::Rgb;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+
+// Ends the truncated lines, when the font has no ellipsis glyph.
+const ELLIPSIS: &str = "...";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TextAlign {
+    Left,
+    #[allow(dead_code)]
+    Center,
+    #[allow(dead_code)]
+    Right,
+    // Spaces are stretched to fill the width, except on the last
+    // line of a paragraph.
+    #[allow(dead_code)]
+    Justify,
+}
+
+/// What happens to the lines longer than the max width.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Overflow {
+    // Broken between words, or inside words longer than a line.
+    Wrap,
+    // Cut and ended by an ellipsis.
+    #[allow(dead_code)]
+    Ellipsis,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutOptions {
+    // Lines are aligned in this width, or in the widest one without it.
+    pub max_width: Option<f32>,
+    pub overflow: Overflow,
+    pub align: TextAlign,
+    // Distance between baselines, times the font size.
+    pub line_height: f32,
+    // The last line kept ends with an ellipsis.
+    pub max_lines: Option<usize>,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            max_width: None,
+            overflow: Overflow::Wrap,
+            align: TextAlign::Left,
+            line_height: 1.2,
+            max_lines: None,
+        }
+    }
+}
+
+// Options are part of the key of the cached texts.
+impl Eq for LayoutOptions {}
+impl Hash for LayoutOptions {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.max_width.map(f32::to_bits).hash(state);
+        self.overflow.hash(state);
+        self.align.hash(state);
+        self.line_height.to_bits().hash(state);
+        self.max_lines.hash(state);
+    }
+}
+
+// Weight added to the distance threshold of the glyphs synthesized in
+// bold, and horizontal shift of the top of the italic ones by height.
+const SYNTHETIC_WEIGHT: f32 = 0.08;
+const SYNTHETIC_SLANT: f32 = 0.2;
+// Under the baseline and thickness of the underlines, by font size.
+const UNDERLINE_OFFSET: f32 = 0.12;
+const UNDERLINE_THICKNESS: f32 = 0.06;
+
+/// Quad of a glyph or an icon in pixels, from the baseline of the
+/// first line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionedGlyph {
+    pub character: char,
+    // Bottom left corner.
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    // Atlas texels of the top left and bottom right corners.
+    pub uv_min: (f32, f32),
+    pub uv_max: (f32, f32),
+    // The text color if None.
+    pub color: Option<Rgb>,
+    // Of the bold and italic glyphs synthesized from regular ones.
+    pub weight: f32,
+    // The top edge is moved this much to the right.
+    pub skew: f32,
+    // In the icon atlas instead of the glyph one.
+    pub is_icon: bool,
+}
+
+/// Line under a run of glyphs, `thickness` down from `y`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Underline {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub thickness: f32,
+    pub color: Option<Rgb>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLine {
+    pub glyphs: Range<usize>,
+    // Pen at the start of the line once aligned, and its advance.
+    pub x: f32,
+    pub width: f32,
+    pub baseline: f32,
+}
+
+/// Glyph runs of a text, drawn and measured the same way. The box of
+/// the text is `width` by `height`, from `top` above the baseline of
+/// the first line.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextLayout {
+    pub glyphs: Vec<PositionedGlyph>,
+    pub underlines: Vec<Underline>,
+    pub lines: Vec<TextLine>,
+    // Widest line.
+    pub width: f32,
+    pub height: f32,
+    pub top: f32,
+}
+
+impl TextLayout {
+    #[allow(unused)]
+    pub fn size(&self) -> (f32, f32) {
+        (self.width, self.height)
+    }
+
+    /// Text of each line, as laid out.
+    #[allow(unused)]
+    pub fn line_contents(&self) -> Vec<String> {
+        self.lines
+            .iter()
+            .map(|line| {
+                self.glyphs[line.glyphs.clone()]
+                    .iter()
+                    .map(|glyph| glyph.character)
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+// Spans applied to a character.
+#[derive(Debug, Clone, PartialEq)]
+struct CharStyle {
+    size: f32,
+    face: Face,
+    color: Option<Rgb>,
+    is_underlined: bool,
+    icon: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct Item {
+    c: char,
+    style: CharStyle,
+}
+
+/// Lay out lines separated by newlines, at the font size in pixels
+/// unless a span changes it. Characters missing from the font are
+/// skipped, like unknown icons.
+pub fn layout(
+    font: &Font,
+    content: &str,
+    spans: &[Span],
+    font_size: f32,
+    options: &LayoutOptions,
+) -> TextLayout {
+    let pen = Pen { font };
+    let base = CharStyle {
+        size: font_size,
+        face: Face::Regular,
+        color: None,
+        is_underlined: false,
+        icon: None,
+    };
+    let items = styled(content, spans, &base);
+
+    // Items of each line, and if it ends its paragraph.
+    let mut lines: Vec<(Vec<Item>, bool)> = vec![];
+    for paragraph in items.split(|item| item.c == '\n') {
+        let len = paragraph.len()
+            - paragraph.last().map_or(0, |item| (item.c == '\r') as usize);
+        let paragraph = paragraph[..len].to_vec();
+
+        match (options.max_width, options.overflow) {
+            (Some(max_width), Overflow::Wrap) => {
+                let mut wrapped = pen.wrap(&paragraph, max_width);
+                let last = wrapped.pop().unwrap_or_default();
+                lines.extend(wrapped.into_iter().map(|line| (line, false)));
+                lines.push((last, true));
+            }
+            (Some(max_width), Overflow::Ellipsis) => {
+                lines.push((pen.truncate(paragraph, max_width), true));
+            }
+            (None, _) => lines.push((paragraph, true)),
+        }
+    }
+
+    if let Some(max_lines) = options.max_lines {
+        if lines.len() > max_lines {
+            lines.truncate(max_lines);
+            if let Some((last, ends_paragraph)) = lines.last_mut() {
+                *last = pen.with_ellipsis(last.clone(), options.max_width);
+                *ends_paragraph = true;
+            }
+        }
+    }
+
+    let widths: Vec<f32> =
+        lines.iter().map(|(items, _)| pen.advance(items)).collect();
+    let widest = widths.iter().cloned().fold(0., f32::max);
+    let box_width = options.max_width.unwrap_or(widest);
+    // Lines are as tall as their biggest character.
+    let sizes: Vec<f32> = lines
+        .iter()
+        .map(|(items, _)| {
+            items
+                .iter()
+                .map(|item| item.style.size)
+                .fold(None, |max: Option<f32>, size| {
+                    Some(max.map_or(size, |max| max.max(size)))
+                })
+                .unwrap_or(font_size)
+        })
+        .collect();
+    let (ascent, descent) = font.vertical_metrics();
+
+    let mut layout = TextLayout {
+        top: ascent * sizes[0] / font.size,
+        ..TextLayout::default()
+    };
+
+    let mut baseline = 0.;
+    for (i, ((items, ends_paragraph), width)) in
+        lines.iter().zip(widths).enumerate()
+    {
+        if i > 0 {
+            baseline -= options.line_height * sizes[i];
+        }
+
+        let spaces = items.iter().filter(|item| item.c == ' ').count();
+        let (x, space_stretch) = match options.align {
+            TextAlign::Left => (0., 0.),
+            TextAlign::Center => ((box_width - width) * 0.5, 0.),
+            TextAlign::Right => (box_width - width, 0.),
+            TextAlign::Justify if !ends_paragraph && spaces > 0 => {
+                (0., (box_width - width) / spaces as f32)
+            }
+            TextAlign::Justify => (0., 0.),
+        };
+
+        let start = layout.glyphs.len();
+        let end = pen.place(items, x, baseline, space_stretch, &mut layout);
+
+        layout.width = layout.width.max(end - x);
+        layout.lines.push(TextLine {
+            glyphs: start..layout.glyphs.len(),
+            x,
+            width: end - x,
+            baseline,
+        });
+    }
+    let last_size = sizes[sizes.len() - 1];
+    layout.height = layout.top - baseline + descent * last_size / font.size;
+
+    layout
+}
+
+// Characters with the spans covering them, inner spans last.
+fn styled(content: &str, spans: &[Span], base: &CharStyle) -> Vec<Item> {
+    content
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let mut style = base.clone();
+            for span in spans.iter().filter(|span| span.range.contains(&i)) {
+                match &span.style {
+                    Style::Bold => {
+                        style.face = Face::new(true, style.face.is_italic())
+                    }
+                    Style::Italic => {
+                        style.face = Face::new(style.face.is_bold(), true)
+                    }
+                    Style::Underline => style.is_underlined = true,
+                    Style::Color(color) => style.color = Some(*color),
+                    Style::Size(size) => style.size = *size,
+                    Style::Icon(name) if c == ICON_CHAR => {
+                        style.icon = Some(name.clone())
+                    }
+                    Style::Icon(_) => (),
+                }
+            }
+
+            Item { c, style }
+        })
+        .collect()
+}
+
+// What the pen draws for an item.
+enum Mark {
+    Glyph(Character),
+    Icon(Icon),
+}
+
+// Measures and places the items with the font.
+struct Pen<'a> {
+    font: &'a Font,
+}
+
+impl Pen<'_> {
+    fn scale(&self, item: &Item) -> f32 {
+        item.style.size / self.font.size
+    }
+
+    // None if the font has nothing for the item.
+    fn mark(&self, item: &Item) -> Option<Mark> {
+        match &item.style.icon {
+            Some(name) => self.font.icon(name).map(Mark::Icon),
+            None if item.c == ICON_CHAR => None,
+            None => self.font.glyph(item.c, item.style.face).map(Mark::Glyph),
+        }
+    }
+
+    fn mark_advance(&self, item: &Item, mark: &Mark) -> f32 {
+        match mark {
+            Mark::Glyph(glyph) => glyph.advance * self.scale(item),
+            // As tall as the font size.
+            Mark::Icon(icon) => icon.width * item.style.size / icon.height,
+        }
+    }
+
+    // Only between characters of the same face and size.
+    fn kerning(&self, previous: Option<&Item>, item: &Item) -> f32 {
+        match previous {
+            Some(previous)
+                if previous.style.icon.is_none()
+                    && item.style.icon.is_none()
+                    && previous.style.face == item.style.face
+                    && previous.style.size == item.style.size =>
+            {
+                self.font.kerning(item.style.face, previous.c, item.c)
+                    * self.scale(item)
+            }
+            _ => 0.,
+        }
+    }
+
+    // Distance the pen moves over the items, without the trailing
+    // spaces.
+    fn advance(&self, items: &[Item]) -> f32 {
+        let mut advance = 0.;
+        let mut previous = None;
+
+        for item in trim_end(items) {
+            if let Some(mark) = self.mark(item) {
+                advance += self.kerning(previous, item);
+                advance += self.mark_advance(item, &mark);
+                previous = Some(item);
+            }
+        }
+
+        advance
+    }
+
+    // Push the quads and underlines of a line, return the pen at its
+    // end.
+    fn place(
+        &self,
+        items: &[Item],
+        x: f32,
+        baseline: f32,
+        space_stretch: f32,
+        layout: &mut TextLayout,
+    ) -> f32 {
+        let mut cursor = x;
+        let mut previous = None;
+        let mut underline: Option<Underline> = None;
+
+        for item in trim_end(items) {
+            let mark = match self.mark(item) {
+                Some(mark) => mark,
+                None => continue,
+            };
+            cursor += self.kerning(previous, item);
+            let start = cursor;
+            let scale = self.scale(item);
+            let style = &item.style;
+
+            let glyph = match &mark {
+                Mark::Glyph(glyph) => {
+                    let y = baseline - (glyph.height - glyph.origin_y) * scale;
+                    let height = glyph.height * scale;
+                    let (uv_min, uv_max) = self.font.uv(glyph);
+
+                    // Faces missing from the font are synthesized.
+                    let is_bold =
+                        style.face.is_bold() && !glyph.face().is_bold();
+                    let slant = if style.face.is_italic()
+                        && !glyph.face().is_italic()
+                    {
+                        SYNTHETIC_SLANT
+                    } else {
+                        0.
+                    };
+
+                    PositionedGlyph {
+                        character: item.c,
+                        // The slant turns around the baseline.
+                        x: cursor
+                            - glyph.origin_x * scale
+                            - slant * (baseline - y),
+                        y,
+                        width: glyph.width * scale,
+                        height,
+                        uv_min,
+                        uv_max,
+                        color: style.color,
+                        weight: if is_bold { SYNTHETIC_WEIGHT } else { 0. },
+                        skew: slant * height,
+                        is_icon: false,
+                    }
+                }
+                Mark::Icon(icon) => {
+                    let (_, descent) = self.font.vertical_metrics();
+                    PositionedGlyph {
+                        character: item.c,
+                        x: cursor,
+                        y: baseline - descent * scale,
+                        width: self.mark_advance(item, &mark),
+                        height: style.size,
+                        uv_min: (icon.x, icon.y),
+                        uv_max: (icon.x + icon.width, icon.y + icon.height),
+                        color: None,
+                        weight: 0.,
+                        skew: 0.,
+                        is_icon: true,
+                    }
+                }
+            };
+            layout.glyphs.push(glyph);
+
+            cursor += self.mark_advance(item, &mark);
+            if item.c == ' ' {
+                cursor += space_stretch;
+            }
+            previous = Some(item);
+
+            // Runs of the same color and size share their underline.
+            let y = baseline - UNDERLINE_OFFSET * style.size;
+            let thickness = (UNDERLINE_THICKNESS * style.size).max(1.);
+            underline = match underline.take() {
+                Some(mut line)
+                    if style.is_underlined
+                        && line.color == style.color
+                        && line.y == y =>
+                {
+                    line.width = cursor - line.x;
+                    Some(line)
+                }
+                line => {
+                    layout.underlines.extend(line);
+                    if style.is_underlined {
+                        Some(Underline {
+                            x: start,
+                            y,
+                            width: cursor - start,
+                            thickness,
+                            color: style.color,
+                        })
+                    } else {
+                        None
+                    }
+                }
+            };
+        }
+        layout.underlines.extend(underline);
+
+        cursor
+    }
+
+    // Greedy, words longer than a line are broken anywhere.
+    fn wrap(&self, items: &[Item], max_width: f32) -> Vec<Vec<Item>> {
+        let mut lines = vec![];
+        let mut line: Vec<Item> = vec![];
+        let mut start = 0;
+
+        for (i, word) in items.split(|item| item.c == ' ').enumerate() {
+            let mut candidate = line.clone();
+            if i > 0 {
+                candidate.push(items[start - 1].clone());
+            }
+            candidate.extend_from_slice(word);
+            start += word.len() + 1;
+
+            if i == 0 || self.advance(&candidate) <= max_width {
+                line = candidate;
+            } else {
+                lines.push(trim_end(&line).to_vec());
+                line = word.to_vec();
+            }
+
+            while line.len() > 1 && self.advance(&line) > max_width {
+                let fits = (1..line.len())
+                    .rev()
+                    .find(|len| self.advance(&line[..*len]) <= max_width)
+                    .unwrap_or(1);
+                lines.push(line[..fits].to_vec());
+                line = line[fits..].to_vec();
+            }
+        }
+        lines.push(trim_end(&line).to_vec());
+
+        lines
+    }
+
+    fn truncate(&self, items: Vec<Item>, max_width: f32) -> Vec<Item> {
+        if self.advance(&items) <= max_width {
+            items
+        } else {
+            self.with_ellipsis(items, Some(max_width))
+        }
+    }
+
+    // Items are removed until the ellipsis fits, it has the style of
+    // the last one kept.
+    fn with_ellipsis(
+        &self,
+        mut items: Vec<Item>,
+        max_width: Option<f32>,
+    ) -> Vec<Item> {
+        loop {
+            items.truncate(trim_end(&items).len());
+            let mut style = match items.last() {
+                Some(item) => item.style.clone(),
+                None => return items,
+            };
+            style.icon = None;
+
+            let ellipsis: Vec<Item> = match self.font.glyph('…', style.face) {
+                Some(_) => vec!['…'],
+                None => ELLIPSIS.chars().collect(),
+            }
+            .into_iter()
+            .map(|c| Item {
+                c,
+                style: style.clone(),
+            })
+            .collect();
+
+            let mut candidate = items.clone();
+            candidate.extend(ellipsis);
+            let fits = match max_width {
+                Some(max_width) => self.advance(&candidate) <= max_width,
+                None => true,
+            };
+            if fits || items.len() == 1 {
+                return candidate;
+            }
+            items.pop();
+        }
+    }
+}
+
+fn trim_end(items: &[Item]) -> &[Item] {
+    let len = items
+        .iter()
+        .rposition(|item| item.c != ' ')
+        .map_or(0, |i| i + 1);
+    &items[..len]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::icons::IconAtlas;
+    use crate::renderer::markup;
+
+    fn helvetica() -> Font {
+        Font::new(
+            "assets/fonts/Helvetica/helvetica.json",
+            "assets/fonts/Helvetica/helvetica.png",
+        )
+    }
+
+    #[test]
+    fn lines_and_measure() {
+        let font = helvetica();
+        let text = layout(
+            &font,
+            "Hello\nworld!\r\n",
+            &[],
+            21.,
+            &LayoutOptions::default(),
+        );
+
+        assert_eq!(text.line_contents(), vec!["Hello", "world!", ""]);
+        // Half the atlas size.
+        let (ascent, descent) = font.vertical_metrics();
+        assert_eq!(text.top, ascent * 0.5);
+        assert_eq!(text.height, (ascent + descent) * 0.5 + 2. * 1.2 * 21.);
+        assert_eq!(text.lines[1].baseline, -1.2 * 21.);
+
+        // The width is where the pen stops.
+        let line = &text.lines[1];
+        let last = &text.glyphs[line.glyphs.end - 1];
+        let advance = font.glyph('!', Face::Regular).unwrap().advance * 0.5;
+        let origin_x = font.glyph('!', Face::Regular).unwrap().origin_x * 0.5;
+        assert!((line.width - (last.x + origin_x + advance)).abs() < 1e-4);
+        assert!(text.width >= text.lines[0].width);
+
+        // Unknown characters are skipped, nothing panics.
+        let text =
+            layout(&font, "\u{2603}", &[], 21., &LayoutOptions::default());
+        assert!(text.glyphs.is_empty());
+        assert_eq!(text.width, 0.);
+    }
+
+    #[test]
+    fn wrap_words() {
+        let font = helvetica();
+        let options = LayoutOptions {
+            max_width: Some(100.),
+            ..LayoutOptions::default()
+        };
+
+        let text =
+            layout(&font, "The quick brown fox jumps over", &[], 21., &options);
+        assert!(text.lines.len() > 1);
+        assert!(text.lines.iter().all(|line| line.width <= 100.));
+        // Words are kept, spaces at the breaks are dropped.
+        assert_eq!(
+            text.line_contents().join(" "),
+            "The quick brown fox jumps over"
+        );
+
+        // Too long for a line, broken inside.
+        let text = layout(&font, "Supercalifragilistic", &[], 21., &options);
+        assert!(text.lines.len() > 1);
+        assert!(text.lines.iter().all(|line| line.width <= 100.));
+        assert_eq!(text.line_contents().concat(), "Supercalifragilistic");
+    }
+
+    #[test]
+    fn align_lines() {
+        let font = helvetica();
+        let aligned = |align| {
+            let options = LayoutOptions {
+                max_width: Some(200.),
+                align,
+                ..LayoutOptions::default()
+            };
+            layout(
+                &font,
+                "Some words to fill two lines here",
+                &[],
+                21.,
+                &options,
+            )
+        };
+
+        let left = aligned(TextAlign::Left);
+        let center = aligned(TextAlign::Center);
+        let right = aligned(TextAlign::Right);
+        let justify = aligned(TextAlign::Justify);
+
+        let close = |a: f32, b: f32| (a - b).abs() < 0.01;
+        for (i, line) in left.lines.iter().enumerate() {
+            assert_eq!(line.x, 0.);
+            assert!(close(center.lines[i].x, (200. - line.width) * 0.5));
+            assert!(close(right.lines[i].x + line.width, 200.));
+        }
+
+        // Filled but the last line.
+        let last = justify.lines.len() - 1;
+        for line in &justify.lines[..last] {
+            assert!(close(line.width, 200.));
+        }
+        assert_eq!(justify.lines[last], left.lines[last]);
+    }
+
+    #[test]
+    fn kerning_pairs() {
+        // Only the TrueType fonts have pairs, not the baked atlas.
+        let font =
+            Font::from_file("assets/fonts/DejaVu/DejaVuSans.ttf", 42.).unwrap();
+        let kerning = font.kerning(Face::Regular, 'A', 'V');
+        assert!(kerning < 0.);
+        assert_eq!(font.kerning(Face::Regular, 'V', 'V'), 0.);
+
+        // Both glyphs have the same advance.
+        let kerned = layout(&font, "AV", &[], 42., &LayoutOptions::default());
+        let plain = layout(&font, "VV", &[], 42., &LayoutOptions::default());
+        assert!(
+            (kerned.glyphs[1].x - (plain.glyphs[1].x + kerning)).abs() < 1e-4
+        );
+        assert!((kerned.width - (plain.width + kerning)).abs() < 1e-4);
+
+        assert_eq!(helvetica().kerning(Face::Regular, 'A', 'V'), 0.);
+    }
+
+    #[test]
+    fn truncate_with_ellipsis() {
+        let font = helvetica();
+        let options = LayoutOptions {
+            max_width: Some(80.),
+            overflow: Overflow::Ellipsis,
+            ..LayoutOptions::default()
+        };
+
+        let text =
+            layout(&font, "A sentence far too long\nOk", &[], 21., &options);
+        let lines = text.line_contents();
+        assert!(lines[0].ends_with("...") && lines[0].len() > 3);
+        assert!(text.lines[0].width <= 80.);
+        assert_eq!(lines[1], "Ok");
+
+        let options = LayoutOptions {
+            max_width: Some(100.),
+            max_lines: Some(2),
+            ..LayoutOptions::default()
+        };
+        let text = layout(
+            &font,
+            "Many words that wrap on more than two lines",
+            &[],
+            21.,
+            &options,
+        );
+        assert_eq!(text.lines.len(), 2);
+        assert!(text.line_contents()[1].ends_with("..."));
+        assert!(text.lines.iter().all(|line| line.width <= 100.));
+    }
+
+    #[test]
+    fn style_spans() {
+        let font = helvetica();
+        let (content, spans) =
+            markup::parse("a <b>b</b> <i>c</i> <color=#ff0000>d</color>")
+                .unwrap();
+        let text = layout(&font, &content, &spans, 21., &Default::default());
+
+        // Helvetica has no styled faces, they are synthesized.
+        let glyph = |c| text.glyphs.iter().find(|g| g.character == c).unwrap();
+        assert_eq!((glyph('a').weight, glyph('a').skew), (0., 0.));
+        assert!(glyph('b').weight > 0.);
+        assert!(glyph('c').skew > 0. && glyph('c').weight == 0.);
+        assert_eq!(glyph('d').color, Some(Rgb::new(1., 0., 0.)));
+        assert_eq!(glyph('a').color, None);
+    }
+
+    #[test]
+    fn lines_as_tall_as_their_biggest_size() {
+        let font = helvetica();
+        let (content, spans) =
+            markup::parse("small\nbig <size=42>big</size>").unwrap();
+        let text = layout(&font, &content, &spans, 21., &Default::default());
+
+        assert_eq!(text.lines[1].baseline, -1.2 * 42.);
+        let small = &text.glyphs[text.lines[1].glyphs.start];
+        let big = &text.glyphs[text.lines[1].glyphs.start + 4];
+        assert_eq!((small.character, big.character), ('b', 'b'));
+        assert!((big.height - small.height * 2.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn inline_icons_and_underlines() {
+        let mut font = helvetica();
+        font.set_icons(IconAtlas::new(
+            "assets/icons/keys.json",
+            "assets/icons/keys.png",
+        ));
+        let (content, spans) =
+            markup::parse("<u>press <icon=key_space> now</u> <icon=nope>")
+                .unwrap();
+        let text = layout(&font, &content, &spans, 21., &Default::default());
+
+        // As tall as the font size, the unknown icon is skipped.
+        let icons: Vec<_> = text.glyphs.iter().filter(|g| g.is_icon).collect();
+        assert_eq!(icons.len(), 1);
+        assert_eq!(icons[0].height, 21.);
+        assert_eq!(icons[0].width, 21. * 80. / 32.);
+        assert_eq!(icons[0].uv_max.0 - icons[0].uv_min.0, 80.);
+
+        // A single line under the whole run.
+        let (content, spans) =
+            markup::parse("press <icon=key_space> now").unwrap();
+        let run = layout(&font, &content, &spans, 21., &Default::default());
+        assert_eq!(text.underlines.len(), 1);
+        let underline = &text.underlines[0];
+        assert_eq!(underline.x, 0.);
+        assert!((underline.width - run.width).abs() < 1e-4);
+        assert!(underline.y < 0. && underline.thickness >= 1.);
+    }
+}
@@ -1,25 +1,271 @@
-use image;
+use image::{
+    self, imageops, DynamicImage, FilterType, GenericImageView, ImageResult,
+};
 
 pub type TextureDim = (u32, u32);
 
-#[derive(Debug, Clone, Default)]
+/// Layout of the pixels in memory, 8 bits per channel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PixelFormat {
+    // Grayscale, sampled as rgb.
+    R8,
+    Rgb8,
+    Rgba8,
+}
+
+impl PixelFormat {
+    pub fn channels(self) -> usize {
+        match self {
+            PixelFormat::R8 => 1,
+            PixelFormat::Rgb8 => 3,
+            PixelFormat::Rgba8 => 4,
+        }
+    }
+}
+
+/// How the values of a texture are encoded.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ColorSpace {
+    // Colors authored on screen, decoded to linear when sampled.
+    Srgb,
+    // Data like roughness, normals or distances.
+    Linear,
+}
+
+/// How a texture is filtered and wrapped, values are opengl enums.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sampler {
+    pub wrap_s: u32,
+    pub wrap_t: u32,
+    pub min_filter: u32,
+    pub mag_filter: u32,
+    // Samples along oblique surfaces, clamped to what the gpu
+    // supports. 1 disables it.
+    pub anisotropy: f32,
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Self {
+            wrap_s: gl::REPEAT,
+            wrap_t: gl::REPEAT,
+            min_filter: gl::LINEAR_MIPMAP_LINEAR,
+            mag_filter: gl::LINEAR,
+            anisotropy: 8.,
+        }
+    }
+}
+
+impl Sampler {
+    /// Clamped to the edges without mipmaps, for atlases and screen
+    /// textures.
+    pub fn clamped() -> Self {
+        Self {
+            wrap_s: gl::CLAMP_TO_EDGE,
+            wrap_t: gl::CLAMP_TO_EDGE,
+            min_filter: gl::LINEAR,
+            mag_filter: gl::LINEAR,
+            anisotropy: 1.,
+        }
+    }
+
+    /// Mipmaps are only generated when the min filter uses them.
+    pub fn has_mipmaps(&self) -> bool {
+        matches!(
+            self.min_filter,
+            gl::NEAREST_MIPMAP_NEAREST
+                | gl::NEAREST_MIPMAP_LINEAR
+                | gl::LINEAR_MIPMAP_NEAREST
+                | gl::LINEAR_MIPMAP_LINEAR
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Texture {
+    // Rows from the first one uploaded, layers one after the other.
     pub raw: Vec<u8>,
     pub dim: TextureDim,
+    pub format: PixelFormat,
+    pub color_space: ColorSpace,
+    pub sampler: Sampler,
+    // Number of layers of a texture array, None for a single image.
+    pub layers: Option<u32>,
+}
+
+impl Default for Texture {
+    fn default() -> Self {
+        Self {
+            raw: vec![],
+            dim: (0, 0),
+            format: PixelFormat::Rgba8,
+            color_space: ColorSpace::Srgb,
+            sampler: Sampler::default(),
+            layers: None,
+        }
+    }
 }
 
 impl Texture {
+    /// RGBA pixels.
     pub fn new(dim: (u32, u32), raw: Vec<u8>) -> Self {
-        Self { raw, dim }
+        Self {
+            raw,
+            dim,
+            ..Self::default()
+        }
     }
-    pub fn from_file(file_path: &str) -> Self {
-        let img =
-            image::open(file_path).expect("Failed to load texture in memory");
+
+    /// Pixels in the image order, from the top row.
+    pub fn from_image(image: DynamicImage) -> Self {
+        let dim = image.dimensions();
+        let (format, raw) = match image {
+            DynamicImage::ImageLuma8(image) => {
+                (PixelFormat::R8, image.into_raw())
+            }
+            DynamicImage::ImageRgb8(image) => {
+                (PixelFormat::Rgb8, image.into_raw())
+            }
+            DynamicImage::ImageRgba8(image) => {
+                (PixelFormat::Rgba8, image.into_raw())
+            }
+            image => (PixelFormat::Rgba8, image.to_rgba().into_raw()),
+        };
 
         Self {
-            // Flip texture vertically so opengl uv mapping are set corretly.
-            raw: img.raw_pixels(),
-            dim: img.to_rgb().dimensions(),
+            raw,
+            dim,
+            format,
+            ..Self::default()
         }
     }
+
+    /// Png or jpeg image, flipped so the first row is at the bottom
+    /// like opengl uv coordinates.
+    pub fn from_file(file_path: &str) -> Self {
+        Self::try_from_file(file_path)
+            .expect("Failed to load texture in memory")
+    }
+
+    /// Same as `from_file`, for the files edited while the game runs.
+    pub fn try_from_file(file_path: &str) -> ImageResult<Self> {
+        Ok(Self::from_image(image::open(file_path)?.flipv()))
+    }
+
+    /// Layers of the same size and format sampled as one texture,
+    /// e.g. the variants of a tile. Layers are converted to RGBA and
+    /// resized like the first one. None without layers.
+    pub fn array(layers: Vec<Texture>) -> Option<Self> {
+        let first = layers.first()?;
+        let (width, height) = first.dim;
+        let color_space = first.color_space;
+        let sampler = first.sampler;
+
+        let mut raw =
+            Vec::with_capacity((width * height) as usize * 4 * layers.len());
+        for layer in layers.iter() {
+            let image = layer.to_image().to_rgba();
+            let image = if image.dimensions() == (width, height) {
+                image
+            } else {
+                imageops::resize(&image, width, height, FilterType::Triangle)
+            };
+            raw.extend_from_slice(&image.into_raw());
+        }
+
+        Some(Self {
+            raw,
+            dim: (width, height),
+            format: PixelFormat::Rgba8,
+            color_space,
+            sampler,
+            layers: Some(layers.len() as u32),
+        })
+    }
+
+    pub fn sampler(mut self, sampler: Sampler) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    pub fn color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
+    // Only the first layer of an array.
+    fn to_image(&self) -> DynamicImage {
+        let (width, height) = self.dim;
+        let len = (width * height) as usize * self.format.channels();
+        let raw = self.raw[..len].to_vec();
+        let error = "Pixels should fill the whole texture";
+
+        match self.format {
+            PixelFormat::R8 => DynamicImage::ImageLuma8(
+                image::GrayImage::from_raw(width, height, raw).expect(error),
+            ),
+            PixelFormat::Rgb8 => DynamicImage::ImageRgb8(
+                image::RgbImage::from_raw(width, height, raw).expect(error),
+            ),
+            PixelFormat::Rgba8 => DynamicImage::ImageRgba8(
+                image::RgbaImage::from_raw(width, height, raw).expect(error),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma, Rgb, RgbImage};
+
+    #[test]
+    fn keep_image_format() {
+        // First row white, second black.
+        let image =
+            RgbImage::from_fn(3, 2, |_, y| Rgb([255 * (1 - y) as u8; 3]));
+        let texture = Texture::from_image(DynamicImage::ImageRgb8(image));
+        assert_eq!(texture.format, PixelFormat::Rgb8);
+        assert_eq!(texture.dim, (3, 2));
+        assert_eq!(texture.raw.len(), 3 * 2 * 3);
+        assert_eq!(texture.raw[0], 255);
+
+        let texture = Texture::from_file("assets/textures/tile.jpg");
+        assert_eq!(texture.format, PixelFormat::Rgb8);
+        assert_eq!(
+            texture.raw.len(),
+            (texture.dim.0 * texture.dim.1 * 3) as usize
+        );
+
+        let texture = Texture::from_file("assets/textures/ground.png");
+        assert_eq!(texture.format, PixelFormat::Rgba8);
+        assert_eq!(
+            texture.raw.len(),
+            (texture.dim.0 * texture.dim.1 * 4) as usize
+        );
+    }
+
+    #[test]
+    fn texture_array_of_variants() {
+        let gray = Texture::from_image(DynamicImage::ImageLuma8(
+            GrayImage::from_pixel(4, 4, Luma([100])),
+        ));
+        let small = Texture::new((2, 2), vec![200; 2 * 2 * 4]);
+
+        let array = Texture::array(vec![gray, small]).unwrap();
+        assert_eq!(array.layers, Some(2));
+        assert_eq!(array.format, PixelFormat::Rgba8);
+        // Resized like the first layer.
+        assert_eq!(array.raw.len(), 4 * 4 * 4 * 2);
+        assert_eq!(&array.raw[..4], &[100, 100, 100, 255]);
+        assert_eq!(&array.raw[4 * 4 * 4..][..4], &[200; 4]);
+
+        assert!(Texture::array(vec![]).is_none());
+    }
+
+    #[test]
+    fn mipmaps_from_filter() {
+        assert!(Sampler::default().has_mipmaps());
+        assert!(!Sampler::clamped().has_mipmaps());
+    }
 }
@@ -0,0 +1,280 @@
+use super::opengl::{self, VBO};
+use crate::entities::{Entities, Entity, Handle};
+use crate::global::{TILEMAP_HEIGHT, TILEMAP_WIDTH, TILES_COUNT, TILE_SIZE};
+use crate::tilemap::{Tile, Tilemap, World};
+use nalgebra_glm as glm;
+use std::collections::HashMap;
+use std::ops::Range;
+
+// Same as the tile grounds drawn before batching.
+const GROUND_HEIGHT: f32 = 0.2;
+/// Layers of the ground texture array, picked per tile.
+pub const GROUND_VARIANTS: [&str; 2] =
+    ["assets/textures/ground.png", "assets/textures/tile.jpg"];
+
+/// What an instance draws, instances are sorted by kind so each one
+/// is a single draw call.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TileKind {
+    Ground,
+    Wall,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TileInstance {
+    // World matrix then texture layer, first as the shader expects.
+    pub model: [f32; 16],
+    pub layer: u32,
+    pub kind: TileKind,
+}
+
+/// Instances of a tilemap, uploaded once and rebuilt only when the
+/// tilemap changes (see `Tilemap::revision`).
+/// The player moves between tiles, it's drawn on its own.
+#[derive(Debug)]
+pub struct TileBatch {
+    pub vbo: VBO,
+    pub instances: Vec<TileInstance>,
+    pub has_player: bool,
+    revision: u32,
+    offset: glm::TVec2<i32>,
+}
+
+impl TileBatch {
+    pub fn new(
+        entities: &Entities,
+        world: &World,
+        tilemap: &Tilemap,
+        offset: &glm::TVec2<i32>,
+    ) -> Self {
+        let (instances, has_player) =
+            tile_instances(entities, world, tilemap, offset);
+        let vbo = opengl::load_instances_to_gpu(&instances);
+
+        Self {
+            vbo,
+            instances,
+            has_player,
+            revision: tilemap.revision(),
+            offset: *offset,
+        }
+    }
+
+    /// Not uploaded on the gpu, for the tests.
+    #[cfg(test)]
+    pub fn headless(instances: Vec<TileInstance>, has_player: bool) -> Self {
+        Self {
+            vbo: 0,
+            instances,
+            has_player,
+            revision: 0,
+            offset: glm::vec2(0, 0),
+        }
+    }
+
+    /// Instances of the given kind.
+    pub fn range(&self, kind: TileKind) -> Range<usize> {
+        let start = self.instances.iter().position(|i| i.kind == kind);
+        match start {
+            Some(start) => {
+                let count = self.instances[start..]
+                    .iter()
+                    .take_while(|i| i.kind == kind)
+                    .count();
+                start..start + count
+            }
+            None => 0..0,
+        }
+    }
+
+    fn is_outdated(&self, tilemap: &Tilemap, offset: &glm::TVec2<i32>) -> bool {
+        self.revision != tilemap.revision() || self.offset != *offset
+    }
+}
+
+impl Drop for TileBatch {
+    fn drop(&mut self) {
+        if self.vbo != 0 {
+            opengl::delete_buffer(self.vbo);
+        }
+    }
+}
+
+/// Batches of all the tilemaps drawn so far, by tilemap.
+#[derive(Debug, Default)]
+pub struct TileBatches {
+    batches: HashMap<Handle<Tilemap>, TileBatch>,
+}
+
+impl TileBatches {
+    /// Batch of the tilemap at the given world position, built again
+    /// if the tilemap changed since the last call.
+    pub fn get(
+        &mut self,
+        entities: &Entities,
+        world: &World,
+        handle: &Handle<Tilemap>,
+        offset: &glm::TVec2<i32>,
+    ) -> &TileBatch {
+        let tilemap = entities.get(handle);
+        let is_outdated = self
+            .batches
+            .get(handle)
+            .is_none_or(|batch| batch.is_outdated(tilemap, offset));
+
+        if is_outdated {
+            let batch = TileBatch::new(entities, world, tilemap, offset);
+            self.batches.insert(*handle, batch);
+        }
+
+        &self.batches[handle]
+    }
+}
+
+/// World matrices of the grounds and walls of a tilemap, sorted by
+/// kind, and whether the player stands on it.
+pub fn tile_instances(
+    entities: &Entities,
+    world: &World,
+    tilemap: &Tilemap,
+    offset: &glm::TVec2<i32>,
+) -> (Vec<TileInstance>, bool) {
+    let mut instances = vec![];
+    let mut has_player = false;
+
+    for x in 0..TILES_COUNT.0 {
+        for y in 0..TILES_COUNT.1 {
+            let tile = tilemap.get_tile(x, y);
+            if tile == Tile::Void {
+                continue;
+            }
+
+            let variant = ground_variant(
+                x + offset.x * TILES_COUNT.0,
+                y + offset.y * TILES_COUNT.1,
+            );
+
+            // Grid is in 2d, so "y" become "z" in 3d.
+            let x = world.offset.x
+                + x as f32 * TILE_SIZE
+                + offset.x as f32 * TILEMAP_WIDTH;
+            let z = world.offset.y
+                + y as f32 * TILE_SIZE
+                + offset.y as f32 * TILEMAP_HEIGHT;
+
+            let ground = glm::translate(
+                &glm::identity(),
+                &glm::vec3(x, -GROUND_HEIGHT, z),
+            ) * glm::scale(
+                &glm::identity(),
+                &glm::vec3(1., GROUND_HEIGHT, 1.),
+            );
+            instances.push(TileInstance {
+                model: to_array(&ground),
+                layer: variant,
+                kind: TileKind::Ground,
+            });
+
+            match tile {
+                Tile::Wall(handle) => {
+                    let wall = entities.get(&handle);
+                    let model =
+                        glm::translate(&glm::identity(), &wall.float_pos);
+                    instances.push(TileInstance {
+                        model: to_array(&model),
+                        layer: 0,
+                        kind: TileKind::Wall,
+                    });
+                }
+                Tile::Player => has_player = true,
+                _ => (),
+            }
+        }
+    }
+
+    instances.sort_by_key(|instance| instance.kind);
+    (instances, has_player)
+}
+
+// Same variant for a tile each time, without a visible pattern.
+fn ground_variant(x: i32, y: i32) -> u32 {
+    let hash = x.wrapping_mul(73_856_093) ^ y.wrapping_mul(19_349_663);
+    hash.rem_euclid(GROUND_VARIANTS.len() as i32) as u32
+}
+
+fn to_array(matrix: &glm::Mat4) -> [f32; 16] {
+    let mut array = [0.; 16];
+    array.copy_from_slice(matrix.as_slice());
+    array
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tilemap::MapFile;
+
+    #[test]
+    fn sort_instances_by_kind() {
+        // A wall in the corner, a void tile next to it and the player
+        // in the middle.
+        let mut rows = vec![vec!["1"; 10]; 13];
+        rows[0][0] = "2";
+        rows[0][1] = "null";
+        let grid: Vec<String> = rows
+            .iter()
+            .map(|row| format!("[{}]", row.join(",")))
+            .collect();
+        let map_file: MapFile = serde_json::from_str(&format!(
+            r#"{{"dimension": [10, 13], "grid": [{}]}}"#,
+            grid.join(",")
+        ))
+        .unwrap();
+
+        let mut entities = Entities::default();
+        let mut tilemap = Tilemap::from_file(map_file, (1, 0), &mut entities);
+        let revision = tilemap.revision();
+        tilemap.set(glm::vec2(4, 6), Tile::Player);
+        assert_ne!(tilemap.revision(), revision);
+
+        let world = World::new(vec![]);
+        let offset = glm::vec2(1, 0);
+        let (instances, has_player) =
+            tile_instances(&entities, &world, &tilemap, &offset);
+
+        // Every tile but the void one has a ground.
+        assert!(has_player);
+        assert_eq!(instances.len(), 10 * 13 - 1 + 1);
+        let kinds: Vec<TileKind> = instances.iter().map(|i| i.kind).collect();
+        let mut sorted = kinds.clone();
+        sorted.sort();
+        assert_eq!(kinds, sorted);
+
+        // Walls are placed from their entity.
+        let instance = instances.last().unwrap();
+        assert_eq!(instance.kind, TileKind::Wall);
+        if let Tile::Wall(handle) = tilemap.get_tile(0, 0) {
+            let wall = entities.get(&handle);
+            assert_eq!(&instance.model[12..15], wall.float_pos.as_slice());
+        } else {
+            panic!("The corner tile should be a wall");
+        }
+
+        // The ground of the last tile, on the second tilemap.
+        let ground = &instances[instances.len() - 2];
+        assert_eq!(ground.model[12], 9. * TILE_SIZE + TILEMAP_WIDTH);
+        assert_eq!(ground.model[13], -GROUND_HEIGHT);
+
+        // Grounds use all the variants, always the same per tile.
+        let grounds = &instances[..instances.len() - 1];
+        for layer in 0..GROUND_VARIANTS.len() as u32 {
+            assert!(grounds.iter().any(|ground| ground.layer == layer));
+        }
+        let (again, _) = tile_instances(&entities, &world, &tilemap, &offset);
+        assert!(instances
+            .iter()
+            .zip(again.iter())
+            .all(|(a, b)| a.layer == b.layer));
+    }
+}
@@ -0,0 +1,327 @@
+use super::Vector;
+use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
+
+/// Position, rotation and scale of an object.
+///
+/// The rotation is stored as a unit quaternion, so we don't suffer from
+/// gimbal lock. Euler angles are only used at the edges (editor, debug),
+/// they are in radians and applied in the x, y, z order.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transform {
+    pub position: Vector,
+    // Serialized as [x, y, z, w], like glTF does.
+    #[serde(with = "quat_xyzw")]
+    pub rotation: glm::Quat,
+    pub scale: Vector,
+}
+
+impl Transform {
+    pub fn scale(mut self, scale: Vector) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Set the rotation from euler angles (in radians).
+    #[allow(unused)]
+    pub fn euler(mut self, angles: Vector) -> Self {
+        self.rotation = quat_from_euler(&angles);
+        self
+    }
+
+    pub fn from_pos(position: Vector) -> Self {
+        Self {
+            position,
+            ..Default::default()
+        }
+    }
+
+    /// From glTF decomposed data: translation, rotation as
+    /// [x, y, z, w] and scale.
+    #[allow(unused)]
+    pub fn from_decomposed(
+        (translation, rotation, scale): ([f32; 3], [f32; 4], [f32; 3]),
+    ) -> Self {
+        Self {
+            position: Vector(translation[0], translation[1], translation[2]),
+            rotation: glm::quat(
+                rotation[0],
+                rotation[1],
+                rotation[2],
+                rotation[3],
+            ),
+            scale: Vector(scale[0], scale[1], scale[2]),
+        }
+    }
+
+    /// To glTF decomposed data, see `from_decomposed`.
+    #[allow(unused)]
+    pub fn to_decomposed(self) -> ([f32; 3], [f32; 4], [f32; 3]) {
+        let q = self.rotation.coords;
+        (
+            (&self.position).into(),
+            [q.x, q.y, q.z, q.w],
+            (&self.scale).into(),
+        )
+    }
+
+    /// Rotation as euler angles (in radians).
+    #[allow(unused)]
+    pub fn to_euler(self) -> Vector {
+        euler_from_mat3(&glm::quat_to_mat3(&self.rotation))
+    }
+
+    /// Rotate around an axis in world space (angle in radians).
+    pub fn rotate(&mut self, axis: Vector, angle: f32) {
+        let rotation = glm::quat_angle_axis(angle, &axis.to_glm());
+        self.rotation = glm::quat_normalize(&(rotation * self.rotation));
+    }
+
+    /// Rotate the object so its local +Z axis points to the target.
+    #[allow(unused)]
+    pub fn look_at(mut self, target: Vector, up: Vector) -> Self {
+        let forward = target.to_glm() - self.position.to_glm();
+        if glm::length(&forward) < f32::EPSILON {
+            return self;
+        }
+
+        let z = glm::normalize(&forward);
+        let mut x = up.to_glm().cross(&z);
+        // Forward and up are colinear, any perpendicular axis will do.
+        if glm::length(&x) < f32::EPSILON {
+            x = glm::vec3(1., 0., 0.).cross(&z);
+            if glm::length(&x) < f32::EPSILON {
+                x = glm::vec3(0., 0., 1.).cross(&z);
+            }
+        }
+        let x = glm::normalize(&x);
+        let y = z.cross(&x);
+
+        let basis = glm::mat3(x.x, y.x, z.x, x.y, y.y, z.y, x.z, y.z, z.z);
+        self.rotation = glm::quat_normalize(&glm::mat3_to_quat(&basis));
+        self
+    }
+
+    /// Interpolate between two transforms, used by animations.
+    /// Position and scale are lerped, rotation is slerped.
+    #[allow(unused)]
+    pub fn interpolate(&self, other: &Self, t: f32) -> Self {
+        Self {
+            position: Vector::from_glm(glm::lerp(
+                &self.position.to_glm(),
+                &other.position.to_glm(),
+                t,
+            )),
+            rotation: slerp(&self.rotation, &other.rotation, t),
+            scale: Vector::from_glm(glm::lerp(
+                &self.scale.to_glm(),
+                &other.scale.to_glm(),
+                t,
+            )),
+        }
+    }
+
+    pub fn to_glm(self) -> (glm::Vec3, glm::Quat, glm::Vec3) {
+        (self.position.to_glm(), self.rotation, self.scale.to_glm())
+    }
+
+    pub fn to_model(self) -> glm::Mat4 {
+        let (pos, rotation, scale) = self.to_glm();
+        let identity = glm::identity();
+
+        let scale_matrix = glm::scale(&identity, &scale);
+        let translate_matrix = glm::translate(&identity, &pos);
+        let rotation_matrix = glm::quat_to_mat4(&rotation);
+
+        translate_matrix * rotation_matrix * scale_matrix
+    }
+
+    /// Decompose a model matrix (without shear) back into a transform.
+    pub fn from_model(model: &glm::Mat4) -> Self {
+        let column =
+            |i: usize| glm::vec3(model[(0, i)], model[(1, i)], model[(2, i)]);
+
+        let position = column(3);
+        let scale = glm::vec3(
+            glm::length(&column(0)),
+            glm::length(&column(1)),
+            glm::length(&column(2)),
+        );
+
+        // Remove the scale from the basis to get the rotation.
+        let (x, y, z) = (
+            column(0) / scale.x,
+            column(1) / scale.y,
+            column(2) / scale.z,
+        );
+        let basis = glm::mat3(x.x, y.x, z.x, x.y, y.y, z.y, x.z, y.z, z.z);
+
+        Self {
+            position: Vector::from_glm(position),
+            rotation: glm::quat_normalize(&glm::mat3_to_quat(&basis)),
+            scale: Vector::from_glm(scale),
+        }
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            position: Vector::default(),
+            rotation: glm::quat_identity(),
+            scale: Vector(1., 1., 1.),
+        }
+    }
+}
+
+/// Rotation applying x, then y, then z axis rotations
+/// (i.e. R = Rx * Ry * Rz).
+pub fn quat_from_euler(angles: &Vector) -> glm::Quat {
+    let qx = glm::quat_angle_axis(angles.0, &glm::vec3(1., 0., 0.));
+    let qy = glm::quat_angle_axis(angles.1, &glm::vec3(0., 1., 0.));
+    let qz = glm::quat_angle_axis(angles.2, &glm::vec3(0., 0., 1.));
+
+    glm::quat_normalize(&(qx * qy * qz))
+}
+
+// Inverse of `quat_from_euler`, from the rotation matrix.
+fn euler_from_mat3(r: &glm::Mat3) -> Vector {
+    let y = r[(0, 2)].clamp(-1., 1.).asin();
+
+    let (x, z) = if y.cos().abs() > 1e-6 {
+        (
+            f32::atan2(-r[(1, 2)], r[(2, 2)]),
+            f32::atan2(-r[(0, 1)], r[(0, 0)]),
+        )
+    } else {
+        // Gimbal lock, only x + z is known.
+        (f32::atan2(r[(2, 1)], r[(1, 1)]), 0.)
+    };
+
+    Vector(x, y, z)
+}
+
+/// Spherical interpolation, always through the shortest path.
+pub fn slerp(a: &glm::Quat, b: &glm::Quat, t: f32) -> glm::Quat {
+    let mut b = *b;
+    let mut dot = glm::quat_dot(a, &b);
+
+    // q and -q are the same rotation, take the nearest one.
+    if dot < 0. {
+        b = -b;
+        dot = -dot;
+    }
+
+    // Almost the same rotation, a normalized lerp is enough
+    // (and avoid dividing by sin(0)).
+    if dot > 0.9995 {
+        return glm::quat_normalize(&(a * (1. - t) + b * t));
+    }
+
+    let theta = dot.acos();
+    let sin_theta = theta.sin();
+    let wa = ((1. - t) * theta).sin() / sin_theta;
+    let wb = (t * theta).sin() / sin_theta;
+
+    glm::quat_normalize(&(a * wa + b * wb))
+}
+
+mod quat_xyzw {
+    use nalgebra_glm as glm;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        q: &glm::Quat,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        [q.coords.x, q.coords.y, q.coords.z, q.coords.w].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<glm::Quat, D::Error> {
+        let [x, y, z, w] = <[f32; 4]>::deserialize(deserializer)?;
+        Ok(glm::quat(x, y, z, w))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_near_mat(a: &glm::Mat4, b: &glm::Mat4) -> bool {
+        (0..16).all(|i| (a[i] - b[i]).abs() < 1e-4)
+    }
+
+    #[test]
+    fn euler_round_trip() {
+        let angles = Vector(0.3, -0.7, 1.2);
+        let transform = Transform::default().euler(angles);
+        let back = transform.to_euler();
+
+        assert!((back.0 - angles.0).abs() < 1e-4);
+        assert!((back.1 - angles.1).abs() < 1e-4);
+        assert!((back.2 - angles.2).abs() < 1e-4);
+
+        // Same order as composing axis rotation matrices.
+        let identity = glm::identity();
+        let expected = glm::rotate_x(&identity, angles.0)
+            * glm::rotate_y(&identity, angles.1)
+            * glm::rotate_z(&identity, angles.2);
+        assert!(is_near_mat(&transform.to_model(), &expected));
+    }
+
+    #[test]
+    fn from_model_round_trip() {
+        let transform = Transform::from_pos(Vector(1., 2., 3.))
+            .euler(Vector(0.3, -0.7, 1.2))
+            .scale(Vector(1., 2., 0.5));
+
+        let decomposed = Transform::from_model(&transform.to_model());
+        assert!(is_near_mat(&decomposed.to_model(), &transform.to_model()));
+    }
+
+    #[test]
+    fn slerp_shortest_path() {
+        let a = quat_from_euler(&Vector(0., 0., 0.));
+        let b = quat_from_euler(&Vector(0., 90f32.to_radians(), 0.));
+
+        let half = slerp(&a, &b, 0.5);
+        let expected = quat_from_euler(&Vector(0., 45f32.to_radians(), 0.));
+        assert!(glm::quat_dot(&half, &expected).abs() > 0.9999);
+
+        // -b is the same rotation, we shouldn't take the long way.
+        let half = slerp(&a, &-b, 0.5);
+        assert!(glm::quat_dot(&half, &expected).abs() > 0.9999);
+    }
+
+    #[test]
+    fn look_at_target() {
+        let transform = Transform::from_pos(Vector(1., 0., 1.))
+            .look_at(Vector(4., 0., 1.), Vector(0., 1., 0.));
+
+        // Local +Z now points to +X.
+        let forward =
+            glm::quat_rotate_vec3(&transform.rotation, &glm::vec3(0., 0., 1.));
+        assert!(glm::distance(&forward, &glm::vec3(1., 0., 0.)) < 1e-4);
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let transform = Transform::from_decomposed((
+            [1., 2., 3.],
+            [0., 0.70710677, 0., 0.70710677],
+            [1., 1., 2.],
+        ));
+
+        let json = serde_json::to_string(&transform).unwrap();
+        assert_eq!(
+            json,
+            r#"{"position":[1.0,2.0,3.0],"rotation":[0.0,0.70710677,0.0,0.70710677],"scale":[1.0,1.0,2.0]}"#
+        );
+
+        let back: Transform = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, transform);
+        assert_eq!(back.to_decomposed(), transform.to_decomposed());
+    }
+}
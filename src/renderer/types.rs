@@ -1,4 +1,5 @@
 use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
 use std::cmp::PartialEq;
 use std::ops::{Add, Mul};
 
@@ -68,6 +69,19 @@ impl Rgba {
     pub fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
         Self { r, g, b, a }
     }
+
+    /// From an sRGB color (i.e. picked on screen) to a linear one,
+    /// like the gpu does for sRGB textures. Alpha is kept.
+    pub fn to_linear(self) -> Self {
+        let decode = |c: f32| {
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        Self::new(decode(self.r), decode(self.g), decode(self.b), self.a)
+    }
 }
 
 impl Colors for Rgba {
@@ -190,7 +204,7 @@ impl From<&Rgba> for Rgb {
 //
 //
 
-#[derive(Default, Debug, PartialEq, Copy, Clone)]
+#[derive(Default, Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub struct Vector(pub f32, pub f32, pub f32);
 
 impl Mul<f32> for Vector {
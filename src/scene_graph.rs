@@ -0,0 +1,296 @@
+/// Transform hierarchy.
+///
+/// Each node keeps its local transform and parent, the world matrix is
+/// composed up the parent chain and cached. A node is recomputed only
+/// when it is dirty: its local transform or parent changed, or one of
+/// its ancestors was recomputed.
+use crate::entities::Handle;
+use crate::renderer::Transform;
+use nalgebra_glm as glm;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, PartialEq)]
+pub enum SceneGraphError {
+    // Handle not (yet) known by the graph.
+    NotFound,
+    // The new parent is the node itself or one of its descendants.
+    Cycle,
+}
+
+#[derive(Debug)]
+struct Node<T> {
+    local: Transform,
+    parent: Option<Handle<T>>,
+    // Parent actually used, `None` if the given one is unknown or
+    // creates a cycle.
+    resolved_parent: Option<Handle<T>>,
+    world: glm::Mat4,
+    is_dirty: bool,
+}
+
+#[derive(Debug)]
+pub struct SceneGraph<T> {
+    nodes: HashMap<Handle<T>, Node<T>>,
+}
+
+impl<T> Default for SceneGraph<T> {
+    fn default() -> Self {
+        Self {
+            nodes: HashMap::new(),
+        }
+    }
+}
+
+impl<T> SceneGraph<T> {
+    /// Sync the graph with the current local transforms and parents,
+    /// then recompute the dirty world matrices from roots to leaves.
+    /// Nodes not given anymore are removed.
+    pub fn update(
+        &mut self,
+        items: impl Iterator<Item = (Handle<T>, Transform, Option<Handle<T>>)>,
+    ) {
+        let mut alive = HashSet::new();
+
+        for (handle, local, parent) in items {
+            alive.insert(handle);
+
+            match self.nodes.get_mut(&handle) {
+                Some(node) => {
+                    if node.local != local || node.parent != parent {
+                        node.local = local;
+                        node.parent = parent;
+                        node.is_dirty = true;
+                    }
+                }
+                None => {
+                    self.nodes.insert(
+                        handle,
+                        Node {
+                            local,
+                            parent,
+                            resolved_parent: None,
+                            world: glm::identity(),
+                            is_dirty: true,
+                        },
+                    );
+                }
+            }
+        }
+
+        self.nodes.retain(|handle, _| alive.contains(handle));
+
+        // Parents could have been removed or changed, resolve them again.
+        let handles: Vec<Handle<T>> = self.nodes.keys().copied().collect();
+        for handle in handles.iter() {
+            let resolved = self.resolve_parent(handle);
+            let node = self.nodes.get_mut(handle).unwrap();
+
+            if node.resolved_parent != resolved {
+                node.resolved_parent = resolved;
+                node.is_dirty = true;
+            }
+        }
+
+        // Parents always come before their children.
+        let mut ordered: Vec<(usize, Handle<T>)> =
+            handles.into_iter().map(|h| (self.depth(&h), h)).collect();
+        ordered.sort_by_key(|(depth, _)| *depth);
+
+        for (_, handle) in ordered.iter() {
+            let (parent_world, is_parent_dirty) =
+                match self.nodes[handle].resolved_parent {
+                    Some(parent) => {
+                        let parent = &self.nodes[&parent];
+                        (parent.world, parent.is_dirty)
+                    }
+                    None => (glm::identity(), false),
+                };
+
+            let node = self.nodes.get_mut(handle).unwrap();
+            if node.is_dirty || is_parent_dirty {
+                node.world = parent_world * node.local.to_model();
+                node.is_dirty = true;
+            }
+        }
+
+        for node in self.nodes.values_mut() {
+            node.is_dirty = false;
+        }
+    }
+
+    /// Cached world matrix, `None` if the node wasn't updated yet.
+    pub fn world_matrix(&self, handle: &Handle<T>) -> Option<glm::Mat4> {
+        self.nodes.get(handle).map(|node| node.world)
+    }
+
+    pub fn parent(&self, handle: &Handle<T>) -> Option<Handle<T>> {
+        self.nodes.get(handle).and_then(|node| node.resolved_parent)
+    }
+
+    /// Compute the local transform the child must have under its new
+    /// parent to keep its current world position.
+    /// The graph should be up to date.
+    pub fn reparent(
+        &self,
+        child: &Handle<T>,
+        new_parent: Option<Handle<T>>,
+    ) -> Result<Transform, SceneGraphError> {
+        let child_world =
+            self.world_matrix(child).ok_or(SceneGraphError::NotFound)?;
+
+        let parent_world = match new_parent {
+            Some(parent) => {
+                if self.is_ancestor(child, &parent) {
+                    return Err(SceneGraphError::Cycle);
+                }
+
+                self.world_matrix(&parent)
+                    .ok_or(SceneGraphError::NotFound)?
+            }
+            None => glm::identity(),
+        };
+
+        let local = glm::inverse(&parent_world) * child_world;
+        Ok(Transform::from_model(&local))
+    }
+
+    // True if `ancestor` is the node itself or one of its ancestors.
+    fn is_ancestor(&self, ancestor: &Handle<T>, handle: &Handle<T>) -> bool {
+        let mut current = Some(*handle);
+
+        while let Some(h) = current {
+            if h == *ancestor {
+                return true;
+            }
+            current = self.parent(&h);
+        }
+
+        false
+    }
+
+    fn resolve_parent(&self, handle: &Handle<T>) -> Option<Handle<T>> {
+        let parent = self.nodes[handle].parent?;
+
+        if !self.nodes.contains_key(&parent) {
+            return None;
+        }
+
+        // Walk up the raw parents, if we come back to the node
+        // itself, it's part of a cycle.
+        let mut current = Some(parent);
+        for _ in 0..self.nodes.len() {
+            match current {
+                Some(h) if h == *handle => return None,
+                Some(h) => current = self.nodes.get(&h).and_then(|n| n.parent),
+                None => break,
+            }
+        }
+
+        Some(parent)
+    }
+
+    fn depth(&self, handle: &Handle<T>) -> usize {
+        let mut depth = 0;
+        let mut current = self.parent(handle);
+
+        while let Some(h) = current {
+            depth += 1;
+            current = self.parent(&h);
+        }
+
+        depth
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::Arena;
+    use crate::renderer::Vector;
+
+    fn is_near(a: &glm::Vec3, b: &glm::Vec3) -> bool {
+        glm::distance(a, b) < 1e-4
+    }
+
+    fn position(matrix: &glm::Mat4) -> glm::Vec3 {
+        glm::vec3(matrix[(0, 3)], matrix[(1, 3)], matrix[(2, 3)])
+    }
+
+    #[test]
+    fn compose_parent_chain() {
+        let mut arena = Arena::<bool>::alloc(3);
+        let (root, child, leaf) =
+            (arena.insert(true), arena.insert(true), arena.insert(true));
+
+        let mut graph = SceneGraph::default();
+        let moved = Transform::from_pos(Vector(1., 0., 0.));
+        let items = vec![
+            (leaf, moved, Some(child)),
+            (child, moved, Some(root)),
+            (root, moved, None),
+        ];
+        graph.update(items.clone().into_iter());
+
+        let world = graph.world_matrix(&leaf).unwrap();
+        assert!(is_near(&position(&world), &glm::vec3(3., 0., 0.)));
+        assert_eq!(graph.parent(&leaf), Some(child));
+
+        // Moving the root moves all its children.
+        let mut items = items;
+        items[2].1 = Transform::from_pos(Vector(1., 2., 0.));
+        graph.update(items.into_iter());
+
+        let world = graph.world_matrix(&leaf).unwrap();
+        assert!(is_near(&position(&world), &glm::vec3(3., 2., 0.)));
+    }
+
+    #[test]
+    fn reparent_keep_world_position() {
+        let mut arena = Arena::<bool>::alloc(2);
+        let (parent, child) = (arena.insert(true), arena.insert(true));
+
+        let parent_transform = Transform::from_pos(Vector(2., 0., 1.))
+            .euler(Vector(0., 90f32.to_radians(), 0.))
+            .scale(Vector(2., 2., 2.));
+        let child_transform = Transform::from_pos(Vector(-1., 3., 4.));
+
+        let mut graph = SceneGraph::default();
+        graph.update(
+            vec![
+                (parent, parent_transform, None),
+                (child, child_transform, None),
+            ]
+            .into_iter(),
+        );
+
+        let local = graph.reparent(&child, Some(parent)).unwrap();
+        graph.update(
+            vec![
+                (parent, parent_transform, None),
+                (child, local, Some(parent)),
+            ]
+            .into_iter(),
+        );
+
+        let world = graph.world_matrix(&child).unwrap();
+        assert!(is_near(&position(&world), &glm::vec3(-1., 3., 4.)));
+        assert!(is_near(&local.scale.to_glm(), &glm::vec3(0.5, 0.5, 0.5)));
+    }
+
+    #[test]
+    fn reject_and_survive_cycles() {
+        let mut arena = Arena::<bool>::alloc(2);
+        let (a, b) = (arena.insert(true), arena.insert(true));
+        let t = Transform::from_pos(Vector(1., 0., 0.));
+
+        let mut graph = SceneGraph::default();
+        graph.update(vec![(a, t, None), (b, t, Some(a))].into_iter());
+        assert_eq!(graph.reparent(&a, Some(b)), Err(SceneGraphError::Cycle));
+        assert_eq!(graph.reparent(&a, Some(a)), Err(SceneGraphError::Cycle));
+
+        // A cycle set by hand is ignored instead of looping forever.
+        graph.update(vec![(a, t, Some(b)), (b, t, Some(a))].into_iter());
+        let world = graph.world_matrix(&a).unwrap();
+        assert!(is_near(&position(&world), &glm::vec3(1., 0., 0.)));
+    }
+}
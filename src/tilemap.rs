@@ -213,6 +213,21 @@ impl World {
         None
     }
 
+    /// World space box around a tilemap, from the bottom of the ground
+    /// to above the walls and the player.
+    pub fn get_tilemap_bounds(
+        &self,
+        world_pos: &glm::TVec2<i32>,
+    ) -> (glm::Vec3, glm::Vec3) {
+        let x = self.offset.x + world_pos.x as f32 * TILEMAP_WIDTH;
+        let z = self.offset.y + world_pos.y as f32 * TILEMAP_HEIGHT;
+
+        (
+            glm::vec3(x, -TILE_SIZE, z),
+            glm::vec3(x + TILEMAP_WIDTH, 2. * TILE_SIZE, z + TILEMAP_HEIGHT),
+        )
+    }
+
     pub fn get_tilemap(
         &self,
         world_index: &glm::TVec2<i32>,
@@ -260,7 +275,10 @@ pub struct WorldFile {
 pub struct Tilemap {
     pub name: String,
     pub pathfile: String,
-    pub grid: LocalGrid,
+    grid: LocalGrid,
+    // Bumped on each change of the grid, the renderer rebuilds its
+    // tile instances when it differs.
+    revision: u32,
 }
 
 impl From<MapFile> for Tilemap {
@@ -284,6 +302,7 @@ impl From<MapFile> for Tilemap {
             grid,
             name: u.name,
             pathfile: u.pathfile,
+            revision: 0,
         }
     }
 }
@@ -325,6 +344,7 @@ impl Tilemap {
             grid,
             name: u.name,
             pathfile: u.pathfile,
+            revision: 0,
         }
     }
 
@@ -366,6 +386,11 @@ impl Tilemap {
 
     pub fn set(&mut self, position: glm::TVec2<i32>, value: Tile) {
         self.grid[position.y as usize][position.x as usize] = value;
+        self.revision = self.revision.wrapping_add(1);
+    }
+
+    pub fn revision(&self) -> u32 {
+        self.revision
     }
 
     #[allow(unused)]